@@ -0,0 +1,76 @@
+//! Repeated `resolve`/`usages_of` on a large file: linear scans on
+//! `ProtoFile` vs. the memoized index of `ArcProtoFile`.
+//!
+//!     cargo bench --bench frozen [-- <messages>]
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use dot_proto_parser::{Field, FieldRule, Message, ProtoFile};
+
+/// File with `count` messages, each with a nested message and fields
+/// referencing its neighbours
+fn large_file(count: usize) -> ProtoFile {
+    let mut file = ProtoFile::new("bench");
+    for i in 0..count {
+        let mut message = Message::new(&format!("Model{}", i));
+        let mut nested = Message::new("Entry");
+        nested
+            .add_field(Field::new("key", "string", 1, FieldRule::Singular))
+            .expect("unique field");
+        message.add_nested_message(nested).expect("unique message");
+        for (number, target) in [(1, (i + 1) % count), (2, (i + 7) % count)] {
+            message
+                .add_field(Field::new(
+                    &format!("ref{}", number),
+                    format!("Model{}", target).as_str(),
+                    number,
+                    FieldRule::Singular,
+                ))
+                .expect("unique field");
+        }
+        message
+            .add_field(Field::new("entries", "Entry", 3, FieldRule::Repeated))
+            .expect("unique field");
+        file.add_message(message).expect("unique message");
+    }
+    file
+}
+
+fn time(queries: &[String], mut query: impl FnMut(&str)) -> Duration {
+    let started = Instant::now();
+    for name in queries {
+        query(name);
+    }
+    started.elapsed()
+}
+
+fn main() {
+    let count = std::env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse().ok())
+        .unwrap_or(2000);
+    let file = large_file(count);
+    let queries: Vec<String> = (0..200)
+        .map(|i| format!("Model{}.Entry", i * 7 % count))
+        .collect();
+
+    let linear = time(&queries, |name| {
+        black_box(file.resolve(name));
+        black_box(file.usages_of(name));
+    });
+    let frozen = file.freeze();
+    let indexed = time(&queries, |name| {
+        black_box(frozen.resolve(name));
+        black_box(frozen.usages_of(name));
+    });
+
+    println!(
+        "{} messages, {} queries: linear {:>8.1?}, indexed {:>8.1?} ({:.0}x)",
+        count,
+        queries.len(),
+        linear,
+        indexed,
+        linear.as_secs_f64() / indexed.as_secs_f64()
+    );
+}
@@ -0,0 +1,98 @@
+//! Serial vs. parallel schema conversion on a generated spec.
+//!
+//!     cargo bench --features parallel --bench parallel [-- <definitions>]
+
+use std::time::{Duration, Instant};
+
+use dot_proto_parser::SwaggerToProtoConverter;
+use serde_json::{Value, json};
+
+/// Swagger 2.0 document with `count` definitions, each with inline objects,
+/// arrays, refs and enums; every tenth enum is shared between definitions
+fn large_spec(count: usize) -> Value {
+    let mut definitions = serde_json::Map::new();
+    for i in 0..count {
+        let mut properties = serde_json::Map::new();
+        for p in 0..20 {
+            properties.insert(format!("field{}", p), json!({ "type": "string" }));
+        }
+        properties.insert(
+            "owner".into(),
+            json!({ "$ref": format!("#/definitions/Model{}", (i + 1) % count) }),
+        );
+        properties.insert(
+            "address".into(),
+            json!({
+                "type": "object",
+                "properties": {
+                    "street": { "type": "string" },
+                    "zip": { "type": "integer", "minimum": 0 },
+                    "geo": {
+                        "type": "object",
+                        "properties": {
+                            "lat": { "type": "number" },
+                            "lon": { "type": "number" },
+                            "label": { "type": "string", "description": format!("Label {}", i) }
+                        }
+                    }
+                }
+            }),
+        );
+        properties.insert(
+            "tags".into(),
+            json!({ "type": "array", "items": { "type": "array", "items": { "type": "string" } } }),
+        );
+        let values: Vec<String> = if i % 10 == 0 {
+            vec!["on".into(), "off".into()]
+        } else {
+            (0..5).map(|v| format!("state_{}_{}", i, v)).collect()
+        };
+        properties.insert("state".into(), json!({ "type": "string", "enum": values }));
+        definitions.insert(
+            format!("Model{}", i),
+            json!({ "type": "object", "description": format!("Model {}", i), "properties": properties }),
+        );
+    }
+    json!({
+        "swagger": "2.0",
+        "info": { "title": "Bench", "version": "1" },
+        "paths": {},
+        "definitions": definitions
+    })
+}
+
+fn convert(spec: &Value, threads: usize) -> (Duration, String) {
+    let mut converter = SwaggerToProtoConverter::builder()
+        .package("bench")
+        .parallelism(threads)
+        .build();
+    let started = Instant::now();
+    let proto = converter
+        .convert_value(spec.clone())
+        .expect("spec converts");
+    let elapsed = started.elapsed();
+    (elapsed, proto.to_proto_text())
+}
+
+fn main() {
+    let count = std::env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse().ok())
+        .unwrap_or(5000);
+    let spec = large_spec(count);
+    let threads = std::thread::available_parallelism().map_or(4, |n| n.get());
+
+    let (serial, expected) = convert(&spec, 1);
+    println!("{} definitions, serial:      {:>8.1?}", count, serial);
+    for n in [2, 4, threads] {
+        let (parallel, output) = convert(&spec, n);
+        assert_eq!(output, expected, "{} threads changed the output", n);
+        println!(
+            "{} definitions, {:>2} threads:  {:>8.1?} ({:.1}x)",
+            count,
+            n,
+            parallel,
+            serial.as_secs_f64() / parallel.as_secs_f64()
+        );
+    }
+}
@@ -0,0 +1,66 @@
+//! Tokenizing and parsing a generated proto of about 10k lines.
+//!
+//!     cargo bench --bench parser [-- <messages>]
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use dot_proto_parser::ProtoParser;
+
+/// Proto text with `count` messages of ten fields each, options, comments
+/// and a service with an rpc per message
+fn large_proto(count: usize) -> String {
+    let mut text = String::from("syntax = \"proto3\";\n\npackage bench.v1;\n\n");
+    text.push_str("import \"google/api/annotations.proto\";\n\n");
+    for i in 0..count {
+        text.push_str(&format!("// Model number {}\nmessage Model{} {{\n", i, i));
+        for f in 1..=8 {
+            text.push_str(&format!(
+                "  optional string field{} = {} [json_name = \"field_{}\"];\n",
+                f, f, f
+            ));
+        }
+        text.push_str(&format!("  repeated Model{} next = 9;\n", (i + 1) % count));
+        text.push_str("  map<string, int64> counts = 10;\n}\n\n");
+    }
+    text.push_str("service BenchService {\n");
+    for i in 0..count {
+        text.push_str(&format!(
+            "  rpc Get{} (Model{}) returns (Model{}) {{\n    \
+             option (google.api.http) = {{get: \"/v1/models/{}\"}};\n  }}\n",
+            i, i, i, i
+        ));
+    }
+    text.push_str("}\n");
+    text
+}
+
+fn main() {
+    let count = std::env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse().ok())
+        .unwrap_or(600);
+    let text = large_proto(count);
+    let lines = text.lines().count();
+
+    let runs = 10;
+    let mut best = Duration::MAX;
+    for _ in 0..runs {
+        let started = Instant::now();
+        black_box(
+            ProtoParser::new()
+                .parse(black_box(&text))
+                .expect("valid proto"),
+        );
+        best = best.min(started.elapsed());
+    }
+
+    println!(
+        "{} lines, {} KiB: best of {} {:>8.1?} ({:.0} lines/ms)",
+        lines,
+        text.len() / 1024,
+        runs,
+        best,
+        lines as f64 / best.as_secs_f64() / 1000.0
+    );
+}
@@ -0,0 +1,172 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{ConverterError, ConverterOptions, SwaggerToProtoConverter};
+
+/// Settings of [`SwaggerToProtoConverter::convert_dir`]
+#[derive(Debug, Clone)]
+pub struct BatchOptions {
+    /// Package of every file, `{stem}` standing for the file name without
+    /// extension in snake_case: `{stem}.v1` turns `user-api.json` into
+    /// `user_api.v1`
+    pub package_template: String,
+    /// Applied to every file
+    pub converter: ConverterOptions,
+    /// Files converted at the same time; 0 and 1 convert one by one
+    pub threads: usize,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self {
+            package_template: "{stem}".to_string(),
+            converter: ConverterOptions::default(),
+            threads: 1,
+        }
+    }
+}
+
+/// Outcome of [`SwaggerToProtoConverter::convert_dir`], in file name order
+#[derive(Debug, Default)]
+pub struct BatchReport {
+    /// Input file and the .proto written for it
+    pub converted: Vec<(PathBuf, PathBuf)>,
+    /// Input file and the reason it was not converted
+    pub failed: Vec<(PathBuf, ConverterError)>,
+}
+
+impl BatchReport {
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+impl SwaggerToProtoConverter {
+    /// Converts every `*.json`, `*.yaml` and `*.yml` file of `input_dir`
+    /// into `<stem>.proto` in `output_dir`, each with a fresh converter.
+    ///
+    /// A file that fails is recorded in the report and the rest still get
+    /// converted; only an unreadable input directory or an output directory
+    /// that cannot be created is an error.
+    pub fn convert_dir(
+        input_dir: &Path,
+        output_dir: &Path,
+        options: &BatchOptions,
+    ) -> Result<BatchReport, ConverterError> {
+        let mut inputs = Vec::new();
+        for entry in std::fs::read_dir(input_dir).map_err(io_error_in(input_dir))? {
+            let path = entry.map_err(io_error_in(input_dir))?.path();
+            if path.is_file() && spec_format(&path).is_some() {
+                inputs.push(path);
+            }
+        }
+        inputs.sort();
+        std::fs::create_dir_all(output_dir).map_err(io_error_in(output_dir))?;
+
+        let results: Vec<Mutex<Option<Result<PathBuf, ConverterError>>>> =
+            inputs.iter().map(|_| Mutex::new(None)).collect();
+        let next = AtomicUsize::new(0);
+        let worker = || {
+            loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(input) = inputs.get(i) else {
+                    break;
+                };
+                let result = convert_one(input, output_dir, options);
+                *results[i].lock().unwrap_or_else(|e| e.into_inner()) = Some(result);
+            }
+        };
+        std::thread::scope(|scope| {
+            for _ in 1..options.threads.min(inputs.len()) {
+                scope.spawn(worker);
+            }
+            worker();
+        });
+
+        let mut report = BatchReport::default();
+        for (input, result) in inputs.into_iter().zip(results) {
+            match result.into_inner().unwrap_or_else(|e| e.into_inner()) {
+                Some(Ok(output)) => report.converted.push((input, output)),
+                Some(Err(error)) => report.failed.push((input, error)),
+                None => {}
+            }
+        }
+        Ok(report)
+    }
+}
+
+/// Swagger/OpenAPI document from a file, `.yaml`/`.yml` read as YAML;
+/// errors carry `path`
+pub(crate) fn read_spec(path: &Path) -> Result<serde_json::Value, ConverterError> {
+    let read = || -> Result<serde_json::Value, ConverterError> {
+        let content = std::fs::read_to_string(path)?;
+        if spec_format(path) == Some(true) {
+            Ok(serde_yaml::from_str(&content)?)
+        } else {
+            Ok(serde_json::from_str(&content)?)
+        }
+    };
+    read().map_err(|e| e.in_file(path))
+}
+
+fn io_error_in(path: &Path) -> impl Fn(std::io::Error) -> ConverterError + '_ {
+    move |error| ConverterError::from(error).in_file(path)
+}
+
+/// Whether a spec file is read as YAML
+fn spec_format(path: &Path) -> Option<bool> {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("json") => Some(false),
+        Some("yaml") | Some("yml") => Some(true),
+        _ => None,
+    }
+}
+
+fn convert_one(
+    input: &Path,
+    output_dir: &Path,
+    options: &BatchOptions,
+) -> Result<PathBuf, ConverterError> {
+    let stem = input
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    let package = options
+        .package_template
+        .replace("{stem}", &package_segment(stem));
+
+    let spec = read_spec(input)?;
+    let mut converter = SwaggerToProtoConverter::builder()
+        .package(&package)
+        .options(options.converter.clone())
+        .base_dir(input.parent().unwrap_or(Path::new("")))
+        .build();
+    let proto = converter
+        .convert_value(spec)
+        .map_err(|e| e.in_file(input))?;
+
+    let output = output_dir.join(format!("{}.proto", stem));
+    std::fs::write(&output, proto.to_proto_text())
+        .map_err(|e| ConverterError::from(e).in_file(&output))?;
+    Ok(output)
+}
+
+/// `user-api` -> `user_api`, usable as a package segment
+fn package_segment(stem: &str) -> String {
+    let segment: String = stem
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if segment.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", segment)
+    } else {
+        segment
+    }
+}
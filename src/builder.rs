@@ -0,0 +1,547 @@
+use std::collections::HashSet;
+
+use crate::{
+    ConverterError, Enum, EnumValue, Field, FieldRule, FieldType, Message, Method, Oneof,
+    OptionValue, ProtoFile, Service,
+};
+
+impl ProtoFile {
+    /// Fluent alternative to [`ProtoFile::new`] and the `add_*` calls.
+    ///
+    /// ```
+    /// use dot_proto_parser::{Enum, FieldRule, Message, ProtoFile, Service};
+    ///
+    /// let file = ProtoFile::builder("api")
+    ///     .message(
+    ///         Message::builder("User")
+    ///             .comment("A user")
+    ///             .field("id", "int64", FieldRule::Optional)
+    ///             .field_with("email", "string", |f| f.comment("RFC 5322")),
+    ///     )
+    ///     .enum_def(Enum::builder("Role").value("ROLE_UNSPECIFIED").value("ROLE_ADMIN"))
+    ///     .service(Service::builder("UserService").rpc("GetUser", "GetUserRequest", "User"))
+    ///     .build()?;
+    ///
+    /// assert_eq!(file.messages[0].fields[1].number, 2);
+    /// assert_eq!(file.enums[0].values[1].number, 1);
+    /// # Ok::<(), dot_proto_parser::ConverterError>(())
+    /// ```
+    pub fn builder(package: &str) -> ProtoFileBuilder {
+        ProtoFileBuilder {
+            file: ProtoFile::new(package),
+            messages: Vec::new(),
+            enums: Vec::new(),
+            services: Vec::new(),
+        }
+    }
+}
+
+impl Message {
+    /// Fluent construction; fields without an explicit number get the
+    /// lowest free ones in declaration order
+    ///
+    /// ```
+    /// use dot_proto_parser::{FieldRule, Message};
+    ///
+    /// let user = Message::builder("User")
+    ///     .field_with("id", "int64", |f| f.number(3))
+    ///     .field_with("email", "string", |f| f.comment("RFC 5322"))
+    ///     .field("tags", "string", FieldRule::Repeated)
+    ///     .build()?;
+    ///
+    /// let numbers: Vec<_> = user.fields.iter().map(|f| (f.name.as_str(), f.number)).collect();
+    /// assert_eq!(numbers, [("id", 3), ("email", 1), ("tags", 2)]);
+    /// assert_eq!(user.fields[1].comments, ["RFC 5322"]);
+    /// # Ok::<(), dot_proto_parser::ConverterError>(())
+    /// ```
+    pub fn builder(name: &str) -> MessageBuilder {
+        MessageBuilder {
+            message: Message::new(name),
+            members: Vec::new(),
+            nested_messages: Vec::new(),
+            nested_enums: Vec::new(),
+        }
+    }
+}
+
+impl Enum {
+    /// Fluent construction; values without an explicit number continue
+    /// from the previous one, starting at 0
+    ///
+    /// ```
+    /// use dot_proto_parser::Enum;
+    ///
+    /// let role = Enum::builder("Role")
+    ///     .value("ROLE_UNSPECIFIED")
+    ///     .value_with("ROLE_ADMIN", |v| v.number(10))
+    ///     .value("ROLE_OWNER")
+    ///     .build()?;
+    ///
+    /// let numbers: Vec<_> = role.values.iter().map(|v| v.number).collect();
+    /// assert_eq!(numbers, [0, 10, 11]);
+    /// # Ok::<(), dot_proto_parser::ConverterError>(())
+    /// ```
+    pub fn builder(name: &str) -> EnumBuilder {
+        EnumBuilder {
+            enum_def: Enum::new(name),
+            values: Vec::new(),
+        }
+    }
+}
+
+impl Service {
+    /// Fluent construction of a service and its rpcs
+    ///
+    /// ```
+    /// use dot_proto_parser::Service;
+    ///
+    /// let service = Service::builder("UserService")
+    ///     .rpc("GetUser", "GetUserRequest", "User")
+    ///     .rpc_with("WatchUsers", "WatchUsersRequest", "User", |m| m.server_streaming())
+    ///     .build()?;
+    ///
+    /// let get_user = service.find_method("GetUser").unwrap();
+    /// assert_eq!(
+    ///     (get_user.input_type.as_str(), get_user.output_type.as_str()),
+    ///     ("GetUserRequest", "User")
+    /// );
+    /// assert!(service.methods[1].server_streaming);
+    /// # Ok::<(), dot_proto_parser::ConverterError>(())
+    /// ```
+    pub fn builder(name: &str) -> ServiceBuilder {
+        ServiceBuilder {
+            service: Service::new(name),
+            methods: Vec::new(),
+        }
+    }
+}
+
+/// See [`ProtoFile::builder`]; duplicates are reported by `build`
+#[derive(Debug, Clone)]
+pub struct ProtoFileBuilder {
+    file: ProtoFile,
+    messages: Vec<MessageBuilder>,
+    enums: Vec<EnumBuilder>,
+    services: Vec<ServiceBuilder>,
+}
+
+impl ProtoFileBuilder {
+    /// `proto3` unless set
+    pub fn syntax(mut self, syntax: &str) -> Self {
+        self.file.syntax = syntax.to_string();
+        self
+    }
+
+    pub fn header_comment(mut self, comment: &str) -> Self {
+        self.file.header_comments.push(comment.to_string());
+        self
+    }
+
+    pub fn import(mut self, import_path: &str) -> Self {
+        self.file.add_import(import_path);
+        self
+    }
+
+    pub fn option(mut self, key: &str, value: &str) -> Self {
+        self.file.add_option(key, value);
+        self
+    }
+
+    pub fn message(mut self, message: MessageBuilder) -> Self {
+        self.messages.push(message);
+        self
+    }
+
+    pub fn enum_def(mut self, enum_def: EnumBuilder) -> Self {
+        self.enums.push(enum_def);
+        self
+    }
+
+    pub fn service(mut self, service: ServiceBuilder) -> Self {
+        self.services.push(service);
+        self
+    }
+
+    /// Builds every definition and adds it, failing on the first error
+    ///
+    /// ```
+    /// use dot_proto_parser::{ConverterError, Message, ProtoFile};
+    ///
+    /// let result = ProtoFile::builder("api")
+    ///     .message(Message::builder("User"))
+    ///     .message(Message::builder("User"))
+    ///     .build();
+    ///
+    /// let error = result.unwrap_err();
+    /// assert!(matches!(&error, ConverterError::DuplicateMessageName(name) if name == "User"));
+    /// assert_eq!(error.code(), "E_CONVERT_005");
+    /// ```
+    pub fn build(self) -> Result<ProtoFile, ConverterError> {
+        let mut file = self.file;
+        for message in self.messages {
+            file.add_message(message.build()?)?;
+        }
+        for enum_def in self.enums {
+            file.add_enum(enum_def.build()?)?;
+        }
+        for service in self.services {
+            file.add_service(service.build()?)?;
+        }
+        Ok(file)
+    }
+}
+
+/// Field or oneof in declaration order, numbers still unassigned
+#[derive(Debug, Clone)]
+enum Member {
+    Field(FieldBuilder),
+    Oneof(OneofBuilder),
+}
+
+/// See [`Message::builder`]
+#[derive(Debug, Clone)]
+pub struct MessageBuilder {
+    message: Message,
+    members: Vec<Member>,
+    nested_messages: Vec<MessageBuilder>,
+    nested_enums: Vec<EnumBuilder>,
+}
+
+impl MessageBuilder {
+    pub fn comment(mut self, comment: &str) -> Self {
+        self.message.add_comment(comment);
+        self
+    }
+
+    pub fn field(self, name: &str, type_: impl Into<FieldType>, rule: FieldRule) -> Self {
+        self.field_with(name, type_, |f| f.rule(rule))
+    }
+
+    /// Field configured by `configure`, singular unless it sets a rule
+    pub fn field_with(
+        mut self,
+        name: &str,
+        type_: impl Into<FieldType>,
+        configure: impl FnOnce(FieldBuilder) -> FieldBuilder,
+    ) -> Self {
+        let field = configure(FieldBuilder::new(name, type_.into()));
+        self.members.push(Member::Field(field));
+        self
+    }
+
+    /// Oneof whose fields share the numbering of the message
+    pub fn oneof(
+        mut self,
+        name: &str,
+        configure: impl FnOnce(OneofBuilder) -> OneofBuilder,
+    ) -> Self {
+        let oneof = configure(OneofBuilder {
+            oneof: Oneof::new(name),
+            fields: Vec::new(),
+        });
+        self.members.push(Member::Oneof(oneof));
+        self
+    }
+
+    pub fn nested_message(mut self, message: MessageBuilder) -> Self {
+        self.nested_messages.push(message);
+        self
+    }
+
+    pub fn nested_enum(mut self, enum_def: EnumBuilder) -> Self {
+        self.nested_enums.push(enum_def);
+        self
+    }
+
+    /// Numbers the fields and adds them, failing on duplicate names or
+    /// numbers
+    pub fn build(self) -> Result<Message, ConverterError> {
+        let mut message = self.message;
+
+        let explicit = self.members.iter().flat_map(|member| match member {
+            Member::Field(field) => vec![field.number],
+            Member::Oneof(oneof) => oneof.fields.iter().map(|f| f.number).collect(),
+        });
+        let mut numbers = FieldNumbers::new(explicit.flatten().collect());
+
+        for member in self.members {
+            match member {
+                Member::Field(field) => {
+                    message.add_field(field.build(&mut numbers)?)?;
+                }
+                Member::Oneof(builder) => {
+                    let mut oneof = builder.oneof;
+                    for field in builder.fields {
+                        oneof.add_field(field.build(&mut numbers)?)?;
+                    }
+                    message.add_oneof(oneof)?;
+                }
+            }
+        }
+        for nested in self.nested_messages {
+            message.add_nested_message(nested.build()?)?;
+        }
+        for enum_def in self.nested_enums {
+            message.add_nested_enum(enum_def.build()?)?;
+        }
+        Ok(message)
+    }
+}
+
+/// Hands out field numbers, skipping the ones set explicitly
+struct FieldNumbers {
+    explicit: Vec<i32>,
+    used: HashSet<i32>,
+    next: i32,
+}
+
+impl FieldNumbers {
+    fn new(explicit: Vec<i32>) -> Self {
+        Self {
+            explicit,
+            used: HashSet::new(),
+            next: 1,
+        }
+    }
+
+    fn assign(&mut self, number: Option<i32>, name: &str) -> Result<i32, ConverterError> {
+        let number = match number {
+            Some(number) => number,
+            None => {
+                while self.explicit.contains(&self.next) || self.used.contains(&self.next) {
+                    self.next += 1;
+                }
+                self.next
+            }
+        };
+        if !self.used.insert(number) {
+            return Err(ConverterError::InvalidFieldName(format!(
+                "Duplicate field number {}: {}",
+                number, name
+            )));
+        }
+        Ok(number)
+    }
+}
+
+/// Field of a [`MessageBuilder`] or [`OneofBuilder`]
+#[derive(Debug, Clone)]
+pub struct FieldBuilder {
+    field: Field,
+    number: Option<i32>,
+}
+
+impl FieldBuilder {
+    fn new(name: &str, type_: FieldType) -> Self {
+        Self {
+            field: Field::new(name, type_, 0, FieldRule::Singular),
+            number: None,
+        }
+    }
+
+    /// Explicit number instead of the next free one
+    pub fn number(mut self, number: i32) -> Self {
+        self.number = Some(number);
+        self
+    }
+
+    pub fn rule(mut self, rule: FieldRule) -> Self {
+        self.field.rule = rule;
+        self
+    }
+
+    pub fn comment(mut self, comment: &str) -> Self {
+        self.field.add_comment(comment);
+        self
+    }
+
+    pub fn option(mut self, key: &str, value: OptionValue) -> Self {
+        self.field.add_option(key, value);
+        self
+    }
+
+    pub fn string_option(mut self, key: &str, value: &str) -> Self {
+        self.field.add_string_option(key, value);
+        self
+    }
+
+    fn build(self, numbers: &mut FieldNumbers) -> Result<Field, ConverterError> {
+        let mut field = self.field;
+        field.number = numbers.assign(self.number, &field.name)?;
+        Ok(field)
+    }
+}
+
+/// See [`MessageBuilder::oneof`]
+#[derive(Debug, Clone)]
+pub struct OneofBuilder {
+    oneof: Oneof,
+    fields: Vec<FieldBuilder>,
+}
+
+impl OneofBuilder {
+    pub fn comment(mut self, comment: &str) -> Self {
+        self.oneof.add_comment(comment);
+        self
+    }
+
+    pub fn field(self, name: &str, type_: impl Into<FieldType>) -> Self {
+        self.field_with(name, type_, |f| f)
+    }
+
+    /// Field configured by `configure`; rules are dropped inside a oneof
+    pub fn field_with(
+        mut self,
+        name: &str,
+        type_: impl Into<FieldType>,
+        configure: impl FnOnce(FieldBuilder) -> FieldBuilder,
+    ) -> Self {
+        self.fields
+            .push(configure(FieldBuilder::new(name, type_.into())));
+        self
+    }
+}
+
+/// See [`Enum::builder`]
+#[derive(Debug, Clone)]
+pub struct EnumBuilder {
+    enum_def: Enum,
+    values: Vec<EnumValueBuilder>,
+}
+
+impl EnumBuilder {
+    pub fn comment(mut self, comment: &str) -> Self {
+        self.enum_def.add_comment(comment);
+        self
+    }
+
+    pub fn value(self, name: &str) -> Self {
+        self.value_with(name, |v| v)
+    }
+
+    pub fn value_with(
+        mut self,
+        name: &str,
+        configure: impl FnOnce(EnumValueBuilder) -> EnumValueBuilder,
+    ) -> Self {
+        self.values.push(configure(EnumValueBuilder {
+            value: EnumValue::new(name, 0),
+            number: None,
+        }));
+        self
+    }
+
+    /// Numbers the values and adds them, failing on duplicate names
+    pub fn build(self) -> Result<Enum, ConverterError> {
+        let mut enum_def = self.enum_def;
+        let mut next = 0;
+        for builder in self.values {
+            let mut value = builder.value;
+            value.number = builder.number.unwrap_or(next);
+            next = value.number + 1;
+            enum_def.add_value(value)?;
+        }
+        Ok(enum_def)
+    }
+}
+
+/// Value of an [`EnumBuilder`]
+#[derive(Debug, Clone)]
+pub struct EnumValueBuilder {
+    value: EnumValue,
+    number: Option<i32>,
+}
+
+impl EnumValueBuilder {
+    /// Explicit number; following values continue from it
+    pub fn number(mut self, number: i32) -> Self {
+        self.number = Some(number);
+        self
+    }
+
+    pub fn comment(mut self, comment: &str) -> Self {
+        self.value.add_comment(comment);
+        self
+    }
+}
+
+/// See [`Service::builder`]
+#[derive(Debug, Clone)]
+pub struct ServiceBuilder {
+    service: Service,
+    methods: Vec<MethodBuilder>,
+}
+
+impl ServiceBuilder {
+    pub fn comment(mut self, comment: &str) -> Self {
+        self.service.add_comment(comment);
+        self
+    }
+
+    pub fn option(mut self, key: &str, value: OptionValue) -> Self {
+        self.service.add_option(key, value);
+        self
+    }
+
+    /// Unary rpc
+    pub fn rpc(self, name: &str, input_type: &str, output_type: &str) -> Self {
+        self.rpc_with(name, input_type, output_type, |m| m)
+    }
+
+    pub fn rpc_with(
+        mut self,
+        name: &str,
+        input_type: &str,
+        output_type: &str,
+        configure: impl FnOnce(MethodBuilder) -> MethodBuilder,
+    ) -> Self {
+        self.methods.push(configure(MethodBuilder {
+            method: Method::new(name, input_type, output_type),
+        }));
+        self
+    }
+
+    /// Adds the rpcs, failing on duplicate names
+    pub fn build(self) -> Result<Service, ConverterError> {
+        let mut service = self.service;
+        for builder in self.methods {
+            service.add_method(builder.method)?;
+        }
+        Ok(service)
+    }
+}
+
+/// Rpc of a [`ServiceBuilder`]
+#[derive(Debug, Clone)]
+pub struct MethodBuilder {
+    method: Method,
+}
+
+impl MethodBuilder {
+    pub fn comment(mut self, comment: &str) -> Self {
+        self.method.add_comment(comment);
+        self
+    }
+
+    /// `rpc X (stream In) ...`
+    pub fn client_streaming(mut self) -> Self {
+        self.method.client_streaming = true;
+        self
+    }
+
+    /// `... returns (stream Out)`
+    pub fn server_streaming(mut self) -> Self {
+        self.method.server_streaming = true;
+        self
+    }
+
+    pub fn option(mut self, key: &str, value: OptionValue) -> Self {
+        self.method.add_option(key, value);
+        self
+    }
+
+    pub fn string_option(mut self, key: &str, value: &str) -> Self {
+        self.method.add_string_option(key, value);
+        self
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::{
+    BasePath, ConverterBuilder, ConverterError, ConverterOptions, FormatOverride, HeaderParams,
+    NamingStyle, NullableStrategy, Pagination, RequiredFields, ServiceGrouping,
+    SwaggerToProtoConverter, Syntax,
+};
+
+/// Converter settings read from a `proto-gen.toml` or `.yaml` file.
+///
+/// Keys are the snake_case names of [`ConverterOptions`] fields, plus
+/// `package`, `include_tags`, `rename` and `naming`. Unknown keys are rejected so a
+/// typo does not go unnoticed:
+///
+/// ```toml
+/// package = "shop.v1"
+/// include_tags = ["orders"]
+/// nullable = "wrappers"
+/// http_annotations = true
+///
+/// [format_overrides]
+/// decimal = { type = "money.Decimal", import = "money/decimal.proto" }
+///
+/// [rename]
+/// UserDTO = "User"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConverterConfig {
+    pub package: Option<String>,
+    pub package_from_info: bool,
+    pub go_package: Option<String>,
+    pub java_package: Option<String>,
+    pub java_multiple_files: bool,
+    pub target_syntax: Option<Syntax>,
+    /// See [`ConverterOptions::only_tags`]
+    pub include_tags: Vec<String>,
+    pub exclude_tags: Vec<String>,
+    pub skip_internal: bool,
+    pub only_referenced: bool,
+    pub service_grouping: ServiceGrouping,
+    pub base_path: BasePath,
+    pub http_annotations: bool,
+    pub patch_field_mask: bool,
+    /// `[pagination]` table, an empty one enables the default names
+    pub pagination: Option<Pagination>,
+    pub response_metadata: bool,
+    pub header_params: HeaderParams,
+    pub required_fields: RequiredFields,
+    pub nullable: NullableStrategy,
+    pub wrap_repeated: bool,
+    pub nest_inline_messages: bool,
+    pub split_read_write: bool,
+    pub inline_aliases: bool,
+    pub prefer_unsigned: bool,
+    pub validation_rules: bool,
+    pub format_overrides: BTreeMap<String, FormatOverride>,
+    pub extension_options: BTreeMap<String, String>,
+    pub security_option: Option<String>,
+    pub enum_value_option: Option<String>,
+    /// See [`ConverterOptions::info_header`], on when unset
+    pub info_header: Option<bool>,
+    pub include_examples: bool,
+    pub strict: bool,
+    pub validate_output: bool,
+    /// See [`ConverterOptions::schema_renames`]
+    pub rename: BTreeMap<String, String>,
+    pub reserved_suffix: Option<String>,
+    pub max_depth: Option<usize>,
+    pub parallelism: usize,
+    /// `default` or `google`, see [`NamingStyle`]
+    pub naming: NamingStyle,
+}
+
+impl ConverterConfig {
+    /// Reads `.yaml`/`.yml` files as YAML, `.json` as JSON and anything
+    /// else as TOML
+    pub fn from_file(path: &Path) -> Result<Self, ConverterError> {
+        let read = || -> Result<Self, ConverterError> {
+            let content = std::fs::read_to_string(path)?;
+            match path.extension().and_then(|e| e.to_str()) {
+                Some("yaml") | Some("yml") => Ok(serde_yaml::from_str(&content)?),
+                Some("json") => Ok(serde_json::from_str(&content)?),
+                _ => Self::from_toml(&content),
+            }
+        };
+        read().map_err(|e| e.in_file(path))
+    }
+
+    pub fn from_toml(toml: &str) -> Result<Self, ConverterError> {
+        Ok(toml::from_str(toml)?)
+    }
+
+    /// Everything but the package
+    pub fn options(&self) -> ConverterOptions {
+        ConverterOptions {
+            header_params: self.header_params,
+            wrap_repeated: self.wrap_repeated,
+            required_fields: self.required_fields,
+            nullable: self.nullable,
+            validation_rules: self.validation_rules,
+            nest_inline_messages: self.nest_inline_messages,
+            service_grouping: self.service_grouping.clone(),
+            strict: self.strict,
+            split_read_write: self.split_read_write,
+            extension_options: self.extension_options.clone(),
+            skip_internal: self.skip_internal,
+            only_tags: self.include_tags.clone(),
+            exclude_tags: self.exclude_tags.clone(),
+            package_from_info: self.package_from_info,
+            go_package: self.go_package.clone(),
+            java_package: self.java_package.clone(),
+            java_multiple_files: self.java_multiple_files,
+            info_header: self.info_header.unwrap_or(true),
+            only_referenced: self.only_referenced,
+            base_path: self.base_path.clone(),
+            security_option: self.security_option.clone(),
+            enum_value_option: self.enum_value_option.clone(),
+            format_overrides: self.format_overrides.clone(),
+            prefer_unsigned: self.prefer_unsigned,
+            inline_aliases: self.inline_aliases,
+            include_examples: self.include_examples,
+            validate_output: self.validate_output,
+            target_syntax: self.target_syntax,
+            http_annotations: self.http_annotations,
+            schema_renames: self.rename.clone(),
+            reserved_suffix: self.reserved_suffix.clone(),
+            patch_field_mask: self.patch_field_mask,
+            pagination: self.pagination.clone(),
+            response_metadata: self.response_metadata,
+            max_depth: self.max_depth,
+            parallelism: self.parallelism,
+        }
+    }
+
+    /// Builder with every setting applied, for further overrides
+    pub fn builder(&self) -> ConverterBuilder {
+        SwaggerToProtoConverter::builder()
+            .package(self.package.as_deref().unwrap_or_default())
+            .options(self.options())
+            .naming(self.naming.strategy())
+    }
+}
+
+impl SwaggerToProtoConverter {
+    pub fn from_config(config: &ConverterConfig) -> Self {
+        config.builder().build()
+    }
+}
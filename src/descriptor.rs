@@ -0,0 +1,695 @@
+use std::collections::HashSet;
+
+use prost_types::descriptor_proto::{ExtensionRange, ReservedRange};
+use prost_types::enum_descriptor_proto::EnumReservedRange;
+use prost_types::field_descriptor_proto::{Label, Type};
+use prost_types::{
+    DescriptorProto, EnumDescriptorProto, EnumOptions, EnumValueDescriptorProto,
+    FieldDescriptorProto, FieldOptions, FileDescriptorProto, FileOptions, MessageOptions,
+    MethodDescriptorProto, MethodOptions, OneofDescriptorProto, ServiceDescriptorProto,
+    ServiceOptions,
+};
+
+use crate::proto2swagger::TypePaths;
+use crate::util::{join_path, to_json_name};
+use crate::validation::MAX_FIELD_NUMBER;
+use crate::{
+    Enum, EnumValue, Error, Field, FieldRule, FieldType, Message, Method, NumberRange, Oneof,
+    OptionValue, ProtoFile, ScalarType, Service,
+};
+
+impl ProtoFile {
+    /// Builds the `FileDescriptorProto` protoc would produce for this file.
+    ///
+    /// Type names are resolved to fully qualified `.package.Type` names,
+    /// `map<K, V>` fields get their synthetic `*Entry` messages and proto3
+    /// `optional` fields their synthetic oneofs. Options without a
+    /// descriptor counterpart are dropped.
+    pub fn to_file_descriptor_proto(&self) -> FileDescriptorProto {
+        let types = DescriptorTypes::new(self);
+        let name = if self.package.is_empty() {
+            "api.proto".to_string()
+        } else {
+            format!("{}.proto", self.package.replace('.', "/"))
+        };
+
+        let options = FileOptions {
+            java_package: self.options.get("java_package").cloned(),
+            java_outer_classname: self.options.get("java_outer_classname").cloned(),
+            java_multiple_files: self.options.get("java_multiple_files").map(|v| v == "true"),
+            go_package: self.options.get("go_package").cloned(),
+            csharp_namespace: self.options.get("csharp_namespace").cloned(),
+            ..Default::default()
+        };
+
+        FileDescriptorProto {
+            name: Some(name),
+            package: (!self.package.is_empty()).then(|| self.package.clone()),
+            dependency: self.imports.clone(),
+            message_type: self
+                .messages
+                .iter()
+                .map(|m| types.message_descriptor(m, "", self.is_proto3()))
+                .collect(),
+            enum_type: self.enums.iter().map(enum_descriptor).collect(),
+            service: self
+                .services
+                .iter()
+                .map(|s| types.service_descriptor(s))
+                .collect(),
+            options: (options != FileOptions::default()).then_some(options),
+            syntax: Some(self.syntax.clone()).filter(|s| !s.is_empty()),
+            ..Default::default()
+        }
+    }
+
+    /// Rebuilds a ProtoFile from a descriptor, e.g. one taken from a
+    /// `FileDescriptorSet` produced by buf or protoc.
+    pub fn from_file_descriptor_proto(descriptor: &FileDescriptorProto) -> Result<Self, Error> {
+        let package = descriptor.package().to_string();
+        let syntax = match descriptor.syntax() {
+            "" => "proto2",
+            syntax => syntax,
+        };
+        let mut proto = ProtoFile {
+            syntax: syntax.to_string(),
+            package: package.clone(),
+            imports: descriptor.dependency.clone(),
+            ..Default::default()
+        };
+
+        if let Some(options) = &descriptor.options {
+            let file_options = [
+                ("java_package", options.java_package.clone()),
+                ("java_outer_classname", options.java_outer_classname.clone()),
+                (
+                    "java_multiple_files",
+                    options.java_multiple_files.map(|v| v.to_string()),
+                ),
+                ("go_package", options.go_package.clone()),
+                ("csharp_namespace", options.csharp_namespace.clone()),
+            ];
+            for (key, value) in file_options {
+                if let Some(value) = value {
+                    proto.add_option(key, &value);
+                }
+            }
+        }
+
+        let proto3 = syntax == "proto3";
+        for message in &descriptor.message_type {
+            proto.add_message(message_from_descriptor(message, &package, proto3)?)?;
+        }
+        for enum_def in &descriptor.enum_type {
+            proto.add_enum(enum_from_descriptor(enum_def)?)?;
+        }
+        for service in &descriptor.service {
+            proto.add_service(service_from_descriptor(service, &package)?)?;
+        }
+
+        Ok(proto)
+    }
+
+    fn is_proto3(&self) -> bool {
+        self.syntax.is_empty() || self.syntax == "proto3"
+    }
+}
+
+/// Type lookup for descriptor generation: local messages and enums by
+/// dotted path
+struct DescriptorTypes {
+    paths: TypePaths,
+    enums: HashSet<String>,
+    package: String,
+}
+
+impl DescriptorTypes {
+    fn new(proto: &ProtoFile) -> Self {
+        fn collect_enums(message: &Message, prefix: &str, enums: &mut HashSet<String>) {
+            let path = join_path(prefix, &message.name);
+            for enum_def in &message.nested_enums {
+                enums.insert(join_path(&path, &enum_def.name));
+            }
+            for nested in &message.nested_messages {
+                collect_enums(nested, &path, enums);
+            }
+        }
+
+        let mut enums: HashSet<String> = proto.enums.iter().map(|e| e.name.clone()).collect();
+        for message in &proto.messages {
+            collect_enums(message, "", &mut enums);
+        }
+
+        Self {
+            paths: TypePaths::new(proto),
+            enums,
+            package: proto.package.clone(),
+        }
+    }
+
+    /// Fully qualified name of a type and whether it is an enum
+    fn qualified(&self, type_: &str, scope: &str) -> (String, bool) {
+        let path = self.paths.resolve(type_, scope);
+        if self.paths.contains(&path) {
+            let qualified = if self.package.is_empty() {
+                format!(".{}", path)
+            } else {
+                format!(".{}.{}", self.package, path)
+            };
+            return (qualified, self.enums.contains(&path));
+        }
+        // Foreign types are taken as written; of the well-known types only
+        // NullValue is an enum
+        let type_ = type_.trim_start_matches('.');
+        (format!(".{}", type_), type_ == "google.protobuf.NullValue")
+    }
+
+    fn message_descriptor(&self, message: &Message, prefix: &str, proto3: bool) -> DescriptorProto {
+        let path = join_path(prefix, &message.name);
+        let mut descriptor = DescriptorProto {
+            name: Some(message.name.clone()),
+            oneof_decl: message
+                .oneofs
+                .iter()
+                .map(|oneof| OneofDescriptorProto {
+                    name: Some(oneof.name.clone()),
+                    options: None,
+                })
+                .collect(),
+            reserved_range: message
+                .reserved_ranges
+                .iter()
+                .map(|range| {
+                    let (start, end) = field_range(range);
+                    ReservedRange {
+                        start: Some(start),
+                        end: Some(end),
+                    }
+                })
+                .collect(),
+            reserved_name: message.reserved_names.clone(),
+            extension_range: message
+                .extension_ranges
+                .iter()
+                .map(|range| {
+                    let (start, end) = field_range(range);
+                    ExtensionRange {
+                        start: Some(start),
+                        end: Some(end),
+                        options: None,
+                    }
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let deprecated = message
+            .options
+            .get("deprecated")
+            .and_then(OptionValue::as_bool);
+        if deprecated.is_some() {
+            descriptor.options = Some(MessageOptions {
+                deprecated,
+                ..Default::default()
+            });
+        }
+
+        let oneof_fields = message
+            .oneofs
+            .iter()
+            .enumerate()
+            .flat_map(|(i, oneof)| oneof.fields.iter().map(move |f| (f, Some(i as i32))));
+        let fields: Vec<(&Field, Option<i32>)> = message
+            .fields
+            .iter()
+            .map(|f| (f, None))
+            .chain(oneof_fields)
+            .collect();
+
+        // Synthetic oneofs of proto3 `optional` fields follow the real ones
+        let mut synthetic_oneofs = Vec::new();
+        for (field, oneof_index) in fields {
+            let mut field_descriptor = self.field_descriptor(field, &path, &mut descriptor);
+            field_descriptor.oneof_index = oneof_index;
+            if proto3 && field.rule == FieldRule::Optional && oneof_index.is_none() {
+                field_descriptor.proto3_optional = Some(true);
+                field_descriptor.oneof_index =
+                    Some((descriptor.oneof_decl.len() + synthetic_oneofs.len()) as i32);
+                synthetic_oneofs.push(OneofDescriptorProto {
+                    name: Some(format!("_{}", field.name)),
+                    options: None,
+                });
+            }
+            descriptor.field.push(field_descriptor);
+        }
+        descriptor.oneof_decl.extend(synthetic_oneofs);
+
+        descriptor.nested_type.extend(
+            message
+                .nested_messages
+                .iter()
+                .map(|nested| self.message_descriptor(nested, &path, proto3)),
+        );
+        descriptor
+            .enum_type
+            .extend(message.nested_enums.iter().map(enum_descriptor));
+        descriptor
+    }
+
+    /// Field descriptor; map fields add their entry message to `parent`
+    fn field_descriptor(
+        &self,
+        field: &Field,
+        scope: &str,
+        parent: &mut DescriptorProto,
+    ) -> FieldDescriptorProto {
+        let mut descriptor = FieldDescriptorProto {
+            name: Some(field.name.clone()),
+            number: Some(field.number),
+            json_name: Some(to_json_name(&field.name)),
+            ..Default::default()
+        };
+
+        if let FieldType::Map {
+            key: key_type,
+            value: value_type,
+        } = &field.type_
+        {
+            let entry_name = format!("{}Entry", capitalize(&to_json_name(&field.name)));
+            let mut key = FieldDescriptorProto {
+                name: Some("key".to_string()),
+                number: Some(1),
+                json_name: Some("key".to_string()),
+                label: Some(Label::Optional as i32),
+                ..Default::default()
+            };
+            self.set_type(&mut key, &FieldType::Scalar(*key_type), scope);
+            let mut value = FieldDescriptorProto {
+                name: Some("value".to_string()),
+                number: Some(2),
+                json_name: Some("value".to_string()),
+                label: Some(Label::Optional as i32),
+                ..Default::default()
+            };
+            self.set_type(&mut value, value_type, scope);
+
+            parent.nested_type.push(DescriptorProto {
+                name: Some(entry_name.clone()),
+                field: vec![key, value],
+                options: Some(MessageOptions {
+                    map_entry: Some(true),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            });
+
+            let entry_path = join_path(scope, &entry_name);
+            descriptor.label = Some(Label::Repeated as i32);
+            descriptor.r#type = Some(Type::Message as i32);
+            descriptor.type_name = Some(match self.package.as_str() {
+                "" => format!(".{}", entry_path),
+                package => format!(".{}.{}", package, entry_path),
+            });
+        } else {
+            let label = match field.rule {
+                FieldRule::Repeated => Label::Repeated,
+                FieldRule::Required => Label::Required,
+                FieldRule::Optional | FieldRule::Singular => Label::Optional,
+            };
+            descriptor.label = Some(label as i32);
+            self.set_type(&mut descriptor, &field.type_, scope);
+        }
+
+        let deprecated = field
+            .options
+            .get("deprecated")
+            .and_then(OptionValue::as_bool);
+        let packed = field.options.get("packed").and_then(OptionValue::as_bool);
+        if deprecated.is_some() || packed.is_some() {
+            descriptor.options = Some(FieldOptions {
+                deprecated,
+                packed,
+                ..Default::default()
+            });
+        }
+        if let Some(json_name) = field.options.get("json_name").and_then(OptionValue::as_str) {
+            descriptor.json_name = Some(json_name.to_string());
+        }
+        descriptor
+    }
+
+    /// Type of a non-map field
+    fn set_type(&self, descriptor: &mut FieldDescriptorProto, type_: &FieldType, scope: &str) {
+        let name = match type_ {
+            FieldType::Scalar(scalar) => {
+                descriptor.r#type = Some(scalar_to_descriptor(*scalar) as i32);
+                return;
+            }
+            // Map values that are maps do not parse, the text is all there is
+            other => other.to_string(),
+        };
+        let (type_name, is_enum) = self.qualified(&name, scope);
+        let kind = if is_enum { Type::Enum } else { Type::Message };
+        descriptor.r#type = Some(kind as i32);
+        descriptor.type_name = Some(type_name);
+    }
+
+    fn service_descriptor(&self, service: &Service) -> ServiceDescriptorProto {
+        let method = service
+            .methods
+            .iter()
+            .map(|method| {
+                let deprecated = method
+                    .options
+                    .get("deprecated")
+                    .and_then(OptionValue::as_bool);
+                MethodDescriptorProto {
+                    name: Some(method.name.clone()),
+                    input_type: Some(self.qualified(&method.input_type, "").0),
+                    output_type: Some(self.qualified(&method.output_type, "").0),
+                    client_streaming: method.client_streaming.then_some(true),
+                    server_streaming: method.server_streaming.then_some(true),
+                    options: deprecated.map(|deprecated| MethodOptions {
+                        deprecated: Some(deprecated),
+                        ..Default::default()
+                    }),
+                }
+            })
+            .collect();
+
+        let deprecated = service
+            .options
+            .get("deprecated")
+            .and_then(OptionValue::as_bool);
+        ServiceDescriptorProto {
+            name: Some(service.name.clone()),
+            method,
+            options: deprecated.map(|deprecated| ServiceOptions {
+                deprecated: Some(deprecated),
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+/// Half-open descriptor range of a message range; `max` is the largest
+/// field number
+fn field_range(range: &NumberRange) -> (i32, i32) {
+    (
+        range.start,
+        range.end.min(MAX_FIELD_NUMBER).saturating_add(1),
+    )
+}
+
+fn enum_descriptor(enum_def: &Enum) -> EnumDescriptorProto {
+    let bool_option = |key| enum_def.options.get(key).and_then(OptionValue::as_bool);
+    let (allow_alias, deprecated) = (bool_option("allow_alias"), bool_option("deprecated"));
+    EnumDescriptorProto {
+        name: Some(enum_def.name.clone()),
+        options: (allow_alias.is_some() || deprecated.is_some()).then(|| EnumOptions {
+            allow_alias,
+            deprecated,
+            ..Default::default()
+        }),
+        // Диапазоны enum в дескрипторе включают конец
+        reserved_range: enum_def
+            .reserved_ranges
+            .iter()
+            .map(|range| EnumReservedRange {
+                start: Some(range.start),
+                end: Some(range.end),
+            })
+            .collect(),
+        reserved_name: enum_def.reserved_names.clone(),
+        value: enum_def
+            .values
+            .iter()
+            .map(|value| EnumValueDescriptorProto {
+                name: Some(value.name.clone()),
+                number: Some(value.number),
+                options: None,
+            })
+            .collect(),
+    }
+}
+
+fn message_from_descriptor(
+    descriptor: &DescriptorProto,
+    package: &str,
+    proto3: bool,
+) -> Result<Message, Error> {
+    let mut message = Message::new(descriptor.name());
+    if descriptor.options.as_ref().and_then(|o| o.deprecated) == Some(true) {
+        message.add_option("deprecated", OptionValue::Bool(true));
+    }
+    // Полуоткрытые диапазоны; конец за последним номером поля — это `max`
+    let range = |start: i32, end: i32| match end.saturating_sub(1) {
+        MAX_FIELD_NUMBER => NumberRange::new(start, i32::MAX),
+        end => NumberRange::new(start, end),
+    };
+    message.reserved_ranges = descriptor
+        .reserved_range
+        .iter()
+        .map(|r| range(r.start(), r.end()))
+        .collect();
+    message.reserved_names = descriptor.reserved_name.clone();
+    message.extension_ranges = descriptor
+        .extension_range
+        .iter()
+        .map(|r| range(r.start(), r.end()))
+        .collect();
+
+    let map_entries: Vec<&DescriptorProto> = descriptor
+        .nested_type
+        .iter()
+        .filter(|nested| nested.options.as_ref().and_then(|o| o.map_entry) == Some(true))
+        .collect();
+
+    let mut oneofs: Vec<Option<Oneof>> = descriptor
+        .oneof_decl
+        .iter()
+        .map(|oneof| Some(Oneof::new(oneof.name())))
+        .collect();
+
+    for field_descriptor in &descriptor.field {
+        let map_entry = map_entries.iter().find(|entry| {
+            field_descriptor
+                .type_name()
+                .rsplit('.')
+                .next()
+                .is_some_and(|name| name == entry.name())
+        });
+
+        let (type_, rule) = match map_entry {
+            Some(entry) if field_descriptor.label() == Label::Repeated => {
+                let key = entry.field.iter().find(|f| f.number() == 1);
+                let value = entry.field.iter().find(|f| f.number() == 2);
+                let (Some(key), Some(value)) = (key, value) else {
+                    return Err(Error::Descriptor(format!(
+                        "map entry {} needs key and value fields",
+                        entry.name()
+                    )));
+                };
+                let FieldType::Scalar(key_type) = field_type(key, package)? else {
+                    return Err(Error::Descriptor(format!(
+                        "map entry {} has a non-scalar key",
+                        entry.name()
+                    )));
+                };
+                let type_ = FieldType::map(key_type, field_type(value, package)?);
+                (type_, FieldRule::Singular)
+            }
+            _ => {
+                let rule = match field_descriptor.label() {
+                    Label::Repeated => FieldRule::Repeated,
+                    Label::Required => FieldRule::Required,
+                    Label::Optional if proto3 && !field_descriptor.proto3_optional() => {
+                        FieldRule::Singular
+                    }
+                    Label::Optional => FieldRule::Optional,
+                };
+                (field_type(field_descriptor, package)?, rule)
+            }
+        };
+
+        let mut field = Field::new(
+            field_descriptor.name(),
+            type_,
+            field_descriptor.number(),
+            rule,
+        );
+        if field_descriptor.options.as_ref().and_then(|o| o.deprecated) == Some(true) {
+            field.add_option("deprecated", OptionValue::Bool(true));
+        }
+        if field_descriptor.json_name.is_some()
+            && field_descriptor.json_name() != to_json_name(field_descriptor.name())
+        {
+            field.add_string_option("json_name", field_descriptor.json_name());
+        }
+
+        match field_descriptor.oneof_index {
+            Some(index) if !field_descriptor.proto3_optional() => {
+                let oneof = oneofs
+                    .get_mut(index as usize)
+                    .and_then(Option::as_mut)
+                    .ok_or_else(|| {
+                        Error::Descriptor(format!(
+                            "field {} refers to missing oneof {}",
+                            field_descriptor.name(),
+                            index
+                        ))
+                    })?;
+                oneof.add_field(field)?;
+            }
+            _ => message.add_field(field)?,
+        }
+    }
+
+    // Synthetic oneofs only hold the proto3 `optional` field
+    for oneof in oneofs.iter_mut().filter_map(Option::take) {
+        if !oneof.fields.is_empty() {
+            message.add_oneof(oneof)?;
+        }
+    }
+
+    for nested in &descriptor.nested_type {
+        if !map_entries
+            .iter()
+            .any(|entry| entry.name() == nested.name())
+        {
+            message.add_nested_message(message_from_descriptor(nested, package, proto3)?)?;
+        }
+    }
+    for enum_def in &descriptor.enum_type {
+        message.add_nested_enum(enum_from_descriptor(enum_def)?)?;
+    }
+
+    Ok(message)
+}
+
+fn enum_from_descriptor(descriptor: &EnumDescriptorProto) -> Result<Enum, Error> {
+    let mut enum_def = Enum::new(descriptor.name());
+    if let Some(options) = &descriptor.options {
+        if options.allow_alias == Some(true) {
+            enum_def.add_option("allow_alias", OptionValue::Bool(true));
+        }
+        if options.deprecated == Some(true) {
+            enum_def.add_option("deprecated", OptionValue::Bool(true));
+        }
+    }
+    enum_def.reserved_ranges = descriptor
+        .reserved_range
+        .iter()
+        .map(|range| NumberRange::new(range.start(), range.end()))
+        .collect();
+    enum_def.reserved_names = descriptor.reserved_name.clone();
+    for value in &descriptor.value {
+        enum_def.add_value(EnumValue::new(value.name(), value.number()))?;
+    }
+    Ok(enum_def)
+}
+
+fn service_from_descriptor(
+    descriptor: &ServiceDescriptorProto,
+    package: &str,
+) -> Result<Service, Error> {
+    let mut service = Service::new(descriptor.name());
+    if descriptor.options.as_ref().and_then(|o| o.deprecated) == Some(true) {
+        service.add_option("deprecated", OptionValue::Bool(true));
+    }
+    for method_descriptor in &descriptor.method {
+        let mut method = Method::new(
+            method_descriptor.name(),
+            &local_type_name(method_descriptor.input_type(), package),
+            &local_type_name(method_descriptor.output_type(), package),
+        );
+        method.client_streaming = method_descriptor.client_streaming();
+        method.server_streaming = method_descriptor.server_streaming();
+        if method_descriptor
+            .options
+            .as_ref()
+            .and_then(|o| o.deprecated)
+            == Some(true)
+        {
+            method.add_option("deprecated", OptionValue::Bool(true));
+        }
+        service.add_method(method)?;
+    }
+    Ok(service)
+}
+
+fn field_type(descriptor: &FieldDescriptorProto, package: &str) -> Result<FieldType, Error> {
+    let scalar = match descriptor.r#type() {
+        Type::Double => ScalarType::Double,
+        Type::Float => ScalarType::Float,
+        Type::Int64 => ScalarType::Int64,
+        Type::Uint64 => ScalarType::Uint64,
+        Type::Int32 => ScalarType::Int32,
+        Type::Fixed64 => ScalarType::Fixed64,
+        Type::Fixed32 => ScalarType::Fixed32,
+        Type::Bool => ScalarType::Bool,
+        Type::String => ScalarType::String,
+        Type::Bytes => ScalarType::Bytes,
+        Type::Uint32 => ScalarType::Uint32,
+        Type::Sfixed32 => ScalarType::Sfixed32,
+        Type::Sfixed64 => ScalarType::Sfixed64,
+        Type::Sint32 => ScalarType::Sint32,
+        Type::Sint64 => ScalarType::Sint64,
+        Type::Message | Type::Enum if descriptor.type_name.is_some() => {
+            return Ok(FieldType::Named(local_type_name(
+                descriptor.type_name(),
+                package,
+            )));
+        }
+        Type::Message | Type::Enum => {
+            return Err(Error::Descriptor(format!(
+                "field {} has no type_name",
+                descriptor.name()
+            )));
+        }
+        Type::Group => {
+            return Err(Error::Descriptor(format!(
+                "field {}: groups are not supported",
+                descriptor.name()
+            )));
+        }
+    };
+    Ok(FieldType::Scalar(scalar))
+}
+
+/// `.pkg.Order.Item` -> `Order.Item`; types from other packages keep their
+/// full name without the leading dot
+fn local_type_name(type_name: &str, package: &str) -> String {
+    let type_name = type_name.trim_start_matches('.');
+    match type_name.strip_prefix(&format!("{}.", package)) {
+        Some(local) if !package.is_empty() => local.to_string(),
+        _ => type_name.to_string(),
+    }
+}
+
+fn scalar_to_descriptor(scalar: ScalarType) -> Type {
+    match scalar {
+        ScalarType::Double => Type::Double,
+        ScalarType::Float => Type::Float,
+        ScalarType::Int64 => Type::Int64,
+        ScalarType::Uint64 => Type::Uint64,
+        ScalarType::Int32 => Type::Int32,
+        ScalarType::Fixed64 => Type::Fixed64,
+        ScalarType::Fixed32 => Type::Fixed32,
+        ScalarType::Bool => Type::Bool,
+        ScalarType::String => Type::String,
+        ScalarType::Bytes => Type::Bytes,
+        ScalarType::Uint32 => Type::Uint32,
+        ScalarType::Sfixed32 => Type::Sfixed32,
+        ScalarType::Sfixed64 => Type::Sfixed64,
+        ScalarType::Sint32 => Type::Sint32,
+        ScalarType::Sint64 => Type::Sint64,
+    }
+}
+
+fn capitalize(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,489 @@
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::proto2swagger::TypePaths;
+use crate::util::join_path;
+use crate::{Enum, Field, FieldRule, FieldType, Message, Method, ProtoFile, Service};
+
+/// A difference between two versions of a file.
+///
+/// Messages, enums and services are matched by qualified name
+/// (`api.Order.Item`), fields by number, enum values and methods by name.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Change {
+    PackageChanged {
+        old: String,
+        new: String,
+    },
+    MessageRemoved {
+        message: String,
+    },
+    MessageAdded {
+        message: String,
+    },
+    FieldRemoved {
+        message: String,
+        field: String,
+        number: i32,
+    },
+    FieldAdded {
+        message: String,
+        field: String,
+        number: i32,
+        required: bool,
+    },
+    /// The same field moved to another number
+    FieldNumberChanged {
+        message: String,
+        field: String,
+        old: i32,
+        new: i32,
+    },
+    /// The field keeps its number but its type (or label) changed
+    FieldTypeChanged {
+        message: String,
+        field: String,
+        old: String,
+        new: String,
+    },
+    /// The number is taken by a different field of a different type
+    FieldNumberReused {
+        message: String,
+        number: i32,
+        old_field: String,
+        new_field: String,
+        old_type: String,
+        new_type: String,
+    },
+    /// Same number and type under another name: the wire format is intact
+    FieldRenamed {
+        message: String,
+        number: i32,
+        old: String,
+        new: String,
+    },
+    FieldBecameRequired {
+        message: String,
+        field: String,
+    },
+    EnumRemoved {
+        enum_name: String,
+    },
+    EnumAdded {
+        enum_name: String,
+    },
+    EnumValueRemoved {
+        enum_name: String,
+        value: String,
+        number: i32,
+    },
+    EnumValueAdded {
+        enum_name: String,
+        value: String,
+        number: i32,
+    },
+    EnumValueNumberChanged {
+        enum_name: String,
+        value: String,
+        old: i32,
+        new: i32,
+    },
+    ServiceRemoved {
+        service: String,
+    },
+    ServiceAdded {
+        service: String,
+    },
+    MethodRemoved {
+        service: String,
+        method: String,
+    },
+    MethodAdded {
+        service: String,
+        method: String,
+    },
+    /// Request/response type or streaming mode changed
+    MethodSignatureChanged {
+        service: String,
+        method: String,
+        old: String,
+        new: String,
+    },
+    HttpBindingChanged {
+        service: String,
+        method: String,
+        old: String,
+        new: String,
+    },
+    CommentChanged {
+        path: String,
+    },
+}
+
+impl Change {
+    /// Whether clients built against the old file can break
+    pub fn is_breaking(&self) -> bool {
+        match self {
+            Change::FieldAdded { required, .. } => *required,
+            Change::MessageAdded { .. }
+            | Change::FieldRenamed { .. }
+            | Change::EnumAdded { .. }
+            | Change::EnumValueAdded { .. }
+            | Change::ServiceAdded { .. }
+            | Change::MethodAdded { .. }
+            | Change::CommentChanged { .. } => false,
+            _ => true,
+        }
+    }
+}
+
+/// Lists the changes from `old` to `new`, breaking and non-breaking
+pub fn compare(old: &ProtoFile, new: &ProtoFile) -> Vec<Change> {
+    let mut changes = Vec::new();
+    if old.package != new.package {
+        changes.push(Change::PackageChanged {
+            old: old.package.clone(),
+            new: new.package.clone(),
+        });
+    }
+
+    let qualify = |path: &str| join_path(&new.package, path);
+    let old_types = TypePaths::new(old);
+    let new_types = TypePaths::new(new);
+
+    let old_messages = collect_messages(old);
+    let new_messages = collect_messages(new);
+    for (path, old_message) in &old_messages {
+        match new_messages.get(path) {
+            Some(new_message) => compare_messages(
+                &qualify(path),
+                path,
+                old_message,
+                new_message,
+                (&old_types, &new_types),
+                &mut changes,
+            ),
+            None => changes.push(Change::MessageRemoved {
+                message: qualify(path),
+            }),
+        }
+    }
+    for path in new_messages
+        .keys()
+        .filter(|p| !old_messages.contains_key(*p))
+    {
+        changes.push(Change::MessageAdded {
+            message: qualify(path),
+        });
+    }
+
+    let old_enums = collect_enums(old);
+    let new_enums = collect_enums(new);
+    for (path, old_enum) in &old_enums {
+        match new_enums.get(path) {
+            Some(new_enum) => compare_enums(&qualify(path), old_enum, new_enum, &mut changes),
+            None => changes.push(Change::EnumRemoved {
+                enum_name: qualify(path),
+            }),
+        }
+    }
+    for path in new_enums.keys().filter(|p| !old_enums.contains_key(*p)) {
+        changes.push(Change::EnumAdded {
+            enum_name: qualify(path),
+        });
+    }
+
+    for old_service in &old.services {
+        match new.services.iter().find(|s| s.name == old_service.name) {
+            Some(new_service) => compare_services(
+                &qualify(&old_service.name),
+                old_service,
+                new_service,
+                (&old_types, &new_types),
+                &mut changes,
+            ),
+            None => changes.push(Change::ServiceRemoved {
+                service: qualify(&old_service.name),
+            }),
+        }
+    }
+    for new_service in &new.services {
+        if !old.services.iter().any(|s| s.name == new_service.name) {
+            changes.push(Change::ServiceAdded {
+                service: qualify(&new_service.name),
+            });
+        }
+    }
+
+    changes
+}
+
+/// `scope` is the message path relative to the package, the same on both
+/// sides even when the package changed
+fn compare_messages(
+    path: &str,
+    scope: &str,
+    old: &Message,
+    new: &Message,
+    (old_types, new_types): (&TypePaths, &TypePaths),
+    changes: &mut Vec<Change>,
+) {
+    if old.comments != new.comments {
+        changes.push(Change::CommentChanged {
+            path: path.to_string(),
+        });
+    }
+
+    let message = || path.to_string();
+
+    for old_field in old.all_fields() {
+        let same_number = new.find_field_by_number(old_field.number);
+        let same_name = new.find_field(&old_field.name);
+        let old_type = field_type(old_field, old_types, scope);
+
+        match (same_number, same_name) {
+            (Some(new_field), _) => {
+                let new_type = field_type(new_field, new_types, scope);
+                if new_field.name != old_field.name {
+                    if new_type == old_type {
+                        changes.push(Change::FieldRenamed {
+                            message: message(),
+                            number: old_field.number,
+                            old: old_field.name.clone(),
+                            new: new_field.name.clone(),
+                        });
+                    } else {
+                        changes.push(Change::FieldNumberReused {
+                            message: message(),
+                            number: old_field.number,
+                            old_field: old_field.name.clone(),
+                            new_field: new_field.name.clone(),
+                            old_type,
+                            new_type,
+                        });
+                    }
+                    continue;
+                }
+
+                if new_type != old_type {
+                    changes.push(Change::FieldTypeChanged {
+                        message: message(),
+                        field: old_field.name.clone(),
+                        old: old_type,
+                        new: new_type,
+                    });
+                }
+                if new_field.rule == FieldRule::Required && old_field.rule != FieldRule::Required {
+                    changes.push(Change::FieldBecameRequired {
+                        message: message(),
+                        field: old_field.name.clone(),
+                    });
+                }
+                if new_field.comments != old_field.comments {
+                    changes.push(Change::CommentChanged {
+                        path: join_path(path, &old_field.name),
+                    });
+                }
+            }
+            (None, Some(new_field)) => changes.push(Change::FieldNumberChanged {
+                message: message(),
+                field: old_field.name.clone(),
+                old: old_field.number,
+                new: new_field.number,
+            }),
+            (None, None) => changes.push(Change::FieldRemoved {
+                message: message(),
+                field: old_field.name.clone(),
+                number: old_field.number,
+            }),
+        }
+    }
+
+    for new_field in new.all_fields() {
+        let known = old
+            .all_fields()
+            .any(|f| f.number == new_field.number || f.name == new_field.name);
+        if !known {
+            changes.push(Change::FieldAdded {
+                message: message(),
+                field: new_field.name.clone(),
+                number: new_field.number,
+                required: new_field.rule == FieldRule::Required,
+            });
+        }
+    }
+}
+
+fn compare_enums(path: &str, old: &Enum, new: &Enum, changes: &mut Vec<Change>) {
+    if old.comments != new.comments {
+        changes.push(Change::CommentChanged {
+            path: path.to_string(),
+        });
+    }
+
+    for old_value in &old.values {
+        match new.values.iter().find(|v| v.name == old_value.name) {
+            Some(new_value) if new_value.number != old_value.number => {
+                changes.push(Change::EnumValueNumberChanged {
+                    enum_name: path.to_string(),
+                    value: old_value.name.clone(),
+                    old: old_value.number,
+                    new: new_value.number,
+                })
+            }
+            Some(_) => {}
+            None => changes.push(Change::EnumValueRemoved {
+                enum_name: path.to_string(),
+                value: old_value.name.clone(),
+                number: old_value.number,
+            }),
+        }
+    }
+    for new_value in &new.values {
+        if !old.values.iter().any(|v| v.name == new_value.name) {
+            changes.push(Change::EnumValueAdded {
+                enum_name: path.to_string(),
+                value: new_value.name.clone(),
+                number: new_value.number,
+            });
+        }
+    }
+}
+
+fn compare_services(
+    path: &str,
+    old: &Service,
+    new: &Service,
+    (old_types, new_types): (&TypePaths, &TypePaths),
+    changes: &mut Vec<Change>,
+) {
+    if old.comments != new.comments {
+        changes.push(Change::CommentChanged {
+            path: path.to_string(),
+        });
+    }
+
+    for old_method in &old.methods {
+        let Some(new_method) = new.find_method(&old_method.name) else {
+            changes.push(Change::MethodRemoved {
+                service: path.to_string(),
+                method: old_method.name.clone(),
+            });
+            continue;
+        };
+
+        let old_signature = signature(old_method, old_types);
+        let new_signature = signature(new_method, new_types);
+        if old_signature != new_signature {
+            changes.push(Change::MethodSignatureChanged {
+                service: path.to_string(),
+                method: old_method.name.clone(),
+                old: old_signature,
+                new: new_signature,
+            });
+        }
+
+        let old_binding = http_binding(old_method);
+        let new_binding = http_binding(new_method);
+        if old_binding != new_binding {
+            changes.push(Change::HttpBindingChanged {
+                service: path.to_string(),
+                method: old_method.name.clone(),
+                old: old_binding,
+                new: new_binding,
+            });
+        }
+
+        if old_method.comments != new_method.comments {
+            changes.push(Change::CommentChanged {
+                path: join_path(path, &old_method.name),
+            });
+        }
+    }
+    for new_method in &new.methods {
+        if !old.methods.iter().any(|m| m.name == new_method.name) {
+            changes.push(Change::MethodAdded {
+                service: path.to_string(),
+                method: new_method.name.clone(),
+            });
+        }
+    }
+}
+
+/// Messages by dotted path relative to the package
+fn collect_messages(file: &ProtoFile) -> IndexMap<String, &Message> {
+    fn visit<'a>(message: &'a Message, prefix: &str, messages: &mut IndexMap<String, &'a Message>) {
+        let path = join_path(prefix, &message.name);
+        for nested in &message.nested_messages {
+            visit(nested, &path, messages);
+        }
+        messages.insert(path, message);
+    }
+
+    let mut messages = IndexMap::new();
+    for message in &file.messages {
+        visit(message, "", &mut messages);
+    }
+    messages
+}
+
+/// Enums by dotted path relative to the package
+fn collect_enums(file: &ProtoFile) -> IndexMap<String, &Enum> {
+    fn visit<'a>(message: &'a Message, prefix: &str, enums: &mut IndexMap<String, &'a Enum>) {
+        let path = join_path(prefix, &message.name);
+        for enum_def in &message.nested_enums {
+            enums.insert(join_path(&path, &enum_def.name), enum_def);
+        }
+        for nested in &message.nested_messages {
+            visit(nested, &path, enums);
+        }
+    }
+
+    let mut enums: IndexMap<String, &Enum> =
+        file.enums.iter().map(|e| (e.name.clone(), e)).collect();
+    for message in &file.messages {
+        visit(message, "", &mut enums);
+    }
+    enums
+}
+
+/// Label and type as they matter on the wire, with named types resolved;
+/// `optional` and plain proto3 fields are the same
+fn field_type(field: &Field, types: &TypePaths, scope: &str) -> String {
+    let resolve = |type_: &FieldType| match type_ {
+        FieldType::Named(name) => types.resolve(name, scope),
+        other => other.to_string(),
+    };
+
+    match (&field.type_, field.rule) {
+        (FieldType::Map { key, value }, _) => format!("map<{}, {}>", key, resolve(value)),
+        (type_, FieldRule::Repeated) => format!("repeated {}", resolve(type_)),
+        (type_, _) => resolve(type_),
+    }
+}
+
+fn signature(method: &Method, types: &TypePaths) -> String {
+    let stream = |streaming: bool| if streaming { "stream " } else { "" };
+    format!(
+        "({}{}) returns ({}{})",
+        stream(method.client_streaming),
+        types.resolve(&method.input_type, ""),
+        stream(method.server_streaming),
+        types.resolve(&method.output_type, "")
+    )
+}
+
+fn http_binding(method: &Method) -> String {
+    match (
+        method.options.get("http_method"),
+        method.options.get("http_path"),
+    ) {
+        (Some(http_method), Some(path)) => format!("{} {}", http_method, path),
+        _ => method
+            .options
+            .get("(google.api.http)")
+            .map(ToString::to_string)
+            .unwrap_or_default(),
+    }
+}
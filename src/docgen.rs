@@ -0,0 +1,212 @@
+use crate::proto2swagger::{TypePaths, http_rule};
+use crate::util::join_path;
+use crate::{Enum, Field, FieldRule, FieldType, Message, ProtoFile, Service};
+
+impl ProtoFile {
+    /// Markdown reference of the file: services with their methods and
+    /// HTTP bindings, then a table per message and enum.
+    ///
+    /// Every definition gets an anchor named after its dotted path
+    /// (`Order.Item`), which the type columns link to. Comments are used as
+    /// descriptions.
+    pub fn to_markdown(&self) -> String {
+        let types = TypePaths::new(self);
+        let mut output = String::new();
+
+        if self.package.is_empty() {
+            output.push_str("# API\n\n");
+        } else {
+            output.push_str(&format!("# Package `{}`\n\n", self.package));
+        }
+        push_comments(&mut output, &self.header_comments);
+
+        if !self.services.is_empty() {
+            output.push_str("## Services\n\n");
+            for service in &self.services {
+                service_section(&mut output, service, &types);
+            }
+        }
+
+        if !self.messages.is_empty() {
+            output.push_str("## Messages\n\n");
+            for message in &self.messages {
+                message_section(&mut output, message, "", 3, &types);
+            }
+        }
+
+        if !self.enums.is_empty() {
+            output.push_str("## Enums\n\n");
+            for enum_def in &self.enums {
+                enum_section(&mut output, enum_def, "", 3);
+            }
+        }
+
+        output
+    }
+}
+
+fn service_section(output: &mut String, service: &Service, types: &TypePaths) {
+    output.push_str(&format!(
+        "<a name=\"{}\"></a>\n### {}\n\n",
+        service.name, service.name
+    ));
+    push_comments(output, &service.comments);
+
+    output.push_str("| Method | Request | Response | HTTP | Description |\n");
+    output.push_str("|---|---|---|---|---|\n");
+    for method in &service.methods {
+        let stream = |streaming: bool| if streaming { "stream " } else { "" };
+        let http = http_rule(method)
+            .map(|rule| format!("`{} {}`", rule.method, rule.path))
+            .unwrap_or_default();
+        let description: Vec<&String> = method
+            .comments
+            .iter()
+            .filter(|c| !c.starts_with("HTTP:"))
+            .collect();
+        output.push_str(&format!(
+            "| {} | {}{} | {}{} | {} | {} |\n",
+            method.name,
+            stream(method.client_streaming),
+            name_link(&method.input_type, "", types),
+            stream(method.server_streaming),
+            name_link(&method.output_type, "", types),
+            http,
+            table_text(description.into_iter()),
+        ));
+    }
+    output.push('\n');
+}
+
+fn message_section(
+    output: &mut String,
+    message: &Message,
+    prefix: &str,
+    level: usize,
+    types: &TypePaths,
+) {
+    let path = join_path(prefix, &message.name);
+    push_heading(output, &path, level);
+    push_comments(output, &message.comments);
+
+    let oneof_fields = message
+        .oneofs
+        .iter()
+        .flat_map(|oneof| oneof.fields.iter().map(move |f| (f, Some(&oneof.name))));
+    let fields: Vec<(&Field, Option<&String>)> = message
+        .fields
+        .iter()
+        .map(|f| (f, None))
+        .chain(oneof_fields)
+        .collect();
+
+    if fields.is_empty() {
+        output.push_str("_No fields._\n\n");
+    } else {
+        output.push_str("| Field | Type | Number | Description |\n");
+        output.push_str("|---|---|---|---|\n");
+        for (field, oneof) in fields {
+            let label = match field.rule {
+                FieldRule::Singular => "",
+                FieldRule::Optional => "optional ",
+                FieldRule::Required => "required ",
+                FieldRule::Repeated => "repeated ",
+            };
+            let oneof_note = oneof.map(|name| format!("Part of oneof `{}`.", name));
+            output.push_str(&format!(
+                "| {} | {}{} | {} | {} |\n",
+                field.name,
+                label,
+                type_link(&field.type_, &path, types),
+                field.number,
+                table_text(field.comments.iter().chain(oneof_note.as_ref())),
+            ));
+        }
+        output.push('\n');
+    }
+
+    for nested in &message.nested_messages {
+        message_section(output, nested, &path, level + 1, types);
+    }
+    for enum_def in &message.nested_enums {
+        enum_section(output, enum_def, &path, level + 1);
+    }
+}
+
+fn enum_section(output: &mut String, enum_def: &Enum, prefix: &str, level: usize) {
+    let path = join_path(prefix, &enum_def.name);
+    push_heading(output, &path, level);
+    push_comments(output, &enum_def.comments);
+
+    // Значения из swagger: показываем исходную JSON-строку
+    let json_values = enum_def.values.iter().any(|v| v.original_value.is_some());
+    if json_values {
+        output.push_str("| Name | Number | JSON | Description |\n");
+        output.push_str("|---|---|---|---|\n");
+    } else {
+        output.push_str("| Name | Number | Description |\n");
+        output.push_str("|---|---|---|\n");
+    }
+    for value in &enum_def.values {
+        let json = match (&value.original_value, json_values) {
+            (Some(original), _) => format!(" `{}` |", table_text(std::iter::once(original))),
+            (None, true) => " |".to_string(),
+            (None, false) => String::new(),
+        };
+        output.push_str(&format!(
+            "| {} | {} |{} {} |\n",
+            value.name,
+            value.number,
+            json,
+            table_text(value.comments.iter()),
+        ));
+    }
+    output.push('\n');
+}
+
+/// Field type with the names of local definitions linked to their
+/// sections; `map<K, V>` links the value type
+fn type_link(type_: &FieldType, scope: &str, types: &TypePaths) -> String {
+    match type_ {
+        FieldType::Scalar(scalar) => format!("`{}`", scalar),
+        FieldType::Named(name) => name_link(name, scope, types),
+        FieldType::Map { key, value } => {
+            format!("map&lt;`{}`, {}&gt;", key, type_link(value, scope, types))
+        }
+    }
+}
+
+/// Link to the section of a message or enum of the file, the bare name
+/// for anything else
+fn name_link(name: &str, scope: &str, types: &TypePaths) -> String {
+    let path = types.resolve(name, scope);
+    if types.contains(&path) {
+        format!("[{}](#{})", path, path)
+    } else {
+        format!("`{}`", name)
+    }
+}
+
+fn push_heading(output: &mut String, path: &str, level: usize) {
+    output.push_str(&format!(
+        "<a name=\"{}\"></a>\n{} {}\n\n",
+        path,
+        "#".repeat(level.min(6)),
+        path
+    ));
+}
+
+fn push_comments(output: &mut String, comments: &[String]) {
+    if !comments.is_empty() {
+        output.push_str(&comments.join("\n"));
+        output.push_str("\n\n");
+    }
+}
+
+/// Joins comment lines into a single table cell
+fn table_text<'a>(lines: impl Iterator<Item = &'a String>) -> String {
+    lines
+        .map(|line| line.replace('|', "\\|"))
+        .collect::<Vec<_>>()
+        .join("<br>")
+}
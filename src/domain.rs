@@ -1,20 +1,53 @@
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 use std::fmt;
+use std::str::FromStr;
 
-use crate::{ConverterError, NameFormatter};
+use crate::formatter::{render, render_string};
+use crate::model_json::{MODEL_VERSION, compatible_model_version, is_false};
+use crate::{ConverterError, FormatOptions, NameFormatter};
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ProtoFile {
+    /// Shape of the serialized model, [`MODEL_VERSION`] for files built by
+    /// this crate; other versions are rejected when deserializing
+    #[serde(deserialize_with = "compatible_model_version")]
+    pub model_version: u32,
+    /// Comment block rendered above the `syntax` line
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub header_comments: Vec<String>,
     pub syntax: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub package: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub imports: Vec<String>,
-    // pub options: HashMap<String, String>,
+    /// File-level options (`go_package`, `java_multiple_files`, ...)
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub options: IndexMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub messages: Vec<Message>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub enums: Vec<Enum>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub services: Vec<Service>,
 }
 
+impl Default for ProtoFile {
+    fn default() -> Self {
+        Self {
+            model_version: MODEL_VERSION,
+            header_comments: Vec::new(),
+            syntax: String::new(),
+            package: String::new(),
+            imports: Vec::new(),
+            options: IndexMap::new(),
+            messages: Vec::new(),
+            enums: Vec::new(),
+            services: Vec::new(),
+        }
+    }
+}
+
 impl NameFormatter for ProtoFile {}
 
 impl ProtoFile {
@@ -22,15 +55,15 @@ impl ProtoFile {
         Self {
             syntax: "proto3".to_string(),
             package: package.to_string(),
-            imports: vec![
-                "google/protobuf/empty.proto".to_string(),
-                "google/protobuf/timestamp.proto".to_string(),
-                "google/protobuf/struct.proto".to_string(),
-            ],
             ..Default::default()
         }
     }
 
+    /// Sets a file-level option, replacing an earlier value
+    pub fn add_option(&mut self, key: &str, value: &str) {
+        self.options.insert(key.to_string(), value.to_string());
+    }
+
     pub fn add_import(&mut self, import_path: &str) {
         if !self.imports.contains(&import_path.to_string()) {
             self.imports.push(import_path.to_string());
@@ -46,7 +79,7 @@ impl ProtoFile {
     }
 
     pub fn add_enum(&mut self, enum_def: Enum) -> Result<(), ConverterError> {
-        if self.enums.iter().any(|e| e.name == enum_def.name) {
+        if self.find_enum(&enum_def.name).is_some() {
             return Err(ConverterError::DuplicateMessageName(enum_def.name));
         }
         self.enums.push(enum_def);
@@ -69,57 +102,141 @@ impl ProtoFile {
         self.messages.iter().find(|m| m.name == name)
     }
 
-    pub fn find_service_mut(&mut self, name: &str) -> Option<&mut Service> {
-        self.services.iter_mut().find(|s| s.name == name)
-    }
+    /// Every message with its dotted path (`Outer.Inner`), parents first
+    pub fn all_messages(&self) -> impl Iterator<Item = (String, &Message)> {
+        fn visit<'a>(message: &'a Message, path: String, out: &mut Vec<(String, &'a Message)>) {
+            out.push((path.clone(), message));
+            for nested in &message.nested_messages {
+                visit(nested, format!("{}.{}", path, nested.name), out);
+            }
+        }
 
-    pub fn find_service(&self, name: &str) -> Option<&Service> {
-        self.services.iter().find(|s| s.name == name)
+        let mut messages = Vec::new();
+        for message in &self.messages {
+            visit(message, message.name.clone(), &mut messages);
+        }
+        messages.into_iter()
     }
 
-    pub fn to_proto_text(&self) -> String {
-        let mut output = String::new();
+    /// Every enum, top-level and nested, with its dotted path
+    pub fn all_enums(&self) -> impl Iterator<Item = (String, &Enum)> {
+        let nested = self.all_messages().flat_map(|(path, message)| {
+            message
+                .nested_enums
+                .iter()
+                .map(move |e| (format!("{}.{}", path, e.name), e))
+        });
+        self.enums.iter().map(|e| (e.name.clone(), e)).chain(nested)
+    }
 
-        output.push_str(&format!("syntax = \"{}\";\n\n", self.syntax));
-        output.push_str(&format!("package {};\n\n", self.package));
+    /// Message at a dotted path (`Outer.Inner`), which may also start with
+    /// the package (`api.Outer.Inner`)
+    pub fn find_message_by_path(&self, path: &str) -> Option<&Message> {
+        let mut segments = self.relative_path(path).split('.');
+        let first = segments.next()?;
+        let mut message = self.find_message(first)?;
+        for segment in segments {
+            message = message.nested_messages.iter().find(|m| m.name == segment)?;
+        }
+        Some(message)
+    }
 
-        for import in &self.imports {
-            output.push_str(&format!("import \"{}\";\n", import));
+    pub fn find_message_by_path_mut(&mut self, path: &str) -> Option<&mut Message> {
+        let relative = self.relative_path(path).to_string();
+        let mut segments = relative.split('.');
+        let first = segments.next()?;
+        let mut message = self.find_message_mut(first)?;
+        for segment in segments {
+            message = message
+                .nested_messages
+                .iter_mut()
+                .find(|m| m.name == segment)?;
         }
-        if !self.imports.is_empty() {
-            output.push_str("\n");
+        Some(message)
+    }
+
+    /// `path` without a leading `.` and package
+    pub(crate) fn relative_path<'a>(&self, path: &'a str) -> &'a str {
+        let path = path.trim_start_matches('.');
+        if self.package.is_empty() {
+            return path;
         }
+        path.strip_prefix(self.package.as_str())
+            .and_then(|rest| rest.strip_prefix('.'))
+            .unwrap_or(path)
+    }
 
-        // for (key, value) in &self.options {
-        //     output.push_str(&format!("option {} = \"{}\";\n", key, value));
-        // }
-        // if !self.options.is_empty() {
-        //     output.push_str("\n");
-        // }
+    pub fn find_enum_mut(&mut self, name: &str) -> Option<&mut Enum> {
+        self.enums.iter_mut().find(|e| e.name == name)
+    }
 
-        for message in &self.messages {
-            output.push_str(&message.to_proto_text(0));
-        }
+    pub fn find_enum(&self, name: &str) -> Option<&Enum> {
+        self.enums.iter().find(|e| e.name == name)
+    }
 
-        for enum_def in &self.enums {
-            output.push_str(&enum_def.to_proto_text(0));
-        }
+    pub fn remove_message(&mut self, name: &str) -> Option<Message> {
+        let index = self.messages.iter().position(|m| m.name == name)?;
+        Some(self.messages.remove(index))
+    }
+
+    pub fn remove_enum(&mut self, name: &str) -> Option<Enum> {
+        let index = self.enums.iter().position(|e| e.name == name)?;
+        Some(self.enums.remove(index))
+    }
 
-        for service in &self.services {
-            output.push_str(&service.to_proto_text());
+    pub fn remove_service(&mut self, name: &str) -> Option<Service> {
+        let index = self.services.iter().position(|s| s.name == name)?;
+        Some(self.services.remove(index))
+    }
+
+    pub fn find_service_mut(&mut self, name: &str) -> Option<&mut Service> {
+        self.services.iter_mut().find(|s| s.name == name)
+    }
+
+    pub fn find_service(&self, name: &str) -> Option<&Service> {
+        self.services.iter().find(|s| s.name == name)
+    }
+
+    /// `path` prefixed with the package (`api.Outer.Inner`)
+    pub fn qualified_name(&self, name: &str) -> String {
+        if self.package.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{}", self.package, name)
         }
+    }
 
-        output
+    /// Renders the file with the default layout, see [`ProtoFile::format`]
+    pub fn to_proto_text(&self) -> String {
+        self.format(&FormatOptions::default())
     }
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Message {
     pub name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub fields: Vec<Field>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub comments: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub nested_messages: Vec<Message>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub nested_enums: Vec<Enum>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub oneofs: Vec<Oneof>,
+    /// `option ...;` lines of the message block, in insertion order
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub options: IndexMap<String, OptionValue>,
+    /// `reserved 2, 9 to 11;`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reserved_ranges: Vec<NumberRange>,
+    /// `reserved "foo", "bar";`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reserved_names: Vec<String>,
+    /// `extensions 100 to max;`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extension_ranges: Vec<NumberRange>,
 }
 
 impl Message {
@@ -131,11 +248,16 @@ impl Message {
     }
 
     pub fn add_comment(&mut self, comment: &str) {
-        self.comments.push(comment.to_string());
+        push_comment(&mut self.comments, comment);
+    }
+
+    /// Adds a message-level option
+    pub fn add_option(&mut self, key: &str, value: OptionValue) {
+        self.options.insert(key.to_string(), value);
     }
 
     pub fn add_field(&mut self, field: Field) -> Result<(), ConverterError> {
-        if self.fields.iter().any(|f| f.name == field.name) {
+        if self.all_fields().any(|f| f.name == field.name) {
             return Err(ConverterError::InvalidFieldName(format!(
                 "Duplicate field name: {}",
                 field.name
@@ -145,6 +267,51 @@ impl Message {
         Ok(())
     }
 
+    pub fn add_oneof(&mut self, oneof: Oneof) -> Result<(), ConverterError> {
+        if self.oneofs.iter().any(|o| o.name == oneof.name) {
+            return Err(ConverterError::InvalidFieldName(format!(
+                "Duplicate oneof name: {}",
+                oneof.name
+            )));
+        }
+        if let Some(field) = oneof
+            .fields
+            .iter()
+            .find(|f| self.all_fields().any(|existing| existing.name == f.name))
+        {
+            return Err(ConverterError::InvalidFieldName(format!(
+                "Duplicate field name: {}",
+                field.name
+            )));
+        }
+        self.oneofs.push(oneof);
+        Ok(())
+    }
+
+    /// Iterates over regular fields followed by the fields of every oneof
+    pub fn all_fields(&self) -> impl Iterator<Item = &Field> {
+        self.fields
+            .iter()
+            .chain(self.oneofs.iter().flat_map(|o| o.fields.iter()))
+    }
+
+    /// Field by name, oneof members included
+    pub fn find_field(&self, name: &str) -> Option<&Field> {
+        self.all_fields().find(|f| f.name == name)
+    }
+
+    pub fn find_field_mut(&mut self, name: &str) -> Option<&mut Field> {
+        self.fields
+            .iter_mut()
+            .chain(self.oneofs.iter_mut().flat_map(|o| o.fields.iter_mut()))
+            .find(|f| f.name == name)
+    }
+
+    /// Field by number, oneof members included
+    pub fn find_field_by_number(&self, number: i32) -> Option<&Field> {
+        self.all_fields().find(|f| f.number == number)
+    }
+
     pub fn add_nested_message(&mut self, message: Message) -> Result<(), ConverterError> {
         if self.nested_messages.iter().any(|m| m.name == message.name) {
             return Err(ConverterError::DuplicateMessageName(message.name));
@@ -161,129 +328,661 @@ impl Message {
         Ok(())
     }
 
-    pub fn to_proto_text(&self, indent_level: usize) -> String {
-        let indent = "  ".repeat(indent_level);
-        let mut output = String::new();
+    /// Wire-level comparison: the same fields (name, number, type, label
+    /// and options), oneofs, nested messages and nested enums. Comments,
+    /// declaration order, option order and the name of the message itself
+    /// are ignored, so two generated messages can be told to be duplicates.
+    pub fn same_shape(&self, other: &Message) -> bool {
+        // Поля oneof сравниваются вместе с именем группы
+        fn fields(message: &Message) -> Vec<(Option<&str>, &Field)> {
+            let mut fields: Vec<_> = message
+                .fields
+                .iter()
+                .map(|f| (None, f))
+                .chain(
+                    message
+                        .oneofs
+                        .iter()
+                        .flat_map(|o| o.fields.iter().map(move |f| (Some(o.name.as_str()), f))),
+                )
+                .collect();
+            fields.sort_by_key(|(_, f)| f.number);
+            fields
+        }
 
-        for comment in &self.comments {
-            output.push_str(&format!("{}// {}\n", indent, comment));
+        fn values(enum_def: &Enum) -> Vec<(&str, i32)> {
+            let mut values: Vec<_> = enum_def
+                .values
+                .iter()
+                .map(|v| (v.name.as_str(), v.number))
+                .collect();
+            values.sort_unstable();
+            values
         }
 
-        output.push_str(&format!("{}message {} {{\n", indent, self.name));
+        let (fields_a, fields_b) = (fields(self), fields(other));
+        let same_fields = fields_a.len() == fields_b.len()
+            && fields_a
+                .iter()
+                .zip(&fields_b)
+                .all(|((oneof_a, a), (oneof_b, b))| {
+                    oneof_a == oneof_b
+                        && a.name == b.name
+                        && a.number == b.number
+                        && a.type_ == b.type_
+                        && a.rule == b.rule
+                        && a.options == b.options
+                });
+
+        let mut nested_a: Vec<&Message> = self.nested_messages.iter().collect();
+        let mut nested_b: Vec<&Message> = other.nested_messages.iter().collect();
+        nested_a.sort_by(|a, b| a.name.cmp(&b.name));
+        nested_b.sort_by(|a, b| a.name.cmp(&b.name));
+        let same_nested = nested_a.len() == nested_b.len()
+            && nested_a
+                .iter()
+                .zip(&nested_b)
+                .all(|(a, b)| a.name == b.name && a.same_shape(b));
+
+        let mut enums_a: Vec<&Enum> = self.nested_enums.iter().collect();
+        let mut enums_b: Vec<&Enum> = other.nested_enums.iter().collect();
+        enums_a.sort_by(|a, b| a.name.cmp(&b.name));
+        enums_b.sort_by(|a, b| a.name.cmp(&b.name));
+        let same_enums = enums_a.len() == enums_b.len()
+            && enums_a
+                .iter()
+                .zip(&enums_b)
+                .all(|(a, b)| a.name == b.name && values(a) == values(b));
+
+        same_fields && same_nested && same_enums
+    }
 
-        for field in &self.fields {
-            output.push_str(&field.to_proto_text(indent_level + 1));
+    pub fn to_proto_text(&self, indent_level: usize) -> String {
+        render_string(|w| self.write_proto_text(w, indent_level))
+    }
+
+    /// Writes the message followed by an empty line
+    pub fn write_proto_text<W: fmt::Write>(
+        &self,
+        writer: &mut W,
+        indent_level: usize,
+    ) -> fmt::Result {
+        render(writer, |f| {
+            f.message(self, indent_level)?;
+            f.blank_line()
+        })
+    }
+}
+
+/// Inclusive range of field or enum value numbers in `reserved` and
+/// `extensions` statements; `to max` is stored as `i32::MAX`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NumberRange {
+    pub start: i32,
+    pub end: i32,
+}
+
+impl NumberRange {
+    pub fn new(start: i32, end: i32) -> Self {
+        Self { start, end }
+    }
+
+    /// A range of one number
+    pub fn single(number: i32) -> Self {
+        Self::new(number, number)
+    }
+
+    pub fn contains(&self, number: i32) -> bool {
+        (self.start..=self.end).contains(&number)
+    }
+}
+
+impl fmt::Display for NumberRange {
+    /// `5`, `9 to 11` or `100 to max`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.end {
+            end if end == self.start => write!(f, "{}", self.start),
+            i32::MAX => write!(f, "{} to max", self.start),
+            end => write!(f, "{} to {}", self.start, end),
         }
+    }
+}
+
+/// Represents a Protocol Buffers oneof group.
+///
+/// Fields inside a oneof carry no label and are stored with
+/// `FieldRule::Singular`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Oneof {
+    pub name: String,
+    pub fields: Vec<Field>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub comments: Vec<String>,
+    /// `option ...;` lines of the oneof block, in insertion order
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub options: IndexMap<String, OptionValue>,
+}
 
-        for message in &self.nested_messages {
-            output.push_str(&message.to_proto_text(indent_level + 1));
+impl Oneof {
+    /// Creates a new Oneof with the given name
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ..Default::default()
         }
+    }
+
+    /// Adds a comment line to the oneof
+    pub fn add_comment(&mut self, comment: &str) {
+        push_comment(&mut self.comments, comment);
+    }
 
-        for enum_def in &self.nested_enums {
-            output.push_str(&enum_def.to_proto_text(indent_level + 1));
+    /// Adds a oneof-level option
+    pub fn add_option(&mut self, key: &str, value: OptionValue) {
+        self.options.insert(key.to_string(), value);
+    }
+
+    /// Adds a field to the oneof
+    pub fn add_field(&mut self, mut field: Field) -> Result<(), ConverterError> {
+        if self.fields.iter().any(|f| f.name == field.name) {
+            return Err(ConverterError::InvalidFieldName(format!(
+                "Duplicate field name: {}",
+                field.name
+            )));
         }
+        field.rule = FieldRule::Singular;
+        self.fields.push(field);
+        Ok(())
+    }
 
-        output.push_str(&format!("{}}}\n\n", indent));
+    /// Converts the Oneof to its textual representation
+    pub fn to_proto_text(&self, indent_level: usize) -> String {
+        render_string(|w| self.write_proto_text(w, indent_level))
+    }
 
-        output
+    /// Writes the oneof block
+    pub fn write_proto_text<W: fmt::Write>(
+        &self,
+        writer: &mut W,
+        indent_level: usize,
+    ) -> fmt::Result {
+        render(writer, |f| f.oneof(self, indent_level))
     }
 }
 
 /// Represents a protofile
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Field {
     pub name: String,
-    pub type_: String,
+    pub type_: FieldType,
     pub number: i32,
     pub rule: FieldRule,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub comments: Vec<String>,
-    pub options: HashMap<String, String>,
+    /// In the order they were added or parsed
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub options: IndexMap<String, OptionValue>,
 }
 
 impl Field {
-    /// Creates a new Field
-    pub fn new(name: &str, type_: &str, number: i32, rule: FieldRule) -> Self {
+    /// Creates a new Field; the type may be given as text (`"string"`,
+    /// `"map<string, Foo>"`, `"Foo"`), see [`FieldType`]
+    pub fn new(name: &str, type_: impl Into<FieldType>, number: i32, rule: FieldRule) -> Self {
         Self {
             name: name.to_string(),
-            type_: type_.to_string(),
+            type_: type_.into(),
             number,
             rule,
             comments: Vec::new(),
-            options: HashMap::new(),
+            options: IndexMap::new(),
         }
     }
 
+    /// The type as written in a `.proto` file, without the label
+    pub fn type_name(&self) -> String {
+        self.type_.to_string()
+    }
+
     /// Adds a comment line to the field
     pub fn add_comment(&mut self, comment: &str) {
-        self.comments.push(comment.to_string());
+        push_comment(&mut self.comments, comment);
     }
 
     /// Adds an option to the field
-    pub fn add_option(&mut self, key: &str, value: &str) {
-        self.options.insert(key.to_string(), value.to_string());
+    pub fn add_option(&mut self, key: &str, value: OptionValue) {
+        self.options.insert(key.to_string(), value);
+    }
+
+    /// Adds an option with a quoted string value (`json_name = "id"`)
+    pub fn add_string_option(&mut self, key: &str, value: &str) {
+        self.add_option(key, OptionValue::String(value.to_string()));
     }
 
     /// Converts the Field to its textual representation
     pub fn to_proto_text(&self, indent_level: usize) -> String {
-        let indent = "  ".repeat(indent_level);
-        let mut output = String::new();
-
-        // Comments
-        for comment in &self.comments {
-            output.push_str(&format!("{}// {}\n", indent, comment));
-        }
-
-        // Field definition
-        let rule_str = match self.rule {
-            FieldRule::Optional => "optional ",
-            FieldRule::Required => "",
-            FieldRule::Repeated => "repeated ",
-        };
-
-        output.push_str(&format!(
-            "{}{}{} {} = {}",
-            indent, rule_str, self.type_, self.name, self.number
-        ));
-
-        // Options
-        if !self.options.is_empty() {
-            let options: Vec<String> = self
-                .options
-                .iter()
-                .map(|(k, v)| format!("{}=\"{}\"", k, v))
-                .collect();
-            output.push_str(&format!(" [{}]", options.join(", ")));
+        render_string(|w| self.write_proto_text(w, indent_level))
+    }
+
+    /// Writes the field declaration with its comments
+    pub fn write_proto_text<W: fmt::Write>(
+        &self,
+        writer: &mut W,
+        indent_level: usize,
+    ) -> fmt::Result {
+        render(writer, |f| f.field(self, indent_level, 0))
+    }
+}
+
+/// Built-in scalar types of proto3
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScalarType {
+    Double,
+    Float,
+    Int32,
+    Int64,
+    Uint32,
+    Uint64,
+    Sint32,
+    Sint64,
+    Fixed32,
+    Fixed64,
+    Sfixed32,
+    Sfixed64,
+    Bool,
+    String,
+    Bytes,
+}
+
+impl ScalarType {
+    const ALL: [ScalarType; 15] = [
+        ScalarType::Double,
+        ScalarType::Float,
+        ScalarType::Int32,
+        ScalarType::Int64,
+        ScalarType::Uint32,
+        ScalarType::Uint64,
+        ScalarType::Sint32,
+        ScalarType::Sint64,
+        ScalarType::Fixed32,
+        ScalarType::Fixed64,
+        ScalarType::Sfixed32,
+        ScalarType::Sfixed64,
+        ScalarType::Bool,
+        ScalarType::String,
+        ScalarType::Bytes,
+    ];
+
+    /// The keyword of the type (`int32`)
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScalarType::Double => "double",
+            ScalarType::Float => "float",
+            ScalarType::Int32 => "int32",
+            ScalarType::Int64 => "int64",
+            ScalarType::Uint32 => "uint32",
+            ScalarType::Uint64 => "uint64",
+            ScalarType::Sint32 => "sint32",
+            ScalarType::Sint64 => "sint64",
+            ScalarType::Fixed32 => "fixed32",
+            ScalarType::Fixed64 => "fixed64",
+            ScalarType::Sfixed32 => "sfixed32",
+            ScalarType::Sfixed64 => "sfixed64",
+            ScalarType::Bool => "bool",
+            ScalarType::String => "string",
+            ScalarType::Bytes => "bytes",
         }
+    }
+
+    /// The scalar named by a keyword, `None` for anything else
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.as_str() == name)
+    }
+
+    /// Integral and string types; floats and bytes cannot key a map
+    pub fn is_valid_map_key(&self) -> bool {
+        !matches!(
+            self,
+            ScalarType::Double | ScalarType::Float | ScalarType::Bytes
+        )
+    }
 
-        output.push_str(";\n");
-        output
+    /// `int32` through `sfixed64`
+    pub fn is_integer(&self) -> bool {
+        self.is_valid_map_key() && !matches!(self, ScalarType::Bool | ScalarType::String)
+    }
+
+    /// `uint32`, `uint64`, `fixed32` and `fixed64`
+    pub fn is_unsigned(&self) -> bool {
+        matches!(
+            self,
+            ScalarType::Uint32 | ScalarType::Uint64 | ScalarType::Fixed32 | ScalarType::Fixed64
+        )
     }
 }
 
+impl fmt::Display for ScalarType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ScalarType {
+    type Err = ConverterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_name(s.trim()).ok_or_else(|| ConverterError::InvalidFieldType(s.to_string()))
+    }
+}
+
+/// Type of a field; the `repeated`/`optional` label is [`Field::rule`].
+///
+/// Serialized as its `.proto` text, so persisted models keep the string form.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FieldType {
+    Scalar(ScalarType),
+    /// A message or enum, as written (`Foo`, `api.Foo`, `.google.protobuf.Any`)
+    Named(String),
+    Map {
+        key: ScalarType,
+        value: Box<FieldType>,
+    },
+}
+
+impl FieldType {
+    /// `map<key, value>`
+    pub fn map(key: ScalarType, value: impl Into<FieldType>) -> Self {
+        FieldType::Map {
+            key,
+            value: Box::new(value.into()),
+        }
+    }
+
+    pub fn is_scalar(&self) -> bool {
+        matches!(self, FieldType::Scalar(_))
+    }
+
+    pub fn is_map(&self) -> bool {
+        matches!(self, FieldType::Map { .. })
+    }
+
+    /// The message or enum name, if the type is one
+    pub fn as_named(&self) -> Option<&str> {
+        match self {
+            FieldType::Named(name) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Message and enum names the type mentions, map values included,
+    /// without a leading `.`
+    pub fn referenced_types(&self) -> Vec<&str> {
+        match self {
+            FieldType::Scalar(_) => Vec::new(),
+            FieldType::Named(name) => vec![name.trim_start_matches('.')],
+            FieldType::Map { value, .. } => value.referenced_types(),
+        }
+    }
+}
+
+impl fmt::Display for FieldType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldType::Scalar(scalar) => f.write_str(scalar.as_str()),
+            FieldType::Named(name) => f.write_str(name),
+            FieldType::Map { key, value } => write!(f, "map<{}, {}>", key, value),
+        }
+    }
+}
+
+impl FromStr for FieldType {
+    type Err = ConverterError;
+
+    /// Parses `int32`, `map<string, Foo>` or a type name; labels such as
+    /// `repeated` are rejected
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let text = s.trim();
+        let invalid = || ConverterError::InvalidFieldType(s.to_string());
+
+        if let Some(inner) = text.strip_prefix("map").map(str::trim_start)
+            && let Some(inner) = inner.strip_prefix('<')
+        {
+            let (key, value) = inner
+                .strip_suffix('>')
+                .and_then(|inner| inner.split_once(','))
+                .ok_or_else(invalid)?;
+            let key: ScalarType = key.parse().map_err(|_| invalid())?;
+            let value: FieldType = value.parse()?;
+            if !key.is_valid_map_key() || value.is_map() {
+                return Err(invalid());
+            }
+            return Ok(FieldType::map(key, value));
+        }
+
+        if text.is_empty() || text.contains(char::is_whitespace) || text.contains(['<', '>']) {
+            return Err(invalid());
+        }
+        Ok(ScalarType::from_name(text)
+            .map_or_else(|| FieldType::Named(text.to_string()), FieldType::Scalar))
+    }
+}
+
+impl From<ScalarType> for FieldType {
+    fn from(scalar: ScalarType) -> Self {
+        FieldType::Scalar(scalar)
+    }
+}
+
+/// Never fails: text that does not parse is kept as a type name
+impl From<&str> for FieldType {
+    fn from(text: &str) -> Self {
+        text.parse()
+            .unwrap_or_else(|_| FieldType::Named(text.trim().to_string()))
+    }
+}
+
+impl From<&String> for FieldType {
+    fn from(text: &String) -> Self {
+        FieldType::from(text.as_str())
+    }
+}
+
+impl From<String> for FieldType {
+    fn from(text: String) -> Self {
+        FieldType::from(text.as_str())
+    }
+}
+
+impl Serialize for FieldType {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for FieldType {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Value of a field or method option, rendered as the matching proto literal
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum OptionValue {
+    /// `"text"`
+    String(String),
+    /// `true`
+    Bool(bool),
+    /// `42`
+    Int(i64),
+    /// `1.5`
+    Float(f64),
+    /// An enum constant such as `REQUIRED`
+    Identifier(String),
+    /// A message literal such as `{get: "/v1/items"}`, kept verbatim
+    Aggregate(String),
+}
+
+impl OptionValue {
+    /// Infers the variant from the text of a proto literal
+    pub fn from_literal(text: &str) -> Self {
+        let text = text.trim();
+        let quoted = text
+            .strip_prefix('"')
+            .and_then(|t| t.strip_suffix('"'))
+            .or_else(|| text.strip_prefix('\'').and_then(|t| t.strip_suffix('\'')));
+        if let Some(inner) = quoted {
+            return OptionValue::String(unescape(inner));
+        }
+
+        match text {
+            "true" => return OptionValue::Bool(true),
+            "false" => return OptionValue::Bool(false),
+            _ => {}
+        }
+        if let Ok(int) = text.parse() {
+            return OptionValue::Int(int);
+        }
+        if text.starts_with('{') {
+            return OptionValue::Aggregate(text.to_string());
+        }
+        if text.starts_with(|c: char| c.is_ascii_digit() || matches!(c, '-' | '+' | '.'))
+            && let Ok(float) = text.parse()
+        {
+            return OptionValue::Float(float);
+        }
+        OptionValue::Identifier(text.to_string())
+    }
+
+    /// Contents of a string value
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            OptionValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            OptionValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for OptionValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OptionValue::String(s) => {
+                let escaped = s
+                    .replace('\\', "\\\\")
+                    .replace('"', "\\\"")
+                    .replace('\n', "\\n");
+                write!(f, "\"{}\"", escaped)
+            }
+            OptionValue::Bool(b) => write!(f, "{}", b),
+            OptionValue::Int(i) => write!(f, "{}", i),
+            // `{:?}` keeps the `.0`, so `1.0` does not read back as an integer
+            OptionValue::Float(x) => write!(f, "{:?}", x),
+            OptionValue::Identifier(s) | OptionValue::Aggregate(s) => f.write_str(s),
+        }
+    }
+}
+
+/// Adds a comment line by line, dropping `//` prefixes the caller already
+/// wrote. `*/` is kept as is: comments are only emitted as `//` lines, and
+/// content types like `*/*` must survive the trip back to OpenAPI.
+fn push_comment(comments: &mut Vec<String>, comment: &str) {
+    for line in comment.trim_end_matches(['\n', '\r']).split('\n') {
+        let mut line = line.trim_end();
+        while let Some(rest) = line.trim_start().strip_prefix("//") {
+            line = rest.strip_prefix(' ').unwrap_or(rest);
+        }
+        comments.push(line.to_string());
+    }
+}
+
+/// Resolves `\"` and `\\` in a quoted literal
+fn unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') => result.push('\n'),
+                Some('t') => result.push('\t'),
+                Some(other) => result.push(other),
+                None => result.push('\\'),
+            },
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
 /// Represents field rules in Protocol Buffers
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum FieldRule {
     Optional,
+    /// proto2 `required`; proto3 has no such label
     Required,
     Repeated,
+    /// No label: a plain proto3 field, a map or a oneof member
+    Singular,
 }
 
 impl fmt::Display for FieldRule {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             FieldRule::Optional => write!(f, "optional"),
-            FieldRule::Required => write!(f, ""),
+            FieldRule::Required => write!(f, "required"),
             FieldRule::Repeated => write!(f, "repeated"),
+            FieldRule::Singular => write!(f, ""),
         }
     }
 }
 
+/// Language version named by the `syntax` statement
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Syntax {
+    /// Every field carries a label; `required` and `[default = ...]` exist
+    Proto2,
+    #[default]
+    Proto3,
+}
+
+impl Syntax {
+    /// Value of the `syntax` statement
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Syntax::Proto2 => "proto2",
+            Syntax::Proto3 => "proto3",
+        }
+    }
+}
+
+impl fmt::Display for Syntax {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 /// Represents a Protocol Buffers enum
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Enum {
     pub name: String,
     pub values: Vec<EnumValue>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub comments: Vec<String>,
+    /// `option ...;` lines of the enum block (`allow_alias = true`)
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub options: IndexMap<String, OptionValue>,
+    /// `reserved 2, 9 to 11;`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reserved_ranges: Vec<NumberRange>,
+    /// `reserved "FOO";`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reserved_names: Vec<String>,
 }
 
 impl Enum {
@@ -297,7 +996,17 @@ impl Enum {
 
     /// Adds a comment line to the enum
     pub fn add_comment(&mut self, comment: &str) {
-        self.comments.push(comment.to_string());
+        push_comment(&mut self.comments, comment);
+    }
+
+    /// Adds an enum-level option
+    pub fn add_option(&mut self, key: &str, value: OptionValue) {
+        self.options.insert(key.to_string(), value);
+    }
+
+    /// Whether `option allow_alias = true;` lets values share a number
+    pub fn allows_alias(&self) -> bool {
+        self.options.get("allow_alias") == Some(&OptionValue::Bool(true))
     }
 
     /// Adds a value to the enum
@@ -314,35 +1023,37 @@ impl Enum {
 
     /// Converts the Enum to its textual representation
     pub fn to_proto_text(&self, indent_level: usize) -> String {
-        let indent = "  ".repeat(indent_level);
-        let mut output = String::new();
-
-        // Comments
-        for comment in &self.comments {
-            output.push_str(&format!("{}// {}\n", indent, comment));
-        }
-
-        // Enum header
-        output.push_str(&format!("{}enum {} {{\n", indent, self.name));
-
-        // Values
-        for value in &self.values {
-            output.push_str(&value.to_proto_text(indent_level + 1));
-        }
-
-        // Closing brace
-        output.push_str(&format!("{}}}\n\n", indent));
+        render_string(|w| self.write_proto_text(w, indent_level))
+    }
 
-        output
+    /// Writes the enum followed by an empty line
+    pub fn write_proto_text<W: fmt::Write>(
+        &self,
+        writer: &mut W,
+        indent_level: usize,
+    ) -> fmt::Result {
+        render(writer, |f| {
+            f.enum_def(self, indent_level)?;
+            f.blank_line()
+        })
     }
 }
 
 /// Represents a Protocol Buffers enum value
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct EnumValue {
     pub name: String,
     pub number: i32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub comments: Vec<String>,
+    /// JSON string the value stands for (`in-progress` for `IN_PROGRESS`),
+    /// rendered as a trailing `// "in-progress"` unless an option already
+    /// carries it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_value: Option<String>,
+    /// `[...]` options of the value, in insertion order
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub options: IndexMap<String, OptionValue>,
 }
 
 impl EnumValue {
@@ -352,37 +1063,60 @@ impl EnumValue {
             name: name.to_string(),
             number,
             comments: Vec::new(),
+            original_value: None,
+            options: IndexMap::new(),
         }
     }
 
     /// Adds a comment line to the enum value
     pub fn add_comment(&mut self, comment: &str) {
-        self.comments.push(comment.to_string());
+        push_comment(&mut self.comments, comment);
     }
 
-    /// Converts the EnumValue to its textual representation
-    pub fn to_proto_text(&self, indent_level: usize) -> String {
-        let indent = "  ".repeat(indent_level);
-        let mut output = String::new();
+    /// Adds an option such as `deprecated = true`
+    pub fn add_option(&mut self, key: &str, value: OptionValue) {
+        self.options.insert(key.to_string(), value);
+    }
 
-        // Comments
-        for comment in &self.comments {
-            output.push_str(&format!("{}// {}\n", indent, comment));
+    /// The trailing comment recording `original_value`, if it is rendered
+    pub(crate) fn original_value_comment(&self) -> Option<String> {
+        let original = self.original_value.as_ref()?;
+        let in_option = self
+            .options
+            .values()
+            .any(|v| matches!(v, OptionValue::String(s) if s == original));
+        if in_option {
+            return None;
         }
+        serde_json::to_string(original).ok()
+    }
 
-        // Value definition
-        output.push_str(&format!("{} {} = {};\n", indent, self.name, self.number));
+    /// Converts the EnumValue to its textual representation
+    pub fn to_proto_text(&self, indent_level: usize) -> String {
+        render_string(|w| self.write_proto_text(w, indent_level))
+    }
 
-        output
+    /// Writes the value line with its comments
+    pub fn write_proto_text<W: fmt::Write>(
+        &self,
+        writer: &mut W,
+        indent_level: usize,
+    ) -> fmt::Result {
+        render(writer, |f| f.enum_value(self, indent_level, 0))
     }
 }
 
 /// Represents a Protocol Buffers service
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Service {
     pub name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub methods: Vec<Method>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub comments: Vec<String>,
+    /// `option ...;` lines of the service block, in insertion order
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub options: IndexMap<String, OptionValue>,
 }
 
 impl Service {
@@ -396,7 +1130,17 @@ impl Service {
 
     /// Adds a comment line to the service
     pub fn add_comment(&mut self, comment: &str) {
-        self.comments.push(comment.to_string());
+        push_comment(&mut self.comments, comment);
+    }
+
+    /// Adds a service-level option
+    pub fn add_option(&mut self, key: &str, value: OptionValue) {
+        self.options.insert(key.to_string(), value);
+    }
+
+    /// Adds an option with a quoted string value
+    pub fn add_string_option(&mut self, key: &str, value: &str) {
+        self.add_option(key, OptionValue::String(value.to_string()));
     }
 
     /// Adds a method to the service
@@ -411,92 +1155,83 @@ impl Service {
         Ok(())
     }
 
-    /// Converts the Service to its textual representation
-    pub fn to_proto_text(&self) -> String {
-        let mut output = String::new();
-
-        // Service header
-        output.push_str(&format!("service {} {{\n", self.name));
+    pub fn find_method(&self, name: &str) -> Option<&Method> {
+        self.methods.iter().find(|m| m.name == name)
+    }
 
-        // Methods with their own comments
-        for method in &self.methods {
-            output.push_str(&method.to_proto_text());
-        }
+    pub fn find_method_mut(&mut self, name: &str) -> Option<&mut Method> {
+        self.methods.iter_mut().find(|m| m.name == name)
+    }
 
-        // Closing brace
-        output.push_str("}\n\n");
+    /// Converts the Service to its textual representation
+    pub fn to_proto_text(&self) -> String {
+        render_string(|w| self.write_proto_text(w))
+    }
 
-        output
+    /// Writes the service followed by an empty line
+    pub fn write_proto_text<W: fmt::Write>(&self, writer: &mut W) -> fmt::Result {
+        render(writer, |f| {
+            f.service(self)?;
+            f.blank_line()
+        })
     }
 }
 
 /// Represents a Protocol Buffers service method
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Method {
     pub name: String,
     pub input_type: String,
     pub output_type: String,
+    /// `rpc X (stream In) ...`
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub client_streaming: bool,
+    /// `... returns (stream Out)`
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub server_streaming: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub comments: Vec<String>,
-    pub options: HashMap<String, String>,
+    /// In the order they were added or parsed
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub options: IndexMap<String, OptionValue>,
 }
 
 impl Method {
-    /// Creates a new Method
+    /// Creates a new unary Method
     pub fn new(name: &str, input_type: &str, output_type: &str) -> Self {
         Self {
             name: name.to_string(),
             input_type: input_type.to_string(),
             output_type: output_type.to_string(),
+            client_streaming: false,
+            server_streaming: false,
             comments: Vec::new(),
-            options: HashMap::new(),
+            options: IndexMap::new(),
         }
     }
 
     /// Adds a comment line to the method
     pub fn add_comment(&mut self, comment: &str) {
-        self.comments.push(comment.to_string());
+        push_comment(&mut self.comments, comment);
     }
 
     /// Adds an option to the method
-    pub fn add_option(&mut self, key: &str, value: &str) {
-        self.options.insert(key.to_string(), value.to_string());
+    pub fn add_option(&mut self, key: &str, value: OptionValue) {
+        self.options.insert(key.to_string(), value);
+    }
+
+    /// Adds an option with a quoted string value
+    pub fn add_string_option(&mut self, key: &str, value: &str) {
+        self.add_option(key, OptionValue::String(value.to_string()));
     }
 
     /// Converts the Method to its textual representation
     pub fn to_proto_text(&self) -> String {
-        let mut output = String::new();
-
-        // Method comments
-        for comment in &self.comments {
-            output.push_str(&format!("  // {}\n", comment));
-        }
-
-        // Add HTTP options as comments
-        if let Some(http_method) = self.options.get("http_method") {
-            if let Some(http_path) = self.options.get("http_path") {
-                output.push_str(&format!("  // HTTP: {} {}\n", http_method, http_path));
-            }
-        }
-
-        // Method definition
-        output.push_str(&format!(
-            "  rpc {} ({}) returns ({})",
-            self.name, self.input_type, self.output_type
-        ));
-
-        // Other options (excluding HTTP options)
-        let other_options: Vec<String> = self
-            .options
-            .iter()
-            .filter(|&(k, _)| k != "http_method" && k != "http_path")
-            .map(|(k, v)| format!("{}=\"{}\"", k, v))
-            .collect();
-
-        if !other_options.is_empty() {
-            output.push_str(&format!(" [{}]", other_options.join(", ")));
-        }
+        render_string(|w| self.write_proto_text(w))
+    }
 
-        output.push_str(";\n\n");
-        output
+    /// Writes the rpc as it appears inside its service
+    pub fn write_proto_text<W: fmt::Write>(&self, writer: &mut W) -> fmt::Result {
+        render(writer, |f| f.method(self, 1))
     }
 }
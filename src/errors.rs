@@ -1,21 +1,159 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::{Serialize, Serializer};
 use thiserror::Error;
 
+/// Every error has a stable code (`E_PROTO_PARSE_001`) for UIs and docs,
+/// see [`Error::code`]; a failure maps to exactly one code, whichever enum
+/// carries it.
 #[derive(Error, Debug)]
 pub enum Error {
-    #[error("IO error: {0}")]
-    Io(#[from] std::io::Error),
-
-    #[error("JSON parse error: {0}")]
-    JsonParse(#[from] serde_json::Error),
-
     #[error("Proto parse error: {0}")]
     ProtoParse(#[from] ProtoParseError),
 
-    #[error("Converter error: {0}")]
+    /// Also IO, JSON and YAML failures, which have no variant of their own
+    #[error(transparent)]
     Converter(#[from] ConverterError),
+
+    #[error("Invalid descriptor: {0}")]
+    Descriptor(String),
+
+    #[error("Model version {found} is not supported (expected {supported})")]
+    IncompatibleModelVersion { found: u64, supported: u32 },
+
+    #[cfg(feature = "watch")]
+    #[error("Watch error: {0}")]
+    Watch(#[from] notify::Error),
     // Другие ошибки...
 }
 
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Error::Converter(error.into())
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Error::Converter(error.into())
+    }
+}
+
+impl From<serde_yaml::Error> for Error {
+    fn from(error: serde_yaml::Error) -> Self {
+        Error::Converter(error.into())
+    }
+}
+
+impl Error {
+    /// Stable machine-readable code; never changes once released.
+    ///
+    /// ```
+    /// use dot_proto_parser::{ConverterError, Error, ProtoParseError};
+    ///
+    /// let io = || std::io::Error::other("disk");
+    /// let json = || serde_json::from_str::<u8>("x").unwrap_err();
+    /// let yaml = || serde_yaml::from_str::<u8>("x").unwrap_err();
+    /// let toml = || toml::from_str::<toml::Table>("=").unwrap_err();
+    /// let s = || "x".to_string();
+    ///
+    /// let codes = [
+    ///     (Error::from(io()), "E_IO_001"),
+    ///     (Error::from(json()), "E_JSON_001"),
+    ///     (Error::from(yaml()), "E_YAML_001"),
+    ///     (Error::Descriptor(s()), "E_DESCRIPTOR_001"),
+    ///     (Error::IncompatibleModelVersion { found: 2, supported: 1 }, "E_MODEL_001"),
+    ///     (ProtoParseError::Io(io()).into(), "E_IO_001"),
+    ///     (ProtoParseError::ParseError { line: 1, message: s() }.into(), "E_PROTO_PARSE_001"),
+    ///     (ProtoParseError::UnexpectedToken(s()).into(), "E_PROTO_PARSE_002"),
+    ///     (ProtoParseError::MissingField(s()).into(), "E_PROTO_PARSE_003"),
+    ///     (ProtoParseError::DuplicateDefinition(s()).into(), "E_PROTO_PARSE_004"),
+    ///     (
+    ///         ProtoParseError::MissingImport { import: s(), importer: s().into(), searched: vec![] }.into(),
+    ///         "E_PROTO_PARSE_005",
+    ///     ),
+    ///     (ProtoParseError::InputTooLarge { limit: 1 }.into(), "E_PROTO_PARSE_006"),
+    ///     (ConverterError::Toml(toml()).into(), "E_TOML_001"),
+    ///     (ConverterError::UnsupportedSchemaType(s()).into(), "E_CONVERT_001"),
+    ///     (ConverterError::MissingReference(s()).into(), "E_CONVERT_002"),
+    ///     (ConverterError::InvalidArrayDefinition.into(), "E_CONVERT_003"),
+    ///     (ConverterError::CircularReference(s()).into(), "E_CONVERT_004"),
+    ///     (ConverterError::DuplicateMessageName(s()).into(), "E_CONVERT_005"),
+    ///     (ConverterError::InvalidParameterLocation(s()).into(), "E_CONVERT_006"),
+    ///     (ConverterError::UnsupportedHttpMethod(s()).into(), "E_CONVERT_007"),
+    ///     (ConverterError::InvalidFieldName(s()).into(), "E_CONVERT_008"),
+    ///     (ConverterError::InvalidFieldType(s()).into(), "E_CONVERT_009"),
+    ///     (ConverterError::ServiceNotFound(s()).into(), "E_CONVERT_010"),
+    ///     (ConverterError::MessageNotFound(s()).into(), "E_CONVERT_011"),
+    ///     (ConverterError::RemoteReference(s()).into(), "E_CONVERT_012"),
+    ///     (ConverterError::CircularExternalReference { from: s(), to: s() }.into(), "E_CONVERT_013"),
+    ///     (ConverterError::ExternalDocument { path: s(), message: s() }.into(), "E_CONVERT_014"),
+    ///     (ConverterError::InvalidOutput(Vec::new()).into(), "E_CONVERT_015"),
+    ///     (
+    ///         ConverterError::ConflictingMessage { definition: s(), message: s(), details: s() }.into(),
+    ///         "E_CONVERT_016",
+    ///     ),
+    ///     (ConverterError::DepthLimitExceeded { limit: 1 }.into(), "E_CONVERT_017"),
+    ///     // The location or file does not change the code
+    ///     (ConverterError::MissingReference(s()).at("#/paths").into(), "E_CONVERT_002"),
+    ///     (ConverterError::from(json()).in_file("api.json".as_ref()).into(), "E_JSON_001"),
+    ///     (
+    ///         ProtoParseError::InFile {
+    ///             path: "api.proto".into(),
+    ///             source: Box::new(ProtoParseError::ParseError { line: 1, message: s() }),
+    ///         }
+    ///         .into(),
+    ///         "E_PROTO_PARSE_001",
+    ///     ),
+    /// ];
+    /// for (error, code) in codes {
+    ///     assert_eq!(error.code(), code, "{}", error);
+    /// }
+    /// ```
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::ProtoParse(error) => error.code(),
+            Error::Converter(error) => error.code(),
+            Error::Descriptor(_) => "E_DESCRIPTOR_001",
+            Error::IncompatibleModelVersion { .. } => "E_MODEL_001",
+            #[cfg(feature = "watch")]
+            Error::Watch(_) => "E_WATCH_001",
+        }
+    }
+}
+
+/// `{ code, message, file?, line?, pointer? }`, the serialized form of
+/// every error
+#[derive(Serialize)]
+struct ErrorReport<'e> {
+    code: &'static str,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<&'e Path>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pointer: Option<&'e str>,
+}
+
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Error::ProtoParse(error) => error.serialize(serializer),
+            Error::Converter(error) => error.serialize(serializer),
+            other => ErrorReport {
+                code: other.code(),
+                message: other.to_string(),
+                file: None,
+                line: None,
+                pointer: None,
+            }
+            .serialize(serializer),
+        }
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum ConverterError {
     #[error("IO error: {0}")]
@@ -24,6 +162,12 @@ pub enum ConverterError {
     #[error("JSON parse error: {0}")]
     JsonParse(#[from] serde_json::Error),
 
+    #[error("YAML error: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+
+    #[error("TOML error: {0}")]
+    Toml(#[from] toml::de::Error),
+
     #[error("Unsupported schema type: {0}")]
     UnsupportedSchemaType(String),
 
@@ -48,11 +192,156 @@ pub enum ConverterError {
     #[error("Invalid field name: {0}")]
     InvalidFieldName(String),
 
+    #[error("Invalid field type: {0}")]
+    InvalidFieldType(String),
+
     #[error("Service not found: {0}")]
     ServiceNotFound(String),
 
     #[error("Message not found: {0}")]
     MessageNotFound(String),
+
+    #[error("Remote references are not supported: {0}")]
+    RemoteReference(String),
+
+    #[error("Circular reference between {from} and {to}")]
+    CircularExternalReference { from: String, to: String },
+
+    #[error("Failed to load external document {path}: {message}")]
+    ExternalDocument { path: String, message: String },
+
+    #[error("{pointer}: {source}")]
+    AtLocation {
+        pointer: String,
+        source: Box<ConverterError>,
+    },
+
+    /// Failure reading, converting or writing the file `path`
+    #[error("{}: {source}", path.display())]
+    InputFile {
+        path: PathBuf,
+        source: Box<ConverterError>,
+    },
+
+    #[error("Generated proto is invalid: {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "))]
+    InvalidOutput(Vec<ValidationError>),
+
+    #[error("Definition {definition} conflicts with existing message {message}: {details}")]
+    ConflictingMessage {
+        definition: String,
+        message: String,
+        details: String,
+    },
+
+    /// Inline schemas nested deeper than [`crate::ConverterOptions::max_depth`]
+    #[error("Schemas nested deeper than {limit} levels")]
+    DepthLimitExceeded { limit: usize },
+}
+
+impl ConverterError {
+    /// Attaches the location of the failing part of the document.
+    ///
+    /// Relative locations (`properties/id`) are prefixed as the error
+    /// travels outwards, until an absolute one (`#/paths/...`) is reached.
+    pub fn at(self, pointer: impl Into<String>) -> Self {
+        let pointer = pointer.into();
+        match self {
+            ConverterError::AtLocation {
+                pointer: inner,
+                source,
+            } => {
+                let pointer = if inner.starts_with('#') {
+                    inner
+                } else {
+                    format!("{}/{}", pointer, inner)
+                };
+                ConverterError::AtLocation { pointer, source }
+            }
+            other => ConverterError::AtLocation {
+                pointer,
+                source: Box::new(other),
+            },
+        }
+    }
+
+    /// Attaches the file the error happened in, unless one is attached
+    /// already: the innermost file is the one that failed
+    pub fn in_file(self, path: &Path) -> Self {
+        match self {
+            ConverterError::InputFile { .. } => self,
+            other => ConverterError::InputFile {
+                path: path.to_path_buf(),
+                source: Box::new(other),
+            },
+        }
+    }
+
+    /// Location in the document the error was raised for
+    pub fn pointer(&self) -> Option<&str> {
+        match self {
+            ConverterError::AtLocation { pointer, .. } => Some(pointer),
+            ConverterError::InputFile { source, .. } => source.pointer(),
+            _ => None,
+        }
+    }
+
+    /// File the error happened in
+    pub fn file(&self) -> Option<&Path> {
+        match self {
+            ConverterError::InputFile { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Stable machine-readable code, see [`Error::code`]; a located error
+    /// has the code of its source
+    pub fn code(&self) -> &'static str {
+        match self {
+            ConverterError::Io(_) => "E_IO_001",
+            ConverterError::JsonParse(_) => "E_JSON_001",
+            ConverterError::Yaml(_) => "E_YAML_001",
+            ConverterError::Toml(_) => "E_TOML_001",
+            ConverterError::UnsupportedSchemaType(_) => "E_CONVERT_001",
+            ConverterError::MissingReference(_) => "E_CONVERT_002",
+            ConverterError::InvalidArrayDefinition => "E_CONVERT_003",
+            ConverterError::CircularReference(_) => "E_CONVERT_004",
+            ConverterError::DuplicateMessageName(_) => "E_CONVERT_005",
+            ConverterError::InvalidParameterLocation(_) => "E_CONVERT_006",
+            ConverterError::UnsupportedHttpMethod(_) => "E_CONVERT_007",
+            ConverterError::InvalidFieldName(_) => "E_CONVERT_008",
+            ConverterError::InvalidFieldType(_) => "E_CONVERT_009",
+            ConverterError::ServiceNotFound(_) => "E_CONVERT_010",
+            ConverterError::MessageNotFound(_) => "E_CONVERT_011",
+            ConverterError::RemoteReference(_) => "E_CONVERT_012",
+            ConverterError::CircularExternalReference { .. } => "E_CONVERT_013",
+            ConverterError::ExternalDocument { .. } => "E_CONVERT_014",
+            ConverterError::InvalidOutput(_) => "E_CONVERT_015",
+            ConverterError::ConflictingMessage { .. } => "E_CONVERT_016",
+            ConverterError::DepthLimitExceeded { .. } => "E_CONVERT_017",
+            ConverterError::AtLocation { source, .. }
+            | ConverterError::InputFile { source, .. } => source.code(),
+        }
+    }
+}
+
+impl Serialize for ConverterError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // Файл и указатель идут отдельными полями, в сообщении их нет
+        let mut error = self;
+        while let ConverterError::AtLocation { source, .. }
+        | ConverterError::InputFile { source, .. } = error
+        {
+            error = source;
+        }
+        ErrorReport {
+            code: self.code(),
+            message: error.to_string(),
+            file: self.file(),
+            line: None,
+            pointer: self.pointer(),
+        }
+        .serialize(serializer)
+    }
 }
 
 #[derive(Error, Debug)]
@@ -71,4 +360,274 @@ pub enum ProtoParseError {
 
     #[error("Duplicate definition: {0}")]
     DuplicateDefinition(String),
+
+    #[error(
+        "{}: import \"{import}\" not found, searched {}",
+        importer.display(),
+        searched.iter().map(|p| p.display().to_string()).collect::<Vec<_>>().join(", ")
+    )]
+    MissingImport {
+        import: String,
+        importer: PathBuf,
+        searched: Vec<PathBuf>,
+    },
+
+    /// Input longer than [`crate::ProtoParser::max_input_size`]
+    #[error("Input is larger than {limit} bytes")]
+    InputTooLarge { limit: usize },
+
+    /// `api.proto:42: message` for parse errors, `api.proto: error` for
+    /// the rest
+    #[error("{}", in_file(path, source))]
+    InFile {
+        path: PathBuf,
+        source: Box<ProtoParseError>,
+    },
+}
+
+fn in_file(path: &Path, error: &ProtoParseError) -> String {
+    match error {
+        ProtoParseError::ParseError { line, message } => {
+            format!("{}:{}: {}", path.display(), line, message)
+        }
+        other => format!("{}: {}", path.display(), other),
+    }
+}
+
+impl ProtoParseError {
+    /// Stable machine-readable code, see [`Error::code`]
+    pub fn code(&self) -> &'static str {
+        match self {
+            ProtoParseError::Io(_) => "E_IO_001",
+            ProtoParseError::ParseError { .. } => "E_PROTO_PARSE_001",
+            ProtoParseError::UnexpectedToken(_) => "E_PROTO_PARSE_002",
+            ProtoParseError::MissingField(_) => "E_PROTO_PARSE_003",
+            ProtoParseError::DuplicateDefinition(_) => "E_PROTO_PARSE_004",
+            ProtoParseError::MissingImport { .. } => "E_PROTO_PARSE_005",
+            ProtoParseError::InputTooLarge { .. } => "E_PROTO_PARSE_006",
+            ProtoParseError::InFile { source, .. } => source.code(),
+        }
+    }
+}
+
+impl Serialize for ProtoParseError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (file, error) = match self {
+            ProtoParseError::InFile { path, source } => (Some(path.as_path()), source.as_ref()),
+            other => (None, other),
+        };
+        let (line, message) = match error {
+            ProtoParseError::ParseError { line, message } => (Some(*line), message.clone()),
+            other => (None, other.to_string()),
+        };
+        ErrorReport {
+            code: self.code(),
+            message,
+            file,
+            line,
+            pointer: None,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Divergence found by [`crate::roundtrip_check`]
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum RoundtripDiff {
+    #[error("Input does not parse: {0}")]
+    Parse(String),
+
+    #[error("Rendered output does not parse: {0}")]
+    Reparse(String),
+
+    #[error("{path}: expected {expected}, got {actual}")]
+    Mismatch {
+        path: String,
+        expected: String,
+        actual: String,
+    },
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ValidationError {
+    #[error("Enum {path}: first value must be 0 in proto3, found {found}")]
+    EnumFirstValueNotZero { path: String, found: i32 },
+
+    #[error("Enum {path}: proto3 enums must have at least one value")]
+    EmptyEnum { path: String },
+
+    #[error("Enum value {path}: number {number} is already used by {other} without allow_alias")]
+    DuplicateEnumNumber {
+        path: String,
+        number: i32,
+        other: String,
+    },
+
+    #[error("{path}: unknown type {type_name}")]
+    UnresolvedType { path: String, type_name: String },
+
+    #[error("{path}: {type_name} needs import \"{import}\"")]
+    MissingImport {
+        path: String,
+        type_name: String,
+        import: String,
+    },
+
+    #[error("{path}: name is already defined")]
+    DuplicateName { path: String },
+
+    #[error(
+        "Field {path}: number {number} is outside 1..=536870911 or in the reserved 19000-19999"
+    )]
+    InvalidFieldNumber { path: String, number: i32 },
+
+    #[error("Field {path}: number {number} is already used by {other}")]
+    DuplicateFieldNumber {
+        path: String,
+        number: i32,
+        other: String,
+    },
+
+    #[error("Oneof {path}: must contain at least one field")]
+    EmptyOneof { path: String },
+
+    #[error("Field {path}: {construct} is not allowed in {syntax}")]
+    UnsupportedInSyntax {
+        path: String,
+        construct: String,
+        syntax: String,
+    },
+}
+
+/// Non-fatal issue found while converting between swagger and proto
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConversionWarning {
+    /// A property is defined by more than one allOf branch; the last one wins
+    DuplicateProperty { schema: String, property: String },
+    /// Two operations map to the same rpc name; the later one was renamed
+    RenamedMethod {
+        service: String,
+        method: String,
+        renamed: String,
+    },
+    /// A parameter in an unsupported location, or a second body parameter
+    DroppedParameter {
+        path: String,
+        name: String,
+        location: String,
+    },
+    /// A `format` without a proto counterpart; the field keeps its base type
+    UnknownFormat { schema: String, format: String },
+    /// A media type other than the one picked for the message
+    SkippedContentType { path: String, media_type: String },
+    /// A response that is neither the rpc result nor a numbered error
+    SkippedResponse { path: String, code: String },
+    /// Response headers, which have no place in the rpc signature
+    DroppedResponseHeaders { path: String, headers: Vec<String> },
+    /// A security requirement naming a scheme that is not defined
+    UnknownSecurityScheme { path: String, scheme: String },
+    /// A HEAD, OPTIONS or TRACE operation
+    SkippedOperation { path: String, method: String },
+    /// An unknown or missing schema type, replaced by `fallback`
+    UnsupportedType {
+        schema: String,
+        type_name: String,
+        fallback: String,
+    },
+    /// An rpc without an HTTP binding, left out of the OpenAPI document
+    MissingHttpBinding { service: String, method: String },
+    /// A generated message or enum name was taken; a numbered one was used
+    RenamedType { name: String, renamed: String },
+    /// An operation or path with several servers; only the first is
+    /// recorded on the rpc
+    ExtraServers {
+        path: String,
+        primary: String,
+        skipped: Vec<String>,
+    },
+    /// A Swagger 2.0 payload without a JSON media type; its schema was not
+    /// converted and the payload is passed through as bytes
+    NonJsonPayload { path: String, media_type: String },
+}
+
+impl fmt::Display for ConversionWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConversionWarning::DuplicateProperty { schema, property } => write!(
+                f,
+                "{}: property '{}' is redefined by a later allOf branch",
+                schema, property
+            ),
+            ConversionWarning::RenamedMethod {
+                service,
+                method,
+                renamed,
+            } => write!(
+                f,
+                "{}: duplicate method '{}' renamed to '{}'",
+                service, method, renamed
+            ),
+            ConversionWarning::DroppedParameter {
+                path,
+                name,
+                location,
+            } => write!(
+                f,
+                "{}: parameter '{}' in '{}' is not supported and was dropped",
+                path, name, location
+            ),
+            ConversionWarning::UnknownFormat { schema, format } => {
+                write!(f, "{}: format '{}' has no proto equivalent", schema, format)
+            }
+            ConversionWarning::SkippedContentType { path, media_type } => {
+                write!(f, "{}: media type '{}' was skipped", path, media_type)
+            }
+            ConversionWarning::SkippedResponse { path, code } => {
+                write!(f, "{}: response '{}' was skipped", path, code)
+            }
+            ConversionWarning::DroppedResponseHeaders { path, headers } => write!(
+                f,
+                "{}: response headers dropped: {}",
+                path,
+                headers.join(", ")
+            ),
+            ConversionWarning::UnknownSecurityScheme { path, scheme } => {
+                write!(f, "{}: security scheme '{}' is not defined", path, scheme)
+            }
+            ConversionWarning::SkippedOperation { path, method } => {
+                write!(f, "{}: {} operations are not converted", path, method)
+            }
+            ConversionWarning::UnsupportedType {
+                schema,
+                type_name,
+                fallback,
+            } => write!(
+                f,
+                "{}: unsupported type '{}' converted to {}",
+                schema, type_name, fallback
+            ),
+            ConversionWarning::MissingHttpBinding { service, method } => {
+                write!(f, "{}.{}: no HTTP binding, method skipped", service, method)
+            }
+            ConversionWarning::RenamedType { name, renamed } => {
+                write!(f, "type name '{}' is taken, generated '{}'", name, renamed)
+            }
+            ConversionWarning::ExtraServers {
+                path,
+                primary,
+                skipped,
+            } => write!(
+                f,
+                "{}: only the first server {} is recorded, skipped: {}",
+                path,
+                primary,
+                skipped.join(", ")
+            ),
+            ConversionWarning::NonJsonPayload { path, media_type } => write!(
+                f,
+                "{}: '{}' payload is not JSON, passed through as bytes",
+                path, media_type
+            ),
+        }
+    }
 }
@@ -0,0 +1,140 @@
+use std::collections::HashSet;
+
+use crate::frozen::referenced_types;
+use crate::imports::well_known_import;
+use crate::proto2swagger::TypePaths;
+use crate::util::join_path;
+use crate::{ConverterError, Message, ProtoFile};
+
+impl ProtoFile {
+    /// Standalone file with the named messages, enums and services plus
+    /// everything they reference, transitively.
+    ///
+    /// Roots may be qualified with the package; a nested message
+    /// (`Order.Item`) keeps its whole top-level parent. Only the
+    /// `google/protobuf/*` imports that are still used are kept, other
+    /// imports are copied as is.
+    pub fn extract(&self, roots: &[&str]) -> Result<ProtoFile, ConverterError> {
+        let mut extractor = Extractor {
+            file: self,
+            types: TypePaths::new(self),
+            definitions: HashSet::new(),
+            services: HashSet::new(),
+            pending: Vec::new(),
+        };
+
+        for root in roots {
+            let name = self.relative_path(root);
+            if let Some(service) = self.find_service(name) {
+                extractor.services.insert(service.name.clone());
+                for method in &service.methods {
+                    let referrer =
+                        join_path(&self.package, &join_path(&service.name, &method.name));
+                    extractor.reference(referenced_types(&method.input_type), "", &referrer)?;
+                    extractor.reference(referenced_types(&method.output_type), "", &referrer)?;
+                }
+            } else if extractor.types.contains(name) {
+                extractor.keep(name);
+            } else {
+                return Err(ConverterError::MessageNotFound(root.to_string()));
+            }
+        }
+
+        while let Some(name) = extractor.pending.pop() {
+            if let Some(message) = self.find_message(&name) {
+                extractor.message(message, "")?;
+            }
+        }
+
+        let definitions = extractor.definitions;
+        let services = extractor.services;
+        let mut extracted = ProtoFile {
+            model_version: self.model_version,
+            header_comments: self.header_comments.clone(),
+            syntax: self.syntax.clone(),
+            package: self.package.clone(),
+            imports: self.imports.clone(),
+            options: self.options.clone(),
+            messages: self
+                .messages
+                .iter()
+                .filter(|m| definitions.contains(&m.name))
+                .cloned()
+                .collect(),
+            enums: self
+                .enums
+                .iter()
+                .filter(|e| definitions.contains(&e.name))
+                .cloned()
+                .collect(),
+            services: self
+                .services
+                .iter()
+                .filter(|s| services.contains(&s.name))
+                .cloned()
+                .collect(),
+        };
+        extracted.sync_wellknown_imports();
+        Ok(extracted)
+    }
+}
+
+struct Extractor<'a> {
+    file: &'a ProtoFile,
+    types: TypePaths,
+    /// Top-level messages and enums to copy
+    definitions: HashSet<String>,
+    services: HashSet<String>,
+    /// Kept messages whose fields are not walked yet
+    pending: Vec<String>,
+}
+
+impl Extractor<'_> {
+    /// Marks the top-level definition containing `path` as kept
+    fn keep(&mut self, path: &str) {
+        let top = path.split('.').next().unwrap_or(path);
+        if self.definitions.insert(top.to_string()) {
+            self.pending.push(top.to_string());
+        }
+    }
+
+    fn message(&mut self, message: &Message, prefix: &str) -> Result<(), ConverterError> {
+        let path = join_path(prefix, &message.name);
+        for field in message.all_fields() {
+            let referrer = join_path(&self.file.package, &join_path(&path, &field.name));
+            self.reference(field.type_.referenced_types(), &path, &referrer)?;
+        }
+        for nested in &message.nested_messages {
+            self.message(nested, &path)?;
+        }
+        Ok(())
+    }
+
+    /// Keeps the definitions the type names of a field or rpc refer to
+    fn reference(
+        &mut self,
+        names: Vec<&str>,
+        scope: &str,
+        referrer: &str,
+    ) -> Result<(), ConverterError> {
+        for name in names {
+            if name
+                .strip_prefix("google.protobuf.")
+                .and_then(well_known_import)
+                .is_some()
+            {
+                continue;
+            }
+
+            let path = self.types.resolve(name, scope);
+            if !self.types.contains(&path) {
+                return Err(ConverterError::MessageNotFound(format!(
+                    "{} (referenced by {})",
+                    name, referrer
+                )));
+            }
+            self.keep(&path);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,542 @@
+use indexmap::IndexMap;
+use std::fmt;
+use std::io;
+
+use crate::{
+    Enum, EnumValue, Field, FieldRule, Message, Method, NumberRange, Oneof, OptionValue, ProtoFile,
+    Service, Syntax,
+};
+
+/// Indentation unit of nested blocks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indent {
+    Tab,
+    Spaces(usize),
+}
+
+impl Default for Indent {
+    fn default() -> Self {
+        Indent::Spaces(2)
+    }
+}
+
+impl Indent {
+    fn unit(&self) -> String {
+        match self {
+            Indent::Tab => "\t".to_string(),
+            Indent::Spaces(n) => " ".repeat(*n),
+        }
+    }
+}
+
+/// Layout settings of [`ProtoFile::format`]
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    pub indent: Indent,
+    /// Empty lines after every top-level message, enum and service
+    pub blank_lines_between_top_level: usize,
+    /// Sorts imports and drops duplicates
+    pub sort_imports: bool,
+    /// Pads field and enum value names so the `= N` columns of a block line up
+    pub align_field_numbers: bool,
+    /// Fields whose options do not fit are split one option per line,
+    /// rpcs move `returns (...)` to the next line
+    pub max_line_width: Option<usize>,
+    /// Emits top-level messages and enums in [`ProtoFile::dependency_order`]
+    /// instead of insertion order, enums no longer trailing the messages
+    pub dependency_order: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            indent: Indent::default(),
+            blank_lines_between_top_level: 1,
+            sort_imports: false,
+            align_field_numbers: false,
+            max_line_width: None,
+            dependency_order: false,
+        }
+    }
+}
+
+impl ProtoFile {
+    /// Renders the file as `.proto` text laid out according to `options`
+    pub fn format(&self, options: &FormatOptions) -> String {
+        let mut output = String::new();
+        self.write_formatted(options, &mut output)
+            .expect("writing to a String does not fail");
+        output
+    }
+
+    /// Writes the file laid out according to `options`, piece by piece,
+    /// without building the whole text first
+    pub fn write_formatted<W: fmt::Write>(
+        &self,
+        options: &FormatOptions,
+        writer: &mut W,
+    ) -> fmt::Result {
+        Formatter::new(options, writer).file(self)
+    }
+
+    /// Writes the default layout to a file, socket or compressor; see
+    /// [`ProtoFile::to_proto_text`]
+    pub fn write_proto_text<W: io::Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        let mut adapter = IoAdapter {
+            inner: writer,
+            error: None,
+        };
+        match self.write_formatted(&FormatOptions::default(), &mut adapter) {
+            Ok(()) => Ok(()),
+            Err(_) => Err(adapter
+                .error
+                .unwrap_or_else(|| io::Error::other("formatter error"))),
+        }
+    }
+}
+
+/// `fmt::Write` over an `io::Write`, keeping the real I/O error
+struct IoAdapter<'a, W: io::Write + ?Sized> {
+    inner: &'a mut W,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write + ?Sized> fmt::Write for IoAdapter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        })
+    }
+}
+
+/// The single rendering path behind `format` and every `to_proto_text`
+pub(crate) struct Formatter<'a, W: fmt::Write> {
+    options: &'a FormatOptions,
+    unit: String,
+    /// Singular fields get an explicit `optional`, set for proto2 files
+    labels: bool,
+    out: &'a mut W,
+}
+
+impl<'a, W: fmt::Write> Formatter<'a, W> {
+    pub(crate) fn new(options: &'a FormatOptions, out: &'a mut W) -> Self {
+        Self {
+            options,
+            unit: options.indent.unit(),
+            labels: false,
+            out,
+        }
+    }
+
+    fn file(&mut self, file: &ProtoFile) -> fmt::Result {
+        self.labels = file.syntax == Syntax::Proto2.as_str();
+        for comment in &file.header_comments {
+            if comment.is_empty() {
+                self.out.write_str("//\n")?;
+            } else {
+                writeln!(self.out, "// {}", comment)?;
+            }
+        }
+        if !file.header_comments.is_empty() {
+            self.out.write_char('\n')?;
+        }
+
+        write!(self.out, "syntax = \"{}\";\n\n", file.syntax)?;
+        if !file.package.is_empty() {
+            write!(self.out, "package {};\n\n", file.package)?;
+        }
+
+        let mut imports: Vec<&String> = file.imports.iter().collect();
+        if self.options.sort_imports {
+            imports.sort();
+            imports.dedup();
+        }
+        for import in &imports {
+            writeln!(self.out, "import \"{}\";", import)?;
+        }
+        if !imports.is_empty() {
+            self.out.write_char('\n')?;
+        }
+
+        for (key, value) in &file.options {
+            if value == "true" || value == "false" {
+                writeln!(self.out, "option {} = {};", key, value)?;
+            } else {
+                writeln!(self.out, "option {} = \"{}\";", key, value)?;
+            }
+        }
+        if !file.options.is_empty() {
+            self.out.write_char('\n')?;
+        }
+
+        let separator = "\n".repeat(self.options.blank_lines_between_top_level);
+        if self.options.dependency_order {
+            for name in file.dependency_order().names {
+                if let Some(message) = file.find_message(&name) {
+                    self.message(message, 0)?;
+                } else if let Some(enum_def) = file.find_enum(&name) {
+                    self.enum_def(enum_def, 0)?;
+                }
+                self.out.write_str(&separator)?;
+            }
+        } else {
+            for message in &file.messages {
+                self.message(message, 0)?;
+                self.out.write_str(&separator)?;
+            }
+            for enum_def in &file.enums {
+                self.enum_def(enum_def, 0)?;
+                self.out.write_str(&separator)?;
+            }
+        }
+        for service in &file.services {
+            self.service(service)?;
+            self.out.write_str(&separator)?;
+        }
+        Ok(())
+    }
+
+    fn indent(&self, level: usize) -> String {
+        self.unit.repeat(level)
+    }
+
+    fn comments(&mut self, comments: &[String], level: usize) -> fmt::Result {
+        let indent = self.indent(level);
+        for comment in comments {
+            if comment.is_empty() {
+                writeln!(self.out, "{}//", indent)?;
+            } else {
+                writeln!(self.out, "{}// {}", indent, comment)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Message block without the trailing empty line
+    pub(crate) fn message(&mut self, message: &Message, level: usize) -> fmt::Result {
+        let indent = self.indent(level);
+        self.comments(&message.comments, level)?;
+        writeln!(self.out, "{}message {} {{", indent, message.name)?;
+        self.block_options(&message.options, level + 1)?;
+        self.reserved(&message.reserved_ranges, &message.reserved_names, level + 1)?;
+        if !message.extension_ranges.is_empty() {
+            writeln!(
+                self.out,
+                "{}{}extensions {};",
+                indent,
+                self.unit,
+                join_ranges(&message.extension_ranges)
+            )?;
+        }
+
+        let align_to = self.align_to(&message.fields, self.labels);
+        for field in &message.fields {
+            self.labeled_field(field, level + 1, align_to, self.labels)?;
+        }
+        for oneof in &message.oneofs {
+            self.oneof(oneof, level + 1)?;
+        }
+        for nested in &message.nested_messages {
+            self.message(nested, level + 1)?;
+            self.out.write_char('\n')?;
+        }
+        for enum_def in &message.nested_enums {
+            self.enum_def(enum_def, level + 1)?;
+            self.out.write_char('\n')?;
+        }
+
+        writeln!(self.out, "{}}}", indent)
+    }
+
+    pub(crate) fn oneof(&mut self, oneof: &Oneof, level: usize) -> fmt::Result {
+        let indent = self.indent(level);
+        self.comments(&oneof.comments, level)?;
+        writeln!(self.out, "{}oneof {} {{", indent, oneof.name)?;
+        self.block_options(&oneof.options, level + 1)?;
+        // Поля oneof не имеют метки и в proto2
+        let align_to = self.align_to(&oneof.fields, false);
+        for field in &oneof.fields {
+            self.labeled_field(field, level + 1, align_to, false)?;
+        }
+        writeln!(self.out, "{}}}", indent)
+    }
+
+    /// `option ...;` lines at the top of a message, enum or oneof block
+    fn block_options(
+        &mut self,
+        options: &IndexMap<String, OptionValue>,
+        level: usize,
+    ) -> fmt::Result {
+        let indent = self.indent(level);
+        for (key, value) in options {
+            writeln!(self.out, "{}option {};", indent, format_option(key, value))?;
+        }
+        Ok(())
+    }
+
+    /// `reserved 2, 9 to 11;` and `reserved "foo";` lines
+    fn reserved(&mut self, ranges: &[NumberRange], names: &[String], level: usize) -> fmt::Result {
+        let indent = self.indent(level);
+        if !ranges.is_empty() {
+            writeln!(self.out, "{}reserved {};", indent, join_ranges(ranges))?;
+        }
+        if !names.is_empty() {
+            let names: Vec<String> = names.iter().map(|n| format!("\"{}\"", n)).collect();
+            writeln!(self.out, "{}reserved {};", indent, names.join(", "))?;
+        }
+        Ok(())
+    }
+
+    /// Width of the longest declaration of a block, when aligning
+    fn align_to(&self, fields: &[Field], labels: bool) -> usize {
+        if !self.options.align_field_numbers {
+            return 0;
+        }
+        fields
+            .iter()
+            .map(|f| field_declaration(f, labels).chars().count())
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub(crate) fn field(&mut self, field: &Field, level: usize, align_to: usize) -> fmt::Result {
+        self.labeled_field(field, level, align_to, self.labels)
+    }
+
+    fn labeled_field(
+        &mut self,
+        field: &Field,
+        level: usize,
+        align_to: usize,
+        labels: bool,
+    ) -> fmt::Result {
+        self.comments(&field.comments, level)?;
+
+        let line = format!(
+            "{}{:<width$} = {}",
+            self.indent(level),
+            field_declaration(field, labels),
+            field.number,
+            width = align_to
+        );
+        let options: Vec<String> = field
+            .options
+            .iter()
+            .map(|(k, v)| format_option(k, v))
+            .collect();
+        if options.is_empty() {
+            return writeln!(self.out, "{};", line);
+        }
+
+        let single_line = format!("{} [{}];", line, options.join(", "));
+        if self.fits(&single_line) {
+            writeln!(self.out, "{}", single_line)
+        } else {
+            let indent = self.indent(level);
+            writeln!(self.out, "{} [", line)?;
+            let count = options.len();
+            for (i, option) in options.into_iter().enumerate() {
+                let comma = if i + 1 < count { "," } else { "" };
+                writeln!(self.out, "{}{}{}{}", indent, self.unit, option, comma)?;
+            }
+            writeln!(self.out, "{}];", indent)
+        }
+    }
+
+    /// Enum block without the trailing empty line
+    pub(crate) fn enum_def(&mut self, enum_def: &Enum, level: usize) -> fmt::Result {
+        let indent = self.indent(level);
+        self.comments(&enum_def.comments, level)?;
+        writeln!(self.out, "{}enum {} {{", indent, enum_def.name)?;
+        self.block_options(&enum_def.options, level + 1)?;
+        self.reserved(
+            &enum_def.reserved_ranges,
+            &enum_def.reserved_names,
+            level + 1,
+        )?;
+
+        let align_to = if self.options.align_field_numbers {
+            enum_def.values.iter().map(|v| v.name.chars().count()).max()
+        } else {
+            None
+        };
+        for value in &enum_def.values {
+            self.enum_value(value, level + 1, align_to.unwrap_or(0))?;
+        }
+
+        writeln!(self.out, "{}}}", indent)
+    }
+
+    pub(crate) fn enum_value(
+        &mut self,
+        value: &EnumValue,
+        level: usize,
+        align_to: usize,
+    ) -> fmt::Result {
+        self.comments(&value.comments, level)?;
+        write!(
+            self.out,
+            "{}{:<width$} = {}",
+            self.indent(level),
+            value.name,
+            value.number,
+            width = align_to
+        )?;
+        if !value.options.is_empty() {
+            let options: Vec<String> = value
+                .options
+                .iter()
+                .map(|(k, v)| format_option(k, v))
+                .collect();
+            write!(self.out, " [{}]", options.join(", "))?;
+        }
+        match value.original_value_comment() {
+            Some(comment) => writeln!(self.out, "; // {}", comment),
+            None => writeln!(self.out, ";"),
+        }
+    }
+
+    /// Service block without the trailing empty line
+    pub(crate) fn service(&mut self, service: &Service) -> fmt::Result {
+        self.comments(&service.comments, 0)?;
+        writeln!(self.out, "service {} {{", service.name)?;
+        for (key, value) in &service.options {
+            writeln!(
+                self.out,
+                "{}option {};",
+                self.unit,
+                format_option(key, value)
+            )?;
+        }
+        if !service.options.is_empty() && !service.methods.is_empty() {
+            self.out.write_char('\n')?;
+        }
+        for method in &service.methods {
+            self.method(method, 1)?;
+        }
+        self.out.write_str("}\n")
+    }
+
+    /// rpc followed by an empty line
+    pub(crate) fn method(&mut self, method: &Method, level: usize) -> fmt::Result {
+        let indent = self.indent(level);
+        self.comments(&method.comments, level)?;
+
+        // Add HTTP options as comments
+        if let Some(server) = method
+            .options
+            .get("http_server")
+            .and_then(OptionValue::as_str)
+        {
+            writeln!(self.out, "{}// Server: {}", indent, server)?;
+        }
+        if let Some(http_method) = method
+            .options
+            .get("http_method")
+            .and_then(OptionValue::as_str)
+            && let Some(http_path) = method
+                .options
+                .get("http_path")
+                .and_then(OptionValue::as_str)
+        {
+            writeln!(self.out, "{}// HTTP: {} {}", indent, http_method, http_path)?;
+        }
+
+        let stream = |streaming: bool| if streaming { "stream " } else { "" };
+        let signature = format!(
+            "{}rpc {} ({}{})",
+            indent,
+            method.name,
+            stream(method.client_streaming),
+            method.input_type
+        );
+        let returns = format!(
+            "returns ({}{})",
+            stream(method.server_streaming),
+            method.output_type
+        );
+
+        // Other options (excluding HTTP options) go into the rpc body
+        let other_options: Vec<String> = method
+            .options
+            .iter()
+            .filter(|&(k, _)| !matches!(k.as_str(), "http_method" | "http_path" | "http_server"))
+            .map(|(k, v)| format_option(k, v))
+            .collect();
+        let end = if other_options.is_empty() { ";" } else { " {" };
+
+        let single_line = format!("{} {}{}", signature, returns, end);
+        if self.fits(&single_line) {
+            self.out.write_str(&single_line)?;
+        } else {
+            write!(
+                self.out,
+                "{}\n{}{}{}{}",
+                signature, indent, self.unit, returns, end
+            )?;
+        }
+        self.out.write_char('\n')?;
+
+        if !other_options.is_empty() {
+            for option in other_options {
+                writeln!(self.out, "{}{}option {};", indent, self.unit, option)?;
+            }
+            writeln!(self.out, "{}}}", indent)?;
+        }
+        self.out.write_char('\n')
+    }
+
+    /// Ends a block rendered on its own with the usual empty line
+    pub(crate) fn blank_line(&mut self) -> fmt::Result {
+        self.out.write_char('\n')
+    }
+
+    fn fits(&self, line: &str) -> bool {
+        self.options
+            .max_line_width
+            .is_none_or(|width| line.chars().count() <= width)
+    }
+}
+
+/// `repeated string tags`, the part in front of `= N`; with `labels`
+/// singular fields other than maps are written as `optional`
+fn field_declaration(field: &Field, labels: bool) -> String {
+    let rule = match field.rule {
+        FieldRule::Optional => "optional ",
+        FieldRule::Required => "required ",
+        FieldRule::Repeated => "repeated ",
+        FieldRule::Singular if labels && !field.type_.is_map() => "optional ",
+        FieldRule::Singular => "",
+    };
+    format!("{}{} {}", rule, field.type_, field.name)
+}
+
+fn format_option(key: &str, value: &OptionValue) -> String {
+    format!("{} = {}", key, value)
+}
+
+/// `2, 9 to 11, 100 to max`
+fn join_ranges(ranges: &[NumberRange]) -> String {
+    let ranges: Vec<String> = ranges.iter().map(ToString::to_string).collect();
+    ranges.join(", ")
+}
+
+/// Writes one element with the default layout
+pub(crate) fn render<W: fmt::Write>(
+    writer: &mut W,
+    element: impl FnOnce(&mut Formatter<W>) -> fmt::Result,
+) -> fmt::Result {
+    let options = FormatOptions::default();
+    element(&mut Formatter::new(&options, writer))
+}
+
+/// Collects one element rendered with the default layout
+pub(crate) fn render_string<F>(element: F) -> String
+where
+    F: FnOnce(&mut String) -> fmt::Result,
+{
+    let mut output = String::new();
+    element(&mut output).expect("writing to a String does not fail");
+    output
+}
@@ -0,0 +1,313 @@
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Deref;
+use std::sync::{Arc, OnceLock};
+
+use crate::util::{fnv1a_64, join_path};
+use crate::{Enum, Message, Method, ProtoFile, ScalarType, Service};
+
+/// A definition found by dotted path inside a ProtoFile
+#[derive(Debug, Clone, Copy)]
+pub enum ResolvedItem<'a> {
+    Message(&'a Message),
+    Enum(&'a Enum),
+    Service(&'a Service),
+    Method(&'a Method),
+}
+
+/// Position of a definition inside the ProtoFile tree.
+///
+/// Stored instead of references so the index can live next to the file it
+/// describes.
+#[derive(Debug, Clone)]
+enum ItemLocation {
+    Message(Vec<usize>),
+    Enum(Vec<usize>, usize),
+    Service(usize),
+    Method(usize, usize),
+}
+
+impl ProtoFile {
+    /// Looks up a message, enum, service or method by dotted path
+    /// (`Pet`, `Pet.Status`, `api.PetService.GetPet`).
+    ///
+    /// This walks the whole file on every call; use [`ArcProtoFile`] for
+    /// repeated queries.
+    pub fn resolve(&self, path: &str) -> Option<ResolvedItem<'_>> {
+        let key = self.relative_path(path);
+        build_index(self)
+            .get(key)
+            .and_then(|location| location_to_item(self, location))
+    }
+
+    /// Returns the dotted paths of all fields and methods that reference
+    /// the given type.
+    pub fn usages_of(&self, type_name: &str) -> Vec<String> {
+        let key = self.relative_path(type_name);
+        build_usages(self).remove(key).unwrap_or_default()
+    }
+
+    /// Stable 64-bit fingerprint of the rendered proto text
+    pub fn fingerprint(&self) -> u64 {
+        fnv1a_64(self.to_proto_text().as_bytes())
+    }
+
+    /// Wraps the file into a cheaply clonable, immutable handle with
+    /// memoized analyses.
+    pub fn freeze(self) -> ArcProtoFile {
+        ArcProtoFile::new(Arc::new(self))
+    }
+}
+
+/// Shared, immutable ProtoFile with lazily computed derived data.
+///
+/// Clones share both the file and its caches. To modify the file, take it
+/// back out with [`ArcProtoFile::into_inner`]; freezing again starts with
+/// fresh caches.
+#[derive(Debug, Clone)]
+pub struct ArcProtoFile {
+    inner: Arc<FrozenInner>,
+}
+
+#[derive(Debug)]
+struct FrozenInner {
+    file: Arc<ProtoFile>,
+    index: OnceLock<HashMap<String, ItemLocation>>,
+    dependencies: OnceLock<BTreeMap<String, Vec<String>>>,
+    usages: OnceLock<HashMap<String, Vec<String>>>,
+    fingerprint: OnceLock<u64>,
+}
+
+impl ArcProtoFile {
+    pub fn new(file: Arc<ProtoFile>) -> Self {
+        Self {
+            inner: Arc::new(FrozenInner {
+                file,
+                index: OnceLock::new(),
+                dependencies: OnceLock::new(),
+                usages: OnceLock::new(),
+                fingerprint: OnceLock::new(),
+            }),
+        }
+    }
+
+    /// Returns the shared file
+    pub fn file(&self) -> &Arc<ProtoFile> {
+        &self.inner.file
+    }
+
+    /// Takes the file back for mutation, cloning it only if it is still shared
+    pub fn into_inner(self) -> ProtoFile {
+        match Arc::try_unwrap(self.inner) {
+            Ok(inner) => Arc::unwrap_or_clone(inner.file),
+            Err(shared) => (*shared.file).clone(),
+        }
+    }
+
+    /// Indexed version of [`ProtoFile::resolve`]
+    pub fn resolve(&self, path: &str) -> Option<ResolvedItem<'_>> {
+        let key = self.inner.file.relative_path(path);
+        self.inner
+            .index
+            .get_or_init(|| build_index(&self.inner.file))
+            .get(key)
+            .and_then(|location| location_to_item(&self.inner.file, location))
+    }
+
+    /// Indexed version of [`ProtoFile::usages_of`]
+    pub fn usages_of(&self, type_name: &str) -> &[String] {
+        let key = self.inner.file.relative_path(type_name);
+        self.inner
+            .usages
+            .get_or_init(|| build_usages(&self.inner.file))
+            .get(key)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Message dependency graph: dotted message path to the type names its
+    /// fields reference
+    pub fn dependencies(&self) -> &BTreeMap<String, Vec<String>> {
+        self.inner
+            .dependencies
+            .get_or_init(|| build_dependencies(&self.inner.file))
+    }
+
+    /// Memoized version of [`ProtoFile::fingerprint`]
+    pub fn fingerprint(&self) -> u64 {
+        *self
+            .inner
+            .fingerprint
+            .get_or_init(|| self.inner.file.fingerprint())
+    }
+}
+
+impl Deref for ArcProtoFile {
+    type Target = ProtoFile;
+
+    fn deref(&self) -> &ProtoFile {
+        &self.inner.file
+    }
+}
+
+impl From<ProtoFile> for ArcProtoFile {
+    fn from(file: ProtoFile) -> Self {
+        file.freeze()
+    }
+}
+
+impl From<Arc<ProtoFile>> for ArcProtoFile {
+    fn from(file: Arc<ProtoFile>) -> Self {
+        Self::new(file)
+    }
+}
+
+fn build_index(file: &ProtoFile) -> HashMap<String, ItemLocation> {
+    let mut index = HashMap::new();
+
+    fn index_message(
+        message: &Message,
+        prefix: &str,
+        location: Vec<usize>,
+        index: &mut HashMap<String, ItemLocation>,
+    ) {
+        let path = join_path(prefix, &message.name);
+        for (i, enum_def) in message.nested_enums.iter().enumerate() {
+            index.insert(
+                join_path(&path, &enum_def.name),
+                ItemLocation::Enum(location.clone(), i),
+            );
+        }
+        for (i, nested) in message.nested_messages.iter().enumerate() {
+            let mut nested_location = location.clone();
+            nested_location.push(i);
+            index_message(nested, &path, nested_location, index);
+        }
+        index.insert(path, ItemLocation::Message(location));
+    }
+
+    for (i, message) in file.messages.iter().enumerate() {
+        index_message(message, "", vec![i], &mut index);
+    }
+    for (i, enum_def) in file.enums.iter().enumerate() {
+        index.insert(enum_def.name.clone(), ItemLocation::Enum(Vec::new(), i));
+    }
+    for (i, service) in file.services.iter().enumerate() {
+        for (j, method) in service.methods.iter().enumerate() {
+            index.insert(
+                join_path(&service.name, &method.name),
+                ItemLocation::Method(i, j),
+            );
+        }
+        index.insert(service.name.clone(), ItemLocation::Service(i));
+    }
+
+    index
+}
+
+fn location_to_item<'a>(file: &'a ProtoFile, location: &ItemLocation) -> Option<ResolvedItem<'a>> {
+    fn message_at<'a>(file: &'a ProtoFile, path: &[usize]) -> Option<&'a Message> {
+        let (first, rest) = path.split_first()?;
+        rest.iter()
+            .try_fold(file.messages.get(*first)?, |message, &i| {
+                message.nested_messages.get(i)
+            })
+    }
+
+    match location {
+        ItemLocation::Message(path) => message_at(file, path).map(ResolvedItem::Message),
+        ItemLocation::Enum(path, i) if path.is_empty() => {
+            file.enums.get(*i).map(ResolvedItem::Enum)
+        }
+        ItemLocation::Enum(path, i) => message_at(file, path)
+            .and_then(|message| message.nested_enums.get(*i))
+            .map(ResolvedItem::Enum),
+        ItemLocation::Service(i) => file.services.get(*i).map(ResolvedItem::Service),
+        ItemLocation::Method(i, j) => file
+            .services
+            .get(*i)
+            .and_then(|service| service.methods.get(*j))
+            .map(ResolvedItem::Method),
+    }
+}
+
+fn build_dependencies(file: &ProtoFile) -> BTreeMap<String, Vec<String>> {
+    let mut graph = BTreeMap::new();
+
+    fn visit(message: &Message, prefix: &str, graph: &mut BTreeMap<String, Vec<String>>) {
+        let path = join_path(prefix, &message.name);
+        let mut deps: Vec<String> = Vec::new();
+        for field in message.all_fields() {
+            for type_name in field.type_.referenced_types() {
+                if !deps.iter().any(|d| d == type_name) {
+                    deps.push(type_name.to_string());
+                }
+            }
+        }
+        for nested in &message.nested_messages {
+            visit(nested, &path, graph);
+        }
+        graph.insert(path, deps);
+    }
+
+    for message in &file.messages {
+        visit(message, "", &mut graph);
+    }
+
+    graph
+}
+
+fn build_usages(file: &ProtoFile) -> HashMap<String, Vec<String>> {
+    let mut usages: HashMap<String, Vec<String>> = HashMap::new();
+
+    fn visit(
+        file: &ProtoFile,
+        message: &Message,
+        prefix: &str,
+        usages: &mut HashMap<String, Vec<String>>,
+    ) {
+        let path = join_path(prefix, &message.name);
+        for field in message.all_fields() {
+            for type_name in field.type_.referenced_types() {
+                usages
+                    .entry(file.relative_path(type_name).to_string())
+                    .or_default()
+                    .push(join_path(&path, &field.name));
+            }
+        }
+        for nested in &message.nested_messages {
+            visit(file, nested, &path, usages);
+        }
+    }
+
+    for message in &file.messages {
+        visit(file, message, "", &mut usages);
+    }
+    for service in &file.services {
+        for method in &service.methods {
+            let path = join_path(&service.name, &method.name);
+            for type_name in [&method.input_type, &method.output_type] {
+                usages
+                    .entry(file.relative_path(type_name).to_string())
+                    .or_default()
+                    .push(path.clone());
+            }
+        }
+    }
+
+    usages
+}
+
+/// The named type of an rpc signature without the leading `.`; nothing for
+/// scalars. Field types use [`crate::FieldType::referenced_types`].
+pub(crate) fn referenced_types(type_: &str) -> Vec<&str> {
+    let type_ = type_.trim().trim_start_matches('.');
+    if type_.is_empty() || is_scalar_type(type_) {
+        Vec::new()
+    } else {
+        vec![type_]
+    }
+}
+
+pub(crate) fn is_scalar_type(type_: &str) -> bool {
+    ScalarType::from_name(type_).is_some()
+}
@@ -0,0 +1,71 @@
+use std::collections::BTreeSet;
+
+use crate::frozen::referenced_types;
+use crate::{Message, ProtoFile};
+
+/// Files [`well_known_import`] points to
+const WELL_KNOWN_FILES: [&str; 7] = [
+    "google/protobuf/any.proto",
+    "google/protobuf/duration.proto",
+    "google/protobuf/empty.proto",
+    "google/protobuf/field_mask.proto",
+    "google/protobuf/struct.proto",
+    "google/protobuf/timestamp.proto",
+    "google/protobuf/wrappers.proto",
+];
+
+impl ProtoFile {
+    /// Imports exactly the `google/protobuf/*` files whose types the fields
+    /// and rpc signatures use; other imports are left alone
+    pub fn sync_wellknown_imports(&mut self) {
+        fn visit(message: &Message, used: &mut BTreeSet<&'static str>) {
+            for field in message.all_fields() {
+                used.extend(well_known_imports(field.type_.referenced_types()));
+            }
+            for nested in &message.nested_messages {
+                visit(nested, used);
+            }
+        }
+
+        let mut used = BTreeSet::new();
+        for message in &self.messages {
+            visit(message, &mut used);
+        }
+        for method in self.services.iter().flat_map(|s| &s.methods) {
+            used.extend(well_known_imports(referenced_types(&method.input_type)));
+            used.extend(well_known_imports(referenced_types(&method.output_type)));
+        }
+
+        // descriptor.proto и прочие файлы google/protobuf здесь не управляются
+        self.imports
+            .retain(|i| !WELL_KNOWN_FILES.contains(&i.as_str()) || used.contains(i.as_str()));
+        for import in used {
+            self.add_import(import);
+        }
+    }
+}
+
+/// Files of the well-known types among the referenced type names
+fn well_known_imports(names: Vec<&str>) -> impl Iterator<Item = &'static str> + '_ {
+    names
+        .into_iter()
+        .filter_map(|name| well_known_import(name.strip_prefix("google.protobuf.")?))
+}
+
+/// File declaring a `google.protobuf` type
+pub(crate) fn well_known_import(name: &str) -> Option<&'static str> {
+    let import = match name {
+        "Any" => "google/protobuf/any.proto",
+        "Duration" => "google/protobuf/duration.proto",
+        "Empty" => "google/protobuf/empty.proto",
+        "FieldMask" => "google/protobuf/field_mask.proto",
+        "Struct" | "Value" | "ListValue" | "NullValue" => "google/protobuf/struct.proto",
+        "Timestamp" => "google/protobuf/timestamp.proto",
+        "DoubleValue" | "FloatValue" | "Int64Value" | "UInt64Value" | "Int32Value"
+        | "UInt32Value" | "BoolValue" | "StringValue" | "BytesValue" => {
+            "google/protobuf/wrappers.proto"
+        }
+        _ => return None,
+    };
+    Some(import)
+}
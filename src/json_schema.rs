@@ -0,0 +1,74 @@
+use serde_json::{Map, Value, json};
+
+use crate::{ConverterError, ProtoFile, ProtoToOpenApiConverter};
+
+const DEFS_PREFIX: &str = "#/$defs/";
+const COMPONENTS_PREFIX: &str = "#/components/schemas/";
+
+impl ProtoFile {
+    /// JSON Schema (draft 2020-12) for the JSON form of a message.
+    ///
+    /// The message and every type it references are put into `$defs` and
+    /// the root is a `$ref` to the message, so recursive messages become
+    /// `$ref` cycles. Types are mapped the same way as in
+    /// [`ProtoToOpenApiConverter`].
+    pub fn to_json_schema(&self, message_name: &str) -> Result<Value, ConverterError> {
+        let mut schemas = ProtoToOpenApiConverter::new().schemas(self);
+
+        let name = message_name.trim_start_matches('.');
+        let name = match name.strip_prefix(&format!("{}.", self.package)) {
+            Some(rest) if !self.package.is_empty() => rest,
+            _ => name,
+        };
+        if !schemas.contains_key(name) {
+            return Err(ConverterError::MessageNotFound(message_name.to_string()));
+        }
+
+        // Only the definitions reachable from the message
+        let mut defs = Map::new();
+        let mut pending = vec![name.to_string()];
+        while let Some(def_name) = pending.pop() {
+            if defs.contains_key(&def_name) {
+                continue;
+            }
+            let Some(mut schema) = schemas.remove(&def_name) else {
+                continue;
+            };
+            to_draft_2020(&mut schema, &mut pending);
+            defs.insert(def_name, schema);
+        }
+        defs.sort_keys();
+
+        Ok(json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "$ref": format!("{}{}", DEFS_PREFIX, name),
+            "$defs": defs,
+        }))
+    }
+}
+
+/// Rewrites an OpenAPI 3.0 schema in place: refs point into `$defs` (the
+/// referenced names are pushed to `refs`) and `nullable` becomes a `null`
+/// type.
+fn to_draft_2020(schema: &mut Value, refs: &mut Vec<String>) {
+    match schema {
+        Value::Object(map) => {
+            if let Some(Value::String(ref_path)) = map.get_mut("$ref")
+                && let Some(name) = ref_path.strip_prefix(COMPONENTS_PREFIX)
+            {
+                refs.push(name.to_string());
+                *ref_path = format!("{}{}", DEFS_PREFIX, name);
+            }
+            if map.remove("nullable") == Some(Value::Bool(true))
+                && let Some(type_) = map.get_mut("type")
+            {
+                *type_ = json!([type_.take(), "null"]);
+            }
+            for child in map.values_mut() {
+                to_draft_2020(child, refs);
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| to_draft_2020(item, refs)),
+        _ => {}
+    }
+}
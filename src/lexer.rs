@@ -0,0 +1,290 @@
+/// Kind of a [`Token`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TokenKind {
+    /// `Order`, `google.protobuf.Any`, `.api.Order`: dotted names are one
+    /// token
+    Ident,
+    Int,
+    Float,
+    /// Quoted with `"` or `'`, quotes and escapes included
+    String,
+    /// `//` or `/* */`; `text` is the content without the markers
+    Comment,
+    /// Any other single character: `{`, `=`, `;`, `<`, ...
+    Punct,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Token<'s> {
+    pub(crate) kind: TokenKind,
+    pub(crate) text: &'s str,
+    /// Byte offset of the token in the source, comment markers included
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+    /// 1-based
+    pub(crate) line: usize,
+    /// 1-based, in characters
+    pub(crate) column: usize,
+    /// A comment after other tokens of its line
+    pub(crate) trailing: bool,
+}
+
+impl Token<'_> {
+    pub(crate) fn is_punct(&self, c: char) -> bool {
+        self.kind == TokenKind::Punct && self.text.len() == c.len_utf8() && self.text.starts_with(c)
+    }
+
+    pub(crate) fn is_ident(&self, word: &str) -> bool {
+        self.kind == TokenKind::Ident && self.text == word
+    }
+}
+
+/// Malformed input found by [`tokenize`]; the lexer carries on after it
+#[derive(Debug, Clone)]
+pub(crate) struct LexError {
+    pub(crate) line: usize,
+    pub(crate) column: usize,
+    pub(crate) message: String,
+}
+
+/// Splits .proto text into tokens, comments included
+pub(crate) fn tokenize(source: &str) -> (Vec<Token<'_>>, Vec<LexError>) {
+    let mut lexer = Lexer {
+        source,
+        bytes: source.as_bytes(),
+        pos: 0,
+        line: 1,
+        line_start: 0,
+        counted_to: 0,
+        counted: 0,
+        line_has_tokens: false,
+        tokens: Vec::with_capacity(source.len() / 4),
+        errors: Vec::new(),
+    };
+    lexer.run();
+    (lexer.tokens, lexer.errors)
+}
+
+struct Lexer<'s> {
+    source: &'s str,
+    bytes: &'s [u8],
+    pos: usize,
+    line: usize,
+    /// Byte offset where the current line starts
+    line_start: usize,
+    /// Characters between `line_start` and the byte offset `counted_to`,
+    /// so that columns on a long line are counted once, not per token
+    counted_to: usize,
+    counted: usize,
+    line_has_tokens: bool,
+    tokens: Vec<Token<'s>>,
+    errors: Vec<LexError>,
+}
+
+impl<'s> Lexer<'s> {
+    fn run(&mut self) {
+        while let Some(&byte) = self.bytes.get(self.pos) {
+            let start = self.pos;
+            match byte {
+                b'\n' => {
+                    self.pos += 1;
+                    self.new_line();
+                }
+                b' ' | b'\t' | b'\r' | b'\x0c' | b'\x0b' => self.pos += 1,
+                b'/' if self.bytes.get(start + 1) == Some(&b'/') => {
+                    let end = self.source[start..]
+                        .find('\n')
+                        .map_or(self.source.len(), |i| start + i);
+                    self.pos = end;
+                    let text = self.source[start + 2..end].trim_end();
+                    self.push_comment(text, start);
+                }
+                b'/' if self.bytes.get(start + 1) == Some(&b'*') => self.block_comment(),
+                b'"' | b'\'' => self.string(byte),
+                b'0'..=b'9' => self.number(),
+                b'.' if self.bytes.get(start + 1).is_some_and(u8::is_ascii_digit) => self.number(),
+                b'.' if self
+                    .bytes
+                    .get(start + 1)
+                    .copied()
+                    .is_some_and(is_ident_start) =>
+                {
+                    self.pos += 1;
+                    self.ident(start);
+                }
+                _ if is_ident_start(byte) => self.ident(start),
+                _ => {
+                    let c = self.source[start..].chars().next().expect("not at the end");
+                    self.pos += c.len_utf8();
+                    if c.is_ascii_punctuation() {
+                        self.push(TokenKind::Punct, start);
+                    } else {
+                        self.error(start, format!("Unexpected character `{}`", c));
+                    }
+                }
+            }
+        }
+    }
+
+    fn new_line(&mut self) {
+        self.line += 1;
+        self.line_start = self.pos;
+        self.line_has_tokens = false;
+    }
+
+    fn column(&mut self, offset: usize) -> usize {
+        if self.counted_to < self.line_start || offset < self.counted_to {
+            self.counted_to = self.line_start;
+            self.counted = 0;
+        }
+        self.counted += self.source[self.counted_to..offset].chars().count();
+        self.counted_to = offset;
+        self.counted + 1
+    }
+
+    fn push(&mut self, kind: TokenKind, start: usize) {
+        let column = self.column(start);
+        self.tokens.push(Token {
+            kind,
+            text: &self.source[start..self.pos],
+            start,
+            end: self.pos,
+            line: self.line,
+            column,
+            trailing: false,
+        });
+        self.line_has_tokens = true;
+    }
+
+    fn push_comment(&mut self, text: &'s str, start: usize) {
+        let column = self.column(start);
+        self.tokens.push(Token {
+            kind: TokenKind::Comment,
+            text,
+            start,
+            end: self.pos,
+            line: self.line,
+            column,
+            trailing: self.line_has_tokens,
+        });
+    }
+
+    fn error(&mut self, offset: usize, message: String) {
+        let column = self.column(offset);
+        self.errors.push(LexError {
+            line: self.line,
+            column,
+            message,
+        });
+    }
+
+    fn ident(&mut self, start: usize) {
+        loop {
+            while self.bytes.get(self.pos).copied().is_some_and(is_ident_char) {
+                self.pos += 1;
+            }
+            // `google.protobuf.Any` is a single token
+            if self.bytes.get(self.pos) == Some(&b'.')
+                && self
+                    .bytes
+                    .get(self.pos + 1)
+                    .copied()
+                    .is_some_and(is_ident_start)
+            {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        self.push(TokenKind::Ident, start);
+    }
+
+    fn number(&mut self) {
+        let start = self.pos;
+        let hex = self.source[start..].starts_with("0x") || self.source[start..].starts_with("0X");
+        if hex {
+            self.pos += 2;
+        }
+        let mut float = false;
+        while let Some(&byte) = self.bytes.get(self.pos) {
+            match byte {
+                b'.' if !hex => float = true,
+                b'e' | b'E' if !hex => {
+                    float = true;
+                    if matches!(self.bytes.get(self.pos + 1), Some(b'+' | b'-')) {
+                        self.pos += 1;
+                    }
+                }
+                _ if byte.is_ascii_alphanumeric() || byte == b'_' => {}
+                _ => break,
+            }
+            self.pos += 1;
+        }
+        let kind = if float {
+            TokenKind::Float
+        } else {
+            TokenKind::Int
+        };
+        self.push(kind, start);
+    }
+
+    fn string(&mut self, quote: u8) {
+        let start = self.pos;
+        self.pos += 1;
+        loop {
+            match self.bytes.get(self.pos) {
+                Some(&b'\\') if self.bytes.get(self.pos + 1) != Some(&b'\n') => self.pos += 2,
+                Some(&byte) if byte == quote => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(&b'\n') | None => {
+                    self.error(start, "Unterminated string".to_string());
+                    break;
+                }
+                Some(_) => self.pos += 1,
+            }
+        }
+        self.pos = self.pos.min(self.source.len());
+        self.push(TokenKind::String, start);
+    }
+
+    fn block_comment(&mut self) {
+        let start = self.pos;
+        let column = self.column(start);
+        let (text_end, end) = match self.source[start + 2..].find("*/") {
+            Some(i) => (start + 2 + i, start + 2 + i + 2),
+            None => {
+                self.error(start, "Unterminated comment".to_string());
+                (self.source.len(), self.source.len())
+            }
+        };
+        let trailing = self.line_has_tokens;
+        let line = self.line;
+        for (i, byte) in self.bytes[start..end].iter().enumerate() {
+            if *byte == b'\n' {
+                self.line += 1;
+                self.line_start = start + i + 1;
+                self.line_has_tokens = false;
+            }
+        }
+        self.pos = end;
+        self.tokens.push(Token {
+            kind: TokenKind::Comment,
+            text: &self.source[start + 2..text_end],
+            start,
+            end,
+            line,
+            column,
+            trailing,
+        });
+    }
+}
+
+fn is_ident_start(byte: u8) -> bool {
+    byte.is_ascii_alphabetic() || byte == b'_'
+}
+
+fn is_ident_char(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
@@ -1,11 +1,62 @@
+mod batch;
+mod builder;
+mod config;
+#[cfg(feature = "descriptor")]
+mod descriptor;
+pub mod diff;
+mod docgen;
 pub mod domain;
 pub mod errors;
+mod extract;
+pub mod formatter;
+pub mod frozen;
+mod imports;
+mod json_schema;
+mod lexer;
+pub mod lint;
+mod model_json;
 pub mod name_formatter;
+mod name_registry;
+mod naming;
+mod ordering;
 pub mod proto2model;
+pub mod proto2swagger;
+mod proto_set;
+mod ref_bundler;
+mod rename;
+mod resolver;
+mod roundtrip;
+mod stats;
 pub mod swagger2proto;
+mod util;
+mod validation;
+#[cfg(feature = "watch")]
+mod watch;
 
+pub use batch::{BatchOptions, BatchReport};
+pub use builder::{
+    EnumBuilder, EnumValueBuilder, FieldBuilder, MessageBuilder, MethodBuilder, OneofBuilder,
+    ProtoFileBuilder, ServiceBuilder,
+};
+pub use config::ConverterConfig;
 pub use domain::*;
 pub use errors::*;
+pub use formatter::{FormatOptions, Indent};
+pub use frozen::{ArcProtoFile, ResolvedItem};
+pub use model_json::MODEL_VERSION;
 pub use name_formatter::NameFormatter;
-pub use proto2model::ProtoParser;
-pub use swagger2proto::SwaggerToProtoConverter;
+pub use naming::{DefaultNaming, GoogleStyleNaming, NamingStrategy, NamingStyle};
+pub use ordering::DependencyOrder;
+pub use proto_set::ProtoSet;
+pub use proto2model::{DEFAULT_MAX_INPUT_SIZE, Diagnostic, ProtoParser, Severity};
+pub use proto2swagger::ProtoToOpenApiConverter;
+pub use resolver::{ResolvedType, TypeResolver};
+pub use roundtrip::roundtrip_check;
+pub use stats::ProtoStats;
+pub use swagger2proto::{
+    BasePath, ConversionReport, ConverterBuilder, ConverterOptions, DEFAULT_MAX_DEPTH,
+    FormatOverride, HeaderParams, NullableStrategy, Pagination, RequiredFields, ServiceGrouping,
+    SwaggerToProtoConverter,
+};
+#[cfg(feature = "watch")]
+pub use watch::{watch_and_convert, watch_until};
@@ -0,0 +1,303 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::name_formatter::PROTO_KEYWORDS;
+use crate::util::join_path;
+use crate::{Enum, Message, NameFormatter, ProtoFile, ScalarType};
+
+/// Field numbers reserved for the protobuf implementation
+const RESERVED_NUMBERS: std::ops::RangeInclusive<i32> = 19000..=19999;
+
+/// Style rules checked by [`lint`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LintRule {
+    /// Message names are PascalCase
+    MessagePascalCase,
+    /// Enum names are PascalCase
+    EnumPascalCase,
+    /// Field names are lower_snake_case
+    FieldLowerSnakeCase,
+    /// Enum values are SCREAMING_SNAKE_CASE
+    EnumValueUpperSnakeCase,
+    /// Enum values start with the enum name (`ORDER_STATUS_`)
+    EnumValuePrefix,
+    /// The zero value of an enum is `<PREFIX>_UNSPECIFIED`
+    EnumZeroValueUnspecified,
+    /// Service names end in `Service`
+    ServiceSuffix,
+    /// rpc names are PascalCase
+    RpcPascalCase,
+    /// No field uses the 19000-19999 range reserved by protobuf
+    FieldNumberReserved,
+}
+
+impl LintRule {
+    pub const ALL: [LintRule; 9] = [
+        LintRule::MessagePascalCase,
+        LintRule::EnumPascalCase,
+        LintRule::FieldLowerSnakeCase,
+        LintRule::EnumValueUpperSnakeCase,
+        LintRule::EnumValuePrefix,
+        LintRule::EnumZeroValueUnspecified,
+        LintRule::ServiceSuffix,
+        LintRule::RpcPascalCase,
+        LintRule::FieldNumberReserved,
+    ];
+
+    /// Stable identifier (`FIELD_LOWER_SNAKE_CASE`)
+    pub fn id(&self) -> &'static str {
+        match self {
+            LintRule::MessagePascalCase => "MESSAGE_PASCAL_CASE",
+            LintRule::EnumPascalCase => "ENUM_PASCAL_CASE",
+            LintRule::FieldLowerSnakeCase => "FIELD_LOWER_SNAKE_CASE",
+            LintRule::EnumValueUpperSnakeCase => "ENUM_VALUE_UPPER_SNAKE_CASE",
+            LintRule::EnumValuePrefix => "ENUM_VALUE_PREFIX",
+            LintRule::EnumZeroValueUnspecified => "ENUM_ZERO_VALUE_UNSPECIFIED",
+            LintRule::ServiceSuffix => "SERVICE_SUFFIX",
+            LintRule::RpcPascalCase => "RPC_PASCAL_CASE",
+            LintRule::FieldNumberReserved => "FIELD_NUMBER_RESERVED",
+        }
+    }
+}
+
+impl fmt::Display for LintRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+/// A rule broken by one element of the file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LintViolation {
+    pub rule: LintRule,
+    /// Qualified name of the offending element (`api.Order.status`)
+    pub element: String,
+    pub message: String,
+    /// What the element could be changed to
+    pub suggestion: String,
+}
+
+impl fmt::Display for LintViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{}] {}: {} (suggested: {})",
+            self.rule, self.element, self.message, self.suggestion
+        )
+    }
+}
+
+/// Which rules [`lint_with`] checks; everything is enabled by default
+#[derive(Debug, Clone, Default)]
+pub struct LintConfig {
+    pub disabled: HashSet<LintRule>,
+    /// Suffix the converter escaped keywords and scalar type names with,
+    /// `_` by default (see [`crate::ConverterOptions::reserved_suffix`])
+    pub reserved_suffix: Option<String>,
+}
+
+impl LintConfig {
+    pub fn disable(mut self, rule: LintRule) -> Self {
+        self.disabled.insert(rule);
+        self
+    }
+
+    pub fn reserved_suffix(mut self, suffix: &str) -> Self {
+        self.reserved_suffix = Some(suffix.to_string());
+        self
+    }
+
+    pub fn is_enabled(&self, rule: LintRule) -> bool {
+        !self.disabled.contains(&rule)
+    }
+}
+
+/// Checks the file against every rule
+pub fn lint(file: &ProtoFile) -> Vec<LintViolation> {
+    lint_with(file, &LintConfig::default())
+}
+
+/// Checks the file against the rules enabled in `config`
+pub fn lint_with(file: &ProtoFile, config: &LintConfig) -> Vec<LintViolation> {
+    let mut linter = Linter {
+        config,
+        violations: Vec::new(),
+    };
+
+    for message in &file.messages {
+        linter.message(message, &file.package);
+    }
+    for enum_def in &file.enums {
+        linter.enum_def(enum_def, &file.package);
+    }
+    for service in &file.services {
+        let path = join_path(&file.package, &service.name);
+        if !service.name.ends_with("Service") {
+            linter.report(
+                LintRule::ServiceSuffix,
+                &path,
+                "service name should end in Service",
+                format!("{}Service", service.name),
+            );
+        }
+        for method in &service.methods {
+            if !is_pascal_case(&method.name) {
+                linter.report(
+                    LintRule::RpcPascalCase,
+                    &join_path(&path, &method.name),
+                    "rpc name should be PascalCase",
+                    linter.to_pascal_case(&method.name),
+                );
+            }
+        }
+    }
+
+    linter.violations
+}
+
+struct Linter<'a> {
+    config: &'a LintConfig,
+    violations: Vec<LintViolation>,
+}
+
+impl NameFormatter for Linter<'_> {
+    fn reserved_suffix(&self) -> &str {
+        self.config.reserved_suffix.as_deref().unwrap_or("_")
+    }
+}
+
+impl Linter<'_> {
+    fn report(&mut self, rule: LintRule, element: &str, message: &str, suggestion: String) {
+        if self.config.is_enabled(rule) {
+            self.violations.push(LintViolation {
+                rule,
+                element: element.to_string(),
+                message: message.to_string(),
+                suggestion,
+            });
+        }
+    }
+
+    /// `message_` -> `message`: a keyword or scalar type name escaped with
+    /// the reserved suffix passes the case checks
+    fn unescaped<'n>(&self, name: &'n str) -> &'n str {
+        match name.strip_suffix(self.reserved_suffix()) {
+            Some(word)
+                if PROTO_KEYWORDS.contains(&word)
+                    || ScalarType::from_name(&word.to_lowercase()).is_some() =>
+            {
+                word
+            }
+            _ => name,
+        }
+    }
+
+    fn message(&mut self, message: &Message, prefix: &str) {
+        let path = join_path(prefix, &message.name);
+        if !is_pascal_case(self.unescaped(&message.name)) {
+            self.report(
+                LintRule::MessagePascalCase,
+                &path,
+                "message name should be PascalCase",
+                self.to_pascal_case(&message.name),
+            );
+        }
+
+        for field in message.all_fields() {
+            let field_path = join_path(&path, &field.name);
+            if !is_lower_snake_case(self.unescaped(&field.name)) {
+                self.report(
+                    LintRule::FieldLowerSnakeCase,
+                    &field_path,
+                    "field name should be lower_snake_case",
+                    self.to_screaming_snake_case(&field.name).to_lowercase(),
+                );
+            }
+            if RESERVED_NUMBERS.contains(&field.number) {
+                self.report(
+                    LintRule::FieldNumberReserved,
+                    &field_path,
+                    "field numbers 19000-19999 are reserved by protobuf",
+                    "a number outside 19000-19999".to_string(),
+                );
+            }
+        }
+
+        for nested in &message.nested_messages {
+            self.message(nested, &path);
+        }
+        for enum_def in &message.nested_enums {
+            self.enum_def(enum_def, &path);
+        }
+    }
+
+    fn enum_def(&mut self, enum_def: &Enum, prefix: &str) {
+        let path = join_path(prefix, &enum_def.name);
+        if !is_pascal_case(&enum_def.name) {
+            self.report(
+                LintRule::EnumPascalCase,
+                &path,
+                "enum name should be PascalCase",
+                self.to_pascal_case(&enum_def.name),
+            );
+        }
+
+        let value_prefix = format!("{}_", self.to_screaming_snake_case(&enum_def.name));
+        for value in &enum_def.values {
+            let value_path = join_path(&path, &value.name);
+            let upper = self.to_screaming_snake_case(&value.name);
+            if !is_upper_snake_case(&value.name) {
+                self.report(
+                    LintRule::EnumValueUpperSnakeCase,
+                    &value_path,
+                    "enum value should be SCREAMING_SNAKE_CASE",
+                    upper.clone(),
+                );
+            }
+            if !upper.starts_with(&value_prefix) {
+                self.report(
+                    LintRule::EnumValuePrefix,
+                    &value_path,
+                    &format!("enum value should start with {}", value_prefix),
+                    format!("{}{}", value_prefix, upper),
+                );
+            }
+        }
+
+        if let Some(zero) = enum_def.values.iter().find(|v| v.number == 0)
+            && zero.name != format!("{}UNSPECIFIED", value_prefix)
+        {
+            self.report(
+                LintRule::EnumZeroValueUnspecified,
+                &join_path(&path, &zero.name),
+                "the zero value should be the _UNSPECIFIED sentinel",
+                format!("{}UNSPECIFIED", value_prefix),
+            );
+        }
+    }
+}
+
+fn is_pascal_case(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase())
+        && name.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+/// `ORDER_STATUS_2FA`; digits may follow letters without an underscore
+fn is_upper_snake_case(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+        && !name.contains("__")
+        && !name.ends_with('_')
+}
+
+fn is_lower_snake_case(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        && !name.contains("__")
+        && !name.ends_with('_')
+}
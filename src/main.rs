@@ -1,17 +1,348 @@
-use std::path::Path;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
-use dot_proto_parser::{ProtoParser, SwaggerToProtoConverter};
+use clap::{Parser, Subcommand, ValueEnum};
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Конвертация Swagger → Proto
-    // let mut converter = SwaggerToProtoConverter::new("api");
-    // converter.convert_file(Path::new("swagger.json"), Path::new("api.proto"))?;
+use dot_proto_parser::{
+    BatchOptions, ConverterConfig, Error, ProtoFile, ProtoParseError, ProtoParser,
+    SwaggerToProtoConverter,
+};
 
-    // Обратная конвертация Proto → Model
+/// swagger -> .proto converter and .proto tooling
+#[derive(Debug, Parser)]
+#[command(name = "dot_proto_parser", version, about)]
+struct Cli {
+    /// Print errors only
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+    /// Also print what is being done
+    #[arg(short, long, global = true)]
+    verbose: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Convert a swagger 2.0 / OpenAPI 3 document (JSON or YAML) to .proto
+    Convert {
+        /// `-` reads JSON from stdin
+        input: PathBuf,
+        /// Output .proto file, `-` for stdout
+        #[arg(short, long)]
+        output: PathBuf,
+        /// `api` by default; with `--dir` a template where `{stem}` is the
+        /// file name, `{stem}` by default
+        #[arg(long)]
+        package: Option<String>,
+        /// Convert only operations with one of these tags
+        #[arg(long, value_delimiter = ',')]
+        tags: Vec<String>,
+        /// Settings file (`proto-gen.toml`, `.yaml`); the flags above take
+        /// precedence over it
+        #[arg(long)]
+        config: Option<PathBuf>,
+        /// Convert every JSON/YAML file of the input directory into the
+        /// output directory
+        #[arg(long)]
+        dir: bool,
+        /// Files converted in parallel with `--dir`
+        #[arg(long, default_value_t = 1)]
+        jobs: usize,
+        /// Convert again whenever the input changes, until Ctrl-C
+        #[cfg(feature = "watch")]
+        #[arg(long, conflicts_with = "dir")]
+        watch: bool,
+    },
+    /// Parse a .proto file and dump the model to stdout
+    Parse {
+        file: PathBuf,
+        #[arg(long, value_enum, default_value_t = DumpFormat::Debug)]
+        format: DumpFormat,
+        /// Same as `--format json`
+        #[arg(long, conflicts_with = "format")]
+        json: bool,
+        /// Dump only the list of top-level messages
+        #[arg(long, conflicts_with = "services_only")]
+        messages_only: bool,
+        /// Dump only the list of services
+        #[arg(long)]
+        services_only: bool,
+    },
+    /// Check a .proto file for definitions protoc would reject
+    Validate { file: PathBuf },
+    /// Reformat a .proto file
+    Fmt {
+        file: PathBuf,
+        /// Overwrite the file instead of printing the result
+        #[arg(long)]
+        write: bool,
+    },
+}
+
+/// How `parse` prints the model
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum DumpFormat {
+    /// Rust debug output, for people
+    Debug,
+    /// The serialized `ProtoFile`, see `ProtoFile::to_json`
+    Json,
+    Yaml,
+}
+
+/// Где и что печатать, по флагам `--quiet` / `--verbose`
+struct Log {
+    quiet: bool,
+    verbose: bool,
+}
+
+impl Log {
+    fn info(&self, message: &str) {
+        if self.verbose {
+            eprintln!("{}", message);
+        }
+    }
+
+    fn warn(&self, message: &str) {
+        if !self.quiet {
+            eprintln!("warning: {}", message);
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let log = Log {
+        quiet: cli.quiet,
+        verbose: cli.verbose,
+    };
+
+    match run(cli.command, &log) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("error: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(command: Command, log: &Log) -> Result<(), String> {
+    match command {
+        Command::Convert {
+            input,
+            output,
+            package,
+            tags,
+            config,
+            dir,
+            jobs,
+            #[cfg(feature = "watch")]
+            watch,
+        } => {
+            let config = match &config {
+                Some(path) => ConverterConfig::from_file(path).map_err(|e| e.to_string())?,
+                None => ConverterConfig::default(),
+            };
+            let package = package.or_else(|| config.package.clone());
+
+            if dir {
+                let mut converter = config.options();
+                if !tags.is_empty() {
+                    converter.only_tags = tags;
+                }
+                let options = BatchOptions {
+                    package_template: package.unwrap_or_else(|| "{stem}".to_string()),
+                    converter,
+                    threads: jobs,
+                };
+                return convert_dir(&input, &output, &options, log);
+            }
+
+            let tags: Vec<&str> = tags.iter().map(String::as_str).collect();
+            let mut builder = config
+                .builder()
+                .package(package.as_deref().unwrap_or("api"));
+            if !tags.is_empty() {
+                builder = builder.only_tags(&tags);
+            }
+            #[cfg(feature = "watch")]
+            if watch {
+                if is_stdio(&input) || is_stdio(&output) {
+                    return Err("--watch needs files, not stdin/stdout".to_string());
+                }
+                log.info(&format!("watching {}", input.display()));
+                return dot_proto_parser::watch_and_convert(&input, &output, &builder)
+                    .map_err(|e| e.to_string());
+            }
+
+            log.info(&format!("converting {}", input.display()));
+            let mut converter = builder
+                .base_dir(input.parent().unwrap_or(Path::new("")))
+                .build();
+
+            let spec = read_spec(&input)?;
+            let proto = converter
+                .convert_value(spec)
+                .map_err(|e| format!("{}: {}", input.display(), e))?;
+            write_output(&output, |w| proto.write_proto_text(w))?;
+            for warning in converter.warnings() {
+                log.warn(&warning.to_string());
+            }
+            log.info(&format!("wrote {}", output.display()));
+            Ok(())
+        }
+        Command::Parse {
+            file,
+            format,
+            json,
+            messages_only,
+            services_only,
+        } => {
+            let proto = parse(&file)?;
+            let format = if json { DumpFormat::Json } else { format };
+            let text = if format == DumpFormat::Debug {
+                if messages_only {
+                    format!("{:#?}", proto.messages)
+                } else if services_only {
+                    format!("{:#?}", proto.services)
+                } else {
+                    format!("{:#?}", proto)
+                }
+            } else {
+                let value = if messages_only {
+                    serde_json::to_value(&proto.messages)
+                } else if services_only {
+                    serde_json::to_value(&proto.services)
+                } else {
+                    serde_json::to_value(&proto)
+                };
+                let value = value.map_err(|e| e.to_string())?;
+                if format == DumpFormat::Yaml {
+                    serde_yaml::to_string(&value).map_err(|e| e.to_string())?
+                } else {
+                    serde_json::to_string_pretty(&value).map_err(|e| e.to_string())?
+                }
+            };
+            write_output(Path::new("-"), |w| writeln!(w, "{}", text.trim_end()))
+        }
+        Command::Validate { file } => {
+            let proto = parse(&file)?;
+            match proto.validate() {
+                Ok(()) => {
+                    log.info(&format!("{}: ok", file.display()));
+                    Ok(())
+                }
+                Err(errors) => {
+                    for error in &errors {
+                        eprintln!("{}: {}", file.display(), error);
+                    }
+                    Err(format!("{} problem(s) in {}", errors.len(), file.display()))
+                }
+            }
+        }
+        Command::Fmt { file, write } => {
+            if write && is_stdio(&file) {
+                return Err("--write needs a file, not stdin".to_string());
+            }
+            let proto = parse(&file)?;
+            let output = if write {
+                file.as_path()
+            } else {
+                Path::new("-")
+            };
+            write_output(output, |w| proto.write_proto_text(w))?;
+            if write {
+                log.info(&format!("formatted {}", file.display()));
+            }
+            Ok(())
+        }
+    }
+}
+
+fn convert_dir(
+    input: &Path,
+    output: &Path,
+    options: &BatchOptions,
+    log: &Log,
+) -> Result<(), String> {
+    let report =
+        SwaggerToProtoConverter::convert_dir(input, output, options).map_err(|e| e.to_string())?;
+    for (spec, proto) in &report.converted {
+        log.info(&format!("{} -> {}", spec.display(), proto.display()));
+    }
+    for (_, error) in &report.failed {
+        eprintln!("{}", error);
+    }
+    if report.is_success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} of {} file(s) failed",
+            report.failed.len(),
+            report.failed.len() + report.converted.len()
+        ))
+    }
+}
+
+/// `-` stands for stdin or stdout
+fn is_stdio(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+/// Parses a .proto file or stdin; errors are prefixed with `file:line`
+fn parse(file: &Path) -> Result<ProtoFile, String> {
     let mut parser = ProtoParser::new();
-    let proto_file = parser.parse_file(Path::new("api.proto"))?;
+    if !is_stdio(file) {
+        // Ошибки `parse_file` уже начинаются с `file:line`
+        return parser.parse_file(file).map_err(|error| match error {
+            Error::ProtoParse(error) => error.to_string(),
+            other => other.to_string(),
+        });
+    }
+    parser
+        .parse_reader(io::stdin().lock())
+        .map_err(|error| match error {
+            Error::ProtoParse(ProtoParseError::ParseError { line, message }) => {
+                format!("stdin:{}: {}", line, message)
+            }
+            other => format!("stdin: {}", other),
+        })
+}
 
-    println!("Parsed proto file: {:?}", proto_file);
+/// Swagger/OpenAPI document, `.yaml`/`.yml` read as YAML, stdin as JSON
+fn read_spec(path: &Path) -> Result<serde_json::Value, String> {
+    if is_stdio(path) {
+        return serde_json::from_reader(io::stdin().lock()).map_err(|e| format!("stdin: {}", e));
+    }
+    let content =
+        std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let is_yaml = matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("yaml") | Some("yml")
+    );
+    let spec = if is_yaml {
+        serde_yaml::from_str(&content).map_err(|e| e.to_string())
+    } else {
+        serde_json::from_str(&content).map_err(|e| e.to_string())
+    };
+    spec.map_err(|e| format!("{}: {}", path.display(), e))
+}
 
-    Ok(())
+/// Runs `write` against the file, or stdout for `-`, buffered
+fn write_output(
+    path: &Path,
+    write: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+) -> Result<(), String> {
+    let result = if is_stdio(path) {
+        let mut stdout = BufWriter::new(io::stdout().lock());
+        write(&mut stdout).and_then(|()| stdout.flush())
+    } else {
+        File::create(path).and_then(|file| {
+            let mut file = BufWriter::new(file);
+            write(&mut file).and_then(|()| file.flush())
+        })
+    };
+    result.map_err(|e| format!("{}: {}", path.display(), e))
 }
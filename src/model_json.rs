@@ -0,0 +1,51 @@
+use serde::{Deserialize, Deserializer};
+
+use crate::{Error, ProtoFile};
+
+/// Version of the serialized [`ProtoFile`] shape; bumped whenever a change
+/// makes older JSON unreadable or ambiguous
+pub const MODEL_VERSION: u32 = 1;
+
+impl ProtoFile {
+    /// Serializes the model for caching. Empty collections are left out
+    /// and options keep their insertion order, so equal models produce
+    /// equal text.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Reads a model written by [`ProtoFile::to_json`], rejecting other
+    /// model versions with [`Error::IncompatibleModelVersion`]
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let value: serde_json::Value = serde_json::from_str(json)?;
+        let found = value
+            .get("model_version")
+            .and_then(serde_json::Value::as_u64)
+            .unwrap_or(0);
+        if found != u64::from(MODEL_VERSION) {
+            return Err(Error::IncompatibleModelVersion {
+                found,
+                supported: MODEL_VERSION,
+            });
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+pub(crate) fn compatible_model_version<'de, D>(deserializer: D) -> Result<u32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let version = u32::deserialize(deserializer)?;
+    if version != MODEL_VERSION {
+        return Err(serde::de::Error::custom(format!(
+            "incompatible model version {}, supported {}",
+            version, MODEL_VERSION
+        )));
+    }
+    Ok(version)
+}
+
+pub(crate) fn is_false(value: &bool) -> bool {
+    !value
+}
@@ -1,5 +1,104 @@
+use std::borrow::Cow;
+
+use crate::ScalarType;
+use crate::util::fnv1a_32;
+
+/// Words of the proto2/proto3 grammar; identifiers spelled like them are
+/// escaped by [`NameFormatter::escape_reserved`]
+pub const PROTO_KEYWORDS: &[&str] = &[
+    "syntax",
+    "edition",
+    "import",
+    "weak",
+    "public",
+    "package",
+    "option",
+    "optional",
+    "required",
+    "repeated",
+    "group",
+    "oneof",
+    "map",
+    "extensions",
+    "extend",
+    "reserved",
+    "to",
+    "max",
+    "enum",
+    "message",
+    "service",
+    "rpc",
+    "stream",
+    "returns",
+    "default",
+    "true",
+    "false",
+    "inf",
+    "nan",
+];
+
 pub trait NameFormatter {
+    /// Appended to identifiers that are keywords or scalar type names
+    fn reserved_suffix(&self) -> &str {
+        "_"
+    }
+
+    /// Keywords and scalar type names get [`NameFormatter::reserved_suffix`]:
+    /// `message` -> `message_`, `string` -> `string_`
+    ///
+    /// ```
+    /// use dot_proto_parser::NameFormatter;
+    /// use dot_proto_parser::name_formatter::PROTO_KEYWORDS;
+    ///
+    /// struct Names;
+    /// impl NameFormatter for Names {}
+    ///
+    /// for keyword in PROTO_KEYWORDS {
+    ///     assert_eq!(Names.sanitize_field_name(keyword), format!("{}_", keyword));
+    /// }
+    /// assert_eq!(Names.sanitize_field_name("bytes"), "bytes_");
+    /// assert_eq!(Names.sanitize_field_name("messages"), "messages");
+    /// assert_eq!(Names.sanitize_type_name("string"), "String_");
+    /// assert_eq!(Names.sanitize_type_name("user_dto"), "UserDto");
+    /// ```
+    fn escape_reserved(&self, name: &str) -> String {
+        if PROTO_KEYWORDS.contains(&name) || ScalarType::from_name(name).is_some() {
+            format!("{}{}", name, self.reserved_suffix())
+        } else {
+            name.to_string()
+        }
+    }
+
+    /// Type names spelled like a scalar type in any case get
+    /// [`NameFormatter::reserved_suffix`]: `String` -> `String_`
+    fn escape_reserved_type(&self, name: &str) -> String {
+        if ScalarType::from_name(&name.to_lowercase()).is_some() {
+            format!("{}{}", name, self.reserved_suffix())
+        } else {
+            name.to_string()
+        }
+    }
+
+    /// PascalCase message or enum name that does not read as a scalar type:
+    /// `string` -> `String_`
+    fn sanitize_type_name(&self, name: &str) -> String {
+        let pascal = self.to_pascal_case(name);
+        if pascal.starts_with(|c: char| c.is_ascii_digit()) {
+            self.escape_reserved_type(&format!("_{}", pascal))
+        } else {
+            self.escape_reserved_type(&pascal)
+        }
+    }
+
+    /// Field name made of ASCII letters, digits and single underscores,
+    /// with keywords escaped
     fn sanitize_field_name(&self, name: &str) -> String {
+        self.escape_reserved(&self.sanitize_identifier(name))
+    }
+
+    /// [`NameFormatter::sanitize_field_name`] without the keyword check
+    fn sanitize_identifier(&self, name: &str) -> String {
+        let name = transliterate(name);
         let mut sanitized = String::with_capacity(name.len());
         let mut prev_was_underscore = false;
 
@@ -40,7 +139,8 @@ pub trait NameFormatter {
     }
 
     fn to_pascal_case(&self, s: &str) -> String {
-        s.split(|c: char| !c.is_alphanumeric())
+        transliterate(s)
+            .split(|c: char| !c.is_alphanumeric())
             .filter(|part| !part.is_empty())
             .map(|part| {
                 let mut c = part.chars();
@@ -51,4 +151,203 @@ pub trait NameFormatter {
             })
             .collect()
     }
+
+    fn to_screaming_snake_case(&self, s: &str) -> String {
+        let s = transliterate(s);
+        let chars: Vec<char> = s.chars().collect();
+        let mut result = String::with_capacity(s.len() + 4);
+
+        for (i, &c) in chars.iter().enumerate() {
+            if !c.is_alphanumeric() {
+                if !result.is_empty() && !result.ends_with('_') {
+                    result.push('_');
+                }
+                continue;
+            }
+
+            // Граница слова: aB -> A_B, ABc -> A_BC
+            if c.is_uppercase() && i > 0 && !result.is_empty() && !result.ends_with('_') {
+                let prev = chars[i - 1];
+                let next_is_lower = chars.get(i + 1).map(|n| n.is_lowercase()).unwrap_or(false);
+                if prev.is_lowercase()
+                    || prev.is_ascii_digit()
+                    || (prev.is_uppercase() && next_is_lower)
+                {
+                    result.push('_');
+                }
+            }
+
+            result.extend(c.to_uppercase());
+        }
+
+        result.trim_end_matches('_').to_string()
+    }
+
+    /// lower_snake_case, the proto style for field names. Runs of capitals
+    /// are kept together as one word.
+    ///
+    /// ```
+    /// use dot_proto_parser::NameFormatter;
+    ///
+    /// struct Names;
+    /// impl NameFormatter for Names {}
+    ///
+    /// assert_eq!(Names.to_snake_case("userId"), "user_id");
+    /// assert_eq!(Names.to_snake_case("HTTPServer"), "http_server");
+    /// assert_eq!(Names.to_snake_case("getHTTPResponseCode"), "get_http_response_code");
+    /// assert_eq!(Names.to_snake_case("v2Api"), "v2_api");
+    /// assert_eq!(Names.to_snake_case("address2"), "address2");
+    /// assert_eq!(Names.to_snake_case("X-Request-ID"), "x_request_id");
+    /// assert_eq!(Names.to_snake_case("created_at"), "created_at");
+    /// ```
+    fn to_snake_case(&self, s: &str) -> String {
+        self.to_screaming_snake_case(s).to_lowercase()
+    }
+
+    /// lowerCamelCase, the form protoc derives `json_name` from
+    ///
+    /// ```
+    /// use dot_proto_parser::NameFormatter;
+    ///
+    /// struct Names;
+    /// impl NameFormatter for Names {}
+    ///
+    /// assert_eq!(Names.to_camel_case("user_id"), "userId");
+    /// assert_eq!(Names.to_camel_case("HTTPServer"), "httpServer");
+    /// assert_eq!(Names.to_camel_case("x-request-id"), "xRequestId");
+    /// assert_eq!(Names.to_camel_case("address_2"), "address2");
+    /// ```
+    fn to_camel_case(&self, s: &str) -> String {
+        let snake = self.to_snake_case(s);
+        let mut words = snake.split('_').filter(|word| !word.is_empty());
+        let mut result = words.next().unwrap_or_default().to_string();
+        for word in words {
+            let mut chars = word.chars();
+            if let Some(first) = chars.next() {
+                result.extend(first.to_uppercase());
+                result.push_str(chars.as_str());
+            }
+        }
+        result
+    }
+}
+
+/// ASCII spelling of `name` for identifiers: Cyrillic and accented Latin
+/// letters are transliterated (`имя_пользователя` -> `imya_polzovatelya`).
+/// Letters of other scripts are dropped and a hash of `name` is appended
+/// instead, so distinct names stay distinct.
+///
+/// ```
+/// use dot_proto_parser::name_formatter::transliterate;
+///
+/// assert_eq!(transliterate("имя_пользователя"), "imya_polzovatelya");
+/// assert_eq!(transliterate("ЖУРНАЛ"), "ZHURNAL");
+/// assert_eq!(transliterate("Größe"), "Grosse");
+/// assert_ne!(transliterate("名前"), transliterate("名字"));
+/// ```
+pub fn transliterate(name: &str) -> Cow<'_, str> {
+    if name.is_ascii() {
+        return Cow::Borrowed(name);
+    }
+
+    let chars: Vec<char> = name.chars().collect();
+    let mut result = String::with_capacity(name.len());
+    let mut lossy = false;
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_ascii() {
+            result.push(c);
+            continue;
+        }
+        let lower = c.to_lowercase().next().unwrap_or(c);
+        match transliterate_char(lower) {
+            Some(ascii) if c.is_uppercase() => {
+                // ЖУК -> ZHUK, Жук -> Zhuk
+                let neighbour_upper = |j: Option<usize>| {
+                    j.and_then(|j| chars.get(j))
+                        .is_some_and(|n| n.is_uppercase())
+                };
+                if neighbour_upper(i.checked_sub(1)) || neighbour_upper(Some(i + 1)) {
+                    result.push_str(&ascii.to_uppercase());
+                } else {
+                    let mut letters = ascii.chars();
+                    result.extend(letters.next().map(|f| f.to_ascii_uppercase()));
+                    result.push_str(letters.as_str());
+                }
+            }
+            Some(ascii) => result.push_str(ascii),
+            None if c.is_alphanumeric() => {
+                lossy = true;
+                result.push('_');
+            }
+            None => result.push('_'),
+        }
+    }
+
+    if lossy {
+        result.push_str(&format!("_u{:08x}", fnv1a_32(name.as_bytes())));
+    }
+    Cow::Owned(result)
+}
+
+fn transliterate_char(c: char) -> Option<&'static str> {
+    let ascii = match c {
+        // Кириллица
+        'а' => "a",
+        'б' => "b",
+        'в' => "v",
+        'г' | 'ґ' => "g",
+        'д' => "d",
+        'е' | 'ё' | 'э' => "e",
+        'є' => "ye",
+        'ж' => "zh",
+        'з' => "z",
+        'и' | 'і' => "i",
+        'ї' => "yi",
+        'й' | 'ы' => "y",
+        'к' => "k",
+        'л' => "l",
+        'м' => "m",
+        'н' => "n",
+        'о' => "o",
+        'п' => "p",
+        'р' => "r",
+        'с' => "s",
+        'т' => "t",
+        'у' => "u",
+        'ф' => "f",
+        'х' => "kh",
+        'ц' => "ts",
+        'ч' => "ch",
+        'ш' => "sh",
+        'щ' => "shch",
+        'ъ' | 'ь' => "",
+        'ю' => "yu",
+        'я' => "ya",
+        // Latin-1 and Latin Extended-A
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'æ' => "ae",
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => "c",
+        'ď' | 'đ' | 'ð' => "d",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => "e",
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => "g",
+        'ĥ' | 'ħ' => "h",
+        'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => "i",
+        'ĵ' => "j",
+        'ķ' => "k",
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => "l",
+        'ñ' | 'ń' | 'ņ' | 'ň' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => "o",
+        'œ' => "oe",
+        'ŕ' | 'ŗ' | 'ř' => "r",
+        'ś' | 'ŝ' | 'ş' | 'š' => "s",
+        'ß' => "ss",
+        'ţ' | 'ť' | 'ŧ' => "t",
+        'þ' => "th",
+        'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => "u",
+        'ŵ' => "w",
+        'ý' | 'ÿ' | 'ŷ' => "y",
+        'ź' | 'ż' | 'ž' => "z",
+        _ => return None,
+    };
+    Some(ascii)
 }
@@ -0,0 +1,138 @@
+use std::collections::{HashMap, HashSet};
+
+use indexmap::IndexSet;
+
+use crate::ScalarType;
+use crate::imports::well_known_import;
+
+/// Top-level message and enum names of the file being generated.
+///
+/// Every top-level name of the file is taken here, including those not
+/// allocated by the registry, such as `<Name>Status` root enums.
+///
+/// Every generated name is allocated here, so a clash is resolved with a
+/// numeric suffix (`PetList`, `PetList2`) instead of failing in
+/// `ProtoFile::add_message`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct NameRegistry {
+    /// In allocation order, so a fork can hand back what it allocated
+    taken: IndexSet<String>,
+    /// Names of the file passed to `with_proto`; definitions may reuse them
+    existing: HashSet<String>,
+    /// Schema name -> name of its message or enum
+    definitions: HashMap<String, String>,
+    /// Schemas already converted into their message or enum
+    converted: HashSet<String>,
+}
+
+impl NameRegistry {
+    /// A message or enum that is already in the file
+    pub(crate) fn reserve_existing(&mut self, name: &str) {
+        self.taken.insert(name.to_string());
+        self.existing.insert(name.to_string());
+    }
+
+    /// Allocates the name of the definition `schema`, `base` unless another
+    /// definition has it. Definitions may be named like `google.protobuf`
+    /// types, the converter always qualifies those. Asking again for the
+    /// same schema returns the same name.
+    pub(crate) fn define(&mut self, schema: &str, base: &str) -> Allocated {
+        if let Some(name) = self.definitions.get(schema) {
+            return Allocated::as_is(name);
+        }
+        let allocated = if self.existing.contains(base) {
+            Allocated::as_is(base)
+        } else {
+            self.next_free(base, |_| false)
+        };
+        self.definitions
+            .insert(schema.to_string(), allocated.name.clone());
+        allocated
+    }
+
+    /// Name allocated for the definition `schema`
+    pub(crate) fn definition(&self, schema: &str) -> Option<&str> {
+        self.definitions.get(schema).map(String::as_str)
+    }
+
+    /// Marks `schema` converted; `false` if it already was
+    pub(crate) fn mark_converted(&mut self, schema: &str) -> bool {
+        self.converted.insert(schema.to_string())
+    }
+
+    pub(crate) fn is_converted(&self, schema: &str) -> bool {
+        self.converted.contains(schema)
+    }
+
+    #[cfg(feature = "parallel")]
+    pub(crate) fn is_taken(&self, name: &str) -> bool {
+        self.taken.contains(name)
+    }
+
+    /// Takes a name allocated on a fork of the converter
+    #[cfg(feature = "parallel")]
+    pub(crate) fn claim(&mut self, name: String) {
+        self.taken.insert(name);
+    }
+
+    /// Number of names taken so far, a mark for [`NameRegistry::release_since`]
+    #[cfg(feature = "parallel")]
+    pub(crate) fn mark(&self) -> usize {
+        self.taken.len()
+    }
+
+    /// Frees the names taken after `mark` and returns them in allocation
+    /// order
+    #[cfg(feature = "parallel")]
+    pub(crate) fn release_since(&mut self, mark: usize) -> Vec<String> {
+        self.taken.drain(mark..).collect()
+    }
+
+    /// `base`, or `base2`, `base3`, ... for the first name that is neither
+    /// allocated, nor a scalar or `google.protobuf` type name, nor taken
+    /// according to `taken_elsewhere`
+    pub(crate) fn allocate(
+        &mut self,
+        base: &str,
+        taken_elsewhere: impl Fn(&str) -> bool,
+    ) -> Allocated {
+        self.next_free(base, |name| {
+            ScalarType::from_name(&name.to_lowercase()).is_some()
+                || well_known_import(name).is_some()
+                || taken_elsewhere(name)
+        })
+    }
+
+    fn next_free(&mut self, base: &str, taken_elsewhere: impl Fn(&str) -> bool) -> Allocated {
+        let is_taken = |name: &str| self.taken.contains(name) || taken_elsewhere(name);
+        let mut name = base.to_string();
+        let mut suffix = 2;
+        while is_taken(&name) {
+            name = format!("{}{}", base, suffix);
+            suffix += 1;
+        }
+
+        self.taken.insert(name.clone());
+        Allocated {
+            renamed: name != base,
+            name,
+        }
+    }
+}
+
+/// Result of [`NameRegistry::allocate`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Allocated {
+    pub(crate) name: String,
+    /// The requested name was taken
+    pub(crate) renamed: bool,
+}
+
+impl Allocated {
+    fn as_is(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            renamed: false,
+        }
+    }
+}
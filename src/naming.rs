@@ -0,0 +1,198 @@
+use std::fmt;
+
+use serde::Deserialize;
+
+use crate::NameFormatter;
+
+/// Names the converter gives to generated messages, fields, enum values,
+/// services and rpcs.
+///
+/// Every method has a default matching the converter's built-in
+/// conventions, so a strategy only overrides what it changes. Names are
+/// returned unescaped; the converter appends
+/// [`NameFormatter::reserved_suffix`] to keywords and scalar type names
+/// itself.
+pub trait NamingStrategy: NameFormatter + fmt::Debug + Send + Sync {
+    /// Message or enum generated for a schema definition: `user_account`
+    /// -> `UserAccount`
+    fn message_name(&self, schema_name: &str) -> String {
+        let pascal = self.to_pascal_case(schema_name);
+        if pascal.starts_with(|c: char| c.is_ascii_digit()) {
+            format!("_{}", pascal)
+        } else {
+            pascal
+        }
+    }
+
+    /// Field for a schema property: `userId` -> `user_id`
+    fn field_name(&self, property: &str) -> String {
+        self.sanitize_identifier(&self.to_snake_case(property))
+    }
+
+    /// Field for a query, path, header or cookie parameter: `petId` ->
+    /// `pet_id`, `X-Request-Id` -> `x_request_id`
+    fn parameter_field_name(&self, parameter: &str, location: &str) -> String {
+        match location {
+            "header" | "cookie" => self.sanitize_identifier(&parameter.to_lowercase()),
+            _ => self.field_name(parameter),
+        }
+    }
+
+    /// Inline enum or message declared by `property` of `parent`:
+    /// `Order` + `status` -> `OrderStatus`
+    fn nested_type_name(&self, parent: &str, property: &str) -> String {
+        format!("{}{}", parent, self.to_pascal_case(property))
+    }
+
+    /// Inline schema number `index` (from 1) of a oneOf/anyOf
+    fn variant_type_name(&self, parent: &str, index: usize) -> String {
+        format!("{}Variant{}", parent, index)
+    }
+
+    /// Wrapper message for a repeated `item`
+    fn list_message_name(&self, item: &str) -> String {
+        format!("{}List", self.to_pascal_case(item))
+    }
+
+    /// Enum value for `value`, a swagger enum value already in
+    /// SCREAMING_SNAKE_CASE. Values share the package scope, so they are
+    /// prefixed with the enum name: `OrderStatus` + `ACTIVE` ->
+    /// `ORDER_STATUS_ACTIVE`.
+    fn enum_value_name(&self, enum_name: &str, value: &str) -> String {
+        let prefix = self.to_screaming_snake_case(enum_name);
+        if value.starts_with(&format!("{}_", prefix)) {
+            value.to_string()
+        } else {
+            format!("{}_{}", prefix, value)
+        }
+    }
+
+    /// Zero value of an enum: `ORDER_STATUS_UNSPECIFIED`
+    fn enum_zero_value_name(&self, enum_name: &str) -> String {
+        format!("{}_UNSPECIFIED", self.to_screaming_snake_case(enum_name))
+    }
+
+    /// Service for a tag or path segment: `pet` -> `PetService`
+    fn service_name(&self, group: &str) -> String {
+        format!("{}Service", self.to_pascal_case(group))
+    }
+
+    /// rpc for an operation: the operationId, or verb and path
+    /// (`GET /v1/user-accounts/{id}` -> `GetV1UserAccountsId`)
+    fn method_name(&self, operation_id: Option<&str>, http_method: &str, path: &str) -> String {
+        match operation_id {
+            Some(id) => self.to_pascal_case(id),
+            None => format!(
+                "{}{}",
+                self.to_pascal_case(&http_method.to_lowercase()),
+                self.to_pascal_case(path)
+            ),
+        }
+    }
+
+    /// Request of an rpc with both parameters and a body; `service` is the
+    /// service name without the `Service` suffix
+    fn request_message_name(&self, service: &str, method: &str) -> String {
+        format!("{}{}Request", service, method)
+    }
+
+    /// Query and path parameters of an rpc
+    fn query_params_message_name(&self, service: &str, method: &str) -> String {
+        format!("{}{}QueryParams", service, method)
+    }
+
+    /// Header and cookie parameters, with [`crate::HeaderParams::SeparateMessage`]
+    fn headers_message_name(&self, service: &str, method: &str) -> String {
+        format!("{}{}Headers", service, method)
+    }
+
+    fn request_body_message_name(&self, service: &str, method: &str) -> String {
+        format!("{}{}RequestBody", service, method)
+    }
+
+    /// Result of an rpc whose response has no schema of its own
+    fn response_message_name(&self, service: &str, method: &str) -> String {
+        format!("{}{}Response", service, method)
+    }
+
+    /// Messages for the error responses of an rpc
+    fn error_message_name(&self, service: &str, method: &str) -> String {
+        format!("{}{}Error", service, method)
+    }
+
+    /// Headers of the successful response, with
+    /// [`crate::ConverterOptions::response_metadata`]
+    fn response_metadata_message_name(&self, service: &str, method: &str) -> String {
+        format!("{}{}ResponseMetadata", service, method)
+    }
+}
+
+/// The converter's built-in conventions
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultNaming;
+
+impl NameFormatter for DefaultNaming {}
+impl NamingStrategy for DefaultNaming {}
+
+/// Names after the Google proto style guide: lower_snake_case for every
+/// field, header parameters split by word (`ETag` -> `e_tag`), and
+/// `<Method>Request` / `<Method>Response` without the service name, which
+/// assumes rpc names are unique within the package
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GoogleStyleNaming;
+
+impl NameFormatter for GoogleStyleNaming {}
+
+impl NamingStrategy for GoogleStyleNaming {
+    fn parameter_field_name(&self, parameter: &str, _location: &str) -> String {
+        self.field_name(parameter)
+    }
+
+    fn request_message_name(&self, _service: &str, method: &str) -> String {
+        format!("{}Request", method)
+    }
+
+    fn query_params_message_name(&self, _service: &str, method: &str) -> String {
+        format!("{}Params", method)
+    }
+
+    fn headers_message_name(&self, _service: &str, method: &str) -> String {
+        format!("{}Headers", method)
+    }
+
+    fn request_body_message_name(&self, _service: &str, method: &str) -> String {
+        format!("{}Body", method)
+    }
+
+    fn response_message_name(&self, _service: &str, method: &str) -> String {
+        format!("{}Response", method)
+    }
+
+    fn error_message_name(&self, _service: &str, method: &str) -> String {
+        format!("{}Error", method)
+    }
+
+    fn response_metadata_message_name(&self, _service: &str, method: &str) -> String {
+        format!("{}ResponseMetadata", method)
+    }
+}
+
+/// Built-in strategies by name, for configuration files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NamingStyle {
+    /// [`DefaultNaming`]
+    #[default]
+    Default,
+    /// [`GoogleStyleNaming`]
+    Google,
+}
+
+impl NamingStyle {
+    pub fn strategy(self) -> Box<dyn NamingStrategy> {
+        match self {
+            NamingStyle::Default => Box::new(DefaultNaming),
+            NamingStyle::Google => Box::new(GoogleStyleNaming),
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::proto2swagger::TypePaths;
+use crate::util::join_path;
+use crate::{Message, ProtoFile};
+
+/// Top-level messages and enums in dependency order, see
+/// [`ProtoFile::dependency_order`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DependencyOrder {
+    /// Names of top-level messages and enums, dependencies first
+    pub names: Vec<String>,
+    /// Groups of mutually recursive messages, in insertion order; they are
+    /// emitted together, keeping that order
+    pub cycles: Vec<Vec<String>>,
+}
+
+impl ProtoFile {
+    /// Orders top-level definitions so that every message comes after the
+    /// messages and enums its fields (nested messages included) reference.
+    ///
+    /// Definitions of the same rank are sorted alphabetically, so enums and
+    /// messages without dependencies come first. References to nested types
+    /// count as references to their top-level parent.
+    pub fn dependency_order(&self) -> DependencyOrder {
+        let types = TypePaths::new(self);
+        let names: Vec<&str> = self
+            .messages
+            .iter()
+            .map(|m| m.name.as_str())
+            .chain(self.enums.iter().map(|e| e.name.as_str()))
+            .collect();
+        let index: HashMap<&str, usize> = names.iter().enumerate().map(|(i, n)| (*n, i)).collect();
+
+        let mut edges: Vec<BTreeSet<usize>> = vec![BTreeSet::new(); names.len()];
+        for (i, message) in self.messages.iter().enumerate() {
+            let mut referenced = Vec::new();
+            collect_references(message, "", &types, &mut referenced);
+            for path in referenced {
+                let top = path.split('.').next().unwrap_or(&path);
+                if let Some(&j) = index.get(top)
+                    && j != i
+                {
+                    edges[i].insert(j);
+                }
+            }
+        }
+
+        // Компоненты сильной связности: каждый цикл становится одной вершиной
+        let components = strongly_connected(&edges);
+        let mut component_of = vec![0; names.len()];
+        for (c, members) in components.iter().enumerate() {
+            for &member in members {
+                component_of[member] = c;
+            }
+        }
+
+        // Tarjan returns components dependencies first, so ranks fill in order
+        let mut rank = vec![0usize; components.len()];
+        for (c, members) in components.iter().enumerate() {
+            rank[c] = members
+                .iter()
+                .flat_map(|&m| &edges[m])
+                .map(|&dep| component_of[dep])
+                .filter(|&dep| dep != c)
+                .map(|dep| rank[dep] + 1)
+                .max()
+                .unwrap_or(0);
+        }
+
+        let mut ordered: Vec<(usize, &str, Vec<usize>)> = components
+            .iter()
+            .enumerate()
+            .map(|(c, members)| {
+                let mut members = members.clone();
+                members.sort_unstable();
+                let key = members.iter().map(|&m| names[m]).min().unwrap_or_default();
+                (rank[c], key, members)
+            })
+            .collect();
+        ordered.sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+
+        let mut order = DependencyOrder::default();
+        for (_, _, members) in ordered {
+            if members.len() > 1 {
+                order
+                    .cycles
+                    .push(members.iter().map(|&m| names[m].to_string()).collect());
+            }
+            order
+                .names
+                .extend(members.iter().map(|&m| names[m].to_string()));
+        }
+        order
+    }
+}
+
+/// Resolved paths of the types the fields of `message` and its nested
+/// messages refer to
+fn collect_references(message: &Message, prefix: &str, types: &TypePaths, out: &mut Vec<String>) {
+    let path = join_path(prefix, &message.name);
+    for field in message.all_fields() {
+        for name in field.type_.referenced_types() {
+            out.push(types.resolve(name, &path));
+        }
+    }
+    for nested in &message.nested_messages {
+        collect_references(nested, &path, types, out);
+    }
+}
+
+/// Tarjan's algorithm; components come out in reverse topological order,
+/// i.e. a component is listed after everything it points to
+fn strongly_connected(edges: &[BTreeSet<usize>]) -> Vec<Vec<usize>> {
+    struct State<'a> {
+        edges: &'a [BTreeSet<usize>],
+        index: Vec<Option<usize>>,
+        low: Vec<usize>,
+        on_stack: Vec<bool>,
+        stack: Vec<usize>,
+        next: usize,
+        components: Vec<Vec<usize>>,
+    }
+
+    fn visit(state: &mut State, v: usize) {
+        state.index[v] = Some(state.next);
+        state.low[v] = state.next;
+        state.next += 1;
+        state.stack.push(v);
+        state.on_stack[v] = true;
+
+        for &w in state.edges[v].iter() {
+            match state.index[w] {
+                None => {
+                    visit(state, w);
+                    state.low[v] = state.low[v].min(state.low[w]);
+                }
+                Some(index) if state.on_stack[w] => state.low[v] = state.low[v].min(index),
+                Some(_) => {}
+            }
+        }
+
+        if Some(state.low[v]) == state.index[v] {
+            let mut component = Vec::new();
+            while let Some(w) = state.stack.pop() {
+                state.on_stack[w] = false;
+                component.push(w);
+                if w == v {
+                    break;
+                }
+            }
+            state.components.push(component);
+        }
+    }
+
+    let mut state = State {
+        edges,
+        index: vec![None; edges.len()],
+        low: vec![0; edges.len()],
+        on_stack: vec![false; edges.len()],
+        stack: Vec::new(),
+        next: 0,
+        components: Vec::new(),
+    };
+    for v in 0..edges.len() {
+        if state.index[v].is_none() {
+            visit(&mut state, v);
+        }
+    }
+    state.components
+}
@@ -1,303 +1,1114 @@
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
 use std::path::Path;
 
+use serde::Serialize;
+
+use crate::lexer::{Token, TokenKind, tokenize};
 use crate::{
-    Enum, EnumValue, Error, Field, FieldRule, Message, Method, NameFormatter, ProtoFile,
-    ProtoParseError, Service,
+    ConverterError, DEFAULT_MAX_DEPTH, Enum, EnumValue, Error, Field, FieldRule, FieldType,
+    Message, Method, NumberRange, Oneof, OptionValue, ProtoFile, ProtoParseError, ScalarType,
+    Service,
 };
 
+/// Longest input accepted by default, see [`ProtoParser::max_input_size`]
+pub const DEFAULT_MAX_INPUT_SIZE: usize = 16 * 1024 * 1024;
+
+/// Parses .proto text into a [`ProtoFile`].
+///
+/// The text is split into tokens first, so a statement may span several
+/// lines or share one with others, and `//` inside a string is not a
+/// comment.
+///
+/// Input size and message nesting are limited, so untrusted files cannot
+/// exhaust memory or the stack:
+///
+/// ```
+/// use dot_proto_parser::{ProtoParseError, ProtoParser};
+///
+/// let deep = "message M { ".repeat(1000) + &"}".repeat(1000);
+/// let error = ProtoParser::new().parse(&deep).unwrap_err();
+/// assert!(error.to_string().contains("nested deeper than 128"));
+///
+/// let error = ProtoParser::new().max_input_size(16).parse(&deep).unwrap_err();
+/// assert!(matches!(
+///     error,
+///     dot_proto_parser::Error::ProtoParse(ProtoParseError::InputTooLarge { limit: 16 })
+/// ));
+/// ```
+#[derive(Debug)]
 pub struct ProtoParser {
-    current_line: usize,
-    pending_comments: Vec<String>,
+    max_depth: usize,
+    max_input_size: usize,
 }
 
-impl ProtoParser {
-    pub fn new() -> Self {
+impl Default for ProtoParser {
+    fn default() -> Self {
         Self {
-            current_line: 0,
-            pending_comments: Vec::new(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_input_size: DEFAULT_MAX_INPUT_SIZE,
         }
     }
+}
 
+/// How bad a [`Diagnostic`] is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The statement was discarded, [`ProtoParser::parse`] fails on it
+    Error,
+    /// Accepted, but probably not what was meant
+    Warning,
+}
+
+/// Problem found by [`ProtoParser::parse_partial`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// 1-based line of the offending token
+    pub line: usize,
+    /// 1-based column of the offending token
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(
+            f,
+            "{}:{}: {}: {}",
+            self.line, self.column, severity, self.message
+        )
+    }
+}
+
+impl ProtoParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Messages nested deeper than `depth` are reported and skipped;
+    /// [`DEFAULT_MAX_DEPTH`] by default
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Longer input fails with [`ProtoParseError::InputTooLarge`] before
+    /// anything is parsed; [`DEFAULT_MAX_INPUT_SIZE`] by default
+    pub fn max_input_size(mut self, bytes: usize) -> Self {
+        self.max_input_size = bytes;
+        self
+    }
+
+    /// Errors carry `path`: `api.proto:42: Invalid field declaration`
     pub fn parse_file(&mut self, path: &Path) -> Result<ProtoFile, Error> {
-        let content = std::fs::read_to_string(path)?;
+        let in_file = |source| ProtoParseError::InFile {
+            path: path.to_path_buf(),
+            source: Box::new(source),
+        };
+        let file = File::open(path).map_err(|e| in_file(ProtoParseError::Io(e)))?;
+        self.parse_reader(file).map_err(|error| match error {
+            Error::ProtoParse(error) => in_file(error).into(),
+            Error::Converter(ConverterError::Io(error)) => {
+                in_file(ProtoParseError::Io(error)).into()
+            }
+            other => other,
+        })
+    }
+
+    /// Parses .proto text from stdin, a socket or any other reader.
+    /// The text is read in full first, so no extra buffering is needed;
+    /// reading stops once it is over [`Self::max_input_size`].
+    pub fn parse_reader<R: Read>(&mut self, reader: R) -> Result<ProtoFile, Error> {
+        let mut content = Vec::new();
+        let limit = self.max_input_size as u64;
+        reader
+            .take(limit.saturating_add(1))
+            .read_to_end(&mut content)?;
+        // Размер проверяется до UTF-8: обрезка могла прийтись на середину символа
+        if content.len() > self.max_input_size {
+            return Err(ProtoParseError::InputTooLarge {
+                limit: self.max_input_size,
+            }
+            .into());
+        }
+        let content = String::from_utf8(content)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
         self.parse(&content)
     }
 
+    /// [`Self::parse_partial`] that fails on the first error; warnings are
+    /// dropped
     pub fn parse(&mut self, content: &str) -> Result<ProtoFile, Error> {
-        let mut proto_file = ProtoFile::default();
-        let mut stack: Vec<ProtoItem> = Vec::new();
+        self.check_size(content)?;
+        let (proto_file, diagnostics) = self.parse_partial(content);
+        match diagnostics
+            .into_iter()
+            .find(|d| d.severity == Severity::Error)
+        {
+            Some(error) => Err(ProtoParseError::ParseError {
+                line: error.line,
+                message: error.message,
+            }
+            .into()),
+            None => Ok(proto_file),
+        }
+    }
+
+    /// Parses as much of `content` as it can, for files that are being
+    /// edited. A broken statement is reported and discarded (a broken
+    /// block header discards the whole block), blocks left open at the end
+    /// are closed. Diagnostics come in source order.
+    pub fn parse_partial(&mut self, content: &str) -> (ProtoFile, Vec<Diagnostic>) {
+        if let Err(error) = self.check_size(content) {
+            let diagnostic = Diagnostic {
+                severity: Severity::Error,
+                line: 1,
+                column: 1,
+                message: error.to_string(),
+            };
+            return (ProtoFile::default(), vec![diagnostic]);
+        }
+        let (tokens, lex_errors) = tokenize(content);
+        let mut parser = Parser {
+            source: content,
+            tokens,
+            pos: 0,
+            last_end: 0,
+            depth: 0,
+            max_depth: self.max_depth,
+            syntax: String::new(),
+            pending_comments: Vec::new(),
+            diagnostics: Vec::new(),
+        };
+        for error in lex_errors {
+            parser.diagnostics.push(Diagnostic {
+                severity: Severity::Error,
+                line: error.line,
+                column: error.column,
+                message: error.message,
+            });
+        }
+
+        let proto_file = parser.file();
+        let mut diagnostics = parser.diagnostics;
+        diagnostics.sort_by_key(|d| (d.line, d.column));
+        (proto_file, diagnostics)
+    }
+
+    fn check_size(&self, content: &str) -> Result<(), ProtoParseError> {
+        if content.len() > self.max_input_size {
+            return Err(ProtoParseError::InputTooLarge {
+                limit: self.max_input_size,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Why a statement is discarded, reported as an error
+struct SyntaxError {
+    line: usize,
+    column: usize,
+    message: String,
+}
+
+type Parsed<T> = Result<T, SyntaxError>;
+
+/// Recursive descent over the tokens of one file
+struct Parser<'s> {
+    source: &'s str,
+    tokens: Vec<Token<'s>>,
+    pos: usize,
+    /// End of the last token taken, for option names
+    last_end: usize,
+    /// Messages being parsed, outermost first
+    depth: usize,
+    max_depth: usize,
+    syntax: String,
+    pending_comments: Vec<String>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// Value of an option as written
+enum Literal<'s> {
+    /// Adjacent string literals, quotes included: `"a" "b"`
+    Strings(Vec<&'s str>),
+    /// Number, identifier or `{ ... }` aggregate
+    Text(String),
+}
+
+impl Literal<'_> {
+    fn into_option_value(self) -> OptionValue {
+        match self {
+            Literal::Strings(parts) if parts.len() == 1 => OptionValue::from_literal(parts[0]),
+            Literal::Strings(parts) => OptionValue::String(
+                parts
+                    .iter()
+                    .filter_map(|part| match OptionValue::from_literal(part) {
+                        OptionValue::String(s) => Some(s),
+                        _ => None,
+                    })
+                    .collect(),
+            ),
+            Literal::Text(text) => OptionValue::from_literal(&text),
+        }
+    }
 
-        for (line_num, line) in content.lines().enumerate() {
-            self.current_line = line_num + 1;
-            let line = line.trim();
+    /// File options keep string contents as written, without quotes
+    fn into_file_value(self) -> String {
+        match self {
+            Literal::Strings(parts) => parts.iter().map(|part| &part[1..part.len() - 1]).collect(),
+            Literal::Text(text) => text,
+        }
+    }
+}
 
-            if line.is_empty() {
+impl<'s> Parser<'s> {
+    fn file(&mut self) -> ProtoFile {
+        let mut file = ProtoFile::default();
+        loop {
+            self.take_comments();
+            let Some(token) = self.peek() else {
+                break;
+            };
+            if token.is_punct('}') {
+                self.next();
+                self.report(self.error_at(token, "Unexpected `}`"));
                 continue;
             }
+            if let Err(error) = self.top_statement(&mut file, token) {
+                self.report(error);
+                self.recover();
+            }
+        }
+        file
+    }
 
-            match self.parse_line(line, &mut stack)? {
-                LineType::Syntax(s) => {
-                    proto_file.syntax = s;
-                    self.pending_comments.clear();
-                }
-                LineType::Package(p) => {
-                    proto_file.package = p;
-                    self.pending_comments.clear();
-                }
-                LineType::Import(i) => {
-                    proto_file.imports.push(i);
-                    self.pending_comments.clear();
-                }
-                LineType::Message(mut m) => {
-                    m.comments = std::mem::take(&mut self.pending_comments);
-                    stack.push(ProtoItem::Message(m));
-                }
-                LineType::Enum(mut e) => {
-                    e.comments = std::mem::take(&mut self.pending_comments);
-                    stack.push(ProtoItem::Enum(e));
-                }
-                LineType::Service(mut s) => {
-                    s.comments = std::mem::take(&mut self.pending_comments);
-                    stack.push(ProtoItem::Service(s));
-                }
-                LineType::Field(mut f) => {
-                    f.comments = std::mem::take(&mut self.pending_comments);
-                    if let Some(ProtoItem::Message(msg)) = stack.last_mut() {
-                        msg.add_field(f)?;
-                    }
-                }
-                LineType::EnumValue(mut v) => {
-                    v.comments = std::mem::take(&mut self.pending_comments);
-                    if let Some(ProtoItem::Enum(en)) = stack.last_mut() {
-                        en.add_value(v)?;
-                    }
-                }
-                LineType::Method(mut m) => {
-                    m.comments = std::mem::take(&mut self.pending_comments);
-                    if let Some(ProtoItem::Service(svc)) = stack.last_mut() {
-                        svc.add_method(m)?;
-                    }
+    fn top_statement(&mut self, file: &mut ProtoFile, token: Token<'s>) -> Parsed<()> {
+        if token.is_punct(';') {
+            self.next();
+            return Ok(());
+        }
+        if token.kind != TokenKind::Ident {
+            return Err(self.unexpected(Some(token), "a declaration"));
+        }
+
+        match token.text {
+            "syntax" => {
+                self.next();
+                self.expect_punct('=', "after `syntax`")?;
+                let syntax = self.expect_string("syntax")?;
+                self.expect_punct(';', "after the syntax")?;
+                file.syntax = syntax.to_string();
+                self.syntax = file.syntax.clone();
+                // Comments above `syntax` describe the whole file
+                file.header_comments = std::mem::take(&mut self.pending_comments);
+            }
+            "package" => {
+                self.next();
+                let package = self.expect_ident("package name")?;
+                self.expect_punct(';', "after the package")?;
+                file.package = package.to_string();
+                self.pending_comments.clear();
+            }
+            "import" => {
+                self.next();
+                if let Some(modifier) = self.peek()
+                    && (modifier.is_ident("public") || modifier.is_ident("weak"))
+                {
+                    self.next();
                 }
-                LineType::End => {
-                    if let Some(item) = stack.pop() {
-                        match item {
-                            ProtoItem::Message(m) => proto_file.add_message(m)?,
-                            ProtoItem::Enum(e) => proto_file.add_enum(e)?,
-                            ProtoItem::Service(s) => proto_file.add_service(s)?,
-                        }
-                    }
-                    self.pending_comments.clear();
+                let import = self.expect_string("import path")?;
+                self.expect_punct(';', "after the import")?;
+                file.imports.push(import.to_string());
+                self.pending_comments.clear();
+            }
+            "option" => {
+                self.next();
+                let (key, value) = self.option_assignment()?;
+                self.expect_punct(';', "after the option")?;
+                file.add_option(&key, &value.into_file_value());
+                self.pending_comments.clear();
+            }
+            "message" => {
+                self.next();
+                let message = self.message(token)?;
+                self.add(token, file.add_message(message));
+            }
+            "enum" => {
+                self.next();
+                let enum_def = self.enum_def(token)?;
+                self.add(token, file.add_enum(enum_def));
+            }
+            "service" => {
+                self.next();
+                let service = self.service(token)?;
+                self.add(token, file.add_service(service));
+            }
+            "extend" => self.drop_extend(token),
+            _ => return Err(self.unexpected(Some(token), "a declaration")),
+        }
+        Ok(())
+    }
+
+    fn message(&mut self, keyword: Token<'s>) -> Parsed<Message> {
+        // Слишком глубокое сообщение пропускается целиком: `recover` не рекурсивен
+        if self.depth >= self.max_depth {
+            let message = format!("Messages nested deeper than {} levels", self.max_depth);
+            return Err(self.error_at(keyword, &message));
+        }
+        let name = self.expect_name("message name")?;
+        let mut message = Message::new(name);
+        message.comments = std::mem::take(&mut self.pending_comments);
+        self.expect_punct('{', "after the message name")?;
+        self.depth += 1;
+        self.block(keyword, name, |parser, token| {
+            parser.message_item(&mut message, token)
+        });
+        self.depth -= 1;
+        Ok(message)
+    }
+
+    fn message_item(&mut self, message: &mut Message, token: Token<'s>) -> Parsed<()> {
+        match token.text {
+            "message" if token.kind == TokenKind::Ident => {
+                self.next();
+                let nested = self.message(token)?;
+                self.add(token, message.add_nested_message(nested));
+            }
+            "enum" if token.kind == TokenKind::Ident => {
+                self.next();
+                let enum_def = self.enum_def(token)?;
+                self.add(token, message.add_nested_enum(enum_def));
+            }
+            "oneof" if token.kind == TokenKind::Ident => {
+                self.next();
+                let oneof = self.oneof(token)?;
+                self.add(token, message.add_oneof(oneof));
+            }
+            "option" if token.kind == TokenKind::Ident => {
+                self.next();
+                let (key, value) = self.option_assignment()?;
+                self.expect_punct(';', "after the option")?;
+                message.add_option(&key, value.into_option_value());
+                self.pending_comments.clear();
+            }
+            "reserved" if token.kind == TokenKind::Ident => {
+                self.next();
+                let (ranges, names) = self.reserved()?;
+                message.reserved_ranges.extend(ranges);
+                message.reserved_names.extend(names);
+                self.pending_comments.clear();
+            }
+            "extensions" if token.kind == TokenKind::Ident => {
+                self.next();
+                let ranges = self.ranges("extension range")?;
+                if self.peek().is_some_and(|t| t.is_punct('[')) {
+                    self.warning(token, "Options of `extensions` are not kept in the model");
+                    self.next();
+                    self.option_list()?;
                 }
-                LineType::Comment => {}
+                self.expect_punct(';', "after the extension ranges")?;
+                message.extension_ranges.extend(ranges);
+                self.pending_comments.clear();
+            }
+            "extend" if token.kind == TokenKind::Ident => self.drop_extend(token),
+            _ => {
+                let field = self.field(token)?;
+                self.add(token, message.add_field(field));
             }
         }
+        Ok(())
+    }
 
-        Ok(proto_file)
+    fn oneof(&mut self, keyword: Token<'s>) -> Parsed<Oneof> {
+        let name = self.expect_name("oneof name")?;
+        let mut oneof = Oneof::new(name);
+        oneof.comments = std::mem::take(&mut self.pending_comments);
+        self.expect_punct('{', "after the oneof name")?;
+        self.block(keyword, name, |parser, token| {
+            if token.is_ident("option") {
+                parser.next();
+                let (key, value) = parser.option_assignment()?;
+                parser.expect_punct(';', "after the option")?;
+                oneof.add_option(&key, value.into_option_value());
+                parser.pending_comments.clear();
+                return Ok(());
+            }
+            let field = parser.field(token)?;
+            parser.add(token, oneof.add_field(field));
+            Ok(())
+        });
+        Ok(oneof)
     }
 
-    fn parse_line(&mut self, line: &str, stack: &[ProtoItem]) -> Result<LineType, ProtoParseError> {
-        if line.is_empty() {
-            return Ok(LineType::Comment);
+    /// `[label] type name = number [options];`
+    fn field(&mut self, start: Token<'s>) -> Parsed<Field> {
+        let rule = match start.text {
+            "repeated" => FieldRule::Repeated,
+            "optional" => FieldRule::Optional,
+            "required" => FieldRule::Required,
+            _ => FieldRule::Singular,
+        };
+        if rule != FieldRule::Singular {
+            self.next();
         }
+        let type_ = self.field_type()?;
+        let name = self.expect_name("field name")?;
+        self.expect_punct('=', "after the field name")?;
+        let number = self.int_value("field number")?;
 
-        if line.starts_with("//") {
-            let comment = line[2..].trim().to_string();
-            self.pending_comments.push(comment);
-            return Ok(LineType::Comment);
+        let mut field = Field::new(name, type_, number, rule);
+        if self.eat_punct('[') {
+            for (key, value) in self.option_list()? {
+                field.add_option(&key, value);
+            }
         }
+        self.expect_punct(';', "after the field")?;
+        field.comments = std::mem::take(&mut self.pending_comments);
 
-        if line == "}" {
-            return Ok(LineType::End);
+        if (19000..=19999).contains(&number) {
+            self.warning(
+                start,
+                format!(
+                    "Field number {} is in the range 19000-19999 reserved by protobuf",
+                    number
+                ),
+            );
+        }
+        if rule == FieldRule::Required && self.syntax == "proto3" {
+            self.warning(start, "`required` is not allowed in proto3");
         }
+        Ok(field)
+    }
 
-        if line.starts_with("syntax") {
-            let parts: Vec<&str> = line.split('=').collect();
-            if parts.len() != 2 {
-                return Err(self.parse_error("Invalid syntax declaration"));
-            }
-            return Ok(LineType::Syntax(
-                parts[1].trim_matches(|c| c == '"' || c == ';').to_string(),
-            ));
+    /// `int32`, `api.Order` or `map<string, Order>`
+    fn field_type(&mut self) -> Parsed<FieldType> {
+        let type_ = self.expect_ident("field type")?;
+        if type_ == "map" && self.eat_punct('<') {
+            let key_token = self.peek();
+            let key = self.expect_ident("map key type")?;
+            self.expect_punct(',', "after the map key type")?;
+            let value_token = self.peek();
+            let value = self.expect_ident("map value type")?;
+            self.expect_punct('>', "after the map value type")?;
+
+            let key = ScalarType::from_name(key)
+                .filter(ScalarType::is_valid_map_key)
+                .ok_or_else(|| self.error_at_or_end(key_token, "Invalid map key type"))?;
+            let value: FieldType = value.parse().map_err(|_| {
+                self.error_at_or_end(value_token, &format!("Invalid field type: {}", value))
+            })?;
+            return Ok(FieldType::map(key, value));
         }
+        Ok(type_.parse().expect("an identifier is a valid type name"))
+    }
 
-        if line.starts_with("package") {
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() != 2 || !parts[1].ends_with(';') {
-                return Err(self.parse_error("Invalid package declaration"));
+    fn enum_def(&mut self, keyword: Token<'s>) -> Parsed<Enum> {
+        let name = self.expect_name("enum name")?;
+        let mut enum_def = Enum::new(name);
+        enum_def.comments = std::mem::take(&mut self.pending_comments);
+        self.expect_punct('{', "after the enum name")?;
+        self.block(keyword, name, |parser, token| {
+            if token.is_ident("option") {
+                parser.next();
+                let (key, value) = parser.option_assignment()?;
+                parser.expect_punct(';', "after the option")?;
+                enum_def.add_option(&key, value.into_option_value());
+                parser.pending_comments.clear();
+                return Ok(());
             }
-            return Ok(LineType::Package(
-                parts[1].trim_end_matches(';').to_string(),
-            ));
-        }
+            if token.is_ident("reserved") {
+                parser.next();
+                let (ranges, names) = parser.reserved()?;
+                enum_def.reserved_ranges.extend(ranges);
+                enum_def.reserved_names.extend(names);
+                parser.pending_comments.clear();
+                return Ok(());
+            }
+            let value = parser.enum_value()?;
+            parser.add(token, enum_def.add_value(value));
+            Ok(())
+        });
+        Ok(enum_def)
+    }
 
-        if line.starts_with("import") {
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() != 2 || !parts[1].ends_with(';') {
-                return Err(self.parse_error("Invalid import declaration"));
+    /// `2, 9 to 11;` or `"foo", "bar";` after `reserved`; editions write
+    /// the names without quotes
+    fn reserved(&mut self) -> Parsed<(Vec<NumberRange>, Vec<String>)> {
+        let mut names = Vec::new();
+        let ranges = match self.peek() {
+            Some(first) if matches!(first.kind, TokenKind::String | TokenKind::Ident) => {
+                loop {
+                    let name = match self.peek() {
+                        Some(token) if token.kind == TokenKind::Ident => {
+                            self.expect_name("reserved name")?
+                        }
+                        _ => self.expect_string("reserved name")?,
+                    };
+                    names.push(name.to_string());
+                    if !self.eat_punct(',') {
+                        break;
+                    }
+                }
+                Vec::new()
             }
-            return Ok(LineType::Import(
-                parts[1].trim_matches(|c| c == '"' || c == ';').to_string(),
-            ));
-        }
+            _ => self.ranges("reserved number")?,
+        };
+        self.expect_punct(';', "after the reserved list")?;
+        Ok((ranges, names))
+    }
 
-        if line.starts_with("message") {
-            let name = line["message".len()..].split('{').next().unwrap().trim();
-            if name.is_empty() {
-                return Err(self.parse_error("Message name cannot be empty"));
+    /// `2, 9 to 11, 100 to max`
+    fn ranges(&mut self, what: &str) -> Parsed<Vec<NumberRange>> {
+        let mut ranges = Vec::new();
+        loop {
+            let start = self.int_value(what)?;
+            let range = if self.peek().is_some_and(|t| t.is_ident("to")) {
+                self.next();
+                if self.peek().is_some_and(|t| t.is_ident("max")) {
+                    self.next();
+                    NumberRange::new(start, i32::MAX)
+                } else {
+                    NumberRange::new(start, self.int_value(what)?)
+                }
+            } else {
+                NumberRange::single(start)
+            };
+            ranges.push(range);
+            if !self.eat_punct(',') {
+                return Ok(ranges);
             }
-            return Ok(LineType::Message(Message::new(name)));
         }
+    }
+
+    /// `extend Foo { ... }`: extensions have no place in the model
+    fn drop_extend(&mut self, keyword: Token<'s>) {
+        self.warning(keyword, "`extend` blocks are not kept in the model");
+        self.recover();
+        self.pending_comments.clear();
+    }
 
-        if line.starts_with("enum") {
-            let name = line["enum".len()..].split('{').next().unwrap().trim();
-            if name.is_empty() {
-                return Err(self.parse_error("Enum name cannot be empty"));
+    /// `NAME = number [options];`
+    fn enum_value(&mut self) -> Parsed<EnumValue> {
+        let name = self.expect_name("enum value name")?;
+        self.expect_punct('=', "after the enum value name")?;
+        let number = self.int_value("enum value number")?;
+        let mut value = EnumValue::new(name, number);
+        if self.eat_punct('[') {
+            for (key, option) in self.option_list()? {
+                value.add_option(&key, option);
             }
-            return Ok(LineType::Enum(Enum::new(name)));
         }
+        self.expect_punct(';', "after the enum value")?;
+        value.comments = std::mem::take(&mut self.pending_comments);
+
+        // `IN_PROGRESS = 2; // "in-progress"`: the JSON value of the constant
+        if let Some(token) = self.tokens.get(self.pos)
+            && token.kind == TokenKind::Comment
+            && token.trailing
+            && let Ok(original) = serde_json::from_str::<String>(token.text.trim())
+        {
+            value.original_value = Some(original);
+        }
+        Ok(value)
+    }
 
-        if line.starts_with("service") {
-            let name = line["service".len()..].split('{').next().unwrap().trim();
-            if name.is_empty() {
-                return Err(self.parse_error("Service name cannot be empty"));
+    fn service(&mut self, keyword: Token<'s>) -> Parsed<Service> {
+        let name = self.expect_name("service name")?;
+        let mut service = Service::new(name);
+        service.comments = std::mem::take(&mut self.pending_comments);
+        self.expect_punct('{', "after the service name")?;
+        self.block(keyword, name, |parser, token| {
+            if token.is_ident("option") {
+                parser.next();
+                let (key, value) = parser.option_assignment()?;
+                parser.expect_punct(';', "after the option")?;
+                service.add_option(&key, value.into_option_value());
+                parser.pending_comments.clear();
+            } else if token.is_ident("rpc") {
+                parser.next();
+                let method = parser.method(token)?;
+                parser.add(token, service.add_method(method));
+            } else {
+                return Err(parser.unexpected(Some(token), "`rpc` or `option`"));
             }
-            return Ok(LineType::Service(Service::new(name)));
+            Ok(())
+        });
+        Ok(service)
+    }
+
+    /// `rpc Name ([stream] In) returns ([stream] Out) [options] (; | { options })`
+    fn method(&mut self, keyword: Token<'s>) -> Parsed<Method> {
+        let name = self.expect_name("rpc name")?;
+        let comments = std::mem::take(&mut self.pending_comments);
+        self.expect_punct('(', "after the rpc name")?;
+        let (client_streaming, input) = self.rpc_type("input type")?;
+        self.expect_punct(')', "after the input type")?;
+        match self.peek() {
+            Some(token) if token.is_ident("returns") => {
+                self.next();
+            }
+            other => return Err(self.unexpected(other, "`returns`")),
         }
+        self.expect_punct('(', "after `returns`")?;
+        let (server_streaming, output) = self.rpc_type("output type")?;
+        self.expect_punct(')', "after the output type")?;
 
-        if line.starts_with("rpc") {
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() < 5 {
-                return Err(self.parse_error("Invalid method declaration"));
+        let mut method = Method::new(name, input, output);
+        method.client_streaming = client_streaming;
+        method.server_streaming = server_streaming;
+        method.comments = comments;
+        restore_http_options(&mut method);
+        if self.eat_punct('[') {
+            for (key, value) in self.option_list()? {
+                method.add_option(&key, value);
             }
+        }
 
-            let mut method = Method::new(
-                parts[1],
-                parts[3].trim_matches('('),
-                parts[4].trim_matches(')'),
-            );
+        if self.eat_punct('{') {
+            self.block(keyword, name, |parser, token| {
+                if !token.is_ident("option") {
+                    return Err(parser.unexpected(Some(token), "an option inside rpc body"));
+                }
+                parser.next();
+                let (key, value) = parser.option_assignment()?;
+                parser.expect_punct(';', "after the option")?;
+                method.add_option(&key, value.into_option_value());
+                parser.pending_comments.clear();
+                Ok(())
+            });
+        } else {
+            self.expect_punct(';', "after the rpc")?;
+        }
+        Ok(method)
+    }
 
-            if let Some(options_start) = line.find('[') {
-                let options_str = &line[options_start..].trim_matches(|c| c == '[' || c == ']');
-                for option in options_str.split(',') {
-                    let option = option.trim();
-                    if let Some((key, value)) = option.split_once('=') {
-                        method.add_option(key.trim(), value.trim().trim_matches('"'));
+    /// `[stream] Type`; `stream` alone is a type name
+    fn rpc_type(&mut self, what: &str) -> Parsed<(bool, &'s str)> {
+        let streaming = self.peek().is_some_and(|t| t.is_ident("stream"))
+            && self
+                .peek_second()
+                .is_some_and(|t| t.kind == TokenKind::Ident);
+        if streaming {
+            self.next();
+        }
+        Ok((streaming, self.expect_ident(what)?))
+    }
+
+    /// Statements up to the `}` closing the block opened by `keyword`; a
+    /// broken statement is reported and skipped
+    fn block(
+        &mut self,
+        keyword: Token<'s>,
+        name: &str,
+        mut item: impl FnMut(&mut Self, Token<'s>) -> Parsed<()>,
+    ) {
+        loop {
+            self.take_comments();
+            match self.peek() {
+                None => {
+                    let message = format!("Missing `}}` for {} {}", keyword.text, name);
+                    self.report(self.error_at(keyword, &message));
+                    return;
+                }
+                Some(token) if token.is_punct('}') => {
+                    self.next();
+                    self.pending_comments.clear();
+                    return;
+                }
+                Some(token) if token.is_punct(';') => {
+                    self.next();
+                }
+                Some(token) => {
+                    if let Err(error) = item(self, token) {
+                        self.report(error);
+                        self.recover();
                     }
                 }
             }
-
-            return Ok(LineType::Method(method));
         }
+    }
+
+    /// `name = value` of an option statement or list entry
+    fn option_assignment(&mut self) -> Parsed<(String, Literal<'s>)> {
+        let name = self.option_name()?;
+        self.expect_punct('=', "after the option name")?;
+        Ok((name, self.option_value()?))
+    }
 
-        if let Some(ProtoItem::Message(_)) = stack.last() {
-            return self.parse_field(line);
+    /// `deprecated`, `(google.api.http)` or `(validate.rules).string.min_len`
+    fn option_name(&mut self) -> Parsed<String> {
+        let start = self.peek().map_or(self.source.len(), |t| t.start);
+        if self.eat_punct('(') {
+            self.expect_ident("option name")?;
+            self.expect_punct(')', "after the option name")?;
+        } else {
+            self.expect_ident("option name")?;
         }
+        // `.string` after `)` is a token of its own
+        while let Some(token) = self.peek()
+            && token.kind == TokenKind::Ident
+            && token.text.starts_with('.')
+        {
+            self.next();
+        }
+        Ok(self.source[start..self.last_end]
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect())
+    }
 
-        if let Some(ProtoItem::Enum(_)) = stack.last() {
-            return self.parse_enum_value(line);
+    fn option_value(&mut self) -> Parsed<Literal<'s>> {
+        let Some(first) = self.peek() else {
+            return Err(self.unexpected(None, "an option value"));
+        };
+        match first.kind {
+            TokenKind::String => {
+                let mut parts = Vec::new();
+                while let Some(token) = self.peek()
+                    && token.kind == TokenKind::String
+                {
+                    self.next();
+                    parts.push(token.text);
+                }
+                Ok(Literal::Strings(parts))
+            }
+            TokenKind::Punct if first.is_punct('{') => self.aggregate().map(Literal::Text),
+            TokenKind::Punct if first.is_punct('-') || first.is_punct('+') => {
+                self.next();
+                match self.peek() {
+                    Some(number)
+                        if matches!(
+                            number.kind,
+                            TokenKind::Int | TokenKind::Float | TokenKind::Ident
+                        ) =>
+                    {
+                        self.next();
+                        Ok(Literal::Text(format!("{}{}", first.text, number.text)))
+                    }
+                    other => Err(self.unexpected(other, "a number")),
+                }
+            }
+            TokenKind::Int | TokenKind::Float | TokenKind::Ident => {
+                self.next();
+                Ok(Literal::Text(first.text.to_string()))
+            }
+            _ => Err(self.unexpected(Some(first), "an option value")),
         }
+    }
 
-        Err(self.parse_error("Unknown line type"))
+    /// `{ ... }` with nested braces, its tokens separated by single spaces
+    /// where the source has any whitespace; comments are dropped
+    fn aggregate(&mut self) -> Parsed<String> {
+        let Some(open) = self.next() else {
+            return Err(self.unexpected(None, "`{`"));
+        };
+        let mut text = String::from(open.text);
+        let mut previous_end = open.end;
+        let mut depth = 1;
+        while depth > 0 {
+            let Some(token) = self.next() else {
+                return Err(self.error_at(open, "Unterminated option value"));
+            };
+            if token.start > previous_end {
+                text.push(' ');
+            }
+            text.push_str(token.text);
+            previous_end = token.end;
+            if token.is_punct('{') {
+                depth += 1;
+            } else if token.is_punct('}') {
+                depth -= 1;
+            }
+        }
+        Ok(text)
     }
 
-    fn parse_field(&mut self, line: &str) -> Result<LineType, ProtoParseError> {
-        let line = line.trim_end_matches(';');
-        let parts: Vec<&str> = line.split_whitespace().collect();
+    /// Entries after `[` up to and including `]`
+    fn option_list(&mut self) -> Parsed<Vec<(String, OptionValue)>> {
+        let mut options = Vec::new();
+        if self.eat_punct(']') {
+            return Ok(options);
+        }
+        loop {
+            let (key, value) = self.option_assignment()?;
+            options.push((key, value.into_option_value()));
+            if !self.eat_punct(',') {
+                self.expect_punct(']', "after the options")?;
+                return Ok(options);
+            }
+        }
+    }
 
-        if parts.len() < 4 {
-            return Err(self.parse_error("Invalid field declaration"));
+    /// Decimal, hex or octal integer with an optional minus sign
+    fn int_value(&mut self, what: &str) -> Parsed<i32> {
+        let negative = self.eat_punct('-');
+        let token = self.peek();
+        let value = token
+            .filter(|t| t.kind == TokenKind::Int)
+            .and_then(|t| parse_int(t.text))
+            .map(|value| if negative { -value } else { value })
+            .and_then(|value| i32::try_from(value).ok());
+        match value {
+            Some(value) => {
+                self.next();
+                Ok(value)
+            }
+            None => Err(self.error_at_or_end(token, &format!("Invalid {}", what))),
         }
+    }
 
-        let mut idx = 0;
-        let rule = match parts[idx] {
-            "repeated" => {
-                idx += 1;
-                FieldRule::Repeated
+    /// Skips the rest of a broken statement: through its `;` or its
+    /// `{ ... }` block, stopping before the `}` of the enclosing block
+    fn recover(&mut self) {
+        while let Some(token) = self.peek() {
+            if token.is_punct('}') {
+                return;
             }
-            "optional" => {
-                idx += 1;
-                FieldRule::Optional
+            self.next();
+            if token.is_punct(';') {
+                return;
             }
-            "required" => {
-                idx += 1;
-                FieldRule::Required
+            if token.is_punct('{') {
+                let mut depth = 1;
+                while let Some(token) = self.next() {
+                    if token.is_punct('{') {
+                        depth += 1;
+                    } else if token.is_punct('}') {
+                        depth -= 1;
+                        if depth == 0 {
+                            return;
+                        }
+                    }
+                }
+                return;
             }
-            _ => FieldRule::Required,
-        };
+        }
+    }
 
-        let type_ = parts[idx].to_string();
-        idx += 1;
-        let name = parts[idx].to_string();
-        idx += 1;
+    /// Moves the comments in front of the next statement into
+    /// `pending_comments`; comments after code on the same line are dropped
+    fn take_comments(&mut self) {
+        while let Some(token) = self.tokens.get(self.pos)
+            && token.kind == TokenKind::Comment
+        {
+            self.pos += 1;
+            if token.trailing {
+                continue;
+            }
+            let block = self.source[token.start..].starts_with("/*");
+            if !block {
+                // Only the space after `//` is markup, indentation is content
+                let text = token.text;
+                self.pending_comments
+                    .push(text.strip_prefix(' ').unwrap_or(text).to_string());
+                continue;
+            }
+            let lines: Vec<&str> = token
+                .text
+                .lines()
+                .map(|line| {
+                    let line = line.trim();
+                    let line = line.strip_prefix('*').unwrap_or(line);
+                    line.strip_prefix(' ').unwrap_or(line)
+                })
+                .collect();
+            let first = lines
+                .iter()
+                .position(|l| !l.is_empty())
+                .unwrap_or(lines.len());
+            let last = lines
+                .iter()
+                .rposition(|l| !l.is_empty())
+                .map_or(first, |i| i + 1);
+            self.pending_comments
+                .extend(lines[first..last].iter().map(|l| l.to_string()));
+        }
+    }
 
-        if parts[idx] != "=" {
-            return Err(self.parse_error("Expected '=' in field declaration"));
+    /// Next token that is not a comment
+    fn peek(&mut self) -> Option<Token<'s>> {
+        while self
+            .tokens
+            .get(self.pos)
+            .is_some_and(|t| t.kind == TokenKind::Comment)
+        {
+            self.pos += 1;
         }
-        idx += 1;
+        self.tokens.get(self.pos).copied()
+    }
 
-        let number = parts[idx]
-            .parse()
-            .map_err(|_| self.parse_error("Invalid field number"))?;
+    fn peek_second(&mut self) -> Option<Token<'s>> {
+        self.peek()?;
+        self.tokens[self.pos + 1..]
+            .iter()
+            .find(|t| t.kind != TokenKind::Comment)
+            .copied()
+    }
 
-        let mut field = Field::new(&name, &type_, number, rule);
-        field.comments = std::mem::take(&mut self.pending_comments);
+    fn next(&mut self) -> Option<Token<'s>> {
+        let token = self.peek()?;
+        self.pos += 1;
+        self.last_end = token.end;
+        Some(token)
+    }
 
-        if let Some(options_start) = line.find('[') {
-            let options_str = &line[options_start..].trim_matches(|c| c == '[' || c == ']');
-            for option in options_str.split(',') {
-                let option = option.trim();
-                if let Some((key, value)) = option.split_once('=') {
-                    field.add_option(key.trim(), value.trim().trim_matches('"'));
-                }
+    fn eat_punct(&mut self, c: char) -> bool {
+        let matched = self.peek().is_some_and(|t| t.is_punct(c));
+        if matched {
+            self.next();
+        }
+        matched
+    }
+
+    /// Errors leave the unexpected token in place for [`Self::recover`]
+    fn expect_punct(&mut self, c: char, context: &str) -> Parsed<Token<'s>> {
+        match self.peek() {
+            Some(token) if token.is_punct(c) => {
+                self.next();
+                Ok(token)
             }
+            other => Err(self.unexpected(other, &format!("`{}` {}", c, context))),
         }
+    }
 
-        Ok(LineType::Field(field))
+    /// A name, possibly dotted
+    fn expect_ident(&mut self, what: &str) -> Parsed<&'s str> {
+        match self.peek() {
+            Some(token) if token.kind == TokenKind::Ident => {
+                self.next();
+                Ok(token.text)
+            }
+            other => Err(self.unexpected(other, what)),
+        }
     }
 
-    fn parse_enum_value(&mut self, line: &str) -> Result<LineType, ProtoParseError> {
-        let line = line.trim_end_matches(';');
-        let parts: Vec<&str> = line.split_whitespace().collect();
+    /// A name without dots
+    fn expect_name(&mut self, what: &str) -> Parsed<&'s str> {
+        match self.peek() {
+            Some(token) if token.kind == TokenKind::Ident && !token.text.contains('.') => {
+                self.next();
+                Ok(token.text)
+            }
+            other => Err(self.unexpected(other, what)),
+        }
+    }
 
-        if parts.len() != 3 || parts[1] != "=" {
-            return Err(self.parse_error("Invalid enum value declaration"));
+    /// Contents of a string literal, as written
+    fn expect_string(&mut self, what: &str) -> Parsed<&'s str> {
+        match self.peek() {
+            Some(token) if token.kind == TokenKind::String && token.text.len() >= 2 => {
+                self.next();
+                Ok(&token.text[1..token.text.len() - 1])
+            }
+            other => Err(self.unexpected(other, what)),
         }
+    }
 
-        let mut value = EnumValue::new(
-            parts[0],
-            parts[2]
-                .parse()
-                .map_err(|_| self.parse_error("Invalid enum value number"))?,
-        );
+    fn unexpected(&self, found: Option<Token<'s>>, expected: &str) -> SyntaxError {
+        match found {
+            Some(token) => self.error_at(
+                token,
+                &format!("Expected {}, found `{}`", expected, token.text),
+            ),
+            None => {
+                self.error_at_or_end(None, &format!("Expected {}, found end of file", expected))
+            }
+        }
+    }
 
-        value.comments = std::mem::take(&mut self.pending_comments);
-        Ok(LineType::EnumValue(value))
+    fn error_at(&self, token: Token<'s>, message: &str) -> SyntaxError {
+        SyntaxError {
+            line: token.line,
+            column: token.column,
+            message: message.to_string(),
+        }
+    }
+
+    /// At `token`, or at the last token of the file
+    fn error_at_or_end(&self, token: Option<Token<'s>>, message: &str) -> SyntaxError {
+        match token.or_else(|| self.tokens.last().copied()) {
+            Some(token) => self.error_at(token, message),
+            None => SyntaxError {
+                line: 1,
+                column: 1,
+                message: message.to_string(),
+            },
+        }
     }
 
-    fn parse_error(&self, msg: &str) -> ProtoParseError {
-        ProtoParseError::ParseError {
-            line: self.current_line,
-            message: msg.to_string(),
+    /// Reports a definition that did not fit its parent, e.g. a duplicate
+    fn add(&mut self, token: Token<'s>, added: Result<(), ConverterError>) {
+        if let Err(error) = added {
+            self.report(self.error_at(token, &error.to_string()));
         }
     }
+
+    fn report(&mut self, error: SyntaxError) {
+        // Комментарии отброшенного объявления
+        self.pending_comments.clear();
+        self.diagnostics.push(Diagnostic {
+            severity: Severity::Error,
+            line: error.line,
+            column: error.column,
+            message: error.message,
+        });
+    }
+
+    fn warning(&mut self, token: Token<'s>, message: impl Into<String>) {
+        self.diagnostics.push(Diagnostic {
+            severity: Severity::Warning,
+            line: token.line,
+            column: token.column,
+            message: message.into(),
+        });
+    }
 }
 
-enum ProtoItem {
-    Message(Message),
-    Enum(Enum),
-    Service(Service),
+fn parse_int(text: &str) -> Option<i64> {
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        return i64::from_str_radix(hex, 16).ok();
+    }
+    if text.len() > 1
+        && let Some(octal) = text.strip_prefix('0')
+    {
+        return i64::from_str_radix(octal, 8).ok();
+    }
+    text.parse().ok()
 }
 
-enum LineType {
-    Syntax(String),
-    Package(String),
-    Import(String),
-    Message(Message),
-    Enum(Enum),
-    Service(Service),
-    Field(Field),
-    EnumValue(EnumValue),
-    Method(Method),
-    End,
-    Comment,
+/// `Method::to_proto_text` renders the `http_method`/`http_path` options as
+/// the last comment line (`// HTTP: GET /path`), preceded by
+/// `// Server: <url>` for `http_server`; turns them back into options
+fn restore_http_options(method: &mut Method) {
+    if method.options.contains_key("http_method") {
+        return;
+    }
+    let binding = method.comments.last().and_then(|comment| {
+        let (http_method, path) = comment.strip_prefix("HTTP: ")?.split_once(' ')?;
+        Some((http_method.to_string(), path.to_string()))
+    });
+    if let Some((http_method, path)) = binding {
+        method.comments.pop();
+        method.add_string_option("http_method", &http_method);
+        method.add_string_option("http_path", &path);
+    }
+    let server = method
+        .comments
+        .last()
+        .and_then(|comment| comment.strip_prefix("Server: "))
+        .filter(|url| !url.contains(' '))
+        .map(str::to_string);
+    if let Some(server) = server {
+        method.comments.pop();
+        method.add_string_option("http_server", &server);
+    }
 }
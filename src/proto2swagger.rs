@@ -0,0 +1,677 @@
+use serde_json::{Map, Value, json};
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::frozen::is_scalar_type;
+use crate::resolver::resolve_path;
+use crate::util::{join_path, to_json_name};
+use crate::{
+    ConversionWarning, Enum, EnumValue, Error, Field, FieldRule, FieldType, Message, Method,
+    OptionValue, ProtoFile,
+};
+use crate::{ProtoParser, Service};
+
+/// Generates an OpenAPI 3.0 document from a ProtoFile.
+///
+/// Messages and enums become `components.schemas`, methods with an HTTP
+/// binding (`http_method`/`http_path` options, the `// HTTP: GET /path`
+/// comment written by [`crate::SwaggerToProtoConverter`] or
+/// `(google.api.http)`) become `paths` entries. Methods without a binding
+/// are skipped with a warning.
+#[derive(Debug, Default)]
+pub struct ProtoToOpenApiConverter {
+    warnings: Vec<ConversionWarning>,
+    types: TypePaths,
+}
+
+/// HTTP binding of an rpc
+pub(crate) struct HttpRule {
+    pub(crate) method: String,
+    pub(crate) path: String,
+    // `body` of google.api.http; `None` means "decide by method"
+    pub(crate) body: Option<String>,
+}
+
+/// Dotted paths (relative to the package) of all messages and enums of a
+/// file, for resolving field types
+#[derive(Debug, Default)]
+pub(crate) struct TypePaths {
+    package: String,
+    known: HashSet<String>,
+}
+
+impl TypePaths {
+    pub(crate) fn new(proto: &ProtoFile) -> Self {
+        let known = proto
+            .all_messages()
+            .map(|(path, _)| path)
+            .chain(proto.all_enums().map(|(path, _)| path))
+            .collect();
+        Self {
+            package: proto.package.clone(),
+            known,
+        }
+    }
+
+    pub(crate) fn package(&self) -> &str {
+        &self.package
+    }
+
+    pub(crate) fn contains(&self, path: &str) -> bool {
+        self.known.contains(path)
+    }
+
+    /// Resolves a field type against the enclosing scopes, innermost first;
+    /// unknown names come back without the leading dot and own package
+    pub(crate) fn resolve(&self, type_: &str, scope: &str) -> String {
+        resolve_path(&self.package, type_, scope, |path| {
+            self.known.contains(path)
+        })
+        .unwrap_or_else(|| {
+            let type_ = type_.trim_start_matches('.');
+            match type_.strip_prefix(&format!("{}.", self.package)) {
+                Some(rest) if !self.package.is_empty() => rest.to_string(),
+                _ => type_.to_string(),
+            }
+        })
+    }
+}
+
+impl ProtoToOpenApiConverter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Warnings collected during the last conversion
+    pub fn warnings(&self) -> &[ConversionWarning] {
+        &self.warnings
+    }
+
+    /// Parses a .proto file and writes the OpenAPI document to
+    /// `output_path`, as YAML for `.yaml`/`.yml` and JSON otherwise.
+    pub fn convert_file(&mut self, input_path: &Path, output_path: &Path) -> Result<(), Error> {
+        let proto = ProtoParser::new().parse_file(input_path)?;
+        let document = self.convert(&proto);
+
+        let is_yaml = matches!(
+            output_path.extension().and_then(|e| e.to_str()),
+            Some("yaml") | Some("yml")
+        );
+        let content = if is_yaml {
+            serde_yaml::to_string(&document)?
+        } else {
+            serde_json::to_string_pretty(&document)?
+        };
+        std::fs::write(output_path, content)?;
+
+        Ok(())
+    }
+
+    pub fn convert(&mut self, proto: &ProtoFile) -> Value {
+        self.warnings.clear();
+        let schemas = self.schemas(proto);
+
+        let mut paths = Map::new();
+        for service in &proto.services {
+            for method in &service.methods {
+                self.add_operation(proto, service, method, &mut paths);
+            }
+        }
+
+        let title = if proto.package.is_empty() {
+            "API".to_string()
+        } else {
+            proto.package.clone()
+        };
+        let mut info = json!({ "title": title, "version": "1.0.0" });
+        if !proto.header_comments.is_empty() {
+            info["description"] = Value::String(proto.header_comments.join("\n"));
+        }
+
+        // Operations are tagged with their service; its comments describe the tag
+        let tags: Vec<Value> = proto
+            .services
+            .iter()
+            .filter(|s| !s.comments.is_empty())
+            .map(|s| json!({ "name": s.name, "description": s.comments.join("\n") }))
+            .collect();
+
+        let mut document = json!({
+            "openapi": "3.0.3",
+            "info": info,
+            "paths": paths,
+            "components": { "schemas": schemas },
+        });
+        if !tags.is_empty() {
+            document["tags"] = Value::Array(tags);
+        }
+        document
+    }
+
+    /// `components.schemas` for every message and enum, keyed by dotted
+    /// path relative to the package
+    pub(crate) fn schemas(&mut self, proto: &ProtoFile) -> Map<String, Value> {
+        self.types = TypePaths::new(proto);
+
+        let mut schemas = Map::new();
+        for message in &proto.messages {
+            self.message_schemas(message, "", &mut schemas);
+        }
+        for enum_def in &proto.enums {
+            schemas.insert(enum_def.name.clone(), enum_schema(enum_def));
+        }
+        schemas
+    }
+
+    /// Adds the schema of `message` and of everything nested in it, under
+    /// dotted names (`Outer.Inner`)
+    fn message_schemas(&self, message: &Message, prefix: &str, schemas: &mut Map<String, Value>) {
+        let path = join_path(prefix, &message.name);
+
+        let mut properties = Map::new();
+        let mut required = Vec::new();
+        for field in message.all_fields() {
+            properties.insert(field.name.clone(), self.field_schema(field, &path));
+            if is_required(field) {
+                required.push(Value::String(field.name.clone()));
+            }
+        }
+
+        let mut schema = json!({ "type": "object", "properties": properties });
+        if !required.is_empty() {
+            schema["required"] = Value::Array(required);
+        }
+        if !message.comments.is_empty() {
+            schema["description"] = Value::String(message.comments.join("\n"));
+        }
+        schemas.insert(path.clone(), schema);
+
+        for nested in &message.nested_messages {
+            self.message_schemas(nested, &path, schemas);
+        }
+        for enum_def in &message.nested_enums {
+            schemas.insert(join_path(&path, &enum_def.name), enum_schema(enum_def));
+        }
+    }
+
+    fn field_schema(&self, field: &Field, scope: &str) -> Value {
+        let mut schema = match field.rule {
+            FieldRule::Repeated if !field.type_.is_map() => json!({
+                "type": "array",
+                "items": self.field_type_schema(&field.type_, scope),
+            }),
+            _ => self.field_type_schema(&field.type_, scope),
+        };
+
+        // Siblings of `$ref` are ignored in 3.0
+        if schema.get("$ref").is_none() {
+            let comments: Vec<&str> = field
+                .comments
+                .iter()
+                .map(String::as_str)
+                .filter(|c| *c != "required")
+                .collect();
+            if !comments.is_empty() {
+                schema["description"] = Value::String(comments.join("\n"));
+            }
+            if field
+                .options
+                .get("deprecated")
+                .and_then(OptionValue::as_bool)
+                == Some(true)
+            {
+                schema["deprecated"] = Value::Bool(true);
+            }
+        }
+        schema
+    }
+
+    fn field_type_schema(&self, type_: &FieldType, scope: &str) -> Value {
+        match type_ {
+            FieldType::Scalar(scalar) => scalar_schema(scalar.as_str()),
+            FieldType::Named(name) => self.type_schema(name, scope),
+            FieldType::Map { value, .. } => json!({
+                "type": "object",
+                "additionalProperties": self.field_type_schema(value, scope),
+            }),
+        }
+    }
+
+    fn type_schema(&self, type_: &str, scope: &str) -> Value {
+        if is_scalar_type(type_) {
+            return scalar_schema(type_);
+        }
+        let type_ = type_.trim_start_matches('.');
+        if let Some(well_known) = type_.strip_prefix("google.protobuf.") {
+            return well_known_schema(well_known);
+        }
+        json!({ "$ref": format!("#/components/schemas/{}", self.types.resolve(type_, scope)) })
+    }
+
+    fn add_operation(
+        &mut self,
+        proto: &ProtoFile,
+        service: &Service,
+        method: &Method,
+        paths: &mut Map<String, Value>,
+    ) {
+        let Some(rule) = http_rule(method) else {
+            self.warnings.push(ConversionWarning::MissingHttpBinding {
+                service: service.name.clone(),
+                method: method.name.clone(),
+            });
+            return;
+        };
+
+        // `{name=projects/*}` -> `{name}`
+        let mut path_params = Vec::new();
+        let mut path = String::new();
+        let mut rest = rule.path.as_str();
+        while let Some((before, after)) = rest.split_once('{') {
+            let Some((param, after)) = after.split_once('}') else {
+                break;
+            };
+            let name = param.split('=').next().unwrap_or(param).trim();
+            path.push_str(before);
+            path.push_str(&format!("{{{}}}", name));
+            path_params.push(name.to_string());
+            rest = after;
+        }
+        path.push_str(rest);
+
+        let mut operation = json!({
+            "operationId": method.name,
+            "tags": [service.name],
+        });
+        let description: Vec<&str> = method
+            .comments
+            .iter()
+            .map(String::as_str)
+            .filter(|c| !c.starts_with("HTTP:"))
+            .collect();
+        if !description.is_empty() {
+            operation["summary"] = Value::String(description[0].to_string());
+            if description.len() > 1 {
+                operation["description"] = Value::String(description[1..].join("\n"));
+            }
+        }
+        if method
+            .options
+            .get("deprecated")
+            .and_then(OptionValue::as_bool)
+            == Some(true)
+        {
+            operation["deprecated"] = Value::Bool(true);
+        }
+        // http_path уже содержит путь сервера: `https://gw/v2` + `/v2/items`
+        if let Some(server) = method
+            .options
+            .get("http_server")
+            .and_then(OptionValue::as_str)
+        {
+            operation["servers"] = json!([{ "url": server }]);
+            let server_path = server
+                .split_once("://")
+                .map_or(server, |(_, rest)| {
+                    rest.find('/').map_or("", |i| &rest[i..])
+                })
+                .trim_end_matches('/');
+            if !server_path.is_empty()
+                && let Some(relative) = path.strip_prefix(server_path)
+                && relative.starts_with('/')
+            {
+                path = relative.to_string();
+            }
+        }
+
+        let (parameters, request_body) = self.request_parts(proto, method, &rule, &path_params);
+        if !parameters.is_empty() {
+            operation["parameters"] = Value::Array(parameters);
+        }
+        if let Some(request_body) = request_body {
+            operation["requestBody"] = request_body;
+        }
+
+        let mut response = json!({ "description": "OK" });
+        if !is_empty_type(&method.output_type) {
+            let schema = self.type_schema(&method.output_type, "");
+            response["content"] = json!({ "application/json": { "schema": schema } });
+        }
+        operation["responses"] = json!({ "200": response });
+
+        let path_item = paths
+            .entry(path)
+            .or_insert_with(|| Value::Object(Map::new()));
+        path_item[rule.method.to_lowercase()] = operation;
+    }
+
+    /// Splits the rpc input into parameters and a request body.
+    ///
+    /// Inputs shaped like the ones [`crate::SwaggerToProtoConverter`]
+    /// generates (`params`/`body`/`headers` fields) are split along those
+    /// fields; any other message follows the google.api.http rules: path
+    /// fields go to the path, the rest to the body (`body: "*"`, the
+    /// default for POST/PUT/PATCH) or to the query.
+    fn request_parts(
+        &self,
+        proto: &ProtoFile,
+        method: &Method,
+        rule: &HttpRule,
+        path_params: &[String],
+    ) -> (Vec<Value>, Option<Value>) {
+        let mut parameters = Vec::new();
+        if is_empty_type(&method.input_type) {
+            return (parameters, None);
+        }
+
+        let input_path = self.types.resolve(&method.input_type, "");
+        let Some(input) = proto.find_message_by_path(&input_path) else {
+            let body = self.request_body(proto, &method.input_type, "");
+            return (parameters, Some(body));
+        };
+
+        let part = |name: &str| {
+            input
+                .fields
+                .iter()
+                .find(|f| f.name == name && !f.type_.is_scalar())
+        };
+        let update_mask = part("update_mask").filter(|f| is_field_mask(&f.type_name()));
+        let generated_layout =
+            part("params").is_some() || part("headers").is_some() || update_mask.is_some();
+
+        if generated_layout {
+            for (field_name, location) in [("params", None), ("headers", Some("header"))] {
+                let Some(field) = part(field_name) else {
+                    continue;
+                };
+                let type_path = self.types.resolve(&field.type_name(), &input_path);
+                if let Some(params) = proto.find_message_by_path(&type_path) {
+                    for param in params.all_fields() {
+                        // Параметры названы так, как их шлют клиенты
+                        let (name, location) = match (location, path_param(param, path_params)) {
+                            (Some(location), _) => (param.name.clone(), location),
+                            (None, Some(name)) => (name.to_string(), "path"),
+                            (None, None) => (json_name(param), "query"),
+                        };
+                        parameters.push(self.parameter(param, &name, location, &type_path));
+                    }
+                }
+            }
+            // Маска PATCH-запроса передаётся в query, как у grpc-gateway
+            if let Some(field) = update_mask {
+                parameters.push(self.parameter(field, &field.name, "query", &input_path));
+            }
+            let body =
+                part("body").map(|field| self.request_body(proto, &field.type_name(), &input_path));
+            return (parameters, body);
+        }
+
+        let body_all = match rule.body.as_deref() {
+            Some("*") => true,
+            Some(_) => false,
+            None => !matches!(rule.method.as_str(), "GET" | "DELETE"),
+        };
+        for field in input.all_fields() {
+            if let Some(name) = path_param(field, path_params) {
+                parameters.push(self.parameter(field, name, "path", &input_path));
+            } else if !body_all && rule.body.as_deref() != Some(field.name.as_str()) {
+                parameters.push(self.parameter(field, &json_name(field), "query", &input_path));
+            }
+        }
+
+        let body = match rule.body.as_deref() {
+            Some(field_name) if field_name != "*" => input
+                .all_fields()
+                .find(|f| f.name == field_name)
+                .map(|f| json_body(self.field_schema(f, &input_path))),
+            _ if body_all => Some(self.request_body(proto, &method.input_type, "")),
+            _ => None,
+        };
+        (parameters, body)
+    }
+
+    /// Request body for a message type. The single-`data`-field wrappers
+    /// the swagger converter generates are unwrapped, and their recorded
+    /// `Content-Type` is restored.
+    fn request_body(&self, proto: &ProtoFile, type_: &str, scope: &str) -> Value {
+        let type_path = self.types.resolve(type_, scope);
+        let Some(message) = proto.find_message_by_path(&type_path) else {
+            return json_body(self.type_schema(type_, scope));
+        };
+
+        let data = match message.fields.as_slice() {
+            [field] if field.name == "data" && message.oneofs.is_empty() => Some(field),
+            _ => None,
+        };
+        let content_type = data
+            .into_iter()
+            .flat_map(|f| {
+                f.comments
+                    .iter()
+                    .map(String::as_str)
+                    .chain(f.options.get("json_name").and_then(OptionValue::as_str))
+            })
+            .chain(message.comments.iter().map(String::as_str))
+            .find_map(|c| match c.strip_prefix("Content-Type: ") {
+                Some(content_type) => Some(content_type),
+                None => c.contains('/').then_some(c),
+            })
+            .unwrap_or("application/json");
+
+        let schema = match data {
+            Some(field) => {
+                let mut schema = self.field_schema(field, &type_path);
+                if let Some(map) = schema.as_object_mut() {
+                    map.remove("description");
+                }
+                schema
+            }
+            None => self.type_schema(type_, scope),
+        };
+        json!({
+            "required": true,
+            "content": { content_type: { "schema": schema } },
+        })
+    }
+
+    fn parameter(&self, field: &Field, name: &str, location: &str, scope: &str) -> Value {
+        let mut parameter = json!({
+            "name": name,
+            "in": location,
+            "schema": self.field_schema(field, scope),
+        });
+        if location == "path" || is_required(field) {
+            parameter["required"] = Value::Bool(true);
+        }
+        parameter
+    }
+}
+
+/// The path template variable bound to `field`, by field or JSON name
+fn path_param<'a>(field: &Field, path_params: &'a [String]) -> Option<&'a str> {
+    let json_name = json_name(field);
+    path_params
+        .iter()
+        .find(|p| **p == field.name || **p == json_name)
+        .map(String::as_str)
+}
+
+/// The name clients send for a field: `json_name`, or the one protoc
+/// derives from the field name
+fn json_name(field: &Field) -> String {
+    match field.options.get("json_name").and_then(OptionValue::as_str) {
+        Some(json_name) => json_name.to_string(),
+        None => to_json_name(&field.name),
+    }
+}
+
+/// Reads the HTTP binding from the method options or the comment the
+/// swagger converter renders them as
+pub(crate) fn http_rule(method: &Method) -> Option<HttpRule> {
+    if let (Some(http_method), Some(path)) = (
+        method
+            .options
+            .get("http_method")
+            .and_then(OptionValue::as_str),
+        method
+            .options
+            .get("http_path")
+            .and_then(OptionValue::as_str),
+    ) {
+        return Some(HttpRule {
+            method: http_method.to_uppercase(),
+            path: path.to_string(),
+            body: None,
+        });
+    }
+
+    if let Some(binding) = method.options.get("(google.api.http)") {
+        return google_api_http(&binding.to_string());
+    }
+
+    method.comments.iter().find_map(|comment| {
+        let (http_method, path) = comment.strip_prefix("HTTP:")?.trim().split_once(' ')?;
+        Some(HttpRule {
+            method: http_method.to_uppercase(),
+            path: path.trim().to_string(),
+            body: None,
+        })
+    })
+}
+
+/// Parses a `{get: "/v1/x" body: "*"}` literal
+fn google_api_http(literal: &str) -> Option<HttpRule> {
+    let literal = literal.trim().trim_start_matches('{').trim_end_matches('}');
+    let value_of = |key: &str| {
+        let start = literal.find(&format!("{}:", key))? + key.len() + 1;
+        let rest = literal[start..].trim_start().strip_prefix('"')?;
+        rest.split_once('"').map(|(value, _)| value.to_string())
+    };
+
+    ["get", "put", "post", "delete", "patch"]
+        .into_iter()
+        .find_map(|http_method| {
+            value_of(http_method).map(|path| HttpRule {
+                method: http_method.to_uppercase(),
+                path,
+                body: value_of("body"),
+            })
+        })
+}
+
+fn json_body(schema: Value) -> Value {
+    json!({
+        "required": true,
+        "content": { "application/json": { "schema": schema } },
+    })
+}
+
+fn is_required(field: &Field) -> bool {
+    // `RequiredFields::Comment` marks required fields with a comment
+    field.rule == FieldRule::Required
+        || field.comments.iter().any(|c| c == "required")
+        || field
+            .options
+            .get("(google.api.field_behavior)")
+            .is_some_and(|v| v.as_str() == Some("REQUIRED"))
+}
+
+fn is_empty_type(type_: &str) -> bool {
+    type_.trim_start_matches('.') == "google.protobuf.Empty"
+}
+
+fn is_field_mask(type_: &str) -> bool {
+    type_.trim_start_matches('.') == "google.protobuf.FieldMask"
+}
+
+/// String enum of the value names. Enums converted from swagger keep each
+/// original JSON value, strings in `original_value` and numbers as the last
+/// comment; when every value but the `_UNSPECIFIED` sentinel has one, those
+/// values are restored instead.
+fn enum_schema(enum_def: &Enum) -> Value {
+    let original_value = |v: &EnumValue| {
+        if let Some(original) = &v.original_value {
+            return Some(Value::String(original.clone()));
+        }
+        let literal = v.comments.last()?;
+        match serde_json::from_str::<Value>(literal).ok()? {
+            value @ (Value::String(_) | Value::Number(_)) => Some(value),
+            _ => None,
+        }
+    };
+    let original: Option<Vec<Value>> = enum_def
+        .values
+        .iter()
+        .map(|v| (v, original_value(v)))
+        // The sentinel has a JSON value only when a zero-like one was mapped to it
+        .filter(|(v, value)| {
+            value.is_some() || !(v.number == 0 && v.name.ends_with("_UNSPECIFIED"))
+        })
+        .map(|(_, value)| value)
+        .collect();
+
+    let mut schema = match original.filter(|values| !values.is_empty()) {
+        Some(values) => {
+            let type_ = if values.iter().all(Value::is_number) {
+                "integer"
+            } else {
+                "string"
+            };
+            json!({ "type": type_, "enum": values })
+        }
+        None => {
+            let values: Vec<&str> = enum_def.values.iter().map(|v| v.name.as_str()).collect();
+            json!({ "type": "string", "enum": values })
+        }
+    };
+    if !enum_def.comments.is_empty() {
+        schema["description"] = Value::String(enum_def.comments.join("\n"));
+    }
+    schema
+}
+
+fn scalar_schema(type_: &str) -> Value {
+    match type_ {
+        "double" | "float" => json!({ "type": "number", "format": type_ }),
+        "int32" | "int64" => json!({ "type": "integer", "format": type_ }),
+        // Same names as the formats the swagger converter maps back
+        "uint32" | "uint64" | "sint32" | "sint64" | "fixed32" | "fixed64" | "sfixed32"
+        | "sfixed64" => json!({ "type": "integer", "format": type_ }),
+        "bool" => json!({ "type": "boolean" }),
+        "bytes" => json!({ "type": "string", "format": "byte" }),
+        _ => json!({ "type": "string" }),
+    }
+}
+
+/// Schema of a `google.protobuf.*` type, following the proto3 JSON mapping
+fn well_known_schema(name: &str) -> Value {
+    let wrapped = match name {
+        "DoubleValue" => Some("double"),
+        "FloatValue" => Some("float"),
+        "Int64Value" => Some("int64"),
+        "UInt64Value" => Some("uint64"),
+        "Int32Value" => Some("int32"),
+        "UInt32Value" => Some("uint32"),
+        "BoolValue" => Some("bool"),
+        "StringValue" => Some("string"),
+        "BytesValue" => Some("bytes"),
+        _ => None,
+    };
+    if let Some(scalar) = wrapped {
+        let mut schema = scalar_schema(scalar);
+        schema["nullable"] = Value::Bool(true);
+        return schema;
+    }
+
+    match name {
+        "Timestamp" => json!({ "type": "string", "format": "date-time" }),
+        "Duration" => json!({ "type": "string", "format": "duration" }),
+        "FieldMask" => json!({ "type": "string" }),
+        "Struct" => json!({ "type": "object", "additionalProperties": true }),
+        "ListValue" => json!({ "type": "array", "items": {} }),
+        "Empty" => json!({ "type": "object" }),
+        // Value, Any and anything else unknown
+        _ => json!({}),
+    }
+}
@@ -0,0 +1,263 @@
+use std::collections::{HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+
+use indexmap::IndexMap;
+
+use crate::{Error, ProtoFile, ProtoParseError, ProtoParser, ResolvedItem};
+
+/// A .proto file with everything it imports, transitively.
+///
+/// Imports are looked up in the include paths like protoc's `-I`; the
+/// `google/protobuf` and `google/api` files the converter imports are
+/// built in, so their include directory is not needed.
+#[derive(Debug, Clone, Default)]
+pub struct ProtoSet {
+    /// Files by import name, the entry first
+    files: IndexMap<String, ProtoFile>,
+}
+
+impl ProtoSet {
+    /// Parses `entry` and the files it imports, each once. Without include
+    /// paths imports are looked up next to `entry`.
+    pub fn load(entry: &Path, include_paths: &[PathBuf]) -> Result<Self, Error> {
+        let include_paths = match include_paths {
+            [] => vec![entry.parent().unwrap_or(Path::new("")).to_path_buf()],
+            paths => paths.to_vec(),
+        };
+        // Имя файла в импортах: путь относительно include-каталога
+        let entry_name = include_paths
+            .iter()
+            .find_map(|dir| entry.strip_prefix(dir).ok())
+            .unwrap_or(entry)
+            .to_string_lossy()
+            .replace('\\', "/");
+
+        let mut set = ProtoSet::default();
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::from([(entry_name, Source::Disk(entry.to_path_buf()))]);
+        while let Some((name, source)) = queue.pop_front() {
+            let file = match &source {
+                Source::Disk(path) => {
+                    // Один файл может быть импортирован под разными именами
+                    if !seen.insert(path.canonicalize().unwrap_or_else(|_| path.clone())) {
+                        continue;
+                    }
+                    ProtoParser::new().parse_file(path)?
+                }
+                Source::Embedded(text) => ProtoParser::new().parse(text)?,
+            };
+
+            for import in &file.imports {
+                if set.files.contains_key(import)
+                    || *import == name
+                    || queue.iter().any(|(queued, _)| queued == import)
+                {
+                    continue;
+                }
+                let found = include_paths
+                    .iter()
+                    .map(|dir| dir.join(import))
+                    .find(|path| path.is_file())
+                    .map(Source::Disk)
+                    .or_else(|| embedded(import).map(Source::Embedded));
+                let Some(found) = found else {
+                    return Err(ProtoParseError::MissingImport {
+                        import: import.clone(),
+                        importer: match &source {
+                            Source::Disk(path) => path.clone(),
+                            Source::Embedded(_) => PathBuf::from(&name),
+                        },
+                        searched: include_paths.iter().map(|dir| dir.join(import)).collect(),
+                    }
+                    .into());
+                };
+                queue.push_back((import.clone(), found));
+            }
+            set.files.insert(name, file);
+        }
+        Ok(set)
+    }
+
+    /// Files by import name, the entry first
+    pub fn files(&self) -> impl Iterator<Item = (&str, &ProtoFile)> {
+        self.files.iter().map(|(name, file)| (name.as_str(), file))
+    }
+
+    /// The file given to [`ProtoSet::load`]
+    pub fn entry(&self) -> Option<&ProtoFile> {
+        self.files.values().next()
+    }
+
+    /// File by import name: `google/protobuf/timestamp.proto`
+    pub fn find_file(&self, name: &str) -> Option<&ProtoFile> {
+        self.files.get(name)
+    }
+
+    /// Message or enum by fully qualified name (`google.protobuf.Timestamp`,
+    /// `.shop.v1.Order.Status`), with the import name of its file
+    pub fn resolve_type(&self, fqn: &str) -> Option<(&str, ResolvedItem<'_>)> {
+        let fqn = fqn.trim_start_matches('.');
+        self.files.iter().find_map(|(name, file)| {
+            let path = match file.package.as_str() {
+                "" => fqn,
+                package => fqn.strip_prefix(package)?.strip_prefix('.')?,
+            };
+            match file.resolve(path)? {
+                item @ (ResolvedItem::Message(_) | ResolvedItem::Enum(_)) => {
+                    Some((name.as_str(), item))
+                }
+                _ => None,
+            }
+        })
+    }
+}
+
+enum Source {
+    Disk(PathBuf),
+    Embedded(&'static str),
+}
+
+/// Built-in copy of a google import. Only the types are kept: options and
+/// `extend` blocks are left out.
+fn embedded(import: &str) -> Option<&'static str> {
+    let text = match import {
+        "google/protobuf/any.proto" => {
+            "syntax = \"proto3\";
+package google.protobuf;
+message Any {
+  string type_url = 1;
+  bytes value = 2;
+}"
+        }
+        "google/protobuf/duration.proto" => {
+            "syntax = \"proto3\";
+package google.protobuf;
+message Duration {
+  int64 seconds = 1;
+  int32 nanos = 2;
+}"
+        }
+        "google/protobuf/empty.proto" => {
+            "syntax = \"proto3\";
+package google.protobuf;
+message Empty {}"
+        }
+        "google/protobuf/field_mask.proto" => {
+            "syntax = \"proto3\";
+package google.protobuf;
+message FieldMask {
+  repeated string paths = 1;
+}"
+        }
+        "google/protobuf/struct.proto" => {
+            "syntax = \"proto3\";
+package google.protobuf;
+message Struct {
+  map<string, Value> fields = 1;
+}
+message Value {
+  oneof kind {
+    NullValue null_value = 1;
+    double number_value = 2;
+    string string_value = 3;
+    bool bool_value = 4;
+    Struct struct_value = 5;
+    ListValue list_value = 6;
+  }
+}
+enum NullValue {
+  NULL_VALUE = 0;
+}
+message ListValue {
+  repeated Value values = 1;
+}"
+        }
+        "google/protobuf/timestamp.proto" => {
+            "syntax = \"proto3\";
+package google.protobuf;
+message Timestamp {
+  int64 seconds = 1;
+  int32 nanos = 2;
+}"
+        }
+        "google/protobuf/wrappers.proto" => {
+            "syntax = \"proto3\";
+package google.protobuf;
+message DoubleValue {
+  double value = 1;
+}
+message FloatValue {
+  float value = 1;
+}
+message Int64Value {
+  int64 value = 1;
+}
+message UInt64Value {
+  uint64 value = 1;
+}
+message Int32Value {
+  int32 value = 1;
+}
+message UInt32Value {
+  uint32 value = 1;
+}
+message BoolValue {
+  bool value = 1;
+}
+message StringValue {
+  string value = 1;
+}
+message BytesValue {
+  bytes value = 1;
+}"
+        }
+        "google/api/annotations.proto" => {
+            "syntax = \"proto3\";
+package google.api;
+import \"google/api/http.proto\";"
+        }
+        "google/api/http.proto" => {
+            "syntax = \"proto3\";
+package google.api;
+message Http {
+  repeated HttpRule rules = 1;
+  bool fully_decode_reserved_expansion = 2;
+}
+message HttpRule {
+  string selector = 1;
+  oneof pattern {
+    string get = 2;
+    string put = 3;
+    string post = 4;
+    string delete = 5;
+    string patch = 6;
+    CustomHttpPattern custom = 8;
+  }
+  string body = 7;
+  string response_body = 12;
+  repeated HttpRule additional_bindings = 11;
+}
+message CustomHttpPattern {
+  string kind = 1;
+  string path = 2;
+}"
+        }
+        "google/api/field_behavior.proto" => {
+            "syntax = \"proto3\";
+package google.api;
+enum FieldBehavior {
+  FIELD_BEHAVIOR_UNSPECIFIED = 0;
+  OPTIONAL = 1;
+  REQUIRED = 2;
+  OUTPUT_ONLY = 3;
+  INPUT_ONLY = 4;
+  IMMUTABLE = 5;
+  UNORDERED_LIST = 6;
+  NON_EMPTY_DEFAULT = 7;
+  IDENTIFIER = 8;
+}"
+        }
+        _ => return None,
+    };
+    Some(text)
+}
@@ -0,0 +1,303 @@
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::ConverterError;
+
+/// Inlines `$ref`s that point into other files.
+///
+/// Every referenced definition is copied into the matching section of the
+/// root document (`components/schemas`, `definitions`, ...) and the `$ref`
+/// is rewritten to point there, so the converter only ever sees local refs.
+/// Refs inside an external file are resolved against that file.
+pub(crate) struct RefBundler {
+    base_dir: PathBuf,
+    swagger2: bool,
+    documents: HashMap<PathBuf, Value>,
+    // (file, pointer) -> local ref in the root document
+    imported: HashMap<(PathBuf, String), String>,
+    used_names: HashSet<(String, String)>,
+    // (section, name, definition) waiting to be added to the root document
+    pending: Vec<(String, String, Value)>,
+}
+
+impl RefBundler {
+    pub(crate) fn new(base_dir: &Path) -> Self {
+        Self {
+            base_dir: base_dir.to_path_buf(),
+            swagger2: false,
+            documents: HashMap::new(),
+            imported: HashMap::new(),
+            used_names: HashSet::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    pub(crate) fn bundle(&mut self, root: &mut Value) -> Result<(), ConverterError> {
+        self.swagger2 = root.get("swagger").is_some();
+        self.reserve_existing_names(root);
+
+        let mut chain = Vec::new();
+        self.walk(root, None, &mut chain)?;
+
+        for (section, name, definition) in std::mem::take(&mut self.pending) {
+            let target = self.section_mut(root, &section);
+            target.insert(name, definition);
+        }
+
+        Ok(())
+    }
+
+    fn reserve_existing_names(&mut self, root: &Value) {
+        let sections = [
+            ("schemas", "/definitions"),
+            ("parameters", "/parameters"),
+            ("responses", "/responses"),
+            ("requestBodies", "/requestBodies"),
+        ];
+        for (section, swagger2_pointer) in sections {
+            let pointer = if self.swagger2 {
+                swagger2_pointer.to_string()
+            } else {
+                format!("/components/{}", section)
+            };
+            if let Some(Value::Object(existing)) = root.pointer(&pointer) {
+                for name in existing.keys() {
+                    self.used_names
+                        .insert((section.to_string(), name.to_string()));
+                }
+            }
+        }
+    }
+
+    fn walk(
+        &mut self,
+        value: &mut Value,
+        current_file: Option<&Path>,
+        chain: &mut Vec<(PathBuf, String)>,
+    ) -> Result<(), ConverterError> {
+        match value {
+            Value::Object(map) => {
+                if let Some(Value::String(ref_path)) = map.get("$ref").cloned()
+                    && let Some(local_ref) = self.resolve(&ref_path, current_file, chain)?
+                {
+                    map.insert("$ref".to_string(), Value::String(local_ref));
+                }
+                for (key, child) in map.iter_mut() {
+                    if key != "$ref" {
+                        self.walk(child, current_file, chain)?;
+                    }
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    self.walk(item, current_file, chain)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Returns the local ref that replaces `ref_path`, or `None` if the ref
+    /// is already local to the root document.
+    fn resolve(
+        &mut self,
+        ref_path: &str,
+        current_file: Option<&Path>,
+        chain: &mut Vec<(PathBuf, String)>,
+    ) -> Result<Option<String>, ConverterError> {
+        let (file_part, pointer) = ref_path.split_once('#').unwrap_or((ref_path, ""));
+
+        if file_part.starts_with("http://") || file_part.starts_with("https://") {
+            return Err(ConverterError::RemoteReference(ref_path.to_string()));
+        }
+
+        let file = match (file_part.is_empty(), current_file) {
+            (true, None) => return Ok(None),
+            (true, Some(current)) => current.to_path_buf(),
+            (false, _) => {
+                let dir = current_file
+                    .and_then(Path::parent)
+                    .unwrap_or(&self.base_dir);
+                let joined = dir.join(file_part);
+                joined
+                    .canonicalize()
+                    .map_err(|e| ConverterError::ExternalDocument {
+                        path: joined.display().to_string(),
+                        message: e.to_string(),
+                    })?
+            }
+        };
+
+        self.import(file, pointer.to_string(), chain).map(Some)
+    }
+
+    fn import(
+        &mut self,
+        file: PathBuf,
+        pointer: String,
+        chain: &mut Vec<(PathBuf, String)>,
+    ) -> Result<String, ConverterError> {
+        let key = (file.clone(), pointer.clone());
+        if let Some(local_ref) = self.imported.get(&key) {
+            return Ok(local_ref.clone());
+        }
+
+        if chain.contains(&key) {
+            let from = chain
+                .last()
+                .map(|(f, _)| f.display().to_string())
+                .unwrap_or_default();
+            return Err(ConverterError::CircularExternalReference {
+                from,
+                to: format!("{}#{}", file.display(), pointer),
+            });
+        }
+
+        let mut target = self
+            .load(&file)?
+            .pointer(&pointer)
+            .cloned()
+            .ok_or_else(|| {
+                ConverterError::MissingReference(format!("{}#{}", file.display(), pointer))
+            })?;
+
+        // A definition that is only an alias for another ref: follow it
+        if let Some(Value::String(alias)) = target.get("$ref").filter(|_| is_pure_ref(&target)) {
+            let alias = alias.clone();
+            chain.push(key.clone());
+            let resolved = self.resolve(&alias, Some(&file), chain);
+            chain.pop();
+            let local_ref = match resolved? {
+                Some(local_ref) => local_ref,
+                None => alias,
+            };
+            self.imported.insert(key, local_ref.clone());
+            return Ok(local_ref);
+        }
+
+        let section = section_of(&pointer);
+        let name = self.unique_name(section, &definition_name(&file, &pointer));
+        let local_ref = self.local_ref(section, &name);
+
+        // Registered before walking so recursive refs resolve to the same name
+        self.imported.insert(key.clone(), local_ref.clone());
+
+        chain.push(key);
+        let walked = self.walk(&mut target, Some(&file), chain);
+        chain.pop();
+        walked?;
+
+        self.pending.push((section.to_string(), name, target));
+        Ok(local_ref)
+    }
+
+    fn load(&mut self, file: &Path) -> Result<&Value, ConverterError> {
+        if !self.documents.contains_key(file) {
+            let content =
+                std::fs::read_to_string(file).map_err(|e| ConverterError::ExternalDocument {
+                    path: file.display().to_string(),
+                    message: e.to_string(),
+                })?;
+
+            let is_yaml = matches!(
+                file.extension().and_then(|e| e.to_str()),
+                Some("yaml") | Some("yml")
+            );
+            let document: Value = if is_yaml {
+                serde_yaml::from_str(&content).map_err(|e| e.to_string())
+            } else {
+                serde_json::from_str(&content).map_err(|e| e.to_string())
+            }
+            .map_err(|message| ConverterError::ExternalDocument {
+                path: file.display().to_string(),
+                message,
+            })?;
+
+            self.documents.insert(file.to_path_buf(), document);
+        }
+
+        Ok(&self.documents[file])
+    }
+
+    fn unique_name(&mut self, section: &str, base: &str) -> String {
+        let mut name = base.to_string();
+        let mut suffix = 2;
+        while self
+            .used_names
+            .contains(&(section.to_string(), name.clone()))
+        {
+            name = format!("{}{}", base, suffix);
+            suffix += 1;
+        }
+        self.used_names.insert((section.to_string(), name.clone()));
+        name
+    }
+
+    fn local_ref(&self, section: &str, name: &str) -> String {
+        let name = name.replace('~', "~0").replace('/', "~1");
+        match (self.swagger2, section) {
+            (true, "schemas") => format!("#/definitions/{}", name),
+            (true, _) => format!("#/{}/{}", section, name),
+            (false, _) => format!("#/components/{}/{}", section, name),
+        }
+    }
+
+    fn section_mut<'a>(&self, root: &'a mut Value, section: &str) -> &'a mut Map<String, Value> {
+        let path: Vec<&str> = match (self.swagger2, section) {
+            (true, "schemas") => vec!["definitions"],
+            (true, _) => vec![section],
+            (false, _) => vec!["components", section],
+        };
+
+        let mut current = root;
+        for key in path {
+            current = ensure_object(current)
+                .entry(key.to_string())
+                .or_insert_with(|| Value::Object(Map::new()));
+        }
+        ensure_object(current)
+    }
+}
+
+fn ensure_object(value: &mut Value) -> &mut Map<String, Value> {
+    if !value.is_object() {
+        *value = Value::Object(Map::new());
+    }
+    value.as_object_mut().unwrap()
+}
+
+fn is_pure_ref(value: &Value) -> bool {
+    value.as_object().map(|map| map.len() == 1).unwrap_or(false)
+}
+
+/// Maps a pointer inside an external document to the root section it
+/// should be copied into; anything unrecognised is treated as a schema.
+fn section_of(pointer: &str) -> &'static str {
+    let mut segments = pointer.trim_start_matches('/').split('/');
+    let section = match segments.next() {
+        Some("components") => segments.next(),
+        other => other,
+    };
+    match section {
+        Some("parameters") => "parameters",
+        Some("responses") => "responses",
+        Some("requestBodies") => "requestBodies",
+        _ => "schemas",
+    }
+}
+
+fn definition_name(file: &Path, pointer: &str) -> String {
+    pointer
+        .rsplit('/')
+        .next()
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+        .unwrap_or_else(|| {
+            file.file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or("External")
+                .to_string()
+        })
+}
@@ -0,0 +1,158 @@
+use crate::proto2swagger::TypePaths;
+use crate::util::join_path;
+use crate::{ConverterError, FieldType, Message, ProtoFile};
+
+impl ProtoFile {
+    /// Renames a message and rewrites every field type and rpc signature
+    /// referring to it or to a type nested in it.
+    ///
+    /// `old` is a dotted path (`Order`, `Order.Item`, optionally with the
+    /// package), `new` the new simple name. References keep their form:
+    /// `api.Order` becomes `api.Invoice`, `Order.Item` becomes `Invoice.Item`.
+    /// Returns the number of rewritten references.
+    pub fn rename_message(&mut self, old: &str, new: &str) -> Result<usize, ConverterError> {
+        let old_path = self.relative_path(old).to_string();
+        if self.find_message_by_path(&old_path).is_none() {
+            return Err(ConverterError::MessageNotFound(old.to_string()));
+        }
+        let count = self.rename_references(&old_path, new)?;
+        if let Some(message) = self.find_message_by_path_mut(&old_path) {
+            message.name = new.to_string();
+        }
+        Ok(count)
+    }
+
+    /// Renames an enum and rewrites the field types referring to it; see
+    /// [`ProtoFile::rename_message`]. Enum values keep their names.
+    pub fn rename_enum(&mut self, old: &str, new: &str) -> Result<usize, ConverterError> {
+        let old_path = self.relative_path(old).to_string();
+        if !self.all_enums().any(|(path, _)| path == old_path) {
+            return Err(ConverterError::MessageNotFound(old.to_string()));
+        }
+        let count = self.rename_references(&old_path, new)?;
+
+        let enum_def = match old_path.rsplit_once('.') {
+            Some((parent, name)) => self
+                .find_message_by_path_mut(parent)
+                .and_then(|m| m.nested_enums.iter_mut().find(|e| e.name == name)),
+            None => self.find_enum_mut(&old_path),
+        };
+        if let Some(enum_def) = enum_def {
+            enum_def.name = new.to_string();
+        }
+        Ok(count)
+    }
+
+    /// Renames a service. Nothing refers to services, so this returns 0
+    /// unless the name is unknown or taken.
+    pub fn rename_service(&mut self, old: &str, new: &str) -> Result<usize, ConverterError> {
+        let old = self.relative_path(old).to_string();
+        if self.find_service(new).is_some() {
+            return Err(ConverterError::DuplicateMessageName(new.to_string()));
+        }
+        let service = self
+            .find_service_mut(&old)
+            .ok_or(ConverterError::ServiceNotFound(old))?;
+        service.name = new.to_string();
+        Ok(0)
+    }
+
+    /// Rewrites references to `old_path` (and types nested in it) for the
+    /// rename of its last segment to `new`
+    fn rename_references(&mut self, old_path: &str, new: &str) -> Result<usize, ConverterError> {
+        let types = TypePaths::new(self);
+        let parent = old_path.rsplit_once('.').map_or("", |(parent, _)| parent);
+        let new_path = join_path(parent, new);
+        if types.contains(&new_path) || (parent.is_empty() && self.find_service(new).is_some()) {
+            return Err(ConverterError::DuplicateMessageName(
+                self.qualified_name(&new_path),
+            ));
+        }
+
+        let renamer = Renamer {
+            types: &types,
+            old_path,
+            new,
+        };
+        let mut count = 0;
+        for message in &mut self.messages {
+            renamer.message(message, "", &mut count);
+        }
+        for service in &mut self.services {
+            for method in &mut service.methods {
+                for type_ in [&mut method.input_type, &mut method.output_type] {
+                    if let Some(renamed) = renamer.type_name(type_, "") {
+                        *type_ = renamed;
+                        count += 1;
+                    }
+                }
+            }
+        }
+        Ok(count)
+    }
+}
+
+struct Renamer<'a> {
+    types: &'a TypePaths,
+    old_path: &'a str,
+    new: &'a str,
+}
+
+impl Renamer<'_> {
+    fn message(&self, message: &mut Message, prefix: &str, count: &mut usize) {
+        let path = join_path(prefix, &message.name);
+        let fields = message
+            .fields
+            .iter_mut()
+            .chain(message.oneofs.iter_mut().flat_map(|o| o.fields.iter_mut()));
+        for field in fields {
+            if let Some(renamed) = self.field_type(&field.type_, &path) {
+                field.type_ = renamed;
+                *count += 1;
+            }
+        }
+        for nested in &mut message.nested_messages {
+            self.message(nested, &path, count);
+        }
+    }
+
+    /// `Foo` or `map<string, Foo>` with the renamed references replaced
+    fn field_type(&self, type_: &FieldType, scope: &str) -> Option<FieldType> {
+        match type_ {
+            FieldType::Scalar(_) => None,
+            FieldType::Named(name) => self.type_name(name, scope).map(FieldType::Named),
+            FieldType::Map { key, value } => self
+                .field_type(value, scope)
+                .map(|value| FieldType::map(*key, value)),
+        }
+    }
+
+    /// The reference with the renamed segment replaced, if it resolves to
+    /// the renamed type or something nested in it
+    fn type_name(&self, name: &str, scope: &str) -> Option<String> {
+        let resolved = self.types.resolve(name, scope);
+        let nested_prefix = format!("{}.", self.old_path);
+        if resolved != self.old_path && !resolved.starts_with(&nested_prefix) {
+            return None;
+        }
+
+        // Ссылка совпадает с хвостом разрешённого пути; префикс пакета не трогаем
+        let relative = name.trim_start_matches('.');
+        let relative = match relative.strip_prefix(&format!("{}.", self.types.package())) {
+            Some(rest) if !self.types.package().is_empty() => rest,
+            _ => relative,
+        };
+        let qualifier = &name[..name.len() - relative.len()];
+
+        let mut segments: Vec<&str> = relative.split('.').collect();
+        let resolved_len = resolved.split('.').count();
+        let renamed_index = self.old_path.split('.').count() - 1;
+        let offset = resolved_len.checked_sub(segments.len())?;
+        if renamed_index < offset {
+            // `Item` written inside `Order` itself does not mention `Order`
+            return None;
+        }
+        segments[renamed_index - offset] = self.new;
+        Some(format!("{}{}", qualifier, segments.join(".")))
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+
+use crate::imports::well_known_import;
+use crate::util::join_path;
+use crate::{Enum, Message, ProtoFile, ScalarType};
+
+/// What a field or rpc type name refers to, see [`TypeResolver::resolve`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResolvedType<'a> {
+    Message(&'a Message),
+    Enum(&'a Enum),
+    Scalar(ScalarType),
+    /// A `google.protobuf` type, with the file declaring it
+    WellKnown(&'static str),
+    /// Not defined in this file, e.g. a type of an imported package
+    Unresolved,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Definition<'a> {
+    Message(&'a Message),
+    Enum(&'a Enum),
+}
+
+/// Resolves type names with protobuf's scoping rules: enclosing messages
+/// innermost first, then the package and its parents. A leading dot makes
+/// a name absolute.
+///
+/// Built once per file, so prefer it to [`ProtoFile::resolve_type`] when
+/// resolving many names.
+#[derive(Debug, Clone)]
+pub struct TypeResolver<'a> {
+    package: &'a str,
+    /// Messages and enums by their dotted path relative to the package
+    definitions: HashMap<String, Definition<'a>>,
+}
+
+impl<'a> TypeResolver<'a> {
+    pub fn new(file: &'a ProtoFile) -> Self {
+        let messages = file
+            .all_messages()
+            .map(|(path, message)| (path, Definition::Message(message)));
+        let enums = file
+            .all_enums()
+            .map(|(path, enum_def)| (path, Definition::Enum(enum_def)));
+        Self {
+            package: &file.package,
+            definitions: messages.chain(enums).collect(),
+        }
+    }
+
+    /// Resolves `name` as written inside the message at `scope`, a dotted
+    /// path relative to the package (`""` for file level)
+    pub fn resolve(&self, name: &str, scope: &str) -> ResolvedType<'a> {
+        if let Some(scalar) = ScalarType::from_name(name) {
+            return ResolvedType::Scalar(scalar);
+        }
+        if let Some(path) = self.resolve_path(name, scope) {
+            return match self.definitions[&path] {
+                Definition::Message(message) => ResolvedType::Message(message),
+                Definition::Enum(enum_def) => ResolvedType::Enum(enum_def),
+            };
+        }
+        match name
+            .trim_start_matches('.')
+            .strip_prefix("google.protobuf.")
+            .and_then(well_known_import)
+        {
+            Some(import) => ResolvedType::WellKnown(import),
+            None => ResolvedType::Unresolved,
+        }
+    }
+
+    /// Dotted path, relative to the package, of the message or enum `name`
+    /// refers to
+    pub fn resolve_path(&self, name: &str, scope: &str) -> Option<String> {
+        resolve_path(self.package, name, scope, |path| {
+            self.definitions.contains_key(path)
+        })
+    }
+}
+
+impl ProtoFile {
+    /// Resolver over the messages and enums of this file
+    pub fn type_resolver(&self) -> TypeResolver<'_> {
+        TypeResolver::new(self)
+    }
+
+    /// Resolves a single type name; see [`TypeResolver::resolve`]
+    pub fn resolve_type(&self, name: &str, scope: &str) -> ResolvedType<'_> {
+        self.type_resolver().resolve(name, scope)
+    }
+}
+
+/// Path of the definition `name` refers to from `scope`, given the paths
+/// that exist
+pub(crate) fn resolve_path(
+    package: &str,
+    name: &str,
+    scope: &str,
+    exists: impl Fn(&str) -> bool,
+) -> Option<String> {
+    if let Some(absolute) = name.strip_prefix('.') {
+        return strip_package(package, absolute, package.split('.').count())
+            .filter(|path| exists(path))
+            .map(str::to_string);
+    }
+
+    // Области видимости сообщений, от внутренней к внешней
+    let mut scope = scope;
+    loop {
+        let candidate = join_path(scope, name);
+        if exists(&candidate) {
+            return Some(candidate);
+        }
+        match scope.rsplit_once('.') {
+            Some((outer, _)) => scope = outer,
+            None if !scope.is_empty() => scope = "",
+            None => break,
+        }
+    }
+
+    // Then the package and its parents: `api.Order` inside `com.example.api`
+    if !package.is_empty() {
+        let segments = package.split('.').count();
+        for qualified in 1..=segments {
+            if let Some(path) = strip_package(package, name, qualified)
+                && exists(path)
+            {
+                return Some(path.to_string());
+            }
+        }
+    }
+
+    // Nested definitions may have been flattened to the top level
+    match name.rsplit_once('.') {
+        Some((_, last)) if exists(last) => Some(last.to_string()),
+        _ => None,
+    }
+}
+
+/// `name` without its leading package qualifier made of the last
+/// `segments` segments of `package`
+fn strip_package<'n>(package: &str, name: &'n str, segments: usize) -> Option<&'n str> {
+    if package.is_empty() {
+        return Some(name);
+    }
+    let parts: Vec<&str> = package.split('.').collect();
+    let qualifier = parts[parts.len().saturating_sub(segments)..].join(".");
+    name.strip_prefix(qualifier.as_str())
+        .and_then(|rest| rest.strip_prefix('.'))
+}
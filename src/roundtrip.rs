@@ -0,0 +1,227 @@
+use indexmap::IndexMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Debug;
+
+use crate::{
+    Enum, Field, Message, Method, Oneof, OptionValue, ProtoFile, ProtoParser, RoundtripDiff,
+    Service,
+};
+
+/// Parses `content`, renders it back with `to_proto_text`, parses that and
+/// checks both models are equivalent (see [`ProtoFile::semantically_equal`]).
+///
+/// Returns the first divergence as a path into the model, e.g.
+/// `messages[2].fields[0].number`.
+pub fn roundtrip_check(content: &str) -> Result<(), RoundtripDiff> {
+    let original = ProtoParser::new()
+        .parse(content)
+        .map_err(|e| RoundtripDiff::Parse(e.to_string()))?;
+    let rendered = original.to_proto_text();
+    let reparsed = ProtoParser::new()
+        .parse(&rendered)
+        .map_err(|e| RoundtripDiff::Reparse(e.to_string()))?;
+
+    match original.first_difference(&reparsed) {
+        Some(diff) => Err(diff),
+        None => Ok(()),
+    }
+}
+
+impl ProtoFile {
+    /// Compares two files ignoring details that do not survive rendering:
+    /// whitespace around comments, the order of imports and of options.
+    pub fn semantically_equal(&self, other: &ProtoFile) -> bool {
+        self.first_difference(other).is_none()
+    }
+
+    /// First place where `other` differs from `self`, in the sense of
+    /// [`ProtoFile::semantically_equal`]
+    pub fn first_difference(&self, other: &ProtoFile) -> Option<RoundtripDiff> {
+        let mut diff = Differ::default();
+        diff.comments(
+            "header_comments",
+            &self.header_comments,
+            &other.header_comments,
+        );
+        diff.value("syntax", &self.syntax, &other.syntax);
+        diff.value("package", &self.package, &other.package);
+        diff.value(
+            "imports",
+            &self.imports.iter().collect::<BTreeSet<_>>(),
+            &other.imports.iter().collect::<BTreeSet<_>>(),
+        );
+        diff.value(
+            "options",
+            &self.options.iter().collect::<BTreeMap<_, _>>(),
+            &other.options.iter().collect::<BTreeMap<_, _>>(),
+        );
+        diff.list("messages", &self.messages, &other.messages, Differ::message);
+        diff.list("enums", &self.enums, &other.enums, Differ::enum_def);
+        diff.list("services", &self.services, &other.services, Differ::service);
+        diff.found
+    }
+}
+
+/// Walks two models side by side, remembering the first mismatch
+#[derive(Default)]
+struct Differ {
+    path: Vec<String>,
+    found: Option<RoundtripDiff>,
+}
+
+impl Differ {
+    fn value<T: Debug + PartialEq>(&mut self, name: &str, expected: &T, actual: &T) {
+        if self.found.is_none() && expected != actual {
+            self.found = Some(RoundtripDiff::Mismatch {
+                path: self.path_to(name),
+                expected: format!("{:?}", expected),
+                actual: format!("{:?}", actual),
+            });
+        }
+    }
+
+    fn comments(&mut self, name: &str, expected: &[String], actual: &[String]) {
+        let normalize = |comments: &[String]| -> Vec<String> {
+            comments.iter().map(|c| c.trim().to_string()).collect()
+        };
+        self.value(name, &normalize(expected), &normalize(actual));
+    }
+
+    fn options(
+        &mut self,
+        expected: &IndexMap<String, OptionValue>,
+        actual: &IndexMap<String, OptionValue>,
+    ) {
+        self.value(
+            "options",
+            &expected.iter().collect::<BTreeMap<_, _>>(),
+            &actual.iter().collect::<BTreeMap<_, _>>(),
+        );
+    }
+
+    fn list<T>(
+        &mut self,
+        name: &str,
+        expected: &[T],
+        actual: &[T],
+        compare: impl Fn(&mut Self, &T, &T),
+    ) {
+        for (i, (e, a)) in expected.iter().zip(actual).enumerate() {
+            if self.found.is_some() {
+                return;
+            }
+            self.path.push(format!("{}[{}]", name, i));
+            compare(self, e, a);
+            self.path.pop();
+        }
+        self.value(&format!("{}.len", name), &expected.len(), &actual.len());
+    }
+
+    fn message(&mut self, expected: &Message, actual: &Message) {
+        self.value("name", &expected.name, &actual.name);
+        self.comments("comments", &expected.comments, &actual.comments);
+        self.options(&expected.options, &actual.options);
+        self.value(
+            "reserved_ranges",
+            &expected.reserved_ranges,
+            &actual.reserved_ranges,
+        );
+        self.value(
+            "reserved_names",
+            &expected.reserved_names,
+            &actual.reserved_names,
+        );
+        self.value(
+            "extension_ranges",
+            &expected.extension_ranges,
+            &actual.extension_ranges,
+        );
+        self.list("fields", &expected.fields, &actual.fields, Self::field);
+        self.list("oneofs", &expected.oneofs, &actual.oneofs, Self::oneof);
+        self.list(
+            "nested_messages",
+            &expected.nested_messages,
+            &actual.nested_messages,
+            Self::message,
+        );
+        self.list(
+            "nested_enums",
+            &expected.nested_enums,
+            &actual.nested_enums,
+            Self::enum_def,
+        );
+    }
+
+    fn oneof(&mut self, expected: &Oneof, actual: &Oneof) {
+        self.value("name", &expected.name, &actual.name);
+        self.comments("comments", &expected.comments, &actual.comments);
+        self.options(&expected.options, &actual.options);
+        self.list("fields", &expected.fields, &actual.fields, Self::field);
+    }
+
+    fn field(&mut self, expected: &Field, actual: &Field) {
+        self.value("name", &expected.name, &actual.name);
+        self.value("type_", &expected.type_, &actual.type_);
+        self.value("number", &expected.number, &actual.number);
+        self.value("rule", &expected.rule, &actual.rule);
+        self.comments("comments", &expected.comments, &actual.comments);
+        self.options(&expected.options, &actual.options);
+    }
+
+    fn enum_def(&mut self, expected: &Enum, actual: &Enum) {
+        self.value("name", &expected.name, &actual.name);
+        self.comments("comments", &expected.comments, &actual.comments);
+        self.options(&expected.options, &actual.options);
+        self.value(
+            "reserved_ranges",
+            &expected.reserved_ranges,
+            &actual.reserved_ranges,
+        );
+        self.value(
+            "reserved_names",
+            &expected.reserved_names,
+            &actual.reserved_names,
+        );
+        self.list("values", &expected.values, &actual.values, |diff, e, a| {
+            diff.value("name", &e.name, &a.name);
+            diff.value("number", &e.number, &a.number);
+            diff.value("original_value", &e.original_value, &a.original_value);
+            diff.comments("comments", &e.comments, &a.comments);
+            diff.options(&e.options, &a.options);
+        });
+    }
+
+    fn service(&mut self, expected: &Service, actual: &Service) {
+        self.value("name", &expected.name, &actual.name);
+        self.comments("comments", &expected.comments, &actual.comments);
+        self.options(&expected.options, &actual.options);
+        self.list("methods", &expected.methods, &actual.methods, Self::method);
+    }
+
+    fn method(&mut self, expected: &Method, actual: &Method) {
+        self.value("name", &expected.name, &actual.name);
+        self.value("input_type", &expected.input_type, &actual.input_type);
+        self.value("output_type", &expected.output_type, &actual.output_type);
+        self.value(
+            "client_streaming",
+            &expected.client_streaming,
+            &actual.client_streaming,
+        );
+        self.value(
+            "server_streaming",
+            &expected.server_streaming,
+            &actual.server_streaming,
+        );
+        self.comments("comments", &expected.comments, &actual.comments);
+        self.options(&expected.options, &actual.options);
+    }
+
+    fn path_to(&self, name: &str) -> String {
+        self.path
+            .iter()
+            .map(String::as_str)
+            .chain([name])
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+}
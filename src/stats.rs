@@ -0,0 +1,83 @@
+use std::collections::BTreeSet;
+
+use serde::Serialize;
+
+use crate::imports::well_known_import;
+use crate::{FieldType, ProtoFile};
+
+/// Size and content summary of a file, see [`ProtoFile::stats`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ProtoStats {
+    /// Every message, nested ones included
+    pub messages: usize,
+    pub top_level_messages: usize,
+    pub nested_messages: usize,
+    /// Every enum, nested ones included
+    pub enums: usize,
+    pub services: usize,
+    pub methods: usize,
+    /// Fields of all messages, oneof members included
+    pub fields: usize,
+    /// `None` when the file has no fields
+    pub max_field_number: Option<i32>,
+    /// Scalar types of fields, map keys and values included
+    pub scalar_types: BTreeSet<&'static str>,
+    /// Imports providing the type of at least one field
+    pub used_imports: BTreeSet<String>,
+    /// Dotted paths of messages without fields
+    pub empty_messages: Vec<String>,
+}
+
+impl ProtoFile {
+    /// Counts definitions and collects the types in use, walking nested
+    /// messages recursively.
+    ///
+    /// Only the `google/protobuf/*` imports can be traced to the fields that
+    /// use them, since other imported files are not loaded.
+    pub fn stats(&self) -> ProtoStats {
+        let mut stats = ProtoStats {
+            top_level_messages: self.messages.len(),
+            enums: self.all_enums().count(),
+            services: self.services.len(),
+            methods: self.services.iter().map(|s| s.methods.len()).sum(),
+            ..Default::default()
+        };
+
+        for (path, message) in self.all_messages() {
+            stats.messages += 1;
+            let mut empty = true;
+            for field in message.all_fields() {
+                empty = false;
+                stats.fields += 1;
+                stats.max_field_number = stats.max_field_number.max(Some(field.number));
+                self.collect_types(&field.type_, &mut stats);
+            }
+            if empty {
+                stats.empty_messages.push(path);
+            }
+        }
+        stats.nested_messages = stats.messages - stats.top_level_messages;
+        stats
+    }
+
+    fn collect_types(&self, type_: &FieldType, stats: &mut ProtoStats) {
+        match type_ {
+            FieldType::Scalar(scalar) => {
+                stats.scalar_types.insert(scalar.as_str());
+            }
+            FieldType::Named(name) => {
+                if let Some(import) = name
+                    .strip_prefix("google.protobuf.")
+                    .and_then(well_known_import)
+                    && self.imports.iter().any(|i| i == import)
+                {
+                    stats.used_imports.insert(import.to_string());
+                }
+            }
+            FieldType::Map { key, value } => {
+                stats.scalar_types.insert(key.as_str());
+                self.collect_types(value, stats);
+            }
+        }
+    }
+}
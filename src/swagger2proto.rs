@@ -1,160 +1,1746 @@
-use rand::random;
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
-use std::collections::{BTreeMap, HashMap};
-use std::path::Path;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
+use crate::name_formatter::transliterate;
+use crate::name_registry::{Allocated, NameRegistry};
+use crate::naming::{DefaultNaming, NamingStrategy};
+use crate::ref_bundler::RefBundler;
 use crate::{
-    ConverterError, Enum, EnumValue, Field, FieldRule, Message, Method, NameFormatter, ProtoFile,
-    Service,
+    ConversionWarning, ConverterError, Enum, EnumValue, Field, FieldRule, FieldType, Message,
+    Method, NameFormatter, Oneof, OptionValue, ProtoFile, ScalarType, Service, Syntax,
 };
 
+/// Knobs that change the shape of the generated proto
+#[derive(Debug, Clone)]
+pub struct ConverterOptions {
+    /// Where `in: header` and `in: cookie` parameters end up
+    pub header_params: HeaderParams,
+    /// Wrap array properties into `<Item>List` messages instead of emitting
+    /// `repeated` fields. rpc return types are always wrapped.
+    pub wrap_repeated: bool,
+    /// How required properties and parameters are marked in proto3 output
+    pub required_fields: RequiredFields,
+    /// What `nullable: true` (or a `"null"` entry in `type`) turns into
+    pub nullable: NullableStrategy,
+    /// Emit protoc-gen-validate rules for min/max/length/pattern constraints
+    pub validation_rules: bool,
+    /// Emit inline object schemas as nested messages of the message that
+    /// uses them (`User.Address`) instead of top-level `UserAddress`
+    pub nest_inline_messages: bool,
+    /// How operations are split into services
+    pub service_grouping: ServiceGrouping,
+    /// Fail on spec problems that are otherwise worked around with a warning,
+    /// such as unknown schema types
+    pub strict: bool,
+    /// Generate `<Name>Input` without readOnly properties for request
+    /// bodies and `<Name>Output` without writeOnly ones for responses.
+    /// Otherwise such properties are only marked with a comment.
+    pub split_read_write: bool,
+    /// `x-*` extensions emitted as options instead of comments, mapped to
+    /// the option name: `x-go-name` -> `(gogoproto.customname)`
+    pub extension_options: BTreeMap<String, String>,
+    /// Leave out operations marked `x-internal: true`
+    pub skip_internal: bool,
+    /// Convert only operations carrying one of these tags; empty converts
+    /// every operation
+    pub only_tags: Vec<String>,
+    /// Leave out operations carrying one of these tags, even if they also
+    /// carry one of `only_tags`
+    pub exclude_tags: Vec<String>,
+    /// Replace the package with one derived from `info.title` and
+    /// `info.version`, see [`package_from_title`]
+    pub package_from_info: bool,
+    /// `option go_package`
+    pub go_package: Option<String>,
+    /// `option java_package`
+    pub java_package: Option<String>,
+    /// `option java_multiple_files = true`
+    pub java_multiple_files: bool,
+    /// Render title, version, description, contact, license and the
+    /// generator version as a comment block at the top of the file. On by
+    /// default.
+    pub info_header: bool,
+    /// Convert only the schemas reachable from paths and webhooks
+    pub only_referenced: bool,
+    /// Prefix of the `http_path` recorded on methods
+    pub base_path: BasePath,
+    /// Record the JSON string of each enum value in this option, e.g.
+    /// `(my.json_value)`, instead of a trailing `// "in-progress"` comment
+    pub enum_value_option: Option<String>,
+    /// Record security requirements in this method option, e.g.
+    /// `(auth.security)`, instead of `// Security: ...` comments
+    pub security_option: Option<String>,
+    /// Proto types for schema formats, e.g. `decimal` -> `money.Decimal`.
+    /// Other formats without a type of their own become `// format:` comments.
+    pub format_overrides: BTreeMap<String, FormatOverride>,
+    /// Map integers with a non-negative `minimum` to `uint32`/`uint64`.
+    /// This changes the wire type of such fields.
+    pub prefer_unsigned: bool,
+    /// Reference top-level array and primitive definitions by their
+    /// underlying type (`repeated string`) instead of generating
+    /// `items`/`value` wrapper messages for them
+    pub inline_aliases: bool,
+    /// Render `example`, `examples` and `default` values as comments.
+    /// Off by default since example payloads may be sensitive.
+    pub include_examples: bool,
+    /// Run [`ProtoFile::validate`] on the result and fail with
+    /// `ConverterError::InvalidOutput` instead of returning a broken file
+    pub validate_output: bool,
+    /// Syntax of the output; unset keeps the one of the file passed to
+    /// `with_proto`, proto3 for a fresh file. Proto2 output marks required
+    /// properties `required` and turns scalar defaults into `[default = ...]`.
+    pub target_syntax: Option<Syntax>,
+    /// Bind rpcs with `option (google.api.http)`, importing
+    /// `google/api/annotations.proto`, instead of `// HTTP:` comments
+    pub http_annotations: bool,
+    /// Names of the messages and enums generated for schemas, by schema
+    /// name: `UserDTO` -> `User`. References are renamed too.
+    pub schema_renames: BTreeMap<String, String>,
+    /// Appended to field and type names that are proto keywords or scalar
+    /// types (`message`, `string`); `_` when unset
+    pub reserved_suffix: Option<String>,
+    /// Threads converting schema definitions; 0 and 1 convert on the
+    /// calling thread. Needs the `parallel` feature, ignored without it.
+    /// The output is the same as with a single thread.
+    pub parallelism: usize,
+    /// Give PATCH requests with a body a `google.protobuf.FieldMask
+    /// update_mask` field next to `body`, in a `<Service><Method>Request`
+    /// message even without parameters
+    pub patch_field_mask: bool,
+    /// Reshape list operations toward AIP-158 when their query parameters
+    /// or response envelope use the names in [`Pagination`]; off when unset
+    pub pagination: Option<Pagination>,
+    /// Generate a `<Method>ResponseMetadata` message with a field per
+    /// header of the successful response. The headers are always listed
+    /// in a comment on the rpc.
+    pub response_metadata: bool,
+    /// How deep inline schemas (properties, `items`,
+    /// `additionalProperties`) may nest before the conversion fails with
+    /// `ConverterError::DepthLimitExceeded`; [`DEFAULT_MAX_DEPTH`] when unset
+    pub max_depth: Option<usize>,
+}
+
+impl Default for ConverterOptions {
+    fn default() -> Self {
+        Self {
+            header_params: Default::default(),
+            wrap_repeated: Default::default(),
+            required_fields: Default::default(),
+            nullable: Default::default(),
+            validation_rules: Default::default(),
+            nest_inline_messages: Default::default(),
+            service_grouping: Default::default(),
+            strict: Default::default(),
+            split_read_write: Default::default(),
+            extension_options: Default::default(),
+            skip_internal: Default::default(),
+            only_tags: Default::default(),
+            exclude_tags: Default::default(),
+            package_from_info: Default::default(),
+            go_package: Default::default(),
+            java_package: Default::default(),
+            java_multiple_files: Default::default(),
+            info_header: true,
+            only_referenced: Default::default(),
+            base_path: Default::default(),
+            enum_value_option: Default::default(),
+            security_option: Default::default(),
+            format_overrides: Default::default(),
+            prefer_unsigned: Default::default(),
+            inline_aliases: Default::default(),
+            include_examples: Default::default(),
+            validate_output: Default::default(),
+            target_syntax: Default::default(),
+            http_annotations: Default::default(),
+            schema_renames: Default::default(),
+            reserved_suffix: Default::default(),
+            parallelism: Default::default(),
+            patch_field_mask: Default::default(),
+            pagination: Default::default(),
+            response_metadata: Default::default(),
+            max_depth: Default::default(),
+        }
+    }
+}
+
+/// Nesting allowed by default, see [`ConverterOptions::max_depth`] and
+/// [`crate::ProtoParser::max_depth`]
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+/// Proto type used for every schema with a given `format`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FormatOverride {
+    #[serde(rename = "type")]
+    pub type_name: String,
+    /// Import declaring `type_name`, e.g. `money/decimal.proto`
+    #[serde(default)]
+    pub import: Option<String>,
+}
+
+/// Names recognized by the pagination heuristic, see
+/// [`ConverterOptions::pagination`]. Names are compared with the query
+/// parameters and envelope properties as they appear in the spec; the
+/// first name of a list that matches wins.
+///
+/// ```
+/// use dot_proto_parser::{Pagination, SwaggerToProtoConverter};
+///
+/// let spec = r##"{
+///   "openapi": "3.0.0",
+///   "info": {"title": "T", "version": "1"},
+///   "paths": {"/items": {"get": {
+///     "operationId": "list",
+///     "parameters": [
+///       {"name": "page", "in": "query", "schema": {"type": "integer"}},
+///       {"name": "per_page", "in": "query", "schema": {"type": "integer"}}
+///     ],
+///     "responses": {"200": {"description": "", "content": {"application/json": {"schema": {
+///       "type": "object",
+///       "properties": {
+///         "data": {"type": "array", "items": {"$ref": "#/components/schemas/Item"}},
+///         "total": {"type": "integer"}
+///       }
+///     }}}}}
+///   }}},
+///   "components": {"schemas": {
+///     "Item": {"type": "object", "properties": {"id": {"type": "string"}}}
+///   }}
+/// }"##;
+///
+/// let mut converter = SwaggerToProtoConverter::builder()
+///     .pagination(Pagination::default())
+///     .build();
+/// let proto = converter.convert_str(spec).unwrap();
+/// let params = proto.find_message("ItemsListQueryParams").unwrap();
+/// assert_eq!(params.fields[0].type_name(), "string");
+/// assert_eq!(params.fields[0].name, "page_token");
+/// assert_eq!(params.fields[1].name, "page_size");
+/// let response = proto.find_message("ItemsListResponse").unwrap();
+/// let names: Vec<_> = response.fields.iter().map(|f| f.name.as_str()).collect();
+/// assert_eq!(names, ["items", "total_size", "next_page_token"]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Pagination {
+    /// Query parameters that become `int32 page_size`
+    pub page_size: Vec<String>,
+    /// Query parameters with a page number or token, become
+    /// `string page_token`
+    pub page_token: Vec<String>,
+    /// Array property of the envelope, becomes `repeated <Item> items`
+    pub items: Vec<String>,
+    /// Integer property of the envelope, becomes `total_size`
+    pub total: Vec<String>,
+    /// Envelope property that becomes `string next_page_token`; the field
+    /// is added when the envelope has none
+    pub next_page_token: Vec<String>,
+}
+
+impl Default for Pagination {
+    fn default() -> Self {
+        let names = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+        Self {
+            page_size: names(&["page_size", "pageSize", "per_page", "perPage"]),
+            page_token: names(&["page_token", "pageToken", "page"]),
+            items: names(&["items", "data", "results"]),
+            total: names(&[
+                "total",
+                "total_size",
+                "totalSize",
+                "total_count",
+                "totalCount",
+            ]),
+            next_page_token: names(&[
+                "next_page_token",
+                "nextPageToken",
+                "next_cursor",
+                "nextCursor",
+                "next",
+            ]),
+        }
+    }
+}
+
+/// Prefix for the HTTP paths of operations
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BasePath {
+    /// Swagger 2.0 `basePath`, or the path of the first OpenAPI server
+    /// (the operation's own servers first)
+    #[default]
+    FromSpec,
+    /// A fixed prefix such as `/api/v2`
+    Custom(String),
+    /// Raw path keys
+    None,
+}
+
+/// Summary of a finished conversion
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConversionReport {
+    /// Number of schema definitions in the spec
+    pub total_definitions: usize,
+    /// Definitions converted into messages
+    pub converted_definitions: usize,
+    /// Definitions left out by `only_referenced`
+    pub skipped_definitions: Vec<String>,
+}
+
+/// Strategy for assigning operations to services
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceGrouping {
+    /// One service per tag; untagged operations are grouped by path segment
+    #[default]
+    ByTag,
+    /// By the first meaningful path segment: `/v1/users/{id}` -> `UsersService`
+    ByFirstPathSegment,
+    /// Every operation in `<name>Service`
+    SingleService(String),
+}
+
+/// Representation of nullable properties
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NullableStrategy {
+    /// Wrapper types from `google/protobuf/wrappers.proto` for scalars,
+    /// `optional` for messages and enums
+    Wrappers,
+    /// `optional`, even when the property is also required
+    Optional,
+    /// Nullable properties are converted like any other
+    #[default]
+    Ignore,
+}
+
+/// Representation of swagger `required` in proto3, which has no such label
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RequiredFields {
+    /// `// required` comment on the field
+    #[default]
+    Comment,
+    /// `[(google.api.field_behavior) = REQUIRED]`, importing
+    /// `google/api/field_behavior.proto`
+    FieldBehavior,
+}
+
+/// Placement of header and cookie parameters in request messages
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HeaderParams {
+    /// Together with query and path parameters in `<Service><Method>QueryParams`
+    #[default]
+    Inline,
+    /// In a separate `<Service><Method>Headers` message
+    SeparateMessage,
+}
+
+/// Converts a swagger 2.0 / OpenAPI 3 document into a [`ProtoFile`].
+///
+/// The result of an rpc is the 2xx response with the lowest status code;
+/// `204` is always `google.protobuf.Empty`. When no 2xx response has
+/// content, the `default` response is used, and `Empty` without one.
+///
+/// ```
+/// use dot_proto_parser::SwaggerToProtoConverter;
+///
+/// let spec = r##"{
+///   "openapi": "3.0.0",
+///   "info": {"title": "T", "version": "1"},
+///   "paths": {"/items": {
+///     "get": {"operationId": "list", "responses": {
+///       "201": {"description": "", "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Created"}}}},
+///       "200": {"description": "", "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Item"}}}}
+///     }},
+///     "delete": {"operationId": "purge", "responses": {
+///       "204": {"description": ""},
+///       "default": {"description": "", "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Problem"}}}}
+///     }},
+///     "put": {"operationId": "replace", "responses": {
+///       "200": {"description": ""},
+///       "default": {"description": "", "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Problem"}}}}
+///     }},
+///     "post": {"operationId": "touch", "responses": {"default": {"description": ""}}}
+///   }},
+///   "components": {"schemas": {
+///     "Item": {"type": "object", "properties": {"id": {"type": "string"}}},
+///     "Created": {"type": "object", "properties": {"id": {"type": "string"}}},
+///     "Problem": {"type": "object", "properties": {"title": {"type": "string"}}}
+///   }}
+/// }"##;
+///
+/// let mut converter = SwaggerToProtoConverter::builder().build();
+/// let proto = converter.convert_str(spec).unwrap();
+/// let service = proto.find_service("ItemsService").unwrap();
+/// let output = |rpc: &str| service.find_method(rpc).unwrap().output_type.as_str();
+/// assert_eq!(output("List"), "Item");
+/// assert_eq!(output("Purge"), "google.protobuf.Empty");
+/// assert_eq!(output("Replace"), "Problem");
+/// assert_eq!(output("Touch"), "google.protobuf.Empty");
+/// ```
 pub struct SwaggerToProtoConverter {
     proto: ProtoFile,
-    generated_messages: HashMap<String, usize>,
-    current_refs: Vec<String>,
+    // top-level message and enum names, see `allocate_type_name`
+    names: NameRegistry,
+    // list item type -> `<Item>List` wrapper generated for it
+    list_wrappers: IndexMap<String, String>,
+    // sorted JSON values -> inline enum generated for them
+    inline_enums: IndexMap<Vec<String>, String>,
+    // structural fingerprint -> inline object message generated for it
+    inline_messages: IndexMap<String, String>,
+    // `$ref` + `Input`/`Output` -> generated request/response variant
+    split_messages: HashMap<String, String>,
+    // messages being built, innermost last
+    scopes: Vec<MessageScope>,
+    // nesting of the schema being converted, see `ConverterOptions::max_depth`
+    depth: usize,
+    // messages of the file passed to `with_proto`
+    seeded_messages: HashSet<String>,
+    // array/primitive definition -> the type its refs resolve to
+    aliases: HashMap<String, ProtoType>,
+    warnings: Vec<ConversionWarning>,
+    report: ConversionReport,
+    // base path of the spec, used for operations without their own servers
+    spec_base_path: String,
+    // top-level `security`, applied to operations without their own
+    global_security: Vec<SecurityRequirement>,
+    // Swagger 2.0 top-level `produces` / `consumes`, for operations without their own
+    global_produces: Vec<String>,
+    global_consumes: Vec<String>,
+    // `securitySchemes` / `securityDefinitions`
+    security_schemes: IndexMap<String, SecurityScheme>,
+    // service name (without the `Service` suffix) -> tag description
+    tag_descriptions: HashMap<String, String>,
+    base_dir: Option<PathBuf>,
+    options: ConverterOptions,
+    naming: Arc<dyn NamingStrategy>,
+}
+
+impl NameFormatter for SwaggerToProtoConverter {
+    fn reserved_suffix(&self) -> &str {
+        self.options.reserved_suffix.as_deref().unwrap_or("_")
+    }
+}
+
+/// Step-by-step configuration of a [`SwaggerToProtoConverter`]
+#[derive(Debug, Clone, Default)]
+pub struct ConverterBuilder {
+    package: String,
+    options: ConverterOptions,
+    pub(crate) base_dir: Option<PathBuf>,
+    naming: Option<Arc<dyn NamingStrategy>>,
 }
 
-impl NameFormatter for SwaggerToProtoConverter {}
+impl ConverterBuilder {
+    pub fn package(mut self, package_name: &str) -> Self {
+        self.package = package_name.to_string();
+        self
+    }
+
+    pub fn options(mut self, options: ConverterOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn base_dir(mut self, base_dir: &Path) -> Self {
+        self.base_dir = Some(base_dir.to_path_buf());
+        self
+    }
+
+    /// Derive the package from `info` instead of the one set with
+    /// [`ConverterBuilder::package`]; it stays as a fallback for an empty title
+    pub fn package_from_info(mut self, enabled: bool) -> Self {
+        self.options.package_from_info = enabled;
+        self
+    }
+
+    pub fn go_package(mut self, go_package: &str) -> Self {
+        self.options.go_package = Some(go_package.to_string());
+        self
+    }
+
+    pub fn java_package(mut self, java_package: &str) -> Self {
+        self.options.java_package = Some(java_package.to_string());
+        self
+    }
+
+    pub fn java_multiple_files(mut self, enabled: bool) -> Self {
+        self.options.java_multiple_files = enabled;
+        self
+    }
+
+    pub fn info_as_header_comment(mut self, enabled: bool) -> Self {
+        self.options.info_header = enabled;
+        self
+    }
+
+    /// Maps schemas with `format` to `type_name`, importing `import` if given
+    pub fn format_override(mut self, format: &str, type_name: &str, import: Option<&str>) -> Self {
+        self.options.format_overrides.insert(
+            format.to_string(),
+            FormatOverride {
+                type_name: type_name.to_string(),
+                import: import.map(str::to_string),
+            },
+        );
+        self
+    }
+
+    pub fn include_examples(mut self, enabled: bool) -> Self {
+        self.options.include_examples = enabled;
+        self
+    }
+
+    pub fn validate_output(mut self, enabled: bool) -> Self {
+        self.options.validate_output = enabled;
+        self
+    }
+
+    pub fn prefer_unsigned(mut self, enabled: bool) -> Self {
+        self.options.prefer_unsigned = enabled;
+        self
+    }
+
+    pub fn target_syntax(mut self, syntax: Syntax) -> Self {
+        self.options.target_syntax = Some(syntax);
+        self
+    }
+
+    /// Operations without one of `tags` are left out
+    pub fn only_tags(mut self, tags: &[&str]) -> Self {
+        self.options.only_tags = tags.iter().map(|t| t.to_string()).collect();
+        self
+    }
+
+    /// Operations with one of `tags` are left out
+    pub fn exclude_tags(mut self, tags: &[&str]) -> Self {
+        self.options.exclude_tags = tags.iter().map(|t| t.to_string()).collect();
+        self
+    }
+
+    pub fn http_annotations(mut self, enabled: bool) -> Self {
+        self.options.http_annotations = enabled;
+        self
+    }
+
+    /// Records the JSON string of enum values in `option`, see
+    /// [`ConverterOptions::enum_value_option`]
+    pub fn enum_value_option(mut self, option: &str) -> Self {
+        self.options.enum_value_option = Some(option.to_string());
+        self
+    }
+
+    /// See [`ConverterOptions::patch_field_mask`]
+    pub fn patch_field_mask(mut self, enabled: bool) -> Self {
+        self.options.patch_field_mask = enabled;
+        self
+    }
+
+    /// See [`ConverterOptions::pagination`]
+    pub fn pagination(mut self, pagination: Pagination) -> Self {
+        self.options.pagination = Some(pagination);
+        self
+    }
+
+    /// See [`ConverterOptions::response_metadata`]
+    pub fn response_metadata(mut self, enabled: bool) -> Self {
+        self.options.response_metadata = enabled;
+        self
+    }
+
+    /// Suffix for names that are proto keywords or scalar types, `_` by
+    /// default
+    pub fn reserved_suffix(mut self, suffix: &str) -> Self {
+        self.options.reserved_suffix = Some(suffix.to_string());
+        self
+    }
+
+    /// Converts schema definitions on `threads` threads, see
+    /// [`ConverterOptions::parallelism`]
+    pub fn parallelism(mut self, threads: usize) -> Self {
+        self.options.parallelism = threads;
+        self
+    }
+
+    /// Fails on inline schemas nested deeper than `depth`, see
+    /// [`ConverterOptions::max_depth`]
+    ///
+    /// ```
+    /// use dot_proto_parser::SwaggerToProtoConverter;
+    ///
+    /// let nested = |levels: usize| {
+    ///     let mut schema = r#"{"type": "string"}"#.to_string();
+    ///     for _ in 0..levels {
+    ///         schema = format!(r#"{{"type": "object", "properties": {{"a": {}}}}}"#, schema);
+    ///     }
+    ///     format!(r#"{{"swagger": "2.0", "paths": {{}}, "definitions": {{"D": {}}}}}"#, schema)
+    /// };
+    ///
+    /// let mut converter = SwaggerToProtoConverter::builder().max_depth(10).build();
+    /// let error = converter.convert_str(&nested(40)).unwrap_err();
+    /// assert_eq!(error.code(), "E_CONVERT_017");
+    ///
+    /// // Far deeper documents are already rejected by the JSON parser
+    /// let mut converter = SwaggerToProtoConverter::builder().build();
+    /// assert!(converter.convert_str(&nested(1000)).is_err());
+    /// ```
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.options.max_depth = Some(depth);
+        self
+    }
+
+    /// Generates the schema `schema` as `name`
+    pub fn rename_schema(mut self, schema: &str, name: &str) -> Self {
+        self.options
+            .schema_renames
+            .insert(schema.to_string(), name.to_string());
+        self
+    }
+
+    /// Skip schemas that no operation uses, directly or through other schemas
+    pub fn only_referenced(mut self, enabled: bool) -> Self {
+        self.options.only_referenced = enabled;
+        self
+    }
+
+    /// Names for generated messages, fields and rpcs, [`DefaultNaming`]
+    /// unless set
+    pub fn naming(mut self, naming: Box<dyn NamingStrategy>) -> Self {
+        self.naming = Some(Arc::from(naming));
+        self
+    }
+
+    pub fn build(self) -> SwaggerToProtoConverter {
+        let mut converter = SwaggerToProtoConverter::new(&self.package).with_options(self.options);
+        converter.base_dir = self.base_dir;
+        if let Some(naming) = self.naming {
+            converter.naming = naming;
+        }
+        converter
+    }
+}
 
 impl SwaggerToProtoConverter {
+    pub fn builder() -> ConverterBuilder {
+        ConverterBuilder::default()
+    }
+
     pub fn new(package_name: &str) -> Self {
         Self {
             proto: ProtoFile::new(package_name),
-            generated_messages: HashMap::new(),
-            current_refs: Vec::new(),
+            names: NameRegistry::default(),
+            list_wrappers: IndexMap::new(),
+            inline_enums: IndexMap::new(),
+            inline_messages: IndexMap::new(),
+            split_messages: HashMap::new(),
+            scopes: Vec::new(),
+            depth: 0,
+            seeded_messages: HashSet::new(),
+            aliases: HashMap::new(),
+            warnings: Vec::new(),
+            report: ConversionReport::default(),
+            spec_base_path: String::new(),
+            global_security: Vec::new(),
+            global_produces: Vec::new(),
+            global_consumes: Vec::new(),
+            security_schemes: IndexMap::new(),
+            tag_descriptions: HashMap::new(),
+            base_dir: None,
+            options: ConverterOptions::default(),
+            naming: Arc::new(DefaultNaming),
+        }
+    }
+
+    /// Replaces the naming conventions, see [`NamingStrategy`]
+    pub fn with_naming(mut self, naming: Box<dyn NamingStrategy>) -> Self {
+        self.naming = Arc::from(naming);
+        self
+    }
+
+    pub fn with_options(mut self, options: ConverterOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Turns schema constraints into `(validate.rules)` field options;
+    /// constraints without a matching rule are kept as field comments
+    pub fn with_validation_rules(mut self, enabled: bool) -> Self {
+        self.options.validation_rules = enabled;
+        self
+    }
+
+    /// Converts into an existing file instead of a fresh one.
+    ///
+    /// Package, imports and order of the file are kept. Swagger definitions
+    /// named like an existing message reuse it, as long as their properties
+    /// match its fields; services are appended, or merged into an existing
+    /// service of the same name.
+    pub fn with_proto(mut self, proto: ProtoFile) -> Self {
+        for message in &proto.messages {
+            self.names.reserve_existing(&message.name);
+            self.seeded_messages.insert(message.name.clone());
         }
+        for enum_def in &proto.enums {
+            self.names.reserve_existing(&enum_def.name);
+        }
+        self.proto = proto;
+        self
+    }
+
+    /// Sets the directory external `$ref`s (`./common.json#/...`) are
+    /// resolved against. `convert_file` defaults to the input file's directory,
+    /// the other entry points to the current directory.
+    pub fn with_base_dir(mut self, base_dir: &Path) -> Self {
+        self.base_dir = Some(base_dir.to_path_buf());
+        self
     }
 
+    /// Reads a swagger/openapi JSON file and writes the resulting .proto file
     pub fn convert_file(
         &mut self,
         input_path: &Path,
         output_path: &Path,
     ) -> Result<(), ConverterError> {
-        let content = std::fs::read_to_string(input_path)?;
-        let spec: SwaggerDoc = serde_json::from_str(&content)?;
+        if self.base_dir.is_none() {
+            self.base_dir = input_path.parent().map(Path::to_path_buf);
+        }
+        let proto = File::open(input_path)
+            .map_err(ConverterError::from)
+            .and_then(|input| Ok(serde_json::from_reader(BufReader::new(input))?))
+            .and_then(|spec| self.convert_value(spec))
+            .map_err(|e| e.in_file(input_path))?;
+        // Файл создаётся только после успешной конвертации
+        File::create(output_path)
+            .and_then(|output| {
+                let mut output = BufWriter::new(output);
+                proto.write_proto_text(&mut output)?;
+                output.flush()
+            })
+            .map_err(|e| ConverterError::from(e).in_file(output_path))
+    }
+
+    /// Reads a swagger/openapi JSON document from `input` and writes the
+    /// .proto text to `output` as it is rendered.
+    ///
+    /// The document is parsed straight from the reader; wrap files and
+    /// sockets into `BufReader`/`BufWriter`, stdin and stdout locks are
+    /// buffered already.
+    pub fn convert_reader<R: Read, W: Write>(
+        &mut self,
+        input: R,
+        mut output: W,
+    ) -> Result<(), ConverterError> {
+        let spec = serde_json::from_reader(input)?;
+        self.convert_value(spec)?.write_proto_text(&mut output)?;
+        Ok(())
+    }
 
+    /// Converts a swagger/openapi JSON file into one .proto per service
+    /// (`users.proto`, `orders.proto`, ...) in `out_dir`.
+    ///
+    /// Each file holds its service and the types only it uses; types shared
+    /// by several services, or used by none, go to `common.proto`. All files
+    /// share the package, so cross-file references stay unqualified.
+    /// Returns the paths of the written files.
+    pub fn convert_to_files(
+        &mut self,
+        input_path: &Path,
+        out_dir: &Path,
+    ) -> Result<Vec<PathBuf>, ConverterError> {
+        if self.base_dir.is_none() {
+            self.base_dir = input_path.parent().map(Path::to_path_buf);
+        }
+        std::fs::read_to_string(input_path)
+            .map_err(ConverterError::from)
+            .and_then(|content| self.convert_str(&content).map(|_| ()))
+            .map_err(|e| e.in_file(input_path))?;
+
+        std::fs::create_dir_all(out_dir).map_err(|e| ConverterError::from(e).in_file(out_dir))?;
+        let mut written = Vec::new();
+        for (file_name, file) in self.split_by_service() {
+            let path = out_dir.join(file_name);
+            std::fs::write(&path, file.to_proto_text())
+                .map_err(|e| ConverterError::from(e).in_file(&path))?;
+            written.push(path);
+        }
+        Ok(written)
+    }
+
+    /// Converts a swagger/openapi document given as JSON text
+    pub fn convert_str(&mut self, swagger_json: &str) -> Result<&ProtoFile, ConverterError> {
+        self.convert_value(serde_json::from_str(swagger_json)?)
+    }
+
+    /// Converts an already parsed swagger/openapi document
+    pub fn convert_value(
+        &mut self,
+        mut spec: serde_json::Value,
+    ) -> Result<&ProtoFile, ConverterError> {
+        // Бандлер и serde обходят документ рекурсивно, поэтому глубина
+        // проверяется заранее и без рекурсии; на уровень схемы приходится
+        // не больше двух-трёх уровней JSON
+        let limit = self.options.max_depth.unwrap_or(DEFAULT_MAX_DEPTH);
+        if json_depth(&spec) > limit.saturating_mul(4) {
+            return Err(ConverterError::DepthLimitExceeded { limit });
+        }
+        let base_dir = self.base_dir.clone().unwrap_or_default();
+        RefBundler::new(&base_dir).bundle(&mut spec)?;
+
+        let spec: SwaggerDoc = serde_json::from_value(spec)?;
         self.process_swagger_doc(&spec)?;
+        self.apply_schema_renames()?;
+        if self.options.validate_output {
+            self.proto
+                .validate()
+                .map_err(ConverterError::InvalidOutput)?;
+        }
+        Ok(&self.proto)
+    }
 
-        let proto_text = self.proto.to_proto_text();
-        std::fs::write(output_path, proto_text)?;
+    /// Returns the model built so far
+    pub fn proto(&self) -> &ProtoFile {
+        &self.proto
+    }
 
-        Ok(())
+    /// Lossy or ambiguous spots encountered during conversion
+    pub fn warnings(&self) -> &[ConversionWarning] {
+        &self.warnings
+    }
+
+    /// Partitions the built model into `(file name, file)` pairs, see
+    /// [`SwaggerToProtoConverter::convert_to_files`]
+    fn split_by_service(&self) -> Vec<(String, ProtoFile)> {
+        const COMMON_FILE: &str = "common.proto";
+
+        let top_level: HashSet<&str> = self
+            .proto
+            .messages
+            .iter()
+            .map(|m| m.name.as_str())
+            .chain(self.proto.enums.iter().map(|e| e.name.as_str()))
+            .collect();
+
+        // top-level type -> indices of the services that use it
+        let mut users: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (i, service) in self.proto.services.iter().enumerate() {
+            let mut pending: Vec<&str> = service
+                .methods
+                .iter()
+                .flat_map(|m| [m.input_type.as_str(), m.output_type.as_str()])
+                .collect();
+            let mut seen = HashSet::new();
+            while let Some(type_name) = pending.pop() {
+                let root = type_name.split('.').next().unwrap_or(type_name);
+                if !top_level.contains(root) || !seen.insert(root) {
+                    continue;
+                }
+                users.entry(root).or_default().push(i);
+                if let Some(message) = self.proto.find_message(root) {
+                    collect_field_types(message, &mut pending);
+                }
+            }
+        }
+
+        let empty_file = || ProtoFile {
+            header_comments: self.proto.header_comments.clone(),
+            syntax: self.proto.syntax.clone(),
+            package: self.proto.package.clone(),
+            imports: self.proto.imports.clone(),
+            options: self.proto.options.clone(),
+            ..Default::default()
+        };
+        let owner = |name: &str| match users.get(name).map(Vec::as_slice) {
+            Some([only]) => Some(*only),
+            _ => None,
+        };
+
+        let mut common = empty_file();
+        let mut service_files: Vec<ProtoFile> =
+            self.proto.services.iter().map(|_| empty_file()).collect();
+        for message in &self.proto.messages {
+            match owner(&message.name) {
+                Some(i) => service_files[i].messages.push(message.clone()),
+                None => common.messages.push(message.clone()),
+            }
+        }
+        for enum_def in &self.proto.enums {
+            match owner(&enum_def.name) {
+                Some(i) => service_files[i].enums.push(enum_def.clone()),
+                None => common.enums.push(enum_def.clone()),
+            }
+        }
+
+        let has_common = !common.messages.is_empty() || !common.enums.is_empty();
+        let mut files = Vec::new();
+        for (service, mut file) in self.proto.services.iter().zip(service_files) {
+            if has_common {
+                file.add_import(COMMON_FILE);
+            }
+            file.services.push(service.clone());
+            file.sync_wellknown_imports();
+
+            let base = service
+                .name
+                .strip_suffix("Service")
+                .unwrap_or(&service.name);
+            let mut stem = self.to_screaming_snake_case(base).to_lowercase();
+            if stem.is_empty() || stem == "common" {
+                stem = self.to_screaming_snake_case(&service.name).to_lowercase();
+            }
+            files.push((format!("{}.proto", stem), file));
+        }
+        if has_common || files.is_empty() {
+            common.sync_wellknown_imports();
+            files.insert(0, (COMMON_FILE.to_string(), common));
+        }
+        files
+    }
+
+    /// How many definitions were converted or skipped
+    pub fn conversion_report(&self) -> &ConversionReport {
+        &self.report
+    }
+
+    /// Consumes the converter and returns the built model
+    pub fn into_proto(self) -> ProtoFile {
+        self.proto
     }
 
     fn process_swagger_doc(&mut self, spec: &SwaggerDoc) -> Result<(), ConverterError> {
-        if let Some(definitions) = &spec.definitions {
-            self.process_schemas(definitions, None)?;
+        self.process_info(&spec.info);
+        self.process_servers(spec);
+
+        let reachable = if self.options.only_referenced {
+            Some(reachable_schemas(&serde_json::to_value(spec)?))
+        } else {
+            None
+        };
+
+        // Definitions claim their names before any generated type can
+        for name in spec
+            .definitions
+            .iter()
+            .flatten()
+            .map(|(name, _)| name)
+            .chain(
+                spec.components
+                    .iter()
+                    .flat_map(|c| c.schemas.iter().flatten())
+                    .map(|(name, _)| name),
+            )
+        {
+            let base = self.escape_reserved_type(&self.naming.message_name(name));
+            let allocated = self.names.define(name, &base);
+            self.report_renamed_type(&base, allocated);
         }
 
-        if let Some(components) = &spec.components {
-            if let Some(schemas) = &components.schemas {
-                self.process_schemas(schemas, Some(components))?;
+        if self.options.inline_aliases {
+            if let Some(definitions) = &spec.definitions {
+                self.collect_aliases(definitions, None)?;
+            }
+            if let Some(components) = &spec.components
+                && let Some(schemas) = &components.schemas
+            {
+                self.collect_aliases(schemas, Some(components))?;
             }
         }
 
+        if let Some(definitions) = &spec.definitions {
+            self.process_schemas(definitions, None, reachable.as_ref())?;
+        }
+
+        if let Some(components) = &spec.components
+            && let Some(schemas) = &components.schemas
+        {
+            self.process_schemas(schemas, Some(components), reachable.as_ref())?;
+        }
+
         self.process_services(&spec.paths, spec)?;
+        self.proto.sync_wellknown_imports();
+
+        Ok(())
+    }
+
+    fn process_info(&mut self, info: &Info) {
+        if let Some(syntax) = self.options.target_syntax {
+            self.proto.syntax = syntax.as_str().to_string();
+        }
+        if self.options.package_from_info
+            && let Some(package) = package_from_title(&info.title, &info.version)
+        {
+            self.proto.package = package;
+        }
+
+        if let Some(go_package) = &self.options.go_package {
+            self.proto.add_option("go_package", go_package);
+        }
+        if let Some(java_package) = &self.options.java_package {
+            self.proto.add_option("java_package", java_package);
+        }
+        if self.options.java_multiple_files {
+            self.proto.add_option("java_multiple_files", "true");
+        }
+
+        // Файл из `with_proto` сохраняет свой заголовок
+        if !self.options.info_header || !self.proto.header_comments.is_empty() {
+            return;
+        }
+        let header = &mut self.proto.header_comments;
+        header.push(
+            format!("{} {}", info.title, info.version)
+                .trim()
+                .to_string(),
+        );
+        if let Some(description) = &info.description {
+            header.push(String::new());
+            for line in description.trim().lines() {
+                header.extend(wrap_line(line.trim_end(), HEADER_WIDTH));
+            }
+        }
+        if let Some(contact) = &info.contact {
+            let parts: Vec<&str> = [&contact.name, &contact.email, &contact.url]
+                .into_iter()
+                .flatten()
+                .map(String::as_str)
+                .collect();
+            if !parts.is_empty() {
+                header.push(String::new());
+                header.push(format!("Contact: {}", parts.join(", ")));
+            }
+        }
+        if let Some(license) = &info.license {
+            let mut line = format!("License: {}", license.name);
+            if let Some(url) = &license.url {
+                line.push_str(&format!(" ({})", url));
+            }
+            header.push(line);
+        }
+        header.push(String::new());
+        header.push(format!(
+            "Generated by dot_proto_parser {}",
+            env!("CARGO_PKG_VERSION")
+        ));
+    }
+
+    /// Lists the servers in the header comment and picks the base path
+    fn process_servers(&mut self, spec: &SwaggerDoc) {
+        let mut urls: Vec<String> = spec
+            .servers
+            .iter()
+            .flatten()
+            .map(|server| match &server.description {
+                Some(description) => format!("{} ({})", server.resolved_url(), description),
+                None => server.resolved_url(),
+            })
+            .collect();
+        if let Some(host) = &spec.host {
+            let base_path = spec.base_path.as_deref().unwrap_or_default();
+            let default_schemes = vec!["https".to_string()];
+            for scheme in spec.schemes.as_ref().unwrap_or(&default_schemes) {
+                urls.push(format!("{}://{}{}", scheme, host, base_path));
+            }
+        }
+        if !urls.is_empty() {
+            let header = &mut self.proto.header_comments;
+            if !header.is_empty() {
+                header.push(String::new());
+            }
+            header.push("Servers:".to_string());
+            header.extend(urls.into_iter().map(|url| format!("  {}", url)));
+        }
+
+        self.spec_base_path = match &spec.base_path {
+            Some(base_path) => base_path.clone(),
+            None => servers_base_path(spec.servers.as_deref()),
+        };
+    }
+
+    /// One line per alternative of a security requirement list:
+    /// `bearerAuth (scopes: read:pets), api_key`. Requirements with no
+    /// schemes (optional auth) read `none`.
+    fn security_requirements(
+        &mut self,
+        pointer: &str,
+        security: &[SecurityRequirement],
+    ) -> Vec<String> {
+        let mut lines = Vec::new();
+        for requirement in security {
+            let mut schemes = Vec::new();
+            for (name, scopes) in requirement {
+                if !self.security_schemes.contains_key(name) {
+                    self.warnings
+                        .push(ConversionWarning::UnknownSecurityScheme {
+                            path: pointer.to_string(),
+                            scheme: name.clone(),
+                        });
+                }
+                if scopes.is_empty() {
+                    schemes.push(name.clone());
+                } else {
+                    schemes.push(format!("{} (scopes: {})", name, scopes.join(", ")));
+                }
+            }
+            if schemes.is_empty() {
+                lines.push("none".to_string());
+            } else {
+                lines.push(schemes.join(", "));
+            }
+        }
+        lines
+    }
+
+    /// Message or enum for a schema definition, kept apart from scalar types
+    /// and other definitions
+    fn definition_name(&self, schema: &str) -> String {
+        match self.names.definition(schema) {
+            Some(name) => name.to_string(),
+            None => self.escape_reserved_type(&self.naming.message_name(schema)),
+        }
+    }
+
+    /// lower_snake_case fields for schema properties: `userId` -> `user_id`.
+    /// Properties that end up with the same name get a number: `user-id`
+    /// and `userId` become `user_id` and `user_id_2`.
+    fn property_field_names<'p>(
+        &self,
+        properties: impl IntoIterator<Item = &'p String>,
+    ) -> HashMap<&'p str, String> {
+        let mut taken = HashSet::new();
+        let mut names = HashMap::new();
+        for property in properties {
+            let base = self.escape_reserved(&self.naming.field_name(property));
+            let mut name = base.clone();
+            let mut suffix = 2;
+            while !taken.insert(name.clone()) {
+                name = format!("{}_{}", base, suffix);
+                suffix += 1;
+            }
+            names.insert(property.as_str(), name);
+        }
+        names
+    }
+
+    /// `path` with the configured base path in front
+    fn http_path(&self, path: &str, operation: &Operation) -> String {
+        let base = match &self.options.base_path {
+            BasePath::None => return path.to_string(),
+            BasePath::Custom(base) => base.clone(),
+            BasePath::FromSpec => match operation.servers.as_deref() {
+                Some(servers) if !servers.is_empty() => servers_base_path(Some(servers)),
+                _ => self.spec_base_path.clone(),
+            },
+        };
+        format!("{}{}", base.trim_end_matches('/'), path)
+    }
+
+    /// Body of `option (google.api.http)`: the whole request is the body of
+    /// POST/PUT/PATCH calls unless it has a separate `body` field
+    fn http_rule(&self, http_method: &str, http_path: &str, request_type: &str) -> String {
+        let verb = http_method.to_lowercase();
+        let body = match self.proto.find_message(request_type) {
+            Some(request) if request.fields.iter().any(|f| f.name == "body") => Some("body"),
+            _ if matches!(verb.as_str(), "post" | "put" | "patch")
+                && request_type != "google.protobuf.Empty" =>
+            {
+                Some("*")
+            }
+            _ => None,
+        };
+        match body {
+            Some(body) => format!("{{{}: \"{}\", body: \"{}\"}}", verb, http_path, body),
+            None => format!("{{{}: \"{}\"}}", verb, http_path),
+        }
+    }
 
+    /// Applies `schema_renames` to the converted messages and enums
+    fn apply_schema_renames(&mut self) -> Result<(), ConverterError> {
+        for (schema, name) in &self.options.schema_renames {
+            let current = self.definition_name(schema);
+            if self.proto.find_message(&current).is_some() {
+                self.proto.rename_message(&current, name)?;
+            } else if self.proto.find_enum(&current).is_some() {
+                self.proto.rename_enum(&current, name)?;
+            } else {
+                return Err(ConverterError::MessageNotFound(schema.clone()));
+            }
+        }
         Ok(())
     }
 
     fn process_schemas(
         &mut self,
-        schemas: &HashMap<String, Schema>,
+        schemas: &IndexMap<String, Schema>,
         components: Option<&Components>,
+        reachable: Option<&HashSet<String>>,
     ) -> Result<(), ConverterError> {
+        #[cfg(feature = "parallel")]
+        let mut ahead = self.plan_ahead(schemas, reachable)?;
         for (name, schema) in schemas {
-            if self.generated_messages.contains_key(name) {
+            self.report.total_definitions += 1;
+            if reachable.is_some_and(|names| !names.contains(name)) {
+                self.report.skipped_definitions.push(name.clone());
+                continue;
+            }
+            if self.aliases.contains_key(name) {
+                continue;
+            }
+            if self.seeded_messages.contains(name) {
+                self.check_seeded_message(name, schema, components.is_some())?;
+                continue;
+            }
+            if self.names.is_converted(name) {
                 continue;
             }
 
-            let message = self.convert_schema_to_message(name, schema, schemas, components)?;
-            self.proto.add_message(message)?;
-            self.generated_messages.insert(name.clone(), 1);
+            let pointer = match components {
+                Some(_) => format!("#/components/schemas/{}", escape_pointer(name)),
+                None => format!("#/definitions/{}", escape_pointer(name)),
+            };
+            #[cfg(feature = "parallel")]
+            let converted = self
+                .convert_ahead(&mut ahead, name, schemas, components)
+                .map_err(|e| e.at(&pointer))?;
+            #[cfg(not(feature = "parallel"))]
+            let converted = None;
+            let mut message = match converted {
+                Some(message) => message,
+                None => self
+                    .convert_schema_to_message(
+                        &self.definition_name(name),
+                        schema,
+                        schemas,
+                        components,
+                    )
+                    .map_err(|e| e.at(&pointer))?,
+            };
+            if !name.is_ascii() {
+                // Имя транслитерировано, исходное остаётся в комментарии
+                message.add_comment(&format!("schema: {}", name));
+            }
+            self.proto
+                .add_message(message)
+                .map_err(|e| e.at(&pointer))?;
+            self.names.mark_converted(name);
+            self.report.converted_definitions += 1;
         }
 
         Ok(())
     }
 
-    fn convert_schema_to_message(
+    /// The definitions `process_schemas` converts, for conversion ahead on
+    /// `parallelism` threads
+    #[cfg(feature = "parallel")]
+    fn plan_ahead<'s>(
+        &self,
+        schemas: &'s IndexMap<String, Schema>,
+        reachable: Option<&HashSet<String>>,
+    ) -> Result<Ahead<'s>, ConverterError> {
+        let pool = match self.options.parallelism {
+            0 | 1 => None,
+            threads => Some(
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build()
+                    .map_err(std::io::Error::other)?,
+            ),
+        };
+        let pending = schemas
+            .iter()
+            .filter(|(name, _)| {
+                reachable.is_none_or(|names| names.contains(*name))
+                    && !self.aliases.contains_key(*name)
+                    && !self.seeded_messages.contains(*name)
+                    && !self.names.is_converted(name)
+            })
+            .collect();
+        Ok(Ahead {
+            pool,
+            pending,
+            cursor: 0,
+            batch: AHEAD_MIN_PER_THREAD,
+            results: HashMap::new(),
+        })
+    }
+
+    /// Message of the definition `name` converted ahead, see [`Ahead`].
+    ///
+    /// Forks start from the current state, so a result is only used if the
+    /// names and shared types it generated are still free when its turn
+    /// comes (see `attach`). Otherwise the rest of the batch is dropped and
+    /// `None` has the definition converted in place; the next batch starts
+    /// from a state that has whatever it collided with. Either way the
+    /// output is what a serial run produces.
+    #[cfg(feature = "parallel")]
+    fn convert_ahead(
         &mut self,
+        ahead: &mut Ahead,
         name: &str,
-        schema: &Schema,
-        definitions: &HashMap<String, Schema>,
+        schemas: &IndexMap<String, Schema>,
         components: Option<&Components>,
-    ) -> Result<Message, ConverterError> {
-        if self.current_refs.contains(&name.to_string()) {
-            return Err(ConverterError::CircularReference(name.to_string()));
-        }
-        self.current_refs.push(name.to_string());
+    ) -> Result<Option<Message>, ConverterError> {
+        use rayon::prelude::*;
 
-        let mut message = Message::new(name);
+        let Some(pool) = &ahead.pool else {
+            return Ok(None);
+        };
+        let Some(position) = ahead.pending[ahead.cursor..]
+            .iter()
+            .position(|(pending, _)| *pending == name)
+            .map(|i| ahead.cursor + i)
+        else {
+            return Ok(None);
+        };
+        ahead.cursor = position + 1;
 
-        if let Some(description) = &schema.description {
-            description.lines().for_each(|line| {
-                message.add_comment(line.trim());
+        if !ahead.results.contains_key(name) {
+            let threads = pool.current_num_threads();
+            let end = (position + threads * ahead.batch).min(ahead.pending.len());
+            let batch = &ahead.pending[position..end];
+            let this = &*self;
+            let converted: Vec<Vec<(&str, Detached)>> = pool.install(|| {
+                batch
+                    .par_chunks(batch.len().div_ceil(threads))
+                    .map(|chunk| {
+                        let mut fork = this.fork();
+                        chunk
+                            .iter()
+                            .map(|(name, schema)| {
+                                let detached = fork.detach(name, schema, schemas, components);
+                                (name.as_str(), detached)
+                            })
+                            .collect()
+                    })
+                    .collect()
             });
+            ahead.results = converted.into_iter().flatten().collect();
+            ahead.batch = (ahead.batch * 2).min(AHEAD_MAX_PER_THREAD);
+        }
+
+        let detached = ahead.results.remove(name).expect("converted above");
+        let message = self.attach(detached)?;
+        if message.is_none() {
+            // Остальная пачка считалась от того же устаревшего состояния
+            ahead.results.clear();
+            ahead.batch = AHEAD_MIN_PER_THREAD;
+        }
+        Ok(message)
+    }
+
+    /// Copy of the converter that generates into an empty file
+    #[cfg(feature = "parallel")]
+    fn fork(&self) -> Self {
+        Self {
+            proto: ProtoFile {
+                syntax: self.proto.syntax.clone(),
+                ..ProtoFile::default()
+            },
+            names: self.names.clone(),
+            list_wrappers: self.list_wrappers.clone(),
+            inline_enums: self.inline_enums.clone(),
+            inline_messages: self.inline_messages.clone(),
+            split_messages: HashMap::new(),
+            scopes: Vec::new(),
+            depth: 0,
+            seeded_messages: self.seeded_messages.clone(),
+            aliases: self.aliases.clone(),
+            warnings: Vec::new(),
+            report: ConversionReport::default(),
+            spec_base_path: String::new(),
+            global_security: Vec::new(),
+            global_produces: Vec::new(),
+            global_consumes: Vec::new(),
+            security_schemes: IndexMap::new(),
+            tag_descriptions: HashMap::new(),
+            base_dir: self.base_dir.clone(),
+            options: self.options.clone(),
+            naming: self.naming.clone(),
+        }
+    }
+
+    /// Converts one definition on a fork and takes back everything it
+    /// generated, leaving the fork as it was
+    #[cfg(feature = "parallel")]
+    fn detach(
+        &mut self,
+        name: &str,
+        schema: &Schema,
+        definitions: &IndexMap<String, Schema>,
+        components: Option<&Components>,
+    ) -> Detached {
+        let names = self.names.mark();
+        let list_wrappers = self.list_wrappers.len();
+        let inline_enums = self.inline_enums.len();
+        let inline_messages = self.inline_messages.len();
+
+        let message = self.convert_schema_to_message(
+            &self.definition_name(name),
+            schema,
+            definitions,
+            components,
+        );
+        Detached {
+            message: message.ok(),
+            messages: std::mem::take(&mut self.proto.messages),
+            enums: std::mem::take(&mut self.proto.enums),
+            imports: std::mem::take(&mut self.proto.imports),
+            names: self.names.release_since(names),
+            list_wrappers: self.list_wrappers.drain(list_wrappers..).collect(),
+            inline_enums: self.inline_enums.drain(inline_enums..).collect(),
+            inline_messages: self.inline_messages.drain(inline_messages..).collect(),
+            warnings: std::mem::take(&mut self.warnings),
+        }
+    }
+
+    /// Applies a definition converted by `detach`, unless a serial run
+    /// would have converted it differently: a name it allocated or a type
+    /// it generated for sharing has been taken since the fork.
+    /// `None` means the definition has to be converted again.
+    #[cfg(feature = "parallel")]
+    fn attach(&mut self, detached: Detached) -> Result<Option<Message>, ConverterError> {
+        let Some(message) = detached.message else {
+            // Ошибку воспроизведёт последовательная конвертация
+            return Ok(None);
+        };
+        // Все имена верхнего уровня проходят через реестр
+        let fits = detached.names.iter().all(|name| !self.names.is_taken(name))
+            && detached
+                .list_wrappers
+                .iter()
+                .all(|(key, _)| !self.list_wrappers.contains_key(key))
+            && detached
+                .inline_enums
+                .iter()
+                .all(|(key, _)| !self.inline_enums.contains_key(key))
+            && detached
+                .inline_messages
+                .iter()
+                .all(|(key, _)| !self.inline_messages.contains_key(key));
+        if !fits {
+            return Ok(None);
+        }
+
+        detached
+            .names
+            .into_iter()
+            .for_each(|name| self.names.claim(name));
+        self.list_wrappers.extend(detached.list_wrappers);
+        self.inline_enums.extend(detached.inline_enums);
+        self.inline_messages.extend(detached.inline_messages);
+        for generated in detached.messages {
+            self.proto.add_message(generated)?;
+        }
+        for generated in detached.enums {
+            self.proto.add_enum(generated)?;
+        }
+        for import in &detached.imports {
+            self.proto.add_import(import);
+        }
+        self.warnings.extend(detached.warnings);
+        Ok(Some(message))
+    }
+
+    /// Resolves the types of array and primitive definitions up front, so
+    /// refs to them can be inlined wherever they appear
+    fn collect_aliases(
+        &mut self,
+        schemas: &IndexMap<String, Schema>,
+        components: Option<&Components>,
+    ) -> Result<(), ConverterError> {
+        for (name, schema) in schemas {
+            if schema.is_array_or_primitive() {
+                let hint = self.definition_name(name);
+                let type_name = self.schema_to_type(schema, &hint, schemas, components)?;
+                self.aliases.insert(name.clone(), type_name);
+            }
+        }
+        Ok(())
+    }
+
+    /// Fails if the properties of a definition do not match the fields of
+    /// the existing message it would be replaced with
+    fn check_seeded_message(
+        &self,
+        name: &str,
+        schema: &Schema,
+        openapi3: bool,
+    ) -> Result<(), ConverterError> {
+        let Some(existing) = self.proto.find_message(name) else {
+            return Ok(());
+        };
+        let conflict = |details: String| ConverterError::ConflictingMessage {
+            definition: if openapi3 {
+                format!("#/components/schemas/{}", name)
+            } else {
+                format!("#/definitions/{}", name)
+            },
+            message: existing.name.clone(),
+            details,
+        };
+
+        let properties = schema.properties.iter().flatten();
+        let field_names = self.property_field_names(properties.clone().map(|(name, _)| name));
+        let mut expected_fields = Vec::new();
+        for (prop_name, prop_schema) in properties {
+            let field_name = field_names[prop_name.as_str()].clone();
+            let Some(field) = existing.find_field(&field_name) else {
+                return Err(conflict(format!("no field for property `{}`", prop_name)));
+            };
+            if let Some(expected) = self.plain_type(prop_schema)
+                && field.type_ != expected
+                && !field
+                    .type_
+                    .as_named()
+                    .is_some_and(|name| name.starts_with("google.protobuf."))
+            {
+                return Err(conflict(format!(
+                    "field `{}` is {}, property `{}` is {}",
+                    field.name, field.type_, prop_name, expected
+                )));
+            }
+            expected_fields.push(field_name);
+        }
+
+        if schema.properties.is_some()
+            && let Some(extra) = existing
+                .all_fields()
+                .find(|f| !expected_fields.contains(&f.name))
+        {
+            return Err(conflict(format!(
+                "field `{}` is not in the schema",
+                extra.name
+            )));
+        }
+        Ok(())
+    }
+
+    /// Field type of a `$ref`, scalar or array of those, without generating
+    /// anything; `None` for schemas that need a generated type
+    fn plain_type(&self, schema: &Schema) -> Option<FieldType> {
+        if let Some(ref_path) = &schema.ref_path {
+            return Some(self.ref_type(ref_path).into_item());
+        }
+        if schema.type_name() == Some("array") {
+            return match schema.items.as_deref()? {
+                SchemaRef::Ref { ref_path } => Some(self.ref_type(ref_path).into_item()),
+                SchemaRef::Inline(items) => self.plain_type(items),
+            };
+        }
+        if schema.enum_values.is_some() {
+            return None;
+        }
+        scalar_type(schema.type_name()?, schema.format.as_deref()).map(FieldType::from)
+    }
+
+    /// Builds the message for a named or inline schema.
+    ///
+    /// `$ref`s to named schemas are emitted as type names and never expanded,
+    /// so self-referencing and mutually recursive schemas convert cleanly.
+    /// Inline schemas are expanded, but JSON cannot nest them cyclically;
+    /// the only real cycle (allOf chains) is caught in `collect_all_of`.
+    fn convert_schema_to_message(
+        &mut self,
+        name: &str,
+        schema: &Schema,
+        definitions: &IndexMap<String, Schema>,
+        components: Option<&Components>,
+    ) -> Result<Message, ConverterError> {
+        let path = match self.scopes.last() {
+            Some(parent) => format!("{}.{}", parent.path, name),
+            None => name.to_string(),
+        };
+        self.scopes.push(MessageScope {
+            name: name.to_string(),
+            path,
+            nested: Vec::new(),
+        });
+
+        let mut message = Message::new(name);
+        let filled = self.fill_schema_message(&mut message, schema, definitions, components);
+
+        let scope = self.scopes.pop().expect("scope pushed above");
+        filled?;
+        for nested in scope.nested {
+            message.add_nested_message(nested)?;
+        }
+        Ok(message)
+    }
+
+    fn fill_schema_message(
+        &mut self,
+        message: &mut Message,
+        schema: &Schema,
+        definitions: &IndexMap<String, Schema>,
+        components: Option<&Components>,
+    ) -> Result<(), ConverterError> {
+        let name = message.name.clone();
+
+        if let Some(description) = &schema.description {
+            description.lines().for_each(|line| {
+                message.add_comment(line.trim());
+            });
+        }
+        if schema.deprecated.unwrap_or(false) {
+            message.add_comment("Deprecated");
+        }
+        // Messages have no options: every extension becomes a comment
+        for (key, value) in &schema.extensions {
+            if ENUM_EXTENSIONS.contains(&key.as_str()) {
+                continue;
+            }
+            message.add_comment(&format!("{}: {}", key, extension_value(value)));
+        }
+        if self.options.include_examples
+            && let Some(example) = &schema.example
+        {
+            message.add_comment(&format!("example: {}", example_value(example)));
         }
 
         if let Some(one_of) = &schema.one_of {
-            self.handle_one_of_any_of(
-                &mut message,
-                name,
-                "OneOf",
+            // Общие свойства рядом с oneOf остаются полями сообщения
+            if let Some(properties) = &schema.properties {
+                self.handle_properties(
+                    message,
+                    &name,
+                    properties,
+                    &schema.required,
+                    definitions,
+                    components,
+                )?;
+            }
+            self.handle_one_of(
+                message,
                 one_of,
+                schema.discriminator.as_ref(),
                 definitions,
                 components,
             )?;
         } else if let Some(all_of) = &schema.all_of {
-            self.handle_all_of(&mut message, all_of, definitions, components)?;
+            self.handle_all_of(message, all_of, definitions, components)
+                .map_err(|e| e.at("allOf"))?;
         } else if let Some(any_of) = &schema.any_of {
-            self.handle_one_of_any_of(
-                &mut message,
-                name,
-                "AnyOf",
-                any_of,
-                definitions,
-                components,
-            )?;
+            self.handle_any_of(message, &name, any_of, definitions, components)?;
         } else if let Some(properties) = &schema.properties {
             self.handle_properties(
-                &mut message,
-                name,
+                message,
+                &name,
                 properties,
                 &schema.required,
                 definitions,
                 components,
             )?;
         } else if let Some(additional_props) = &schema.additional_properties {
-            self.handle_additional_properties(
-                &mut message,
-                additional_props,
-                definitions,
-                components,
-            )?;
-        } else if let Some(enum_values) = &schema.enum_values {
-            self.handle_root_enum(&mut message, name, enum_values)?;
+            self.handle_additional_properties(message, additional_props, definitions, components)
+                .map_err(|e| e.at("additionalProperties"))?;
+        } else if schema.enum_values.is_some() {
+            self.handle_root_enum(message, &name, schema)?;
+        } else if schema.is_array_or_primitive() {
+            self.handle_root_value(message, schema, definitions, components)?;
         }
 
-        self.current_refs.pop();
-        Ok(message)
+        Ok(())
+    }
+
+    /// Top-level arrays become `repeated items`, primitives a single `value`
+    fn handle_root_value(
+        &mut self,
+        message: &mut Message,
+        schema: &Schema,
+        definitions: &IndexMap<String, Schema>,
+        components: Option<&Components>,
+    ) -> Result<(), ConverterError> {
+        let name = message.name.clone();
+        let proto_type = self.schema_to_type(schema, &name, definitions, components)?;
+
+        let mut field = match proto_type {
+            ProtoType::Repeated(item_type) => {
+                Field::new("items", item_type, 1, FieldRule::Repeated)
+            }
+            ProtoType::Single(type_) => {
+                let mut field = Field::new("value", type_, 1, FieldRule::Singular);
+                self.add_format_comment(&mut field, schema.format.as_deref());
+                field
+            }
+        };
+        if self.options.validation_rules {
+            self.add_validation_rules(&mut field, schema);
+        }
+        message.add_field(field)
+    }
+
+    /// Maps `oneOf` to a `oneof value { ... }` group.
+    ///
+    /// Field names come from the discriminator mapping keys when present,
+    /// then from the referenced schema names, and fall back to `variant_n`
+    /// for inline schemas. Variants are numbered after the fields already in
+    /// the message.
+    fn handle_one_of(
+        &mut self,
+        message: &mut Message,
+        items: &[SchemaRef],
+        discriminator: Option<&Discriminator>,
+        definitions: &IndexMap<String, Schema>,
+        components: Option<&Components>,
+    ) -> Result<(), ConverterError> {
+        let mapping = discriminator.and_then(|d| d.mapping.as_ref());
+        let mut oneof = Oneof::new("value");
+        let first_number = message.all_fields().map(|f| f.number).max().unwrap_or(0) + 1;
+
+        for (i, item) in items.iter().enumerate() {
+            let hint = self.naming.variant_type_name(&message.name, i + 1);
+            let proto_type = self
+                .schema_ref_to_type(item, &hint, definitions, components)
+                .map_err(|e| e.at(format!("oneOf/{}", i)))?;
+            let field_type = self.single_type(proto_type)?;
+
+            let field_name = match item {
+                SchemaRef::Ref { ref_path } => mapping
+                    .and_then(|m| m.iter().find(|(_, target)| *target == ref_path))
+                    .map(|(key, _)| self.escape_reserved(&self.naming.field_name(key)))
+                    .unwrap_or_else(|| {
+                        let name = self.naming.field_name(&self.resolve_ref_name(ref_path));
+                        self.escape_reserved(&name)
+                    }),
+                SchemaRef::Inline(_) => format!("variant_{}", i + 1),
+            };
+
+            oneof.add_field(Field::new(
+                &field_name,
+                field_type,
+                first_number + i as i32,
+                FieldRule::Singular,
+            ))?;
+        }
+
+        message.add_oneof(oneof)
     }
-    fn handle_one_of_any_of(
+
+    fn handle_any_of(
         &mut self,
         message: &mut Message,
         name: &str,
-        suffix: &str,
         items: &[SchemaRef],
-        definitions: &HashMap<String, Schema>,
+        definitions: &IndexMap<String, Schema>,
         components: Option<&Components>,
     ) -> Result<(), ConverterError> {
         let mut fields = Vec::new();
+        let suffix = "AnyOf";
         let type_name = format!("{}{}", name, suffix);
 
         for (i, item) in items.iter().enumerate() {
-            let field_type = self.schema_ref_to_type(item, definitions, components)?;
+            let hint = self.naming.variant_type_name(&type_name, i + 1);
+            let proto_type = self
+                .schema_ref_to_type(item, &hint, definitions, components)
+                .map_err(|e| e.at(format!("anyOf/{}", i)))?;
             fields.push(Field::new(
                 &format!("variant_{}", i + 1),
-                &field_type,
+                self.single_type(proto_type)?,
                 (i + 1) as i32,
                 FieldRule::Optional,
             ));
@@ -178,671 +1764,2955 @@ impl SwaggerToProtoConverter {
         &mut self,
         message: &mut Message,
         items: &[SchemaRef],
-        definitions: &HashMap<String, Schema>,
+        definitions: &IndexMap<String, Schema>,
         components: Option<&Components>,
     ) -> Result<(), ConverterError> {
-        let mut field_number = 1;
+        let message_name = message.name.clone();
+        let mut merged = MergedAllOf::default();
+
+        self.collect_all_of(&message_name, items, definitions, components, &mut merged)?;
+
+        self.handle_properties(
+            message,
+            &message_name,
+            &merged.properties,
+            &Some(merged.required),
+            definitions,
+            components,
+        )
+    }
+
+    /// Flattens allOf branches (recursively) into a single property map.
+    ///
+    /// Properties keep the position of their first appearance; a later
+    /// branch redefining a property replaces its schema and records a warning.
+    fn collect_all_of(
+        &mut self,
+        schema_name: &str,
+        items: &[SchemaRef],
+        definitions: &IndexMap<String, Schema>,
+        components: Option<&Components>,
+        merged: &mut MergedAllOf,
+    ) -> Result<(), ConverterError> {
         for item in items {
+            if let SchemaRef::Ref { ref_path } = item {
+                if merged.visited.contains(ref_path) {
+                    return Err(ConverterError::CircularReference(ref_path.clone()));
+                }
+                // Цепочка allOf через $ref растёт без вложенности в JSON
+                let limit = self.options.max_depth.unwrap_or(DEFAULT_MAX_DEPTH);
+                if merged.visited.len() >= limit {
+                    return Err(ConverterError::DepthLimitExceeded { limit });
+                }
+                merged.visited.push(ref_path.clone());
+            }
+
             let resolved = self.resolve_schema_ref(item, definitions, components)?;
-            if let Some(properties) = &resolved.properties {
-                for (prop_name, prop_schema) in properties {
-                    let type_name = self.schema_to_type(prop_schema, definitions, components)?;
-                    message.add_field(Field::new(
-                        &self.sanitize_field_name(prop_name),
-                        &type_name,
-                        field_number,
-                        FieldRule::Optional,
-                    ))?;
-                    field_number += 1;
+
+            if let Some(nested) = &resolved.all_of {
+                self.collect_all_of(schema_name, nested, definitions, components, merged)?;
+            }
+
+            if let Some(branch_properties) = &resolved.properties {
+                for (prop_name, prop_schema) in branch_properties {
+                    if merged
+                        .properties
+                        .insert(prop_name.clone(), prop_schema.clone())
+                        .is_some()
+                    {
+                        self.warnings.push(ConversionWarning::DuplicateProperty {
+                            schema: schema_name.to_string(),
+                            property: prop_name.clone(),
+                        });
+                    }
+                }
+            }
+
+            for name in resolved.required.iter().flatten() {
+                if !merged.required.contains(name) {
+                    merged.required.push(name.clone());
+                }
+            }
+
+            if let SchemaRef::Ref { .. } = item {
+                merged.visited.pop();
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_properties(
+        &mut self,
+        message: &mut Message,
+        message_name: &str,
+        properties: &IndexMap<String, Schema>,
+        required_fields: &Option<Vec<String>>,
+        definitions: &IndexMap<String, Schema>,
+        components: Option<&Components>,
+    ) -> Result<(), ConverterError> {
+        let mut field_number = 1;
+        let field_names = self.property_field_names(properties.keys());
+
+        for (prop_name, prop_schema) in properties {
+            if prop_name.starts_with("//") {
+                continue;
+            }
+
+            let pointer = format!("properties/{}", escape_pointer(prop_name));
+            let proto_type = if prop_schema.enum_values.is_some() {
+                let base_name = self.naming.nested_type_name(message_name, prop_name);
+                self.inline_enum(&base_name, prop_schema)
+                    .map(ProtoType::from)
+            } else {
+                let hint = self.naming.nested_type_name(message_name, prop_name);
+                self.schema_to_type(prop_schema, &hint, definitions, components)
+            }
+            .map_err(|e| e.at(&pointer))?;
+
+            let (final_type, field_rule) = match proto_type {
+                ProtoType::Repeated(item_type) if self.options.wrap_repeated => (
+                    FieldType::Named(self.list_wrapper(&item_type)?),
+                    FieldRule::Optional,
+                ),
+                ProtoType::Repeated(item_type) => (item_type, FieldRule::Repeated),
+                ProtoType::Single(type_) if type_.is_map() => (type_, FieldRule::Singular),
+                ProtoType::Single(type_) => (type_, FieldRule::Optional),
+            };
+
+            let mut field = Field::new(
+                &field_names[prop_name.as_str()],
+                final_type,
+                field_number,
+                field_rule,
+            );
+            // protoc выводит json_name из имени поля; если он не совпадает
+            // с исходным ключом, сохраняем ключ явно
+            if self.to_camel_case(&field.name) != *prop_name {
+                field.add_string_option("json_name", prop_name);
+            }
+
+            // Добавляем описание свойства как комментарий
+            if let Some(description) = &prop_schema.description {
+                description.lines().for_each(|line| {
+                    field.add_comment(line.trim());
+                });
+            }
+
+            if required_fields
+                .as_ref()
+                .map(|r| r.contains(prop_name))
+                .unwrap_or(false)
+            {
+                self.mark_required(&mut field);
+            }
+
+            if let Some(value) = &prop_schema.const_value {
+                field.add_comment(&format!("const: {}", value));
+            }
+
+            self.add_format_comment(&mut field, prop_schema.format.as_deref());
+            self.add_example_comments(
+                &mut field,
+                prop_schema.example.as_ref(),
+                prop_schema.default.as_ref(),
+            );
+            self.add_default_option(&mut field, prop_schema.default.as_ref());
+
+            if prop_schema.deprecated.unwrap_or(false) {
+                field.add_option("deprecated", OptionValue::Bool(true));
+            }
+
+            let (comments, options) = self.extensions(&prop_schema.extensions);
+            comments.iter().for_each(|c| field.add_comment(c));
+            options
+                .iter()
+                .for_each(|(k, v)| field.add_option(k, v.clone()));
+
+            if prop_schema.read_only.unwrap_or(false) {
+                field.add_comment("readOnly");
+            }
+            if prop_schema.write_only.unwrap_or(false) {
+                field.add_comment("writeOnly");
+            }
+
+            if prop_schema.is_nullable() {
+                self.mark_nullable(&mut field);
+            }
+
+            if self.options.validation_rules {
+                self.add_validation_rules(&mut field, prop_schema);
+            }
+
+            message.add_field(field).map_err(|e| e.at(&pointer))?;
+
+            field_number += 1;
+        }
+        Ok(())
+    }
+
+    fn handle_additional_properties(
+        &mut self,
+        message: &mut Message,
+        additional_props: &SchemaRef,
+        definitions: &IndexMap<String, Schema>,
+        components: Option<&Components>,
+    ) -> Result<(), ConverterError> {
+        let hint = format!("{}Value", message.name);
+        let value_type =
+            self.schema_ref_to_type(additional_props, &hint, definitions, components)?;
+        message.add_field(Field::new(
+            "properties",
+            FieldType::map(ScalarType::String, self.single_type(value_type)?),
+            1,
+            FieldRule::Singular,
+        ))
+    }
+
+    fn handle_root_enum(
+        &mut self,
+        message: &mut Message,
+        message_name: &str,
+        schema: &Schema,
+    ) -> Result<(), ConverterError> {
+        let enum_name = self.allocate_type_name(&format!("{}Status", message_name));
+        let enum_def = self.build_enum(&enum_name, schema)?;
+
+        self.proto.add_enum(enum_def)?;
+        message.add_field(Field::new("status", &enum_name, 1, FieldRule::Optional))
+    }
+
+    /// Returns the enum for an inline `enum` list, generating it on first
+    /// use. Properties with the same set of values (and value names) share
+    /// one enum.
+    fn inline_enum(&mut self, base_name: &str, schema: &Schema) -> Result<String, ConverterError> {
+        let enum_values = schema.enum_values.as_deref().unwrap_or_default();
+        let varnames = enum_extension(schema, "x-enum-varnames");
+        let mut key: Vec<String> = enum_values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| match varnames.get(i) {
+                Some(Some(varname)) => format!("{}={}", v, varname),
+                _ => v.to_string(),
+            })
+            .collect();
+        key.sort();
+        key.dedup();
+
+        if let Some(enum_name) = self.inline_enums.get(&key) {
+            return Ok(enum_name.clone());
+        }
+
+        let enum_name = self.allocate_type_name(base_name);
+        let enum_def = self.build_enum(&enum_name, schema)?;
+        self.proto.add_enum(enum_def)?;
+        self.inline_enums.insert(key, enum_name.clone());
+        Ok(enum_name)
+    }
+
+    /// Builds a proto3-compliant enum from swagger enum values.
+    ///
+    /// proto enum values share the scope of the enclosing package, so every
+    /// variant is prefixed with the enum name (`ORDER_STATUS_ACTIVE`) and
+    /// carries the original JSON value as a comment.
+    ///
+    /// proto3 requires the first value to be `0`, named
+    /// `<ENUM_NAME>_UNSPECIFIED`. If one of the swagger values already looks
+    /// like a zero value (`0`, `UNSPECIFIED`, `UNKNOWN`, `NONE`) it is moved
+    /// to the front and becomes that value, keeping its JSON value in the
+    /// comment; otherwise the sentinel is inserted.
+    ///
+    /// `x-enum-varnames` supplies the variant names (typical for integer
+    /// enums) and `x-enum-descriptions` adds a comment per value.
+    fn build_enum(&self, enum_name: &str, schema: &Schema) -> Result<Enum, ConverterError> {
+        let enum_values = schema.enum_values.as_deref().unwrap_or_default();
+        let varnames = enum_extension(schema, "x-enum-varnames");
+        let descriptions = enum_extension(schema, "x-enum-descriptions");
+
+        let mut enum_def = Enum::new(enum_name);
+
+        let variants: Vec<(String, bool)> = enum_values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| match (varnames.get(i), value) {
+                (Some(Some(varname)), _) => {
+                    let name = self
+                        .to_screaming_snake_case(varname)
+                        .replace(|c: char| !c.is_alphanumeric() && c != '_', "_");
+                    let zero_like = matches!(name.as_str(), "UNSPECIFIED" | "UNKNOWN" | "NONE")
+                        || name.ends_with("_UNSPECIFIED")
+                        || value.as_f64() == Some(0.0);
+                    (name, zero_like)
+                }
+                (_, serde_json::Value::String(s)) => {
+                    let name = transliterate(s)
+                        .to_uppercase()
+                        .replace(|c: char| !c.is_ascii_alphanumeric(), "_");
+                    let zero_like = matches!(name.as_str(), "UNSPECIFIED" | "UNKNOWN" | "NONE")
+                        || name.ends_with("_UNSPECIFIED");
+                    (name, zero_like)
+                }
+                (_, serde_json::Value::Number(n)) => (
+                    format!("VALUE_{}", n).replace(['.', '-'], "_"),
+                    n.as_f64() == Some(0.0),
+                ),
+                _ => (format!("VALUE_{}", i + 1), false),
+            })
+            .map(|(name, zero_like)| (self.naming.enum_value_name(enum_name, &name), zero_like))
+            .collect();
+
+        // Описание значения и исходное JSON-значение, чтобы не потерять маппинг
+        let add_comments = |value: &mut EnumValue, i: usize| {
+            if let Some(Some(description)) = descriptions.get(i) {
+                for line in description.lines() {
+                    value.add_comment(line);
                 }
             }
+            match &enum_values[i] {
+                serde_json::Value::String(original) => {
+                    if let Some(option) = &self.options.enum_value_option {
+                        value.add_option(option, OptionValue::String(original.clone()));
+                    }
+                    value.original_value = Some(original.clone());
+                }
+                other => value.add_comment(&other.to_string()),
+            }
+        };
+
+        let zero_name = self.naming.enum_zero_value_name(enum_name);
+        let zero_index = variants.iter().position(|(_, zero_like)| *zero_like);
+        match zero_index {
+            Some(index) => {
+                let mut value = EnumValue::new(&zero_name, 0);
+                add_comments(&mut value, index);
+                enum_def.add_value(value)?
+            }
+            None => enum_def.add_value(EnumValue::new(&zero_name, 0))?,
+        }
+
+        let mut number = 1;
+        for (i, (variant_name, _)) in variants.iter().enumerate() {
+            if Some(i) == zero_index {
+                continue;
+            }
+            let mut value = EnumValue::new(variant_name, number);
+            add_comments(&mut value, i);
+            enum_def.add_value(value)?;
+            number += 1;
+        }
+
+        Ok(enum_def)
+    }
+
+    /// Proto type of a primitive: a `format_overrides` entry, else the
+    /// shared scalar table. Formats nothing knows about are warned about.
+    fn primitive_type(
+        &mut self,
+        schema: &str,
+        type_: &str,
+        format: Option<&str>,
+    ) -> Option<FieldType> {
+        if let Some(format) = format
+            && let Some(mapping) = self.options.format_overrides.get(format)
+        {
+            let mapping = mapping.clone();
+            if let Some(import) = &mapping.import {
+                self.proto.add_import(import);
+            }
+            return Some(FieldType::from(mapping.type_name));
+        }
+
+        let scalar = scalar_type(type_, format)?;
+        if let Some(format) = format
+            && !KNOWN_FORMATS.contains(&format)
+            && string_format_rule(format).is_none()
+        {
+            self.warnings.push(ConversionWarning::UnknownFormat {
+                schema: schema.to_string(),
+                format: format.to_string(),
+            });
+        }
+        Some(FieldType::from(scalar))
+    }
+
+    /// `// example: ...` and `// default: ...` with `include_examples`
+    fn add_example_comments(
+        &self,
+        field: &mut Field,
+        example: Option<&serde_json::Value>,
+        default: Option<&serde_json::Value>,
+    ) {
+        if !self.options.include_examples {
+            return;
+        }
+        if let Some(example) = example {
+            field.add_comment(&format!("example: {}", example_value(example)));
+        }
+        if let Some(default) = default {
+            field.add_comment(&format!("default: {}", example_value(default)));
+        }
+    }
+
+    /// `[default = ...]` for a scalar field of proto2 output, when the
+    /// schema default fits its type
+    fn add_default_option(&self, field: &mut Field, default: Option<&serde_json::Value>) {
+        if self.proto.syntax != Syntax::Proto2.as_str() || field.rule == FieldRule::Repeated {
+            return;
+        }
+        let (Some(default), FieldType::Scalar(scalar)) = (default, &field.type_) else {
+            return;
+        };
+
+        let value = match (scalar, default) {
+            (ScalarType::Bool, serde_json::Value::Bool(b)) => OptionValue::Bool(*b),
+            (ScalarType::String, serde_json::Value::String(s)) => OptionValue::String(s.clone()),
+            (ScalarType::Double | ScalarType::Float, serde_json::Value::Number(n)) => {
+                match n.as_f64() {
+                    Some(f) => OptionValue::Float(f),
+                    None => return,
+                }
+            }
+            (_, serde_json::Value::Number(n)) if scalar.is_integer() => match n.as_i64() {
+                Some(i) if i >= 0 || !scalar.is_unsigned() => OptionValue::Int(i),
+                _ => return,
+            },
+            _ => return,
+        };
+        field.add_option("default", value);
+    }
+
+    /// Keeps a format the field type does not express as a comment
+    fn add_format_comment(&self, field: &mut Field, format: Option<&str>) {
+        if let Some(format) = format
+            && !KNOWN_FORMATS.contains(&format)
+        {
+            field.add_comment(&format!("format: {}", format));
+        }
+    }
+
+    /// Maps a schema to a proto type, arrays to repeated fields of their
+    /// item type.
+    ///
+    /// `name_hint` is used to name enums and messages generated for inline
+    /// schemas, so that the output is stable across runs.
+    fn schema_to_type(
+        &mut self,
+        schema: &Schema,
+        name_hint: &str,
+        definitions: &IndexMap<String, Schema>,
+        components: Option<&Components>,
+    ) -> Result<ProtoType, ConverterError> {
+        // Каждый вложенный inline-schema проходит здесь: глубина стека
+        // ограничена, что бы ни пришло во входном документе
+        let limit = self.options.max_depth.unwrap_or(DEFAULT_MAX_DEPTH);
+        if self.depth >= limit {
+            return Err(ConverterError::DepthLimitExceeded { limit });
+        }
+        self.depth += 1;
+        let result = self.nested_schema_to_type(schema, name_hint, definitions, components);
+        self.depth -= 1;
+        result
+    }
+
+    fn nested_schema_to_type(
+        &mut self,
+        schema: &Schema,
+        name_hint: &str,
+        definitions: &IndexMap<String, Schema>,
+        components: Option<&Components>,
+    ) -> Result<ProtoType, ConverterError> {
+        if let Some(ref_path) = &schema.ref_path {
+            return Ok(self.ref_type(ref_path));
+        }
+
+        if schema.enum_values.is_some() {
+            return self
+                .inline_enum(&format!("{}Enum", name_hint), schema)
+                .map(ProtoType::from);
+        }
+
+        // 3.1: `oneOf: [{$ref: X}, {type: "null"}]` is just a nullable X
+        if let Some(inner) = schema.nullable_union() {
+            return self.schema_ref_to_type(inner, name_hint, definitions, components);
+        }
+
+        // 3.1: `type: [string, integer]` has no proto counterpart
+        if let Some(SchemaType::Multiple(types)) = &schema.type_
+            && types.iter().filter(|t| *t != "null").count() > 1
+        {
+            return Ok(ProtoType::from("google.protobuf.Value"));
+        }
+
+        // 3.1 `const` without `type`: the value decides
+        let type_name = schema.type_name().or(match &schema.const_value {
+            Some(serde_json::Value::String(_)) => Some("string"),
+            Some(serde_json::Value::Bool(_)) => Some("boolean"),
+            Some(serde_json::Value::Number(n)) if n.is_f64() => Some("number"),
+            Some(serde_json::Value::Number(_)) => Some("integer"),
+            _ => None,
+        });
+        // Object keywords without `type: object`
+        let implied_object = schema.properties.is_some()
+            || schema.all_of.is_some()
+            || schema.additional_properties.is_some();
+        let type_name = type_name.or(implied_object.then_some("object"));
+
+        if let Some(type_name) = type_name
+            && let Some(scalar) =
+                self.primitive_type(name_hint, type_name, schema.format.as_deref())
+        {
+            if self.options.prefer_unsigned && schema.is_non_negative() {
+                match scalar {
+                    FieldType::Scalar(ScalarType::Int32) => return Ok(ScalarType::Uint32.into()),
+                    FieldType::Scalar(ScalarType::Int64) => return Ok(ScalarType::Uint64.into()),
+                    _ => {}
+                }
+            }
+            return Ok(scalar.into());
+        }
+
+        match type_name {
+            Some("array") => {
+                let items = schema
+                    .items
+                    .as_ref()
+                    .ok_or(ConverterError::InvalidArrayDefinition)?;
+                let hint = format!("{}Item", name_hint);
+                let item_type = self
+                    .schema_ref_to_type(items, &hint, definitions, components)
+                    .map_err(|e| e.at("items"))?;
+                // Arrays of arrays: the inner one needs a message
+                Ok(ProtoType::Repeated(self.single_type(item_type)?))
+            }
+            Some("object") => {
+                if schema.properties.is_some() || schema.all_of.is_some() {
+                    let fingerprint = structural_fingerprint(schema);
+                    if let Some(existing) = self.inline_messages.get(&fingerprint) {
+                        return Ok(ProtoType::from(existing));
+                    }
+
+                    let type_name = if self.options.nest_inline_messages && !self.scopes.is_empty()
+                    {
+                        self.nested_message(schema, name_hint, definitions, components)?
+                    } else {
+                        let temp_name = self.allocate_type_name(name_hint);
+                        let message = self.convert_schema_to_message(
+                            &temp_name,
+                            schema,
+                            definitions,
+                            components,
+                        )?;
+                        self.proto.add_message(message)?;
+                        temp_name
+                    };
+                    self.inline_messages.insert(fingerprint, type_name.clone());
+                    Ok(ProtoType::from(type_name))
+                } else if let Some(additional_props) = &schema.additional_properties {
+                    let hint = format!("{}Value", name_hint);
+                    let value_type = self
+                        .schema_ref_to_type(additional_props, &hint, definitions, components)
+                        .map_err(|e| e.at("additionalProperties"))?;
+                    let value_type = self.single_type(value_type)?;
+                    Ok(FieldType::map(ScalarType::String, value_type).into())
+                } else {
+                    Ok(ProtoType::from("google.protobuf.Struct"))
+                }
+            }
+            None => self.unsupported_type(name_hint, "unknown", "google.protobuf.Struct"),
+            Some("file") => self.unsupported_type(name_hint, "file", "bytes"),
+            Some(t) => self.unsupported_type(name_hint, t, "google.protobuf.Value"),
+        }
+    }
+
+    /// Error in strict mode, otherwise `fallback` and a warning
+    fn unsupported_type(
+        &mut self,
+        schema: &str,
+        type_name: &str,
+        fallback: &str,
+    ) -> Result<ProtoType, ConverterError> {
+        if self.options.strict {
+            return Err(ConverterError::UnsupportedSchemaType(type_name.to_string()));
+        }
+        self.warnings.push(ConversionWarning::UnsupportedType {
+            schema: schema.to_string(),
+            type_name: type_name.to_string(),
+            fallback: fallback.to_string(),
+        });
+        Ok(ProtoType::from(fallback))
+    }
+
+    /// Builds an inline object as a nested message of the innermost message
+    /// being converted and returns its dotted path (`User.Address`).
+    ///
+    /// Name hints start with the parent name (`UserAddress`), which is
+    /// dropped from the nested name.
+    fn nested_message(
+        &mut self,
+        schema: &Schema,
+        name_hint: &str,
+        definitions: &IndexMap<String, Schema>,
+        components: Option<&Components>,
+    ) -> Result<String, ConverterError> {
+        let scope = self.scopes.last().expect("called inside a message");
+        let base = name_hint
+            .strip_prefix(scope.name.as_str())
+            .filter(|rest| !rest.is_empty())
+            .unwrap_or(name_hint);
+
+        let mut name = base.to_string();
+        let mut suffix = 2;
+        while scope.nested.iter().any(|m| m.name == name) {
+            name = format!("{}{}", base, suffix);
+            suffix += 1;
+        }
+        let path = format!("{}.{}", scope.path, name);
+
+        let message = self.convert_schema_to_message(&name, schema, definitions, components)?;
+        self.scopes
+            .last_mut()
+            .expect("called inside a message")
+            .nested
+            .push(message);
+        Ok(path)
+    }
+
+    fn schema_ref_to_type(
+        &mut self,
+        schema_ref: &SchemaRef,
+        name_hint: &str,
+        definitions: &IndexMap<String, Schema>,
+        components: Option<&Components>,
+    ) -> Result<ProtoType, ConverterError> {
+        match schema_ref {
+            SchemaRef::Ref { ref_path } => Ok(self.ref_type(ref_path)),
+            SchemaRef::Inline(schema) => {
+                self.schema_to_type(schema, name_hint, definitions, components)
+            }
+        }
+    }
+
+    /// A type that can stand on its own (oneof member, map value, item of
+    /// another array): arrays are wrapped into an `<Item>List` message
+    fn single_type(&mut self, proto_type: ProtoType) -> Result<FieldType, ConverterError> {
+        match proto_type {
+            ProtoType::Single(type_) => Ok(type_),
+            ProtoType::Repeated(item_type) => self.list_wrapper(&item_type).map(FieldType::Named),
+        }
+    }
+
+    fn process_services(
+        &mut self,
+        paths: &IndexMap<String, PathItem>,
+        spec: &SwaggerDoc,
+    ) -> Result<(), ConverterError> {
+        let mut services: BTreeMap<String, Vec<(String, String, &Operation)>> = BTreeMap::new();
+
+        let definitions = spec.definitions.as_ref().unwrap_or_else(|| {
+            static EMPTY: once_cell::sync::Lazy<IndexMap<String, Schema>> =
+                once_cell::sync::Lazy::new(IndexMap::new);
+            &EMPTY
+        });
+
+        // Swagger 2.0 keeps shared parameters and responses at the top level
+        let swagger2_components = Components {
+            schemas: None,
+            responses: spec.responses.clone(),
+            parameters: spec.parameters.clone(),
+            examples: None,
+            request_bodies: None,
+            headers: None,
+            security_schemes: None,
+        };
+        let components = Some(spec.components.as_ref().unwrap_or(&swagger2_components));
+
+        self.global_security = spec.security.clone().unwrap_or_default();
+        self.global_produces = spec.produces.clone().unwrap_or_default();
+        self.global_consumes = spec.consumes.clone().unwrap_or_default();
+        self.tag_descriptions = spec
+            .tags
+            .iter()
+            .flatten()
+            .filter_map(|tag| {
+                let description = tag.description.as_ref()?;
+                Some((
+                    self.to_pascal_case(&tag.name),
+                    description.trim().to_string(),
+                ))
+            })
+            .collect();
+        self.security_schemes = spec
+            .security_definitions
+            .iter()
+            .chain(components.and_then(|c| c.security_schemes.as_ref()))
+            .flatten()
+            .map(|(name, scheme)| (name.clone(), scheme.clone()))
+            .collect();
+
+        let shared_parameters = components.and_then(|c| c.parameters.as_ref());
+        let paths = paths
+            .iter()
+            .map(|(path, item)| Ok((path, item.with_path_defaults(shared_parameters)?)))
+            .collect::<Result<Vec<_>, ConverterError>>()?;
+
+        for (path, item) in &paths {
+            for (method, operation) in item.operations() {
+                self.collect_operations(&mut services, path, method, Some(operation));
+            }
+            for (method, operation) in [
+                ("head", &item.head),
+                ("options", &item.options),
+                ("trace", &item.trace),
+            ] {
+                if operation.is_some() {
+                    self.warnings.push(ConversionWarning::SkippedOperation {
+                        path: format!("#/paths/{}/{}", escape_pointer(path), method),
+                        method: method.to_uppercase(),
+                    });
+                }
+            }
+        }
+
+        if let Some(default_ops) = services.remove("Default")
+            && !default_ops.is_empty()
+        {
+            self.generate_service("Default", "paths", &default_ops, definitions, components)?;
+        }
+
+        for (service_name, methods) in services {
+            if methods.is_empty() {
+                continue;
+            }
+
+            self.generate_service(&service_name, "paths", &methods, definitions, components)?;
+        }
+
+        // Operations the server invokes on the client: one service per
+        // webhook (3.1) and per operation with callbacks (3.0). The callback
+        // URL expression takes the place of the path.
+        for (name, item) in spec.webhooks.iter().flatten() {
+            let item = item.with_path_defaults(shared_parameters)?;
+            let methods: Vec<_> = item
+                .operations()
+                .map(|(method, operation)| (name.clone(), method.to_string(), operation))
+                .collect();
+            let service_name = format!("{}Webhook", self.to_pascal_case(name));
+            self.generate_service(&service_name, "webhooks", &methods, definitions, components)?;
+        }
+
+        for (path, item) in &paths {
+            for (method, operation) in item.operations() {
+                let Some(callbacks) = &operation.callbacks else {
+                    continue;
+                };
+
+                // Callback operations rarely have an operationId; the
+                // callback name reads better than the URL expression
+                let mut operations = Vec::new();
+                for (callback_name, expressions) in callbacks {
+                    for (expression, callback_item) in expressions {
+                        let callback_item = callback_item.with_path_defaults(shared_parameters)?;
+                        for (method, callback_operation) in callback_item.operations() {
+                            let mut callback_operation = callback_operation.clone();
+                            callback_operation.operation_id.get_or_insert_with(|| {
+                                format!("{} {}", callback_name, method.to_lowercase())
+                            });
+                            operations.push((expression.clone(), method, callback_operation));
+                        }
+                    }
+                }
+                let methods: Vec<_> = operations
+                    .iter()
+                    .map(|(expression, method, operation)| {
+                        (expression.clone(), method.to_string(), operation)
+                    })
+                    .collect();
+
+                let operation_name = self.generate_method_name(path, method, operation);
+                let service_name = format!("{}Callback", operation_name);
+                let section = format!(
+                    "paths/{}/{}/callbacks",
+                    escape_pointer(path),
+                    method.to_lowercase()
+                );
+                self.generate_service(&service_name, &section, &methods, definitions, components)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `section` is the document section holding the operations (`paths`,
+    /// `webhooks`), used for the locations in warnings
+    fn generate_service(
+        &mut self,
+        service_name: &str,
+        section: &str,
+        methods: &[(String, String, &Operation)],
+        definitions: &IndexMap<String, Schema>,
+        components: Option<&Components>,
+    ) -> Result<(), ConverterError> {
+        let mut service = Service::new(&self.naming.service_name(service_name));
+        if let Some(description) = self.tag_descriptions.get(service_name) {
+            service.add_comment(description);
+        }
+
+        for (path, http_method, operation) in methods {
+            let method_name = self.unique_method_name(&service, path, http_method, operation)?;
+            let pointer = format!(
+                "#/{}/{}/{}",
+                section,
+                escape_pointer(path),
+                http_method.to_lowercase()
+            );
+
+            // An empty list on the operation turns the global security off;
+            // webhooks and callbacks are secured by the client
+            let security = match &operation.security {
+                Some(security) => security.clone(),
+                None if section == "paths" => self.global_security.clone(),
+                None => Vec::new(),
+            };
+            let requirements = self.security_requirements(&pointer, &security);
+
+            let (request_type, request_messages) = self
+                .generate_request_message(
+                    RpcNames {
+                        service: service_name,
+                        method: &method_name,
+                    },
+                    http_method,
+                    &pointer,
+                    operation,
+                    definitions,
+                    components,
+                )
+                .map_err(|e| e.at(&pointer))?;
+
+            for message in request_messages {
+                self.proto
+                    .add_message(message)
+                    .map_err(|e| e.at(&pointer))?;
+            }
+
+            let response_type = self
+                .generate_response_type(
+                    &self
+                        .naming
+                        .response_message_name(service_name, &method_name),
+                    &pointer,
+                    operation,
+                    definitions,
+                    components,
+                )
+                .map_err(|e| e.at(&pointer))?;
+
+            let error_types = self
+                .generate_error_types(
+                    &self.naming.error_message_name(service_name, &method_name),
+                    &pointer,
+                    operation,
+                    response_type.from_default,
+                    definitions,
+                    components,
+                )
+                .map_err(|e| e.at(&pointer))?;
+
+            let header_comments = self
+                .response_headers(
+                    &self
+                        .naming
+                        .response_metadata_message_name(service_name, &method_name),
+                    &pointer,
+                    operation,
+                    definitions,
+                    components,
+                )
+                .map_err(|e| e.at(&pointer))?;
+
+            let mut method = Method::new(&method_name, &request_type, &response_type.name);
+            if let Some(content_type) = &response_type.stream_content_type {
+                method.server_streaming = true;
+                method.add_comment(&format!("Content-Type: {}", content_type));
+            }
+            if response_type.from_default {
+                method.add_comment("Returns the `default` response, no 2xx response has content");
+            }
+
+            if let Some(summary) = &operation.summary {
+                method.add_comment(summary);
+            }
+            if let Some(description) = &operation.description {
+                for line in description.lines() {
+                    method.add_comment(line.trim());
+                }
+            }
+            if operation.deprecated.unwrap_or(false) {
+                method.add_option("deprecated", OptionValue::Bool(true));
+            }
+            if !error_types.is_empty() {
+                let errors: Vec<String> = error_types
+                    .iter()
+                    .map(|(code, type_name)| format!("{} -> {}", code, type_name))
+                    .collect();
+                method.add_comment(&format!("Errors: {}", errors.join(", ")));
+            }
+            header_comments.iter().for_each(|c| method.add_comment(c));
+
+            match &self.options.security_option {
+                Some(option) if !requirements.is_empty() => {
+                    method.add_string_option(option, &requirements.join(" | "));
+                }
+                _ => requirements
+                    .iter()
+                    .for_each(|r| method.add_comment(&format!("Security: {}", r))),
+            }
+
+            let (comments, options) = self.extensions(&operation.extensions);
+            comments.iter().for_each(|c| method.add_comment(c));
+            options
+                .iter()
+                .for_each(|(k, v)| method.add_option(k, v.clone()));
+
+            // Webhooks and callbacks are served by the client: no gateway
+            // binding, only the webhook key or the callback URL expression
+            if section == "webhooks" {
+                method.add_comment(&format!("Webhook: {} {}", http_method, path));
+            } else if section != "paths" {
+                method.add_comment(&format!("Callback: {} {}", http_method, path));
+            } else if self.options.http_annotations {
+                let http_path = self.http_path(path, operation);
+                let rule = self.http_rule(http_method, &http_path, &request_type);
+                method.add_option("(google.api.http)", OptionValue::Aggregate(rule));
+                self.proto.add_import("google/api/annotations.proto");
+            } else {
+                let http_path = self.http_path(path, operation);
+                method.add_string_option("http_method", http_method);
+                method.add_string_option("http_path", &http_path);
+            }
+
+            // Свои servers операции или пути: шлюз, отличный от общего
+            if section == "paths"
+                && let Some(servers) = operation.servers.as_deref()
+                && let Some(primary) = servers.first()
+            {
+                if servers.len() > 1 {
+                    self.warnings.push(ConversionWarning::ExtraServers {
+                        path: format!("{}/servers", pointer),
+                        primary: primary.resolved_url(),
+                        skipped: servers[1..].iter().map(Server::resolved_url).collect(),
+                    });
+                }
+                method.add_string_option("http_server", &primary.resolved_url());
+            }
+
+            service.add_method(method)?;
+        }
+
+        // Appending to an existing file: merge into its service
+        if let Some(existing) = self.proto.find_service_mut(&service.name) {
+            for method in service.methods {
+                if !existing.methods.iter().any(|m| m.name == method.name) {
+                    existing.add_method(method)?;
+                }
+            }
+            return Ok(());
+        }
+        self.proto.add_service(service)?;
+        Ok(())
+    }
+
+    fn generate_request_message(
+        &mut self,
+        names: RpcNames<'_>,
+        http_method: &str,
+        pointer: &str,
+        operation: &Operation,
+        definitions: &IndexMap<String, Schema>,
+        components: Option<&Components>,
+    ) -> Result<(String, Vec<Message>), ConverterError> {
+        let mut messages = Vec::new();
+        // (field name, field number, message type) of each request part
+        let mut parts: Vec<(&str, i32, String)> = Vec::new();
+
+        let shared_parameters = components.and_then(|c| c.parameters.as_ref());
+        let parameters = operation
+            .parameters
+            .iter()
+            .flatten()
+            .map(|p| p.resolve(shared_parameters, "parameters"))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Only the first body parameter is used, unknown locations are ignored
+        let mut has_body = false;
+        for param in &parameters {
+            let dropped = match param.in_.as_str() {
+                "body" => std::mem::replace(&mut has_body, true),
+                "query" | "path" | "header" | "cookie" | "formData" => false,
+                _ => true,
+            };
+            if dropped {
+                self.warnings.push(ConversionWarning::DroppedParameter {
+                    path: pointer.to_string(),
+                    name: param.name.clone(),
+                    location: param.in_.clone(),
+                });
+            }
+        }
+
+        let separate_headers = self.options.header_params == HeaderParams::SeparateMessage;
+        let is_header = |p: &Parameter| p.in_ == "header" || p.in_ == "cookie";
+
+        let query_params: Vec<_> = parameters
+            .iter()
+            .copied()
+            .filter(|p| p.in_ == "query" || p.in_ == "path" || (is_header(p) && !separate_headers))
+            .collect();
+
+        if !query_params.is_empty() {
+            let query_message_name = self.allocate_type_name(
+                &self
+                    .naming
+                    .query_params_message_name(names.service, names.method),
+            );
+            let mut message = self.generate_parameters_message(
+                &query_message_name,
+                query_params.clone(),
+                definitions,
+                components,
+            )?;
+            self.page_request_fields(&mut message, &query_params);
+            messages.push(message);
+            parts.push(("params", 1, query_message_name));
+        }
+
+        let header_params: Vec<_> = parameters
+            .iter()
+            .copied()
+            .filter(|p| is_header(p) && separate_headers)
+            .collect();
+
+        if !header_params.is_empty() {
+            let headers_message_name = self.allocate_type_name(
+                &self
+                    .naming
+                    .headers_message_name(names.service, names.method),
+            );
+            let message = self.generate_parameters_message(
+                &headers_message_name,
+                header_params,
+                definitions,
+                components,
+            )?;
+            messages.push(message);
+            parts.push(("headers", 3, headers_message_name));
+        }
+
+        // Process formData parameters (Swagger 2.0)
+        let form_params: Vec<_> = parameters
+            .iter()
+            .copied()
+            .filter(|p| p.in_ == "formData")
+            .collect();
+
+        let consumes = operation
+            .consumes
+            .clone()
+            .unwrap_or_else(|| self.global_consumes.clone());
+
+        if !form_params.is_empty() {
+            let content_type = match consumes.iter().find(|c| is_form_content_type(c)) {
+                Some(content_type) => content_type.as_str(),
+                None if form_params
+                    .iter()
+                    .any(|p| p.type_.as_deref() == Some("file")) =>
+                {
+                    "multipart/form-data"
+                }
+                None => "application/x-www-form-urlencoded",
+            };
+            let body_message_name = self.allocate_type_name(
+                &self
+                    .naming
+                    .request_body_message_name(names.service, names.method),
+            );
+            let mut message = self.generate_parameters_message(
+                &body_message_name,
+                form_params,
+                definitions,
+                components,
+            )?;
+            message.add_comment(&format!("Content-Type: {}", content_type));
+            messages.push(message);
+            parts.push(("body", 2, body_message_name));
+        }
+
+        // Process body parameters (Swagger 2.0)
+        if let Some(body_param) = parameters.iter().find(|p| p.in_ == "body") {
+            let body_message_name = self.allocate_type_name(
+                &self
+                    .naming
+                    .request_body_message_name(names.service, names.method),
+            );
+            let mut fake_request_body = RequestBody {
+                description: body_param.description.clone(),
+                content: IndexMap::new(),
+                required: body_param.required,
+            };
+
+            if let Some(schema_ref) = &body_param.schema {
+                fake_request_body.content = self.swagger2_content(pointer, schema_ref, &consumes);
+            }
+
+            let message = self
+                .generate_body_message(
+                    &body_message_name,
+                    pointer,
+                    &fake_request_body,
+                    definitions,
+                    components,
+                )
+                .map_err(|e| e.at(format!("parameters/{}/schema", body_param.name)))?;
+            messages.push(message);
+            parts.push(("body", 2, body_message_name));
+        }
+
+        // Process request body (OpenAPI 3.0)
+        if let Some(request_body) = &operation.request_body {
+            let request_body = request_body.resolve(
+                components.and_then(|c| c.request_bodies.as_ref()),
+                "requestBodies",
+            )?;
+            let body_message_name = self.allocate_type_name(
+                &self
+                    .naming
+                    .request_body_message_name(names.service, names.method),
+            );
+            let body_pointer = format!("{}/requestBody", pointer);
+            let message = self
+                .generate_body_message(
+                    &body_message_name,
+                    &body_pointer,
+                    request_body,
+                    definitions,
+                    components,
+                )
+                .map_err(|e| e.at(&body_pointer))?;
+            messages.push(message);
+            parts.push(("body", 2, body_message_name));
+        }
+
+        // PATCH с частичным телом: тело и маска обновляемых полей
+        let field_mask = self.options.patch_field_mask
+            && http_method.eq_ignore_ascii_case("patch")
+            && parts.iter().any(|(field_name, _, _)| *field_name == "body");
+
+        let request_type = match parts.len() {
+            0 => "google.protobuf.Empty".to_string(),
+            1 if !field_mask => parts.remove(0).2,
+            _ => {
+                let combined_name = self.allocate_type_name(
+                    &self
+                        .naming
+                        .request_message_name(names.service, names.method),
+                );
+                let mut combined_message = Message::new(&combined_name);
+                parts.sort_by_key(|(_, number, _)| *number);
+                for (field_name, number, type_name) in parts {
+                    combined_message.add_field(Field::new(
+                        field_name,
+                        &type_name,
+                        number,
+                        FieldRule::Optional,
+                    ))?;
+                }
+                if field_mask {
+                    self.proto.add_import("google/protobuf/field_mask.proto");
+                    let number = combined_message
+                        .fields
+                        .iter()
+                        .map(|f| f.number)
+                        .max()
+                        .unwrap_or(0)
+                        + 1;
+                    let mut mask = Field::new(
+                        "update_mask",
+                        "google.protobuf.FieldMask",
+                        number,
+                        FieldRule::Optional,
+                    );
+                    mask.add_comment("Fields of `body` to update");
+                    combined_message.add_field(mask)?;
+                }
+                messages.push(combined_message);
+                combined_name
+            }
+        };
+
+        Ok((request_type, messages))
+    }
+
+    /// Returns the rpc response type, and for `text/event-stream` and
+    /// ndjson responses the streamed content type: such rpcs stream the
+    /// array items (or the schema itself) instead of returning a list.
+    fn generate_response_type(
+        &mut self,
+        name_hint: &str,
+        pointer: &str,
+        operation: &Operation,
+        definitions: &IndexMap<String, Schema>,
+        components: Option<&Components>,
+    ) -> Result<ResponseType, ConverterError> {
+        // The lowest 2xx is the result, the others are dropped
+        let success_code = success_code(operation).cloned();
+        for code in operation.responses.keys() {
+            if code.starts_with('2') && Some(code) != success_code.as_ref() {
+                self.warnings.push(ConversionWarning::SkippedResponse {
+                    path: format!("{}/responses/{}", pointer, code),
+                    code: code.clone(),
+                });
+            }
+        }
+
+        // 204 No Content
+        if success_code.as_deref() == Some("204") {
+            return Ok(ResponseType::new("google.protobuf.Empty"));
+        }
+
+        let success_response = success_code
+            .as_ref()
+            .map(|code| {
+                operation.responses[code]
+                    .resolve(components.and_then(|c| c.responses.as_ref()), "responses")
+            })
+            .transpose()?;
+        let response_pointer = format!(
+            "{}/responses/{}",
+            pointer,
+            success_code.as_deref().unwrap_or_default()
+        );
+        let streaming = success_response
+            .and_then(|r| r.content.as_ref())
+            .and_then(|content| {
+                content
+                    .iter()
+                    .find(|(content_type, _)| is_streaming_content_type(content_type))
+            });
+        if let Some((content_type, media_type)) = streaming {
+            let item_type = match &media_type.schema {
+                Some(schema_ref) => self
+                    .schema_ref_to_type(schema_ref, name_hint, definitions, components)?
+                    .into_item(),
+                None => FieldType::Scalar(ScalarType::Bytes),
+            };
+            let item_type = match item_type {
+                FieldType::Named(name) => name,
+                other => self.data_message(name_hint, other, None)?,
+            };
+            return Ok(ResponseType {
+                stream_content_type: Some(content_type.clone()),
+                ..ResponseType::new(&item_type)
+            });
+        }
+
+        let produces = operation
+            .produces
+            .clone()
+            .unwrap_or_else(|| self.global_produces.clone());
+        if let Some(response) = success_response
+            && let Some(type_name) = self
+                .response_to_type(
+                    response,
+                    &produces,
+                    name_hint,
+                    &response_pointer,
+                    definitions,
+                    components,
+                )
+                .map_err(|e| e.at(&response_pointer))?
+        {
+            let type_name = self
+                .paged_response(&type_name, name_hint)?
+                .unwrap_or(type_name);
+            return Ok(ResponseType::new(&type_name));
+        }
+
+        // Без 2xx с содержимым результатом служит `default`
+        if let Some(response) = operation.responses.get("default") {
+            let response =
+                response.resolve(components.and_then(|c| c.responses.as_ref()), "responses")?;
+            let default_pointer = format!("{}/responses/default", pointer);
+            if let Some(type_name) = self
+                .response_to_type(
+                    response,
+                    &produces,
+                    name_hint,
+                    &default_pointer,
+                    definitions,
+                    components,
+                )
+                .map_err(|e| e.at(&default_pointer))?
+            {
+                return Ok(ResponseType {
+                    from_default: true,
+                    ..ResponseType::new(&type_name)
+                });
+            }
+        }
+
+        Ok(ResponseType::new("google.protobuf.Empty"))
+    }
+
+    /// Converts the schemas of non-2xx responses and returns
+    /// `(status code, type name)` pairs in document order. `default_used`:
+    /// the `default` response is the rpc result.
+    fn generate_error_types(
+        &mut self,
+        name_hint: &str,
+        pointer: &str,
+        operation: &Operation,
+        default_used: bool,
+        definitions: &IndexMap<String, Schema>,
+        components: Option<&Components>,
+    ) -> Result<Vec<(String, String)>, ConverterError> {
+        let mut errors = Vec::new();
+        let produces = operation
+            .produces
+            .clone()
+            .unwrap_or_else(|| self.global_produces.clone());
+
+        for (code, response) in &operation.responses {
+            let response_pointer = format!("{}/responses/{}", pointer, code);
+            if code.starts_with('2') || (default_used && code == "default") {
+                continue;
+            }
+            // `default`, `4XX` and other ranges have no status to attach to
+            if !code.chars().all(|c| c.is_ascii_digit()) {
+                self.warnings.push(ConversionWarning::SkippedResponse {
+                    path: response_pointer,
+                    code: code.clone(),
+                });
+                continue;
+            }
+
+            let response =
+                response.resolve(components.and_then(|c| c.responses.as_ref()), "responses")?;
+            self.response_headers_warning(&response_pointer, response);
+            let hint = format!("{}{}", name_hint, code);
+            if let Some(type_name) = self
+                .response_to_type(
+                    response,
+                    &produces,
+                    &hint,
+                    &response_pointer,
+                    definitions,
+                    components,
+                )
+                .map_err(|e| e.at(&response_pointer))?
+            {
+                errors.push((code.clone(), type_name));
+            }
+        }
+
+        Ok(errors)
+    }
+
+    /// Comment lines listing the headers of the first 2xx response and,
+    /// with `response_metadata`, naming the message generated for them
+    fn response_headers(
+        &mut self,
+        message_name: &str,
+        pointer: &str,
+        operation: &Operation,
+        definitions: &IndexMap<String, Schema>,
+        components: Option<&Components>,
+    ) -> Result<Vec<String>, ConverterError> {
+        let Some(code) = success_code(operation) else {
+            return Ok(Vec::new());
+        };
+        let response = &operation.responses[code];
+        let response =
+            response.resolve(components.and_then(|c| c.responses.as_ref()), "responses")?;
+        let shared_headers = components.and_then(|c| c.headers.as_ref());
+        let headers_pointer = format!("{}/responses/{}/headers", pointer, code);
+        let headers = response
+            .headers
+            .iter()
+            .flatten()
+            .map(|(name, header)| Ok((name.as_str(), header.resolve(shared_headers, "headers")?)))
+            .collect::<Result<Vec<_>, ConverterError>>()
+            .map_err(|e| e.at(&headers_pointer))?;
+        if headers.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut comments = vec!["Response headers:".to_string()];
+        for (name, header) in &headers {
+            let mut line = format!("  {}: {}", name, header.type_description());
+            if let Some(description) = &header.description {
+                line.push_str(&format!(" - {}", description.trim()));
+            }
+            comments.push(line);
+        }
+
+        if self.options.response_metadata {
+            let message_name = self.allocate_type_name(message_name);
+            let mut message = Message::new(&message_name);
+            for (number, (name, header)) in (1..).zip(&headers) {
+                let hint = self.naming.nested_type_name(&message_name, name);
+                let proto_type = self
+                    .schema_ref_to_type(&header.schema_ref(), &hint, definitions, components)
+                    .map_err(|e| e.at(format!("{}/{}", headers_pointer, escape_pointer(name))))?;
+                let (type_, rule) = match proto_type {
+                    ProtoType::Repeated(item_type) => (item_type, FieldRule::Repeated),
+                    ProtoType::Single(type_) if type_.is_map() => (type_, FieldRule::Singular),
+                    ProtoType::Single(type_) => (type_, FieldRule::Optional),
+                };
+                let field_name = self.naming.parameter_field_name(name, "header");
+                let field_name = self.escape_reserved(&field_name);
+                let mut field = Field::new(&field_name, type_, number, rule);
+                field.add_comment(&format!("header: {}", name));
+                if let Some(description) = &header.description {
+                    field.add_comment(description.trim());
+                }
+                if header.required.unwrap_or(false) {
+                    self.mark_required(&mut field);
+                }
+                message.add_field(field)?;
+            }
+            self.proto.add_message(message)?;
+            comments.push(format!("Response metadata: {}", message_name));
+        }
+
+        Ok(comments)
+    }
+
+    fn response_headers_warning(&mut self, pointer: &str, response: &Response) {
+        if let Some(headers) = response.headers.as_ref().filter(|h| !h.is_empty()) {
+            self.warnings
+                .push(ConversionWarning::DroppedResponseHeaders {
+                    path: pointer.to_string(),
+                    headers: headers.keys().cloned().collect(),
+                });
+        }
+    }
+
+    /// Builds the OpenAPI 3 `content` of a Swagger 2.0 body or response from
+    /// `produces` / `consumes`. Swagger 2.0 schemas describe JSON, so other
+    /// media types get no schema; if there is no JSON one at all the payload
+    /// becomes bytes.
+    fn swagger2_content(
+        &mut self,
+        pointer: &str,
+        schema_ref: &SchemaRef,
+        media_types: &[String],
+    ) -> IndexMap<String, MediaType> {
+        let json = ["application/json".to_string()];
+        let media_types = if media_types.is_empty() {
+            &json[..]
+        } else {
+            media_types
+        };
+        if let Some(media_type) = media_types.iter().min_by_key(|m| media_type_rank(m))
+            && !is_json_content_type(media_type)
+        {
+            self.warnings.push(ConversionWarning::NonJsonPayload {
+                path: pointer.to_string(),
+                media_type: media_type.clone(),
+            });
+        }
+
+        media_types
+            .iter()
+            .map(|media_type| {
+                let schema = is_json_content_type(media_type).then(|| schema_ref.clone());
+                let media = MediaType {
+                    schema,
+                    example: None,
+                    examples: None,
+                };
+                (media_type.clone(), media)
+            })
+            .collect()
+    }
+
+    /// Records the media types of `content` other than the converted one
+    fn skipped_media_types(
+        &mut self,
+        pointer: &str,
+        content: &IndexMap<String, MediaType>,
+        used: Option<&str>,
+    ) {
+        for media_type in content.keys() {
+            if Some(media_type.as_str()) != used {
+                self.warnings.push(ConversionWarning::SkippedContentType {
+                    path: pointer.to_string(),
+                    media_type: media_type.clone(),
+                });
+            }
+        }
+    }
+
+    /// `produces` is the Swagger 2.0 list of the operation, empty for
+    /// OpenAPI 3
+    fn response_to_type(
+        &mut self,
+        response: &Response,
+        produces: &[String],
+        name_hint: &str,
+        pointer: &str,
+        definitions: &IndexMap<String, Schema>,
+        components: Option<&Components>,
+    ) -> Result<Option<String>, ConverterError> {
+        // Swagger 2.0 declares the media types on the operation
+        let swagger2_content = match (&response.content, &response.schema) {
+            (None, Some(schema_ref)) if !produces.is_empty() => {
+                Some(self.swagger2_content(pointer, schema_ref, produces))
+            }
+            _ => None,
+        };
+        let content = response.content.as_ref().or(swagger2_content.as_ref());
+
+        let media = content.and_then(preferred_media_type);
+        if let Some(content) = content {
+            self.skipped_media_types(
+                &format!("{}/content", pointer),
+                content,
+                media.map(|(content_type, _)| content_type.as_str()),
+            );
+        }
+        let content_type = media.map(|(content_type, _)| content_type.as_str());
+        let schema_ref = match (media, &swagger2_content) {
+            (Some((_, media_type)), Some(_)) => media_type.schema.as_ref(),
+            _ => media
+                .and_then(|(_, media_type)| media_type.schema.as_ref())
+                .or(response.schema.as_ref()),
+        };
+
+        let Some(schema_ref) = schema_ref else {
+            // Non-JSON payloads without a schema are passed through as bytes
+            return match content_type {
+                Some(content_type) if !is_json_content_type(content_type) => self
+                    .data_message(name_hint, ScalarType::Bytes.into(), Some(content_type))
+                    .map(Some),
+                _ => Ok(None),
+            };
+        };
+
+        let proto_type =
+            match self.split_message(schema_ref, Direction::Response, definitions, components)? {
+                Some(type_name) => ProtoType::from(type_name),
+                None => self.schema_ref_to_type(schema_ref, name_hint, definitions, components)?,
+            };
+
+        match proto_type {
+            // rpc не может вернуть repeated напрямую, оборачиваем в сообщение
+            ProtoType::Repeated(item_type) => self.list_wrapper(&item_type).map(Some),
+            ProtoType::Single(FieldType::Named(name)) => Ok(Some(name)),
+            // То же для скаляров и map
+            ProtoType::Single(type_) => self.data_message(name_hint, type_, content_type).map(Some),
+        }
+    }
+
+    /// With `split_read_write`, returns the variant of a referenced schema
+    /// for one side of an operation: `<Name>Input` without readOnly
+    /// properties or `<Name>Output` without writeOnly ones. Field numbers
+    /// match the full message. `None` if the schema has nothing to drop.
+    fn split_message(
+        &mut self,
+        schema_ref: &SchemaRef,
+        direction: Direction,
+        definitions: &IndexMap<String, Schema>,
+        components: Option<&Components>,
+    ) -> Result<Option<String>, ConverterError> {
+        let SchemaRef::Ref { ref_path } = schema_ref else {
+            return Ok(None);
+        };
+        if !self.options.split_read_write {
+            return Ok(None);
+        }
+
+        let (suffix, dropped): (&str, fn(&Schema) -> bool) = match direction {
+            Direction::Request => ("Input", |p| p.read_only.unwrap_or(false)),
+            Direction::Response => ("Output", |p| p.write_only.unwrap_or(false)),
+        };
+        let key = format!("{}{}", ref_path, suffix);
+        if let Some(name) = self.split_messages.get(&key) {
+            return Ok(Some(name.clone()));
+        }
+
+        let schema = self.resolve_schema_ref(schema_ref, definitions, components)?;
+        let field_names =
+            self.property_field_names(schema.properties.iter().flatten().map(|(name, _)| name));
+        let dropped_fields: Vec<String> = schema
+            .properties
+            .iter()
+            .flatten()
+            .filter(|(_, property)| dropped(property))
+            .map(|(name, _)| field_names[name.as_str()].clone())
+            .collect();
+        if dropped_fields.is_empty() {
+            return Ok(None);
+        }
+
+        let base = format!(
+            "{}{}",
+            self.definition_name(&self.resolve_ref_name(ref_path)),
+            suffix
+        );
+        let name = self.allocate_type_name(&base);
+        let mut message =
+            self.convert_schema_to_message(&name, &schema, definitions, components)?;
+        message
+            .fields
+            .retain(|field| !dropped_fields.contains(&field.name));
+        self.proto.add_message(message)?;
+
+        self.split_messages.insert(key, name.clone());
+        Ok(Some(name))
+    }
+
+    /// Splits `x-*` extensions into comment lines and the options listed in
+    /// `extension_options`
+    fn extensions(
+        &self,
+        extensions: &IndexMap<String, serde_json::Value>,
+    ) -> (Vec<String>, Vec<(String, OptionValue)>) {
+        let mut comments = Vec::new();
+        let mut options = Vec::new();
+        for (key, value) in extensions {
+            if ENUM_EXTENSIONS.contains(&key.as_str()) {
+                continue;
+            }
+            match self.options.extension_options.get(key) {
+                Some(option) => options.push((option.clone(), extension_option_value(value))),
+                None => comments.push(format!("{}: {}", key, extension_value(value))),
+            }
+        }
+        (comments, options)
+    }
+
+    /// Generates a message with a single `data` field for payloads that
+    /// cannot be an rpc type on their own
+    fn data_message(
+        &mut self,
+        name_hint: &str,
+        type_: FieldType,
+        content_type: Option<&str>,
+    ) -> Result<String, ConverterError> {
+        let message_name = self.allocate_type_name(name_hint);
+        let mut field = Field::new("data", type_, 1, FieldRule::Singular);
+        if let Some(content_type) = content_type {
+            field.add_comment(&format!("Content-Type: {}", content_type));
+        }
+
+        let mut message = Message::new(&message_name);
+        message.add_field(field)?;
+        self.proto.add_message(message)?;
+        Ok(message_name)
+    }
+
+    /// Records that a field is required: the `required` label for proto2,
+    /// otherwise a plain field annotated according to `RequiredFields`.
+    fn mark_required(&mut self, field: &mut Field) {
+        if field.rule == FieldRule::Repeated {
+            return;
+        }
+
+        if self.proto.syntax == "proto2" {
+            field.rule = FieldRule::Required;
+            return;
+        }
+
+        field.rule = FieldRule::Singular;
+        match self.options.required_fields {
+            RequiredFields::Comment => field.add_comment("required"),
+            RequiredFields::FieldBehavior => {
+                self.proto.add_import("google/api/field_behavior.proto");
+                field.add_option(
+                    "(google.api.field_behavior)",
+                    OptionValue::Identifier("REQUIRED".to_string()),
+                );
+            }
+        }
+    }
+
+    /// Gives a nullable field presence according to `NullableStrategy`
+    fn mark_nullable(&mut self, field: &mut Field) {
+        if field.rule == FieldRule::Repeated || field.type_.is_map() {
+            return;
+        }
+
+        let wrapper = match field.type_ {
+            FieldType::Scalar(ScalarType::Double) => "google.protobuf.DoubleValue",
+            FieldType::Scalar(ScalarType::Float) => "google.protobuf.FloatValue",
+            FieldType::Scalar(ScalarType::Int64) => "google.protobuf.Int64Value",
+            FieldType::Scalar(ScalarType::Uint64) => "google.protobuf.UInt64Value",
+            FieldType::Scalar(ScalarType::Int32) => "google.protobuf.Int32Value",
+            FieldType::Scalar(ScalarType::Uint32) => "google.protobuf.UInt32Value",
+            FieldType::Scalar(ScalarType::Bool) => "google.protobuf.BoolValue",
+            FieldType::Scalar(ScalarType::String) => "google.protobuf.StringValue",
+            FieldType::Scalar(ScalarType::Bytes) => "google.protobuf.BytesValue",
+            _ => "",
+        };
+
+        match self.options.nullable {
+            NullableStrategy::Ignore => {}
+            NullableStrategy::Wrappers if !wrapper.is_empty() => {
+                self.proto.add_import("google/protobuf/wrappers.proto");
+                field.type_ = FieldType::Named(wrapper.to_string());
+                field.rule = FieldRule::Singular;
+            }
+            NullableStrategy::Wrappers | NullableStrategy::Optional => {
+                field.rule = FieldRule::Optional;
+            }
+        }
+    }
+
+    /// Adds protoc-gen-validate rules for the schema constraints; those the
+    /// field type has no rule for become comments.
+    fn add_validation_rules(&mut self, field: &mut Field, schema: &Schema) {
+        let kind = if field.rule == FieldRule::Repeated {
+            "repeated"
+        } else {
+            validation_type(&field.type_)
+        };
+        let numeric = matches!(
+            kind,
+            "int32"
+                | "int64"
+                | "uint32"
+                | "uint64"
+                | "sint32"
+                | "sint64"
+                | "fixed32"
+                | "fixed64"
+                | "sfixed32"
+                | "sfixed64"
+                | "double"
+                | "float"
+        );
+
+        let mut rules = Vec::new();
+        let mut unsupported = Vec::new();
+
+        let bounds = [
+            (
+                "minimum",
+                schema.minimum.as_ref(),
+                schema.exclusive_minimum.as_ref(),
+                ("gte", "gt"),
+            ),
+            (
+                "maximum",
+                schema.maximum.as_ref(),
+                schema.exclusive_maximum.as_ref(),
+                ("lte", "lt"),
+            ),
+        ];
+        for (name, limit, exclusive, (inclusive_rule, exclusive_rule)) in bounds {
+            // 3.0: `exclusiveMinimum: true` next to `minimum`; 3.1: the bound itself
+            let (value, exclusive) = match (exclusive, limit) {
+                (Some(serde_json::Value::Number(n)), _) => (n, true),
+                (Some(serde_json::Value::Bool(b)), Some(n)) => (n, *b),
+                (_, Some(n)) => (n, false),
+                (_, None) => continue,
+            };
+            let integral = value.is_i64() || value.is_u64();
+            if numeric && (integral || !kind.contains("int")) {
+                let rule = if exclusive {
+                    exclusive_rule
+                } else {
+                    inclusive_rule
+                };
+                rules.push(format!("{}: {}", rule, value));
+            } else if exclusive {
+                unsupported.push(format!("exclusive{}: {}", self.to_pascal_case(name), value));
+            } else {
+                unsupported.push(format!("{}: {}", name, value));
+            }
+        }
+
+        let lengths = [
+            ("minLength", schema.min_length, "min_len"),
+            ("maxLength", schema.max_length, "max_len"),
+            ("minItems", schema.min_items, "min_items"),
+            ("maxItems", schema.max_items, "max_items"),
+        ];
+        for (name, value, rule) in lengths {
+            let Some(value) = value else { continue };
+            let applies = match rule {
+                "min_items" | "max_items" => kind == "repeated",
+                _ => kind == "string" || kind == "bytes",
+            };
+            if applies {
+                rules.push(format!("{}: {}", rule, value));
+            } else {
+                unsupported.push(format!("{}: {}", name, value));
+            }
+        }
+
+        if kind == "string"
+            && let Some(rule) = schema.format.as_deref().and_then(string_format_rule)
+        {
+            rules.push(format!("{}: true", rule));
+        }
+
+        if let Some(pattern) = &schema.pattern {
+            if kind == "string" {
+                let escaped = pattern.replace('\\', "\\\\").replace('"', "\\\"");
+                rules.push(format!("pattern: \"{}\"", escaped));
+            } else {
+                unsupported.push(format!("pattern: {}", pattern));
+            }
+        }
+
+        if !rules.is_empty() {
+            self.proto.add_import("validate/validate.proto");
+            field.add_option(
+                &format!("(validate.rules).{}", kind),
+                OptionValue::Aggregate(format!("{{{}}}", rules.join(", "))),
+            );
+        }
+        for constraint in unsupported {
+            field.add_comment(&constraint);
+        }
+    }
+
+    /// Returns the `<Item>List { repeated Item items = 1; }` wrapper type,
+    /// generating it on first use.
+    fn list_wrapper(&mut self, item_type: &FieldType) -> Result<String, ConverterError> {
+        // `string` -> `StringList`, `User.Address` -> `AddressList`
+        let item_name = item_type.to_string();
+        let base = item_name.rsplit('.').next().unwrap_or(&item_name);
+        if let Some(list_type) = self.list_wrappers.get(&item_name) {
+            return Ok(list_type.clone());
+        }
+
+        let list_type = self.allocate_type_name(&self.naming.list_message_name(base));
+        {
+            let mut list_message = Message::new(&list_type);
+            list_message.add_field(Field::new(
+                "items",
+                item_type.clone(),
+                1,
+                FieldRule::Repeated,
+            ))?;
+            self.proto.add_message(list_message)?;
+            self.list_wrappers.insert(item_name, list_type.clone());
+        }
+
+        Ok(list_type)
+    }
+
+    fn generate_parameters_message(
+        &mut self,
+        message_name: &str,
+        parameters: Vec<&Parameter>,
+        definitions: &IndexMap<String, Schema>,
+        components: Option<&Components>,
+    ) -> Result<Message, ConverterError> {
+        if let Some(message) = self.proto.find_message(message_name) {
+            return Ok(message.clone());
+        }
+
+        let mut message = Message::new(message_name);
+        for (field_number, param) in (1..).zip(parameters) {
+            if let Some(desc) = &param.description {
+                message.add_comment(desc);
+            }
+
+            let hint = self.naming.nested_type_name(message_name, &param.name);
+            let mut collection_format = None;
+            let proto_type = if let Some(schema_ref) = &param.schema {
+                self.schema_ref_to_type(schema_ref, &hint, definitions, components)
+                    .map_err(|e| e.at(format!("parameters/{}/schema", param.name)))?
+            } else if param.type_.as_deref() == Some("array") {
+                // Swagger 2.0: only `multi` repeats the parameter, the other
+                // formats pack all values into one delimited string
+                match param.collection_format.as_deref().unwrap_or("csv") {
+                    "multi" => {
+                        let item_type = match &param.items {
+                            Some(items) => {
+                                let hint = format!("{}Item", hint);
+                                let item_type =
+                                    self.schema_ref_to_type(items, &hint, definitions, components)?;
+                                self.single_type(item_type)?
+                            }
+                            None => ScalarType::String.into(),
+                        };
+                        ProtoType::Repeated(item_type)
+                    }
+                    format => {
+                        collection_format = Some(format);
+                        ScalarType::String.into()
+                    }
+                }
+            } else {
+                match param.type_.as_deref() {
+                    Some("file") => ScalarType::Bytes.into(),
+                    Some(type_) => self
+                        .primitive_type(&hint, type_, param.format.as_deref())
+                        .unwrap_or(FieldType::Scalar(ScalarType::String))
+                        .into(),
+                    None => ScalarType::String.into(),
+                }
+            };
+
+            let (proto_type, rule) = match proto_type {
+                ProtoType::Repeated(item_type) => (item_type, FieldRule::Repeated),
+                ProtoType::Single(type_) if type_.is_map() => (type_, FieldRule::Singular),
+                ProtoType::Single(type_) => (type_, FieldRule::Optional),
+            };
+            let field_name = self.naming.parameter_field_name(&param.name, &param.in_);
+            let field_name = self.escape_reserved(&field_name);
+            let mut field = Field::new(&field_name, proto_type, field_number, rule);
+            if param.in_ == "header" || param.in_ == "cookie" {
+                // `X-Request-Id` -> `x_request_id`, keeping the original name
+                field.add_comment(&format!("{}: {}", param.in_, param.name));
+            } else if self.to_camel_case(&field.name) != param.name {
+                // Клиенты шлют исходное имя, как и ключи свойств
+                field.add_string_option("json_name", &param.name);
+            }
+
+            if let Some(format) = collection_format {
+                field.add_comment(&format!("collectionFormat: {}", format));
+            }
+
+            let format = match &param.schema {
+                Some(SchemaRef::Inline(schema)) => schema.format.as_deref(),
+                _ => param.format.as_deref(),
+            };
+            self.add_format_comment(&mut field, format);
+
+            let (example, default) = match &param.schema {
+                Some(SchemaRef::Inline(schema)) => {
+                    (schema.example.as_ref(), schema.default.as_ref())
+                }
+                _ => (None, None),
+            };
+            let default = default.or(param.default.as_ref());
+            self.add_example_comments(&mut field, example, default);
+            self.add_default_option(&mut field, default);
+
+            if param.required.unwrap_or(false) {
+                self.mark_required(&mut field);
+            }
+
+            if param.deprecated.unwrap_or(false) {
+                field.add_option("deprecated", OptionValue::Bool(true));
+            }
+
+            let (comments, options) = self.extensions(&param.extensions);
+            comments.iter().for_each(|c| field.add_comment(c));
+            options
+                .iter()
+                .for_each(|(k, v)| field.add_option(k, v.clone()));
+
+            if self.options.validation_rules
+                && let Some(SchemaRef::Inline(schema)) = &param.schema
+            {
+                self.add_validation_rules(&mut field, schema);
+            }
+
+            message.add_field(field)?;
+        }
+
+        Ok(message)
+    }
+
+    /// AIP-158 request fields with [`ConverterOptions::pagination`]: the
+    /// first query parameter named like a page size becomes `int32
+    /// page_size`, the first page number or token `string page_token`
+    fn page_request_fields(&self, message: &mut Message, parameters: &[&Parameter]) {
+        let Some(pagination) = &self.options.pagination else {
+            return;
+        };
+        for (target, type_, names) in [
+            ("page_size", ScalarType::Int32, &pagination.page_size),
+            ("page_token", ScalarType::String, &pagination.page_token),
+        ] {
+            // Номер поля параметра - его позиция в списке
+            let Some((number, param)) = names.iter().find_map(|name| {
+                (1..)
+                    .zip(parameters)
+                    .find(|(_, p)| p.in_ == "query" && p.name == *name)
+            }) else {
+                continue;
+            };
+            if message
+                .find_field(target)
+                .is_some_and(|field| field.number != number)
+            {
+                continue;
+            }
+            let Some(field) = message.fields.iter_mut().find(|f| f.number == number) else {
+                continue;
+            };
+            if field.type_ != FieldType::Scalar(type_) {
+                // default и правила валидации относятся к старому типу
+                field.options.clear();
+                field.type_ = FieldType::Scalar(type_);
+            }
+            field.rule = FieldRule::Singular;
+            if field.name != target {
+                field.name = target.to_string();
+                field.add_comment(&format!("query: {}", param.name));
+            }
+        }
+    }
+
+    /// AIP-158 shape of a page envelope `{items: [...], total: n}` with
+    /// [`ConverterOptions::pagination`]: a `name_hint` message with
+    /// `repeated <Item> items`, `total_size`, `string next_page_token` and
+    /// the other properties of the envelope. `None` when `type_name` has no
+    /// items array, or neither a total nor a next page property.
+    fn paged_response(
+        &mut self,
+        type_name: &str,
+        name_hint: &str,
+    ) -> Result<Option<String>, ConverterError> {
+        let Some(pagination) = &self.options.pagination else {
+            return Ok(None);
+        };
+        let Some(envelope) = self.proto.find_message(type_name) else {
+            return Ok(None);
+        };
+        // Ключ свойства в JSON: json_name или имя поля в lowerCamelCase
+        let keys: Vec<String> = envelope
+            .fields
+            .iter()
+            .map(|field| match field.options.get("json_name") {
+                Some(OptionValue::String(key)) => key.clone(),
+                _ => self.to_camel_case(&field.name),
+            })
+            .collect();
+        let find = |names: &[String], fits: fn(&Field) -> bool| {
+            names.iter().find_map(|name| {
+                (0..keys.len()).find(|&i| keys[i] == *name && fits(&envelope.fields[i]))
+            })
+        };
+        let Some(items) = find(&pagination.items, |f| {
+            f.rule == FieldRule::Repeated && !f.type_.is_map()
+        }) else {
+            return Ok(None);
+        };
+        let total = find(&pagination.total, |f| {
+            f.rule != FieldRule::Repeated
+                && matches!(&f.type_, FieldType::Scalar(scalar) if scalar.is_integer())
+        });
+        let next = find(&pagination.next_page_token, |f| {
+            f.rule != FieldRule::Repeated && f.type_.is_scalar()
+        });
+        if total.is_none() && next.is_none() {
+            return Ok(None);
+        }
+
+        let mut message = envelope.clone();
+        for (index, target) in [
+            (Some(items), "items"),
+            (total, "total_size"),
+            (next, "next_page_token"),
+        ] {
+            let Some(index) = index else {
+                continue;
+            };
+            if message
+                .find_field(target)
+                .is_some_and(|field| field.name != message.fields[index].name)
+            {
+                continue;
+            }
+            let field = &mut message.fields[index];
+            if target == "next_page_token" {
+                if field.type_ != FieldType::Scalar(ScalarType::String) {
+                    field.options.clear();
+                    field.type_ = ScalarType::String.into();
+                }
+                field.rule = FieldRule::Singular;
+            }
+            if field.name != target {
+                field.name = target.to_string();
+                field.add_comment(&format!("response: {}", keys[index]));
+            }
+            // JSON остаётся прежним
+            if self.to_camel_case(target) == keys[index] {
+                field.options.shift_remove("json_name");
+            } else {
+                field.add_string_option("json_name", &keys[index]);
+            }
         }
-        Ok(())
+        if next.is_none() && message.find_field("next_page_token").is_none() {
+            let number = message.fields.iter().map(|f| f.number).max().unwrap_or(0) + 1;
+            let mut field = Field::new(
+                "next_page_token",
+                ScalarType::String,
+                number,
+                FieldRule::Singular,
+            );
+            field.add_comment("Not in the swagger response");
+            message.add_field(field)?;
+        }
+
+        // Встроенный конверт меняем на месте, общий копируем
+        if type_name == name_hint {
+            if let Some(envelope) = self.proto.find_message_mut(type_name) {
+                *envelope = message;
+            }
+            return Ok(Some(type_name.to_string()));
+        }
+        let name = self.allocate_type_name(name_hint);
+        message.name = name.clone();
+        self.proto.add_message(message)?;
+        Ok(Some(name))
     }
 
-    fn handle_properties(
+    fn generate_body_message(
         &mut self,
-        message: &mut Message,
         message_name: &str,
-        properties: &HashMap<String, Schema>,
-        required_fields: &Option<Vec<String>>,
-        definitions: &HashMap<String, Schema>,
+        pointer: &str,
+        request_body: &RequestBody,
+        definitions: &IndexMap<String, Schema>,
         components: Option<&Components>,
-    ) -> Result<(), ConverterError> {
-        let mut field_number = 1;
+    ) -> Result<Message, ConverterError> {
+        if let Some(message) = self.proto.find_message(message_name) {
+            return Ok(message.clone());
+        }
 
-        for (prop_name, prop_schema) in properties {
-            if prop_name.starts_with("//") {
-                continue;
-            }
+        let mut message = Message::new(message_name);
 
-            // Добавляем описание свойства как комментарий
-            if let Some(description) = &prop_schema.description {
-                description.lines().for_each(|line| {
-                    message.add_comment(&format!("// {}", line.trim()));
-                });
-            }
+        if let Some(description) = &request_body.description {
+            message.add_comment(description);
+        }
 
-            let type_name = if let Some(enum_values) = &prop_schema.enum_values {
-                let enum_name = format!("{}{}", message_name, self.to_pascal_case(prop_name));
-                let mut enum_def = Enum::new(&enum_name);
+        let media = preferred_media_type(&request_body.content);
+        self.skipped_media_types(
+            &format!("{}/content", pointer),
+            &request_body.content,
+            media.map(|(content_type, _)| content_type.as_str()),
+        );
 
-                for (i, value) in enum_values.iter().enumerate() {
-                    let variant_name = match value {
-                        serde_json::Value::String(s) => s
-                            .to_uppercase()
-                            .replace(|c: char| !c.is_alphanumeric(), "_"),
-                        serde_json::Value::Number(n) => format!("VALUE_{}", n),
-                        _ => format!("VALUE_{}", i + 1),
-                    };
-                    enum_def.add_value(EnumValue::new(&variant_name, (i) as i32))?;
-                }
+        if self.options.include_examples
+            && let Some((_, media_type)) = media
+        {
+            if let Some(example) = &media_type.example {
+                message.add_comment(&format!("example: {}", example_value(example)));
+            }
+            for (name, example) in media_type.examples.iter().flatten() {
+                let value = match (&example.value, &example.external_value) {
+                    (Some(value), _) => example_value(value),
+                    (None, Some(url)) => url.clone(),
+                    (None, None) => example.summary.clone().unwrap_or_default(),
+                };
+                message.add_comment(&format!("example {}: {}", name, value));
+            }
+        }
 
-                self.proto.add_enum(enum_def)?;
-                enum_name
-            } else {
-                self.schema_to_type(prop_schema, definitions, components)?
+        if let Some((content_type, media_type)) = media {
+            let form_schema = match &media_type.schema {
+                Some(schema_ref) if is_form_content_type(content_type) => {
+                    Some(self.resolve_schema_ref(schema_ref, definitions, components)?)
+                }
+                _ => None,
             };
 
-            let (final_type, field_rule) = if type_name.starts_with("repeated ") {
-                let item_type = type_name.trim_start_matches("repeated ");
-                let list_type = format!("{}List", item_type);
+            if let Some(properties) = form_schema.as_ref().and_then(|s| s.properties.as_ref()) {
+                // Every form part becomes its own field; files map to bytes
+                message.add_comment(&format!("Content-Type: {}", content_type));
+                let required = form_schema.as_ref().and_then(|s| s.required.clone());
+                self.handle_properties(
+                    &mut message,
+                    message_name,
+                    properties,
+                    &required,
+                    definitions,
+                    components,
+                )?;
+            } else if let Some(schema_ref) = &media_type.schema {
+                let hint = format!("{}Data", message_name);
+                let proto_type = match self.split_message(
+                    schema_ref,
+                    Direction::Request,
+                    definitions,
+                    components,
+                )? {
+                    Some(type_name) => ProtoType::from(type_name),
+                    None => self.schema_ref_to_type(schema_ref, &hint, definitions, components)?,
+                };
 
-                if !self.generated_messages.contains_key(&list_type) {
-                    let mut list_message = Message::new(&list_type);
-                    list_message.add_field(Field::new(
-                        "items",
-                        &format!("repeated {}", item_type),
-                        1,
-                        FieldRule::Optional,
-                    ))?;
-                    self.proto.add_message(list_message)?;
-                    self.generated_messages.insert(list_type.clone(), 1);
+                match proto_type {
+                    ProtoType::Single(type_)
+                        if type_.is_map() || type_.as_named() == Some("google.protobuf.Struct") =>
+                    {
+                        let mut field = Field::new("data", type_, 1, FieldRule::Optional);
+                        field.add_string_option("json_name", content_type);
+                        message.add_field(field)?;
+                    }
+                    ProtoType::Repeated(item_type) => {
+                        let mut field = Field::new("data", item_type, 1, FieldRule::Repeated);
+                        field.add_comment(&format!("Content-Type: {}", content_type));
+                        message.add_field(field)?;
+                    }
+                    ProtoType::Single(type_) => {
+                        let mut field = Field::new("data", type_, 1, FieldRule::Optional);
+                        field.add_comment(&format!("Content-Type: {}", content_type));
+                        message.add_field(field)?;
+                    }
                 }
-
-                (list_type, FieldRule::Optional)
+            } else if !is_json_content_type(content_type) {
+                let mut field = Field::new("data", "bytes", 1, FieldRule::Optional);
+                field.add_comment(&format!("Content-Type: {}", content_type));
+                message.add_field(field)?;
             } else {
-                let rule = if required_fields
-                    .as_ref()
-                    .map(|r| r.contains(prop_name))
-                    .unwrap_or(false)
-                {
-                    FieldRule::Required
-                } else {
-                    FieldRule::Optional
-                };
-                (type_name, rule)
-            };
-
-            message.add_field(Field::new(
-                &self.sanitize_field_name(prop_name),
-                &final_type,
-                field_number,
-                field_rule,
-            ))?;
-
-            field_number += 1;
+                message.add_field(Field::new("data", "string", 1, FieldRule::Optional))?;
+            }
+        } else {
+            message.add_comment("No content schema defined");
         }
-        Ok(())
-    }
 
-    fn handle_additional_properties(
-        &mut self,
-        message: &mut Message,
-        additional_props: &SchemaRef,
-        definitions: &HashMap<String, Schema>,
-        components: Option<&Components>,
-    ) -> Result<(), ConverterError> {
-        let value_type = self.schema_ref_to_type(additional_props, definitions, components)?;
-        message.add_field(Field::new(
-            "properties",
-            &format!("map<string, {}>", value_type),
-            1,
-            FieldRule::Optional,
-        ))
+        Ok(message)
     }
 
-    fn handle_root_enum(
-        &mut self,
-        message: &mut Message,
-        message_name: &str,
-        enum_values: &[serde_json::Value],
-    ) -> Result<(), ConverterError> {
-        let enum_name = format!("{}Status", message_name);
-        let mut enum_def = Enum::new(&enum_name);
-
-        for (i, value) in enum_values.iter().enumerate() {
-            let variant_name = match value {
-                serde_json::Value::String(s) => s
-                    .to_uppercase()
-                    .replace(|c: char| !c.is_alphanumeric(), "_"),
-                serde_json::Value::Number(n) => format!("VALUE_{}", n),
-                _ => format!("VALUE_{}", i + 1),
+    fn collect_operations<'a>(
+        &self,
+        services: &mut BTreeMap<String, Vec<(String, String, &'a Operation)>>,
+        path: &str,
+        method: &str,
+        operation: Option<&'a Operation>,
+    ) {
+        if let Some(op) = operation {
+            let is_internal =
+                op.extensions.get("x-internal") == Some(&serde_json::Value::Bool(true));
+            if self.options.skip_internal && is_internal {
+                return;
+            }
+            if !self.options.only_tags.is_empty()
+                && !op
+                    .tags
+                    .iter()
+                    .flatten()
+                    .any(|tag| self.options.only_tags.contains(tag))
+            {
+                return;
+            }
+            if op
+                .tags
+                .iter()
+                .flatten()
+                .any(|tag| self.options.exclude_tags.contains(tag))
+            {
+                return;
+            }
+
+            let tags = op.tags.as_ref().filter(|tags| !tags.is_empty());
+            let groups = match (&self.options.service_grouping, tags) {
+                (ServiceGrouping::SingleService(name), _) => vec![name.clone()],
+                (ServiceGrouping::ByTag, Some(tags)) => tags.clone(),
+                _ => vec![first_path_segment(path)],
             };
-            enum_def.add_value(EnumValue::new(&variant_name, (i + 1) as i32))?;
-        }
 
-        self.proto.add_enum(enum_def)?;
-        message.add_field(Field::new("status", &enum_name, 1, FieldRule::Optional))
+            // Keyed by the final service name, so a tag and a path segment
+            // that map to the same service are merged
+            for group in groups {
+                let methods = services.entry(self.to_pascal_case(&group)).or_default();
+                if !methods.iter().any(|(p, m, _)| p == path && m == method) {
+                    methods.push((path.to_string(), method.to_string(), op));
+                }
+            }
+        }
     }
 
-    fn schema_to_type(
+    /// Method name that is free in `service`: on a collision the HTTP verb
+    /// is appended, then a counter (`GetUser`, `GetUserPost`, `GetUser2`).
+    /// In strict mode a collision is an error.
+    fn unique_method_name(
         &mut self,
-        schema: &Schema,
-        definitions: &HashMap<String, Schema>,
-        components: Option<&Components>,
+        service: &Service,
+        path: &str,
+        http_method: &str,
+        operation: &Operation,
     ) -> Result<String, ConverterError> {
-        if let Some(ref_path) = &schema.ref_path {
-            return Ok(self.resolve_ref_name(ref_path));
+        let base = self.generate_method_name(path, http_method, operation);
+        let is_taken = |name: &str| service.methods.iter().any(|m| m.name == name);
+        if !is_taken(&base) {
+            return Ok(base);
         }
 
-        if let Some(enum_values) = &schema.enum_values {
-            let enum_name = format!("Enum_{}", random::<u32>());
-            let mut enum_def = Enum::new(&enum_name);
-
-            for (i, value) in enum_values.iter().enumerate() {
-                let variant_name = match value {
-                    serde_json::Value::String(s) => s
-                        .to_uppercase()
-                        .replace(|c: char| !c.is_alphanumeric(), "_"),
-                    serde_json::Value::Number(n) => format!("VALUE_{}", n),
-                    _ => format!("VALUE_{}", i + 1),
-                };
-                enum_def.add_value(EnumValue::new(&variant_name, (i + 1) as i32))?;
-            }
-
-            self.proto.add_enum(enum_def)?;
-            return Ok(enum_name);
+        if self.options.strict {
+            return Err(ConverterError::InvalidFieldName(format!(
+                "Duplicate method name: {}",
+                base
+            )));
         }
 
-        match schema.type_.as_deref() {
-            Some("integer") => match schema.format.as_deref() {
-                Some("int64") => Ok("int64".to_string()),
-                Some("int32") => Ok("int32".to_string()),
-                _ => Ok("int64".to_string()),
-            },
-            Some("number") => match schema.format.as_deref() {
-                Some("double") => Ok("double".to_string()),
-                Some("float") => Ok("float".to_string()),
-                _ => Ok("double".to_string()),
-            },
-            Some("boolean") => Ok("bool".to_string()),
-            Some("string") => match schema.format.as_deref() {
-                Some("date") => Ok("google.protobuf.Timestamp".to_string()),
-                Some("date-time") => Ok("google.protobuf.Timestamp".to_string()),
-                Some("byte") => Ok("bytes".to_string()),
-                Some("binary") => Ok("bytes".to_string()),
-                _ => Ok("string".to_string()),
-            },
-            Some("array") => {
-                let items = schema
-                    .items
-                    .as_ref()
-                    .ok_or(ConverterError::InvalidArrayDefinition)?;
-                let item_type = self.schema_ref_to_type(items, definitions, components)?;
-                Ok(format!("repeated {}", item_type))
-            }
-            Some("object") => {
-                if schema.properties.is_some() || schema.all_of.is_some() {
-                    let temp_name = format!("NestedObject_{}", random::<u32>());
-                    let message = self.convert_schema_to_message(
-                        &temp_name,
-                        schema,
-                        definitions,
-                        components,
-                    )?;
-                    self.proto.add_message(message)?;
-                    Ok(temp_name)
-                } else if let Some(additional_props) = &schema.additional_properties {
-                    let value_type =
-                        self.schema_ref_to_type(additional_props, definitions, components)?;
-                    Ok(format!("map<string, {}>", value_type))
-                } else {
-                    Ok("google.protobuf.Struct".to_string())
-                }
-            }
-            None if schema.enum_values.is_some() => {
-                let temp_name = format!("Enum_{}", random::<u32>());
-                let mut enum_def = Enum::new(&temp_name);
-                for (i, value) in schema.enum_values.as_ref().unwrap().iter().enumerate() {
-                    let variant_name = match value {
-                        serde_json::Value::String(s) => s
-                            .to_uppercase()
-                            .replace(|c: char| !c.is_alphanumeric(), "_"),
-                        _ => format!("VALUE_{}", i + 1),
-                    };
-                    enum_def.add_value(EnumValue::new(&variant_name, (i + 1) as i32))?;
-                }
-                self.proto.add_enum(enum_def)?;
-                Ok(temp_name)
-            }
-            None => Err(ConverterError::UnsupportedSchemaType("unknown".to_string())),
-            Some(t) => Err(ConverterError::UnsupportedSchemaType(t.to_string())),
+        let mut name = format!(
+            "{}{}",
+            base,
+            self.to_pascal_case(&http_method.to_lowercase())
+        );
+        let mut suffix = 2;
+        while is_taken(&name) {
+            name = format!("{}{}", base, suffix);
+            suffix += 1;
         }
+
+        self.warnings.push(ConversionWarning::RenamedMethod {
+            service: service.name.clone(),
+            method: base,
+            renamed: name.clone(),
+        });
+        Ok(name)
     }
 
-    fn schema_ref_to_type(
-        &mut self,
+    fn generate_method_name(&self, path: &str, http_method: &str, operation: &Operation) -> String {
+        self.naming
+            .method_name(operation.operation_id.as_deref(), http_method, path)
+    }
+
+    fn resolve_schema_ref(
+        &self,
         schema_ref: &SchemaRef,
-        definitions: &HashMap<String, Schema>,
+        definitions: &IndexMap<String, Schema>,
         components: Option<&Components>,
-    ) -> Result<String, ConverterError> {
+    ) -> Result<Schema, ConverterError> {
         match schema_ref {
-            SchemaRef::Ref { ref_path } => Ok(self.resolve_ref_name(ref_path)),
-            SchemaRef::Inline(schema) => self.schema_to_type(schema, definitions, components),
-        }
-    }
-
-    fn process_services(
-        &mut self,
-        paths: &HashMap<String, PathItem>,
-        spec: &SwaggerDoc,
-    ) -> Result<(), ConverterError> {
-        let mut services: BTreeMap<String, Vec<(String, String, &Operation)>> = BTreeMap::new();
-
-        let definitions = spec.definitions.as_ref().unwrap_or_else(|| {
-            static EMPTY: once_cell::sync::Lazy<HashMap<String, Schema>> =
-                once_cell::sync::Lazy::new(|| HashMap::new());
-            &EMPTY
-        });
+            SchemaRef::Ref { ref_path } => {
+                let ref_name = ref_path
+                    .split('/')
+                    .next_back()
+                    .ok_or_else(|| ConverterError::MissingReference(ref_path.clone()))?;
 
-        let components = spec.components.as_ref();
+                // Check definitions (Swagger 2.0)
+                if let Some(schema) = definitions.get(ref_name) {
+                    return Ok(schema.clone());
+                }
 
-        for (path, item) in paths {
-            self.collect_operations(&mut services, path, "GET", item.get.as_ref());
-            self.collect_operations(&mut services, path, "POST", item.post.as_ref());
-            self.collect_operations(&mut services, path, "PUT", item.put.as_ref());
-            self.collect_operations(&mut services, path, "DELETE", item.delete.as_ref());
-            self.collect_operations(&mut services, path, "PATCH", item.patch.as_ref());
-        }
+                // Check components (OpenAPI 3.0)
+                if let Some(components) = components
+                    && let Some(schemas) = &components.schemas
+                    && let Some(schema) = schemas.get(ref_name)
+                {
+                    return Ok(schema.clone());
+                }
 
-        if let Some(default_ops) = services.remove("Default") {
-            if !default_ops.is_empty() {
-                self.generate_service("Default", &default_ops, definitions, components)?;
+                Err(ConverterError::MissingReference(ref_path.clone()))
             }
+            SchemaRef::Inline(schema) => Ok(*schema.clone()),
         }
+    }
 
-        for (tag, methods) in services {
-            if methods.is_empty() {
-                continue;
-            }
+    /// `base`, or `base2`, ... if a message, enum or definition already has
+    /// that name; a changed name is reported as a warning
+    fn allocate_type_name(&mut self, base: &str) -> String {
+        let proto = &self.proto;
+        let allocated = self.names.allocate(base, |name| {
+            proto.find_message(name).is_some() || proto.find_enum(name).is_some()
+        });
+        let name = allocated.name.clone();
+        self.report_renamed_type(base, allocated);
+        name
+    }
 
-            let service_name = self.to_pascal_case(&tag);
-            self.generate_service(&service_name, &methods, definitions, components)?;
+    fn report_renamed_type(&mut self, requested: &str, allocated: Allocated) {
+        if allocated.renamed {
+            self.warnings.push(ConversionWarning::RenamedType {
+                name: requested.to_string(),
+                renamed: allocated.name,
+            });
         }
-
-        Ok(())
     }
 
-    fn generate_service(
-        &mut self,
-        service_name: &str,
-        methods: &[(String, String, &Operation)],
-        definitions: &HashMap<String, Schema>,
-        components: Option<&Components>,
-    ) -> Result<(), ConverterError> {
-        let mut service = Service::new(&format!("{}Service", service_name));
+    /// Type a `$ref` stands for: the definition's message (schema names are
+    /// PascalCased, `user_account` -> `UserAccount`, and kept apart from
+    /// scalar types, `String` -> `String_`), or the inlined type of
+    /// an alias
+    fn ref_type(&self, ref_path: &str) -> ProtoType {
+        let name = self.resolve_ref_name(ref_path);
+        self.aliases
+            .get(&name)
+            .cloned()
+            .unwrap_or_else(|| ProtoType::from(self.definition_name(&name)))
+    }
 
-        for (path, http_method, operation) in methods {
-            let method_name = self.generate_method_name(path, http_method, operation);
+    fn resolve_ref_name(&self, ref_path: &str) -> String {
+        ref_path
+            .split('/')
+            .next_back()
+            .unwrap_or("UnknownRef")
+            .to_string()
+    }
+}
 
-            let (request_type, request_messages) = self.generate_request_message(
-                service_name,
-                &method_name,
-                operation,
-                definitions,
-                components,
-            )?;
+/// Formats `scalar_type` maps to a type of their own
+const KNOWN_FORMATS: &[&str] = &[
+    "int32",
+    "int64",
+    "uint32",
+    "uint64",
+    "sint32",
+    "sint64",
+    "fixed32",
+    "fixed64",
+    "sfixed32",
+    "sfixed64",
+    "float",
+    "double",
+    "date",
+    "date-time",
+    "byte",
+    "binary",
+];
 
-            for message in request_messages {
-                self.proto.add_message(message)?;
-            }
+/// protoc-gen-validate string rule for a well-known string format
+fn string_format_rule(format: &str) -> Option<&'static str> {
+    let rule = match format {
+        "uuid" => "uuid",
+        "email" => "email",
+        "uri" => "uri",
+        "uri-reference" => "uri_ref",
+        "hostname" => "hostname",
+        "ipv4" => "ipv4",
+        "ipv6" => "ipv6",
+        _ => return None,
+    };
+    Some(rule)
+}
 
-            let response_type = self.generate_response_type(operation, definitions, components)?;
+/// Proto scalar for a swagger primitive `type` + `format` pair, shared by
+/// schemas and Swagger 2.0 non-body parameters
+fn scalar_type(type_: &str, format: Option<&str>) -> Option<&'static str> {
+    let scalar = match (type_, format) {
+        ("integer", Some("int32")) => "int32",
+        ("integer", Some("uint32")) => "uint32",
+        ("integer", Some("uint64")) => "uint64",
+        ("integer", Some("sint32")) => "sint32",
+        ("integer", Some("sint64")) => "sint64",
+        ("integer", Some("fixed32")) => "fixed32",
+        ("integer", Some("fixed64")) => "fixed64",
+        ("integer", Some("sfixed32")) => "sfixed32",
+        ("integer", Some("sfixed64")) => "sfixed64",
+        ("integer", _) => "int64",
+        ("number", Some("float")) => "float",
+        ("number", _) => "double",
+        ("boolean", _) => "bool",
+        ("string", Some("date" | "date-time")) => "google.protobuf.Timestamp",
+        ("string", Some("byte" | "binary")) => "bytes",
+        ("string", _) => "string",
+        _ => return None,
+    };
+    Some(scalar)
+}
 
-            let mut method = Method::new(&method_name, &request_type, &response_type);
+/// Pushes the types referenced by the fields of `message` and its nested
+/// messages
+fn collect_field_types<'a>(message: &'a Message, types: &mut Vec<&'a str>) {
+    for field in message.all_fields() {
+        types.extend(field.type_.referenced_types());
+    }
+    for nested in &message.nested_messages {
+        collect_field_types(nested, types);
+    }
+}
 
-            if let Some(summary) = &operation.summary {
-                method.add_comment(summary);
-            }
-            if let Some(description) = &operation.description {
-                for line in description.lines() {
-                    method.add_comment(line.trim());
-                }
+/// Nesting of arrays and objects, counted without recursion
+fn json_depth(value: &serde_json::Value) -> usize {
+    let mut deepest = 0;
+    let mut pending = vec![(value, 0)];
+    while let Some((value, depth)) = pending.pop() {
+        deepest = deepest.max(depth);
+        match value {
+            serde_json::Value::Array(items) => {
+                pending.extend(items.iter().map(|item| (item, depth + 1)))
             }
-            if operation.deprecated.unwrap_or(false) {
-                method.add_comment("Deprecated");
+            serde_json::Value::Object(map) => {
+                pending.extend(map.values().map(|item| (item, depth + 1)))
             }
+            _ => {}
+        }
+    }
+    deepest
+}
 
-            method.add_option("http_method", http_method);
-            method.add_option("http_path", path);
-
-            service.add_method(method)?;
+/// Names of the schema definitions reachable from `paths` and `webhooks`.
+///
+/// Follows every `$ref` transitively through the whole document, so schemas
+/// used via shared parameters, responses or request bodies are included.
+fn reachable_schemas(document: &serde_json::Value) -> HashSet<String> {
+    fn collect_refs<'a>(value: &'a serde_json::Value, refs: &mut Vec<&'a str>) {
+        match value {
+            serde_json::Value::Object(map) => {
+                for (key, child) in map {
+                    match (key.as_str(), child) {
+                        ("$ref", serde_json::Value::String(r)) => refs.push(r),
+                        _ => collect_refs(child, refs),
+                    }
+                }
+            }
+            serde_json::Value::Array(items) => items.iter().for_each(|i| collect_refs(i, refs)),
+            _ => {}
         }
+    }
 
-        self.proto.add_service(service)?;
-        Ok(())
+    let mut pending = Vec::new();
+    for root in ["paths", "webhooks"] {
+        if let Some(value) = document.get(root) {
+            collect_refs(value, &mut pending);
+        }
     }
 
-    fn generate_request_message(
-        &mut self,
-        service_name: &str,
-        method_name: &str,
-        operation: &Operation,
-        definitions: &HashMap<String, Schema>,
-        components: Option<&Components>,
-    ) -> Result<(String, Vec<Message>), ConverterError> {
-        let mut messages = Vec::new();
-        let mut has_query = false;
-        let mut has_body = false;
-        let mut query_message_name = String::new();
+    let mut visited = HashSet::new();
+    let mut schemas = HashSet::new();
+    while let Some(ref_path) = pending.pop() {
+        if !visited.insert(ref_path) {
+            continue;
+        }
+        let pointer = ref_path.trim_start_matches('#');
+        let name = pointer
+            .strip_prefix("/definitions/")
+            .or_else(|| pointer.strip_prefix("/components/schemas/"))
+            .and_then(|rest| rest.split('/').next());
+        if let Some(name) = name {
+            schemas.insert(name.replace("~1", "/").replace("~0", "~"));
+        }
+        if let Some(target) = document.pointer(pointer) {
+            collect_refs(target, &mut pending);
+        }
+    }
+    schemas
+}
 
-        if let Some(parameters) = &operation.parameters {
-            let query_params: Vec<_> = parameters
-                .iter()
-                .filter(|p| p.in_ == "query" || p.in_ == "path")
-                .collect();
+/// Package name for a spec title: lowercase alphanumeric words joined by `_`.
+/// A trailing `vN` word becomes its own segment ("Pet Store API v2" ->
+/// `pet_store_api.v2`); without one the major part of `version` is used
+/// (`1.4.0` -> `.v1`). Returns `None` for a title without words.
+pub fn package_from_title(title: &str, version: &str) -> Option<String> {
+    let is_version = |word: &str| {
+        word.strip_prefix('v')
+            .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+    };
 
-            if !query_params.is_empty() {
-                has_query = true;
-                query_message_name = format!("{}{}QueryParams", service_name, method_name);
-                let message = self.generate_parameters_message(
-                    &query_message_name,
-                    query_params,
-                    definitions,
-                    components,
-                )?;
-                messages.push(message);
-            }
+    let mut words: Vec<String> = title
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect();
 
-            // Process body parameters (Swagger 2.0)
-            if let Some(body_param) = parameters.iter().find(|p| p.in_ == "body") {
-                has_body = true;
-                let body_message_name = format!("{}{}RequestBody", service_name, method_name);
-                let mut fake_request_body = RequestBody {
-                    description: body_param.description.clone(),
-                    content: HashMap::new(),
-                    required: body_param.required,
-                };
+    let version_segment = match words.last() {
+        Some(last) if is_version(last) => words.pop(),
+        _ => version
+            .trim_start_matches(['v', 'V'])
+            .split('.')
+            .next()
+            .filter(|major| !major.is_empty() && major.chars().all(|c| c.is_ascii_digit()))
+            .map(|major| format!("v{}", major)),
+    };
 
-                if let Some(schema_ref) = &body_param.schema {
-                    let media_type = MediaType {
-                        schema: Some(schema_ref.clone()),
-                        example: None,
-                        examples: None,
-                    };
-                    fake_request_body
-                        .content
-                        .insert("application/json".to_string(), media_type);
-                }
+    if words.is_empty() {
+        return None;
+    }
+    let mut package = words.join("_");
+    if package.starts_with(|c: char| c.is_ascii_digit()) {
+        package.insert(0, '_');
+    }
+    if let Some(segment) = version_segment {
+        package.push('.');
+        package.push_str(&segment);
+    }
+    Some(package)
+}
 
-                let message = self.generate_body_message(
-                    &body_message_name,
-                    &fake_request_body,
-                    definitions,
-                    components,
-                )?;
-                messages.push(message);
-            }
-        }
+/// Columns of the info description in the header comment
+const HEADER_WIDTH: usize = 100;
 
-        // Process request body (OpenAPI 3.0)
-        if let Some(request_body) = &operation.request_body {
-            has_body = true;
-            let body_message_name = format!("{}{}RequestBody", service_name, method_name);
-            let message = self.generate_body_message(
-                &body_message_name,
-                request_body,
-                definitions,
-                components,
-            )?;
-            messages.push(message);
+/// Splits `line` at spaces into lines of at most `width` characters; words
+/// longer than that stay whole. Leading indentation is kept on every line.
+fn wrap_line(line: &str, width: usize) -> Vec<String> {
+    let indent = &line[..line.len() - line.trim_start().len()];
+    let mut lines = Vec::new();
+    let mut current = indent.to_string();
+    for word in line.split_whitespace() {
+        let fits = current.chars().count() + 1 + word.chars().count() <= width;
+        if current.len() > indent.len() && !fits {
+            lines.push(std::mem::replace(&mut current, indent.to_string()));
+        }
+        if current.len() > indent.len() {
+            current.push(' ');
         }
+        current.push_str(word);
+    }
+    lines.push(current);
+    lines
+}
 
-        let request_type = match (has_query, has_body) {
-            (true, true) => {
-                let combined_name = format!("{}{}Request", service_name, method_name);
-                let mut combined_message = Message::new(&combined_name);
-                combined_message.add_field(Field::new(
-                    "params",
-                    &query_message_name,
-                    1,
-                    FieldRule::Optional,
-                ))?;
-                combined_message.add_field(Field::new(
-                    "body",
-                    &format!("{}{}RequestBody", service_name, method_name),
-                    2,
-                    FieldRule::Optional,
-                ))?;
-                messages.push(combined_message);
-                combined_name
-            }
-            (true, false) => query_message_name,
-            (false, true) => format!("{}{}RequestBody", service_name, method_name),
-            (false, false) => "google.protobuf.Empty".to_string(),
-        };
+/// Path part of the first server URL: `https://host/api/v2` -> `/api/v2`
+fn servers_base_path(servers: Option<&[Server]>) -> String {
+    let Some(server) = servers.and_then(|servers| servers.first()) else {
+        return String::new();
+    };
+    let url = server.resolved_url();
+    let path = match url.split_once("://") {
+        Some((_, rest)) => rest.find('/').map(|i| &rest[i..]).unwrap_or_default(),
+        None => url.as_str(),
+    };
+    path.trim_end_matches('/').to_string()
+}
 
-        Ok((request_type, messages))
-    }
+/// Escapes a key for use as a JSON pointer segment
+fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
 
-    fn generate_response_type(
-        &mut self,
-        operation: &Operation,
-        definitions: &HashMap<String, Schema>,
-        components: Option<&Components>,
-    ) -> Result<String, ConverterError> {
-        // Find first successful response (2xx)
-        let success_response = operation
-            .responses
-            .iter()
-            .find(|(code, _)| code.starts_with('2'))
-            .map(|(_, r)| r);
-
-        if let Some(response) = success_response {
-            // OpenAPI 3.0 style - check content first
-            if let Some(content) = &response.content {
-                if let Some((_, media_type)) = content.iter().next() {
-                    if let Some(schema_ref) = &media_type.schema {
-                        let type_name =
-                            self.schema_ref_to_type(schema_ref, definitions, components)?;
-
-                        // НОВЫЙ КОД: Обработка массивов
-                        if type_name.starts_with("repeated ") {
-                            let item_type = type_name.trim_start_matches("repeated ");
-                            let list_type = format!("{}List", item_type);
-
-                            if !self.generated_messages.contains_key(&list_type) {
-                                let mut list_message = Message::new(&list_type);
-                                list_message.add_field(Field::new(
-                                    "items",
-                                    &type_name,
-                                    1,
-                                    FieldRule::Optional,
-                                ))?;
-                                self.proto.add_message(list_message)?;
-                                self.generated_messages.insert(list_type.clone(), 1);
-                            }
+/// Service group of an untagged operation: the first path segment that is
+/// neither a parameter nor an `api`/version prefix
+fn first_path_segment(path: &str) -> String {
+    let is_version = |segment: &str| {
+        segment
+            .strip_prefix(['v', 'V'])
+            .is_some_and(|rest| !rest.is_empty() && rest.chars().all(|c| c.is_ascii_digit()))
+    };
 
-                            return Ok(list_type);
-                        }
+    path.split('/')
+        .find(|segment| {
+            !segment.is_empty()
+                && !segment.starts_with('{')
+                && !segment.eq_ignore_ascii_case("api")
+                && !is_version(segment)
+        })
+        .unwrap_or("Default")
+        .to_string()
+}
 
-                        return Ok(type_name);
-                    }
-                }
-            }
+/// Compact JSON of an example or default, cut to fit on a comment line
+fn example_value(value: &serde_json::Value) -> String {
+    const MAX_CHARS: usize = 80;
+    let json = value.to_string();
+    if json.chars().count() <= MAX_CHARS {
+        return json;
+    }
+    let truncated: String = json.chars().take(MAX_CHARS).collect();
+    format!("{}...", truncated)
+}
 
-            // Swagger 2.0 compatibility - check schema directly
-            if let Some(schema_ref) = &response.schema {
-                return self.schema_ref_to_type(schema_ref, definitions, components);
-            }
+fn extension_value(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
 
-            if let Some(ref_path) = &response.ref_path {
-                return Ok(self.resolve_ref_name(ref_path));
-            }
+/// Typed option value of an `x-*` extension: `ALL_CAPS` strings are taken
+/// for enum constants, objects become aggregates
+fn extension_option_value(value: &serde_json::Value) -> OptionValue {
+    match value {
+        serde_json::Value::Bool(b) => OptionValue::Bool(*b),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => OptionValue::Int(i),
+            None => OptionValue::Float(n.as_f64().unwrap_or_default()),
+        },
+        serde_json::Value::String(s)
+            if !s.is_empty()
+                && s.starts_with(|c: char| c.is_ascii_uppercase())
+                && s.chars()
+                    .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_') =>
+        {
+            OptionValue::Identifier(s.clone())
         }
-
-        Ok("google.protobuf.Empty".to_string())
+        serde_json::Value::String(s) => OptionValue::String(s.clone()),
+        serde_json::Value::Object(_) => OptionValue::Aggregate(text_format(value)),
+        other => OptionValue::String(other.to_string()),
     }
+}
 
-    fn generate_parameters_message(
-        &mut self,
-        message_name: &str,
-        parameters: Vec<&Parameter>,
-        definitions: &HashMap<String, Schema>,
-        components: Option<&Components>,
-    ) -> Result<Message, ConverterError> {
-        if let Some(message) = self.proto.find_message(message_name) {
-            return Ok(message.clone());
+/// JSON value in protobuf text format, as used inside aggregate options
+fn text_format(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Object(fields) => {
+            let fields: Vec<String> = fields
+                .iter()
+                .map(|(k, v)| format!("{}: {}", k, text_format(v)))
+                .collect();
+            format!("{{{}}}", fields.join(", "))
+        }
+        serde_json::Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(text_format).collect();
+            format!("[{}]", items.join(", "))
         }
+        serde_json::Value::String(s) => OptionValue::String(s.clone()).to_string(),
+        other => other.to_string(),
+    }
+}
 
-        let mut message = Message::new(message_name);
-        let mut field_number = 1;
+// Keeps only the `x-*` keys of a flattened object
+/// Vendor extensions consumed by enum generation rather than rendered as
+/// comments
+const ENUM_EXTENSIONS: &[&str] = &["x-enum-varnames", "x-enum-descriptions"];
 
-        for param in parameters {
-            if let Some(desc) = &param.description {
-                message.add_comment(desc);
-            }
+/// Reads a per-value `x-enum-*` list; non-string entries become `None`
+fn enum_extension<'a>(schema: &'a Schema, key: &str) -> Vec<Option<&'a str>> {
+    match schema.extensions.get(key) {
+        Some(serde_json::Value::Array(items)) => items.iter().map(|v| v.as_str()).collect(),
+        _ => Vec::new(),
+    }
+}
 
-            let proto_type = if let Some(schema_ref) = &param.schema {
-                self.schema_ref_to_type(schema_ref, definitions, components)?
-            } else {
-                match param.type_.as_deref() {
-                    Some("integer") => "int64".to_string(),
-                    Some("number") => "double".to_string(),
-                    Some("boolean") => "bool".to_string(),
-                    _ => "string".to_string(),
-                }
-            };
+fn deserialize_extensions<'de, D>(
+    deserializer: D,
+) -> Result<IndexMap<String, serde_json::Value>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let mut extensions = IndexMap::<String, serde_json::Value>::deserialize(deserializer)?;
+    extensions.retain(|key, _| key.starts_with("x-"));
+    Ok(extensions)
+}
 
-            let rule = if param.required.unwrap_or(false) {
-                FieldRule::Required
-            } else {
-                FieldRule::Optional
-            };
-            let field_name = self.sanitize_field_name(&param.name);
+/// Proto type a schema maps to; arrays become repeated fields of their items
+#[derive(Debug, Clone, PartialEq)]
+enum ProtoType {
+    Single(FieldType),
+    Repeated(FieldType),
+}
 
-            message.add_field(Field::new(&field_name, &proto_type, field_number, rule))?;
-            field_number += 1;
+impl ProtoType {
+    /// The type of a single element
+    fn into_item(self) -> FieldType {
+        match self {
+            ProtoType::Single(type_) | ProtoType::Repeated(type_) => type_,
         }
+    }
+}
 
-        Ok(message)
+impl<T: Into<FieldType>> From<T> for ProtoType {
+    fn from(type_: T) -> Self {
+        ProtoType::Single(type_.into())
     }
+}
 
-    fn generate_body_message(
-        &mut self,
-        message_name: &str,
-        request_body: &RequestBody,
-        definitions: &HashMap<String, Schema>,
-        components: Option<&Components>,
-    ) -> Result<Message, ConverterError> {
-        if let Some(message) = self.proto.find_message(message_name) {
-            return Ok(message.clone());
-        }
+/// Service and rpc an operation is converted into, for the names of its
+/// request messages
+#[derive(Debug, Clone, Copy)]
+struct RpcNames<'a> {
+    service: &'a str,
+    method: &'a str,
+}
 
-        let mut message = Message::new(message_name);
+/// Result of an rpc, see `SwaggerToProtoConverter::generate_response_type`
+struct ResponseType {
+    name: String,
+    /// Set for server-streaming rpcs
+    stream_content_type: Option<String>,
+    /// Taken from the `default` response, no 2xx had content
+    from_default: bool,
+}
 
-        if let Some(description) = &request_body.description {
-            message.add_comment(description);
+impl ResponseType {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            stream_content_type: None,
+            from_default: false,
         }
+    }
+}
+
+/// Side of an operation a schema is used on
+#[derive(Debug, Clone, Copy)]
+enum Direction {
+    Request,
+    Response,
+}
 
-        if let Some((content_type, media_type)) = request_body.content.iter().next() {
-            if let Some(schema_ref) = &media_type.schema {
-                let proto_type = self.schema_ref_to_type(schema_ref, definitions, components)?;
+/// Definitions per thread in the first batch converted ahead of
+/// `process_schemas`, and after a batch that was dropped; batches that
+/// apply in full double, up to `AHEAD_MAX_PER_THREAD`
+#[cfg(feature = "parallel")]
+const AHEAD_MIN_PER_THREAD: usize = 8;
+#[cfg(feature = "parallel")]
+const AHEAD_MAX_PER_THREAD: usize = 256;
 
-                if proto_type.contains("map<") || proto_type == "google.protobuf.Struct" {
-                    let mut field = Field::new("data", &proto_type, 1, FieldRule::Optional);
-                    field.add_option("json_name", content_type);
-                    message.add_field(field)?;
-                } else {
-                    let mut field = Field::new("data", &proto_type, 1, FieldRule::Optional);
-                    field.add_comment(&format!("Content-Type: {}", content_type));
-                    message.add_field(field)?;
+/// Definitions of one `process_schemas` call converted ahead on forks of
+/// the converter, a batch at a time
+#[cfg(feature = "parallel")]
+struct Ahead<'s> {
+    /// `None` without `parallelism`
+    pool: Option<rayon::ThreadPool>,
+    /// The definitions `process_schemas` converts, in order
+    pending: Vec<(&'s String, &'s Schema)>,
+    /// Index in `pending` of the next definition to convert
+    cursor: usize,
+    /// Definitions per thread in the next batch
+    batch: usize,
+    /// The current batch, by schema name
+    results: HashMap<&'s str, Detached>,
+}
+
+/// A definition converted on a fork, see
+/// `SwaggerToProtoConverter::convert_ahead`
+#[cfg(feature = "parallel")]
+struct Detached {
+    /// `None` if the conversion failed
+    message: Option<Message>,
+    /// Types generated on the way, in the order they were added
+    messages: Vec<Message>,
+    enums: Vec<Enum>,
+    imports: Vec<String>,
+    /// Names allocated on the fork
+    names: Vec<String>,
+    list_wrappers: Vec<(String, String)>,
+    inline_enums: Vec<(Vec<String>, String)>,
+    inline_messages: Vec<(String, String)>,
+    warnings: Vec<ConversionWarning>,
+}
+
+/// A message whose schema is being converted
+struct MessageScope {
+    name: String,
+    // dotted path from the package, e.g. `User.Address`
+    path: String,
+    nested: Vec<Message>,
+}
+
+/// Shape of an inline schema with documentation stripped, so that copies of
+/// the same object in different operations map to one message
+fn structural_fingerprint(schema: &Schema) -> String {
+    fn strip_docs(schema: &mut serde_json::Value) {
+        let Some(map) = schema.as_object_mut() else {
+            return;
+        };
+        for key in ["description", "example", "default"] {
+            map.remove(key);
+        }
+        for (key, child) in map.iter_mut() {
+            match (key.as_str(), child) {
+                // Keys of `properties` are property names, not schema keywords
+                ("properties", serde_json::Value::Object(properties)) => {
+                    properties.values_mut().for_each(strip_docs)
                 }
-            } else {
-                message.add_field(Field::new("data", "string", 1, FieldRule::Optional))?;
+                (_, serde_json::Value::Array(items)) => items.iter_mut().for_each(strip_docs),
+                (_, child) => strip_docs(child),
             }
-        } else {
-            message.add_comment("No content schema defined");
         }
-
-        Ok(message)
     }
 
-    fn collect_operations<'a>(
-        &self,
-        services: &mut BTreeMap<String, Vec<(String, String, &'a Operation)>>,
-        path: &str,
-        method: &str,
-        operation: Option<&'a Operation>,
-    ) {
-        if let Some(op) = operation {
-            let default_tags = vec!["Default".to_string()];
-            let tags = op.tags.as_ref().unwrap_or(&default_tags);
+    let mut value = serde_json::to_value(schema).unwrap_or_default();
+    strip_docs(&mut value);
+    value.to_string()
+}
 
-            for tag in tags {
-                services.entry(tag.clone()).or_default().push((
-                    path.to_string(),
-                    method.to_string(),
-                    op,
-                ));
-            }
-        }
+/// The protoc-gen-validate rule set for a field type; wrappers are
+/// validated by the rules of the wrapped scalar
+fn validation_type(type_: &FieldType) -> &'static str {
+    match type_ {
+        FieldType::Scalar(ScalarType::Bool) => "",
+        FieldType::Scalar(scalar) => scalar.as_str(),
+        FieldType::Named(name) => match name.as_str() {
+            "google.protobuf.DoubleValue" => "double",
+            "google.protobuf.FloatValue" => "float",
+            "google.protobuf.Int64Value" => "int64",
+            "google.protobuf.UInt64Value" => "uint64",
+            "google.protobuf.Int32Value" => "int32",
+            "google.protobuf.UInt32Value" => "uint32",
+            "google.protobuf.StringValue" => "string",
+            "google.protobuf.BytesValue" => "bytes",
+            _ => "",
+        },
+        FieldType::Map { .. } => "",
     }
+}
 
-    fn generate_method_name(&self, path: &str, http_method: &str, operation: &Operation) -> String {
-        operation.operation_id.as_ref().map_or_else(
-            || {
-                let clean_path = path
-                    .trim_matches('/')
-                    .replace(['/', '{', '}'], "_")
-                    .replace(|c: char| !c.is_alphanumeric(), "");
-                format!("{}{}", http_method, self.to_pascal_case(&clean_path))
-            },
-            |id| self.to_pascal_case(id),
-        )
+/// The 2xx response that becomes the rpc result: the lowest status code,
+/// then ranges such as `2XX`
+fn success_code(operation: &Operation) -> Option<&String> {
+    operation
+        .responses
+        .keys()
+        .filter(|code| code.starts_with('2'))
+        .min_by_key(|code| code.parse::<u16>().unwrap_or(u16::MAX))
+}
+
+/// Picks the media type to convert: `application/json`, then `+json`
+/// types, then `*/*`, then the first of the rest in document order
+fn preferred_media_type(content: &IndexMap<String, MediaType>) -> Option<(&String, &MediaType)> {
+    content
+        .iter()
+        .min_by_key(|(content_type, _)| media_type_rank(content_type))
+}
+
+fn media_type_rank(content_type: &str) -> u8 {
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+
+    if essence == "application/json" {
+        0
+    } else if essence.ends_with("+json") {
+        1
+    } else if essence == "*/*" {
+        2
+    } else {
+        3
     }
+}
 
-    fn resolve_schema_ref(
-        &self,
-        schema_ref: &SchemaRef,
-        definitions: &HashMap<String, Schema>,
-        components: Option<&Components>,
-    ) -> Result<Schema, ConverterError> {
-        match schema_ref {
-            SchemaRef::Ref { ref_path } => {
-                let ref_name = ref_path
-                    .split('/')
-                    .last()
-                    .ok_or_else(|| ConverterError::MissingReference(ref_path.clone()))?;
+fn is_json_content_type(content_type: &str) -> bool {
+    media_type_rank(content_type) < 3
+}
 
-                // Check definitions (Swagger 2.0)
-                if let Some(schema) = definitions.get(ref_name) {
-                    return Ok(schema.clone());
-                }
+fn is_streaming_content_type(content_type: &str) -> bool {
+    matches!(
+        content_type.split(';').next().unwrap_or_default().trim(),
+        "text/event-stream" | "application/x-ndjson" | "application/jsonl"
+    )
+}
 
-                // Check components (OpenAPI 3.0)
-                if let Some(components) = components {
-                    if let Some(schemas) = &components.schemas {
-                        if let Some(schema) = schemas.get(ref_name) {
-                            return Ok(schema.clone());
-                        }
-                    }
-                }
+fn is_form_content_type(content_type: &str) -> bool {
+    matches!(
+        content_type,
+        "multipart/form-data" | "application/x-www-form-urlencoded"
+    )
+}
 
-                Err(ConverterError::MissingReference(ref_path.clone()))
-            }
-            SchemaRef::Inline(schema) => Ok(*schema.clone()),
+/// Either an inline object or a `$ref` into one of the shared sections
+/// (`components/parameters`, `components/responses`, ...)
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(untagged)]
+enum RefOr<T> {
+    Ref {
+        #[serde(rename = "$ref")]
+        ref_path: String,
+    },
+    Item(T),
+}
+
+impl<T> RefOr<T> {
+    /// Resolves `#/components/<section>/<name>` (OpenAPI 3) or
+    /// `#/<section>/<name>` (Swagger 2.0) against the given section.
+    fn resolve<'a>(
+        &'a self,
+        shared: Option<&'a IndexMap<String, T>>,
+        section: &str,
+    ) -> Result<&'a T, ConverterError> {
+        match self {
+            RefOr::Item(item) => Ok(item),
+            RefOr::Ref { ref_path } => ref_path
+                .strip_prefix(&format!("#/components/{}/", section))
+                .or_else(|| ref_path.strip_prefix(&format!("#/{}/", section)))
+                .map(|name| name.replace("~1", "/").replace("~0", "~"))
+                .and_then(|name| shared?.get(&name))
+                .ok_or_else(|| ConverterError::MissingReference(ref_path.clone())),
         }
     }
+}
 
-    fn resolve_ref_name(&self, ref_path: &str) -> String {
-        ref_path
-            .split('/')
-            .last()
-            .unwrap_or("UnknownRef")
-            .to_string()
-    }
+/// Accumulated result of flattening allOf branches
+#[derive(Default)]
+struct MergedAllOf {
+    properties: IndexMap<String, Schema>,
+    required: Vec<String>,
+    // $refs on the current branch path, to detect cycles
+    visited: Vec<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -855,14 +4725,16 @@ enum SchemaRef {
     Inline(Box<Schema>),
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
 struct Schema {
     #[serde(rename = "type")]
-    type_: Option<String>,
+    type_: Option<SchemaType>,
     format: Option<String>,
     description: Option<String>,
     items: Option<Box<SchemaRef>>,
-    properties: Option<HashMap<String, Schema>>,
+    properties: Option<IndexMap<String, Schema>>,
+    #[serde(default, deserialize_with = "deserialize_additional_properties")]
     additional_properties: Option<Box<SchemaRef>>,
     required: Option<Vec<String>>,
     #[serde(rename = "enum")]
@@ -872,9 +4744,125 @@ struct Schema {
     one_of: Option<Vec<SchemaRef>>,
     all_of: Option<Vec<SchemaRef>>,
     any_of: Option<Vec<SchemaRef>>,
+    discriminator: Option<Discriminator>,
     nullable: Option<bool>,
+    read_only: Option<bool>,
+    write_only: Option<bool>,
+    deprecated: Option<bool>,
+    #[serde(rename = "const")]
+    const_value: Option<serde_json::Value>,
+    minimum: Option<serde_json::Number>,
+    maximum: Option<serde_json::Number>,
+    exclusive_minimum: Option<serde_json::Value>,
+    exclusive_maximum: Option<serde_json::Value>,
+    min_length: Option<u64>,
+    max_length: Option<u64>,
+    pattern: Option<String>,
+    min_items: Option<u64>,
+    max_items: Option<u64>,
     default: Option<serde_json::Value>,
     example: Option<serde_json::Value>,
+    #[serde(flatten, deserialize_with = "deserialize_extensions")]
+    extensions: IndexMap<String, serde_json::Value>,
+}
+
+/// `type` is a single name, or a list of names in OpenAPI 3.1
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(untagged)]
+enum SchemaType {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl Schema {
+    /// The schema type without the `"null"` of a 3.1 type list
+    fn type_name(&self) -> Option<&str> {
+        match self.type_.as_ref()? {
+            SchemaType::Single(name) => Some(name.as_str()),
+            SchemaType::Multiple(names) => names
+                .iter()
+                .map(String::as_str)
+                .find(|name| *name != "null"),
+        }
+    }
+
+    fn is_nullable(&self) -> bool {
+        let null_in_type = match &self.type_ {
+            Some(SchemaType::Multiple(names)) => names.iter().any(|name| name == "null"),
+            _ => false,
+        };
+        self.nullable.unwrap_or(false) || null_in_type || self.nullable_union().is_some()
+    }
+
+    /// A plain array or primitive, without properties, composition or enum
+    fn is_array_or_primitive(&self) -> bool {
+        let plain = self.properties.is_none()
+            && self.additional_properties.is_none()
+            && self.all_of.is_none()
+            && self.one_of.is_none()
+            && self.any_of.is_none()
+            && self.enum_values.is_none()
+            && self.ref_path.is_none();
+        plain
+            && matches!(
+                self.type_name(),
+                Some("array" | "string" | "integer" | "number" | "boolean")
+            )
+    }
+
+    /// `minimum` (or 3.1 `exclusiveMinimum`) rules out negative values
+    fn is_non_negative(&self) -> bool {
+        let at_least = |n: &serde_json::Number, bound: f64| n.as_f64().is_some_and(|n| n >= bound);
+        match (&self.exclusive_minimum, &self.minimum) {
+            (Some(serde_json::Value::Number(n)), _) => at_least(n, -1.0),
+            (_, Some(n)) => at_least(n, 0.0),
+            _ => false,
+        }
+    }
+
+    fn is_null_type(&self) -> bool {
+        matches!(&self.type_, Some(SchemaType::Single(name)) if name == "null")
+    }
+
+    /// The only alternative of a `oneOf`/`anyOf` whose other member is
+    /// `{type: "null"}`
+    fn nullable_union(&self) -> Option<&SchemaRef> {
+        let alternatives = self.one_of.as_ref().or(self.any_of.as_ref())?;
+        let is_null = |item: &SchemaRef| matches!(item, SchemaRef::Inline(s) if s.is_null_type());
+
+        match alternatives.as_slice() {
+            [a, b] if is_null(b) && !is_null(a) => Some(a),
+            [a, b] if is_null(a) && !is_null(b) => Some(b),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct Discriminator {
+    property_name: String,
+    mapping: Option<IndexMap<String, String>>,
+}
+
+// `additionalProperties` may be a boolean; `true`/`false` carry no schema
+fn deserialize_additional_properties<'de, D>(
+    deserializer: D,
+) -> Result<Option<Box<SchemaRef>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum BoolOrSchema {
+        Schema(Box<SchemaRef>),
+        Bool(serde::de::IgnoredAny),
+    }
+
+    Ok(match Option::<BoolOrSchema>::deserialize(deserializer)? {
+        Some(BoolOrSchema::Schema(schema)) => Some(schema),
+        Some(BoolOrSchema::Bool(_)) | None => None,
+    })
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -882,10 +4870,51 @@ struct SwaggerDoc {
     swagger: Option<String>,
     openapi: Option<String>,
     info: Info,
-    paths: HashMap<String, PathItem>,
-    definitions: Option<HashMap<String, Schema>>,
+    // 3.1 documents may only have webhooks
+    #[serde(default)]
+    paths: IndexMap<String, PathItem>,
+    webhooks: Option<IndexMap<String, PathItem>>,
+    definitions: Option<IndexMap<String, Schema>>,
+    parameters: Option<IndexMap<String, Parameter>>,
+    responses: Option<IndexMap<String, Response>>,
     components: Option<Components>,
     tags: Option<Vec<Tag>>,
+    security: Option<Vec<SecurityRequirement>>,
+    // Swagger 2.0
+    #[serde(rename = "securityDefinitions")]
+    security_definitions: Option<IndexMap<String, SecurityScheme>>,
+    host: Option<String>,
+    #[serde(rename = "basePath")]
+    base_path: Option<String>,
+    schemes: Option<Vec<String>>,
+    produces: Option<Vec<String>>,
+    consumes: Option<Vec<String>>,
+    // OpenAPI 3
+    servers: Option<Vec<Server>>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+struct Server {
+    url: String,
+    description: Option<String>,
+    variables: Option<IndexMap<String, ServerVariable>>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+struct ServerVariable {
+    default: String,
+}
+
+impl Server {
+    /// The URL with every `{variable}` replaced by its default
+    fn resolved_url(&self) -> String {
+        self.variables
+            .iter()
+            .flatten()
+            .fold(self.url.clone(), |url, (name, variable)| {
+                url.replace(&format!("{{{}}}", name), &variable.default)
+            })
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -917,12 +4946,24 @@ struct Tag {
 }
 
 #[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 struct Components {
-    schemas: Option<HashMap<String, Schema>>,
-    responses: Option<HashMap<String, Response>>,
-    parameters: Option<HashMap<String, Parameter>>,
-    examples: Option<HashMap<String, Example>>,
-    request_bodies: Option<HashMap<String, RequestBody>>,
+    schemas: Option<IndexMap<String, Schema>>,
+    responses: Option<IndexMap<String, Response>>,
+    parameters: Option<IndexMap<String, Parameter>>,
+    examples: Option<IndexMap<String, Example>>,
+    request_bodies: Option<IndexMap<String, RequestBody>>,
+    headers: Option<IndexMap<String, Header>>,
+    security_schemes: Option<IndexMap<String, SecurityScheme>>,
+}
+
+/// Scheme name -> required scopes
+type SecurityRequirement = IndexMap<String, Vec<String>>;
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+struct SecurityScheme {
+    #[serde(rename = "type")]
+    type_: String,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -935,22 +4976,97 @@ struct PathItem {
     head: Option<Operation>,
     options: Option<Operation>,
     trace: Option<Operation>,
-    parameters: Option<Vec<Parameter>>,
+    parameters: Option<Vec<RefOr<Parameter>>>,
+    servers: Option<Vec<Server>>,
     #[serde(rename = "$ref")]
     ref_path: Option<String>,
 }
 
+impl PathItem {
+    /// Operations converted to rpcs, with their HTTP methods
+    fn operations(&self) -> impl Iterator<Item = (&'static str, &Operation)> {
+        [
+            ("GET", &self.get),
+            ("POST", &self.post),
+            ("PUT", &self.put),
+            ("DELETE", &self.delete),
+            ("PATCH", &self.patch),
+        ]
+        .into_iter()
+        .filter_map(|(method, operation)| Some((method, operation.as_ref()?)))
+    }
+
+    /// Copies the path-level parameters and servers into every operation;
+    /// an operation parameter with the same name and location, or servers
+    /// of its own, take precedence.
+    fn with_path_defaults(
+        &self,
+        shared: Option<&IndexMap<String, Parameter>>,
+    ) -> Result<PathItem, ConverterError> {
+        let mut item = self.clone();
+        let path_servers = item.servers.take();
+        let path_params = item.parameters.take();
+
+        let operations = [
+            &mut item.get,
+            &mut item.post,
+            &mut item.put,
+            &mut item.delete,
+            &mut item.patch,
+            &mut item.head,
+            &mut item.options,
+            &mut item.trace,
+        ];
+        for operation in operations.into_iter().flatten() {
+            if operation.servers.is_none() {
+                operation.servers = path_servers.clone();
+            }
+            let Some(path_params) = &path_params else {
+                continue;
+            };
+
+            let own = operation.parameters.take().unwrap_or_default();
+            let mut overridden = Vec::new();
+            for param in &own {
+                let param = param.resolve(shared, "parameters")?;
+                overridden.push((param.name.as_str(), param.in_.as_str()));
+            }
+
+            let mut merged = Vec::new();
+            for param in path_params {
+                let resolved = param.resolve(shared, "parameters")?;
+                if !overridden.contains(&(resolved.name.as_str(), resolved.in_.as_str())) {
+                    merged.push(param.clone());
+                }
+            }
+            merged.extend(own.iter().cloned());
+            operation.parameters = Some(merged);
+        }
+
+        Ok(item)
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
 struct Operation {
     tags: Option<Vec<String>>,
     summary: Option<String>,
     description: Option<String>,
     operation_id: Option<String>,
-    parameters: Option<Vec<Parameter>>,
-    request_body: Option<RequestBody>,
-    responses: HashMap<String, Response>,
+    parameters: Option<Vec<RefOr<Parameter>>>,
+    request_body: Option<RefOr<RequestBody>>,
+    responses: IndexMap<String, RefOr<Response>>,
+    // Swagger 2.0, override the top-level lists
+    produces: Option<Vec<String>>,
+    consumes: Option<Vec<String>>,
     deprecated: Option<bool>,
-    security: Option<Vec<HashMap<String, Vec<String>>>>,
+    security: Option<Vec<SecurityRequirement>>,
+    servers: Option<Vec<Server>>,
+    // callback name -> URL expression -> operations
+    callbacks: Option<IndexMap<String, IndexMap<String, PathItem>>>,
+    #[serde(flatten, deserialize_with = "deserialize_extensions")]
+    extensions: IndexMap<String, serde_json::Value>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -960,17 +5076,23 @@ struct Parameter {
     in_: String,
     description: Option<String>,
     required: Option<bool>,
+    deprecated: Option<bool>,
     schema: Option<SchemaRef>,
     #[serde(rename = "type")]
     type_: Option<String>,
     format: Option<String>,
+    items: Option<SchemaRef>,
+    #[serde(rename = "collectionFormat")]
+    collection_format: Option<String>,
     default: Option<serde_json::Value>,
+    #[serde(flatten, deserialize_with = "deserialize_extensions")]
+    extensions: IndexMap<String, serde_json::Value>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 struct RequestBody {
     description: Option<String>,
-    content: HashMap<String, MediaType>,
+    content: IndexMap<String, MediaType>,
     required: Option<bool>,
 }
 
@@ -978,10 +5100,11 @@ struct RequestBody {
 struct MediaType {
     schema: Option<SchemaRef>,
     example: Option<serde_json::Value>,
-    examples: Option<HashMap<String, Example>>,
+    examples: Option<IndexMap<String, Example>>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
 struct Example {
     summary: Option<String>,
     description: Option<String>,
@@ -992,10 +5115,8 @@ struct Example {
 #[derive(Debug, Deserialize, Serialize, Clone)]
 struct Response {
     description: String,
-    content: Option<HashMap<String, MediaType>>,
-    #[serde(rename = "$ref")]
-    ref_path: Option<String>,
-    headers: Option<HashMap<String, Header>>,
+    content: Option<IndexMap<String, MediaType>>,
+    headers: Option<IndexMap<String, RefOr<Header>>>,
     // For Swagger 2.0 compatibility:
     schema: Option<SchemaRef>,
 }
@@ -1003,7 +5124,49 @@ struct Response {
 #[derive(Debug, Deserialize, Serialize, Clone)]
 struct Header {
     description: Option<String>,
+    required: Option<bool>,
+    // OpenAPI 3
+    schema: Option<SchemaRef>,
+    // Swagger 2.0
     #[serde(rename = "type")]
-    type_: String,
+    type_: Option<String>,
     format: Option<String>,
+    items: Option<SchemaRef>,
+    #[serde(rename = "enum")]
+    enum_values: Option<Vec<serde_json::Value>>,
+}
+
+impl Header {
+    /// The OpenAPI 3 schema, or one built from the Swagger 2.0 fields
+    fn schema_ref(&self) -> SchemaRef {
+        if let Some(schema) = &self.schema {
+            return schema.clone();
+        }
+        SchemaRef::Inline(Box::new(Schema {
+            type_: Some(SchemaType::Single(
+                self.type_.clone().unwrap_or_else(|| "string".to_string()),
+            )),
+            format: self.format.clone(),
+            items: self.items.clone().map(Box::new),
+            enum_values: self.enum_values.clone(),
+            ..Schema::default()
+        }))
+    }
+
+    /// `integer (int32)`, `array of string`, or the schema name for a `$ref`
+    fn type_description(&self) -> String {
+        describe_schema_ref(&self.schema_ref())
+    }
+}
+
+fn describe_schema_ref(schema_ref: &SchemaRef) -> String {
+    match schema_ref {
+        SchemaRef::Ref { ref_path } => ref_path.rsplit('/').next().unwrap_or_default().to_string(),
+        SchemaRef::Inline(schema) => match (schema.type_name(), &schema.format, &schema.items) {
+            (Some("array"), _, Some(items)) => format!("array of {}", describe_schema_ref(items)),
+            (Some(type_), Some(format), _) => format!("{} ({})", type_, format),
+            (Some(type_), None, _) => type_.to_string(),
+            (None, _, _) => "any".to_string(),
+        },
+    }
 }
@@ -0,0 +1,214 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::frozen::referenced_types;
+use crate::util::join_path;
+use crate::{
+    Enum, Field, FieldRule, Message, ProtoFile, ResolvedType, Syntax, TypeResolver, ValidationError,
+};
+
+/// Largest field number protobuf accepts (2^29 - 1)
+pub(crate) const MAX_FIELD_NUMBER: i32 = 536_870_911;
+
+/// Field numbers reserved for the protobuf implementation
+const RESERVED_FIELD_NUMBERS: std::ops::RangeInclusive<i32> = 19000..=19999;
+
+impl ProtoFile {
+    /// Checks the model for constructs that protoc would reject: dangling
+    /// type references, duplicate names, illegal field numbers, enum values
+    /// sharing a number without `allow_alias`, proto3 enums without a zero
+    /// first value, empty oneofs, and `required` labels or
+    /// `[default = ...]` options in proto3.
+    ///
+    /// `google.protobuf` types resolve only when their file is imported.
+    /// Types of other packages are trusted if the file imports anything
+    /// besides the well-known types, since those files are not loaded.
+    ///
+    /// Returns every problem found rather than stopping at the first one.
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let mut validator = Validator {
+            file: self,
+            types: self.type_resolver(),
+            names: HashSet::new(),
+            errors: Vec::new(),
+        };
+
+        for message in &self.messages {
+            validator.message(message, "");
+        }
+        for enum_def in &self.enums {
+            validator.enum_def(enum_def, "");
+        }
+        for service in &self.services {
+            validator.define(&service.name);
+            for method in &service.methods {
+                let path = self.qualified_name(&join_path(&service.name, &method.name));
+                validator.type_reference(referenced_types(&method.input_type), "", &path);
+                validator.type_reference(referenced_types(&method.output_type), "", &path);
+            }
+        }
+
+        if validator.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(validator.errors)
+        }
+    }
+}
+
+struct Validator<'a> {
+    file: &'a ProtoFile,
+    types: TypeResolver<'a>,
+    /// Names defined so far, relative to the package; enum values live in
+    /// the scope of their enum's parent
+    names: HashSet<String>,
+    errors: Vec<ValidationError>,
+}
+
+impl Validator<'_> {
+    /// Registers a definition, reporting it if the name is taken
+    fn define(&mut self, path: &str) {
+        if !self.names.insert(path.to_string()) {
+            self.errors.push(ValidationError::DuplicateName {
+                path: self.file.qualified_name(path),
+            });
+        }
+    }
+
+    fn message(&mut self, message: &Message, prefix: &str) {
+        let path = join_path(prefix, &message.name);
+        self.define(&path);
+
+        let mut names = HashSet::new();
+        let mut numbers: HashMap<i32, &str> = HashMap::new();
+        for field in message.all_fields() {
+            let field_path = self.file.qualified_name(&join_path(&path, &field.name));
+            if !names.insert(&field.name) {
+                self.errors.push(ValidationError::DuplicateName {
+                    path: field_path.clone(),
+                });
+            }
+            if let Some(other) = numbers.insert(field.number, &field.name) {
+                self.errors.push(ValidationError::DuplicateFieldNumber {
+                    path: field_path.clone(),
+                    number: field.number,
+                    other: other.to_string(),
+                });
+            }
+            self.field(field, &path, &field_path);
+        }
+
+        for oneof in &message.oneofs {
+            if oneof.fields.is_empty() {
+                self.errors.push(ValidationError::EmptyOneof {
+                    path: self.file.qualified_name(&join_path(&path, &oneof.name)),
+                });
+            }
+        }
+
+        for nested in &message.nested_messages {
+            self.message(nested, &path);
+        }
+        for enum_def in &message.nested_enums {
+            self.enum_def(enum_def, &path);
+        }
+    }
+
+    fn field(&mut self, field: &Field, scope: &str, path: &str) {
+        if field.number < 1
+            || field.number > MAX_FIELD_NUMBER
+            || RESERVED_FIELD_NUMBERS.contains(&field.number)
+        {
+            self.errors.push(ValidationError::InvalidFieldNumber {
+                path: path.to_string(),
+                number: field.number,
+            });
+        }
+        self.type_reference(field.type_.referenced_types(), scope, path);
+
+        if self.file.syntax == Syntax::Proto3.as_str() {
+            let mut unsupported = Vec::new();
+            if field.rule == FieldRule::Required {
+                unsupported.push("required label");
+            }
+            if field.options.contains_key("default") {
+                unsupported.push("explicit default");
+            }
+            for construct in unsupported {
+                self.errors.push(ValidationError::UnsupportedInSyntax {
+                    path: path.to_string(),
+                    construct: construct.to_string(),
+                    syntax: self.file.syntax.clone(),
+                });
+            }
+        }
+    }
+
+    fn enum_def(&mut self, enum_def: &Enum, prefix: &str) {
+        let path = join_path(prefix, &enum_def.name);
+        self.define(&path);
+
+        let mut numbers: HashMap<i32, &str> = HashMap::new();
+        for value in &enum_def.values {
+            self.define(&join_path(prefix, &value.name));
+            if let Some(other) = numbers.insert(value.number, &value.name)
+                && !enum_def.allows_alias()
+            {
+                self.errors.push(ValidationError::DuplicateEnumNumber {
+                    path: self.file.qualified_name(&join_path(&path, &value.name)),
+                    number: value.number,
+                    other: other.to_string(),
+                });
+            }
+        }
+
+        if self.file.syntax == "proto3" {
+            let qualified = self.file.qualified_name(&path);
+            match enum_def.values.first() {
+                None => self
+                    .errors
+                    .push(ValidationError::EmptyEnum { path: qualified }),
+                Some(first) if first.number != 0 => {
+                    self.errors.push(ValidationError::EnumFirstValueNotZero {
+                        path: qualified,
+                        found: first.number,
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+    }
+
+    /// Reports the type names of a field or rpc that resolve to nothing
+    fn type_reference(&mut self, names: Vec<&str>, scope: &str, path: &str) {
+        for name in names {
+            match self.types.resolve(name, scope) {
+                ResolvedType::WellKnown(import)
+                    if !self.file.imports.iter().any(|i| i == import) =>
+                {
+                    self.errors.push(ValidationError::MissingImport {
+                        path: path.to_string(),
+                        type_name: name.to_string(),
+                        import: import.to_string(),
+                    });
+                }
+                ResolvedType::Unresolved if !self.is_imported(name) => {
+                    self.errors.push(ValidationError::UnresolvedType {
+                        path: path.to_string(),
+                        type_name: name.to_string(),
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// A qualified type from another package, declared in an imported file
+    fn is_imported(&self, name: &str) -> bool {
+        let has_other_imports = self
+            .file
+            .imports
+            .iter()
+            .any(|i| !i.starts_with("google/protobuf/"));
+        has_other_imports && name.contains('.') && !name.starts_with("google.protobuf.")
+    }
+}
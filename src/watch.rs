@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use notify::{RecursiveMode, Watcher};
+
+use crate::batch::read_spec;
+use crate::{ConverterBuilder, ConverterError, Error};
+
+/// Quiet period after the last filesystem event before converting; editors
+/// tend to write a file in several steps
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// How often the stop flag is checked while waiting for events
+const POLL: Duration = Duration::from_millis(100);
+
+/// Converts `input` into `output` and again after every change of `input`,
+/// until Ctrl-C (SIGINT) or SIGTERM.
+///
+/// Every run uses a fresh converter built from `builder`. Results are
+/// printed as one line each; conversion errors are printed too and the
+/// previous output is kept.
+pub fn watch_and_convert(
+    input: &Path,
+    output: &Path,
+    builder: &ConverterBuilder,
+) -> Result<(), Error> {
+    let stop = Arc::new(AtomicBool::new(false));
+    for signal in [signal_hook::consts::SIGINT, signal_hook::consts::SIGTERM] {
+        signal_hook::flag::register(signal, Arc::clone(&stop))?;
+    }
+    watch_until(input, output, builder, &stop)
+}
+
+/// [`watch_and_convert`] stopped by setting `stop` instead of a signal
+pub fn watch_until(
+    input: &Path,
+    output: &Path,
+    builder: &ConverterBuilder,
+    stop: &AtomicBool,
+) -> Result<(), Error> {
+    let file_name = input.file_name().map(|name| name.to_os_string());
+    // Редакторы заменяют файл целиком, поэтому следим за каталогом
+    let dir = match input.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    let (sender, events) = mpsc::channel::<notify::Result<notify::Event>>();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+
+    let mut previous = std::fs::read_to_string(output).ok();
+    run(input, output, builder, &mut previous);
+
+    let mut changed_at: Option<Instant> = None;
+    while !stop.load(Ordering::Relaxed) {
+        match events.recv_timeout(POLL) {
+            Ok(Ok(event)) => {
+                let touches_input = event
+                    .paths
+                    .iter()
+                    .any(|path| path.file_name().map(|n| n.to_os_string()) == file_name);
+                if touches_input && !event.kind.is_access() {
+                    changed_at = Some(Instant::now());
+                }
+            }
+            Ok(Err(error)) => eprintln!("watch error: {}", error),
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+
+        if changed_at.is_some_and(|at| at.elapsed() >= DEBOUNCE) {
+            changed_at = None;
+            run(input, output, builder, &mut previous);
+        }
+    }
+    Ok(())
+}
+
+/// One conversion, reported on a single line
+fn run(input: &Path, output: &Path, builder: &ConverterBuilder, previous: &mut Option<String>) {
+    let started = Instant::now();
+    match convert(input, output, builder, previous.as_deref()) {
+        Ok(text) => {
+            let summary = match previous.as_deref() {
+                Some(old) if old == text => "unchanged".to_string(),
+                Some(old) => {
+                    let (added, removed) = line_changes(old, &text);
+                    format!("+{} -{} lines", added, removed)
+                }
+                None => format!("{} lines", text.lines().count()),
+            };
+            println!(
+                "{} -> {}: {} ({} ms)",
+                input.display(),
+                output.display(),
+                summary,
+                started.elapsed().as_millis()
+            );
+            *previous = Some(text);
+        }
+        Err(error) => println!("{}", error),
+    }
+}
+
+fn convert(
+    input: &Path,
+    output: &Path,
+    builder: &ConverterBuilder,
+    previous: Option<&str>,
+) -> Result<String, ConverterError> {
+    let mut builder = builder.clone();
+    if builder.base_dir.is_none() {
+        builder.base_dir = input.parent().map(Path::to_path_buf);
+    }
+    let mut converter = builder.build();
+    let text = converter
+        .convert_value(read_spec(input)?)
+        .map_err(|e| e.in_file(input))?
+        .to_proto_text();
+    if previous != Some(text.as_str()) {
+        std::fs::write(output, &text).map_err(|e| ConverterError::from(e).in_file(output))?;
+    }
+    Ok(text)
+}
+
+/// Lines only in `new` and lines only in `old`, counting repeats
+fn line_changes(old: &str, new: &str) -> (usize, usize) {
+    let mut counts: HashMap<&str, isize> = HashMap::new();
+    for line in new.lines() {
+        *counts.entry(line).or_default() += 1;
+    }
+    for line in old.lines() {
+        *counts.entry(line).or_default() -= 1;
+    }
+    let added = counts
+        .values()
+        .filter(|&&c| c > 0)
+        .map(|&c| c as usize)
+        .sum();
+    let removed = counts
+        .values()
+        .filter(|&&c| c < 0)
+        .map(|&c| (-c) as usize)
+        .sum();
+    (added, removed)
+}
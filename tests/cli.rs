@@ -0,0 +1,45 @@
+#![cfg(feature = "cli")]
+
+mod common;
+
+use std::process::{Command, Output};
+
+use common::{assert_golden, fixture_path};
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_dot_proto_parser"))
+        .args(args)
+        .output()
+        .expect("binary runs")
+}
+
+fn parse(flags: &[&str]) -> String {
+    let file = fixture_path("formatting.proto");
+    let mut args = vec!["parse", file.to_str().unwrap()];
+    args.extend_from_slice(flags);
+    let output = run(&args);
+    assert!(output.status.success(), "{output:?}");
+    assert!(output.stderr.is_empty(), "{output:?}");
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn parse_json_output() {
+    assert_golden("formatting.parse.json", &parse(&["--json"]));
+}
+
+#[test]
+fn parse_services_only_output() {
+    assert_golden(
+        "formatting.services.json",
+        &parse(&["--format", "json", "--services-only"]),
+    );
+}
+
+#[test]
+fn parse_errors_go_to_stderr() {
+    let output = run(&["parse", "missing.proto", "--json"]);
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("missing.proto"));
+}
@@ -0,0 +1,37 @@
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+
+use dot_proto_parser::{ConverterBuilder, ProtoFile, SwaggerToProtoConverter};
+
+pub fn fixture_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+pub fn fixture(name: &str) -> String {
+    std::fs::read_to_string(fixture_path(name)).expect("fixture exists")
+}
+
+/// Converts `spec` with the default options
+pub fn convert(spec: &str) -> ProtoFile {
+    convert_with(SwaggerToProtoConverter::builder(), spec)
+}
+
+pub fn convert_with(builder: ConverterBuilder, spec: &str) -> ProtoFile {
+    let mut converter = builder.build();
+    converter.convert_str(spec).expect("spec converts").clone()
+}
+
+/// Compares `actual` with `tests/fixtures/<name>`; `UPDATE_GOLDEN=1`
+/// rewrites the file instead
+pub fn assert_golden(name: &str, actual: &str) {
+    let path = fixture_path(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&path, actual).expect("golden file is writable");
+        return;
+    }
+    let expected = std::fs::read_to_string(&path).expect("golden file exists");
+    assert_eq!(actual, expected, "output differs from {}", path.display());
+}
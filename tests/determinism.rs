@@ -0,0 +1,81 @@
+mod common;
+
+use std::path::PathBuf;
+
+use common::{convert, fixture, fixture_path};
+use dot_proto_parser::SwaggerToProtoConverter;
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("dot_proto_parser_{}_{}", std::process::id(), name))
+}
+
+#[test]
+fn repeated_convert_file_is_byte_identical() {
+    let input = fixture_path("inline_types.json");
+    let outputs: Vec<Vec<u8>> = (0..2)
+        .map(|run| {
+            let output = temp_path(&format!("inline_types_{}.proto", run));
+            SwaggerToProtoConverter::builder()
+                .build()
+                .convert_file(&input, &output)
+                .unwrap();
+            let bytes = std::fs::read(&output).unwrap();
+            std::fs::remove_file(&output).unwrap();
+            bytes
+        })
+        .collect();
+
+    assert_eq!(outputs[0], outputs[1]);
+}
+
+#[test]
+fn inline_types_are_named_from_their_context() {
+    let proto = convert(&fixture("inline_types.json"));
+
+    for name in [
+        "OrderAddress",
+        "OrderAddressGeo",
+        "OrderLinesItem",
+        "CustomerAddress",
+    ] {
+        assert!(proto.find_message(name).is_some(), "message {}", name);
+    }
+    for name in ["OrderStatus", "OrderLinesItemKind", "CustomerTier"] {
+        assert!(proto.find_enum(name).is_some(), "enum {}", name);
+    }
+    let text = proto.to_proto_text();
+    assert!(!text.contains("Enum_") && !text.contains("NestedObject_"));
+}
+
+#[test]
+fn fields_are_numbered_in_document_order() {
+    let spec = fixture("wide_schema.json");
+    let document: serde_json::Value = serde_json::from_str(&spec).unwrap();
+    let properties: Vec<&String> = document["definitions"]["Wide"]["properties"]
+        .as_object()
+        .unwrap()
+        .keys()
+        .collect();
+    assert!(properties.len() >= 20);
+
+    let outputs: Vec<String> = (0..5).map(|_| convert(&spec).to_proto_text()).collect();
+    assert!(outputs.windows(2).all(|pair| pair[0] == pair[1]));
+
+    let proto = convert(&spec);
+    let wide = proto.find_message("Wide").unwrap();
+    let fields: Vec<(&str, i32)> = wide
+        .fields
+        .iter()
+        .map(|f| (f.name.as_str(), f.number))
+        .collect();
+    let expected: Vec<(&str, i32)> = properties
+        .iter()
+        .map(|name| name.as_str())
+        .zip(1..)
+        .collect();
+    assert_eq!(fields, expected);
+
+    let derived = proto.find_message("Derived").unwrap();
+    let names: Vec<&str> = derived.fields.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(names, ["omega", "beta", "nu", "epsilon"]);
+}
@@ -0,0 +1,261 @@
+use dot_proto_parser::ProtoParser;
+use dot_proto_parser::diff::{Change, compare};
+
+const BASE: &str = r#"
+syntax = "proto2";
+package shop.v1;
+
+message Order {
+  message Item {
+    optional string sku = 1;
+    optional int32 quantity = 2;
+  }
+  optional string id = 1;
+  optional int64 total = 2;
+  repeated Item items = 3;
+  optional string note = 4;
+  optional Status status = 5;
+}
+
+enum Status {
+  STATUS_UNSPECIFIED = 0;
+  STATUS_OPEN = 1;
+  STATUS_CLOSED = 2;
+}
+
+service Orders {
+  rpc GetOrder (Order) returns (Order);
+  rpc DeleteOrder (Order) returns (Order);
+}
+"#;
+
+fn changes(old: &str, new: &str) -> Vec<Change> {
+    let old = ProtoParser::new().parse(old).unwrap();
+    let new = ProtoParser::new().parse(new).unwrap();
+    compare(&old, &new)
+}
+
+/// Changes of `BASE` after replacing `from` with `to`
+fn edit(from: &str, to: &str) -> Vec<Change> {
+    assert!(BASE.contains(from), "{from}");
+    changes(BASE, &BASE.replacen(from, to, 1))
+}
+
+fn breaking(changes: &[Change]) -> bool {
+    changes.iter().any(Change::is_breaking)
+}
+
+#[test]
+fn identical_files_have_no_changes() {
+    assert_eq!(changes(BASE, BASE), []);
+}
+
+#[test]
+fn field_removed() {
+    let changes = edit("  optional string note = 4;\n", "");
+    assert_eq!(
+        changes,
+        [Change::FieldRemoved {
+            message: "shop.v1.Order".to_string(),
+            field: "note".to_string(),
+            number: 4,
+        }]
+    );
+    assert!(breaking(&changes));
+}
+
+#[test]
+fn field_number_reused_with_another_type() {
+    let changes = edit("optional string note = 4;", "optional int32 priority = 4;");
+    assert_eq!(
+        changes,
+        [Change::FieldNumberReused {
+            message: "shop.v1.Order".to_string(),
+            number: 4,
+            old_field: "note".to_string(),
+            new_field: "priority".to_string(),
+            old_type: "string".to_string(),
+            new_type: "int32".to_string(),
+        }]
+    );
+    assert!(breaking(&changes));
+}
+
+#[test]
+fn field_type_changed() {
+    let changes = edit("optional int64 total = 2;", "optional double total = 2;");
+    assert_eq!(
+        changes,
+        [Change::FieldTypeChanged {
+            message: "shop.v1.Order".to_string(),
+            field: "total".to_string(),
+            old: "int64".to_string(),
+            new: "double".to_string(),
+        }]
+    );
+    assert!(breaking(&changes));
+}
+
+#[test]
+fn field_number_changed() {
+    let changes = edit("optional string note = 4;", "optional string note = 6;");
+    assert_eq!(
+        changes,
+        [Change::FieldNumberChanged {
+            message: "shop.v1.Order".to_string(),
+            field: "note".to_string(),
+            old: 4,
+            new: 6,
+        }]
+    );
+    assert!(breaking(&changes));
+}
+
+#[test]
+fn field_became_required() {
+    let changes = edit("optional string id = 1;", "required string id = 1;");
+    assert_eq!(
+        changes,
+        [Change::FieldBecameRequired {
+            message: "shop.v1.Order".to_string(),
+            field: "id".to_string(),
+        }]
+    );
+    assert!(breaking(&changes));
+}
+
+#[test]
+fn enum_value_number_changed() {
+    let changes = edit("STATUS_CLOSED = 2;", "STATUS_CLOSED = 3;");
+    assert_eq!(
+        changes,
+        [Change::EnumValueNumberChanged {
+            enum_name: "shop.v1.Status".to_string(),
+            value: "STATUS_CLOSED".to_string(),
+            old: 2,
+            new: 3,
+        }]
+    );
+    assert!(breaking(&changes));
+}
+
+#[test]
+fn method_removed() {
+    let changes = edit("  rpc DeleteOrder (Order) returns (Order);\n", "");
+    assert_eq!(
+        changes,
+        [Change::MethodRemoved {
+            service: "shop.v1.Orders".to_string(),
+            method: "DeleteOrder".to_string(),
+        }]
+    );
+    assert!(breaking(&changes));
+}
+
+#[test]
+fn additions_and_renames_are_not_breaking() {
+    let new = BASE
+        .replacen(
+            "optional string note = 4;",
+            "optional string comment = 4;",
+            1,
+        )
+        .replacen(
+            "optional Status status = 5;",
+            "optional Status status = 5;\n  optional string coupon = 6;",
+            1,
+        )
+        .replacen(
+            "STATUS_CLOSED = 2;",
+            "STATUS_CLOSED = 2;\n  STATUS_HELD = 3;",
+            1,
+        )
+        .replacen(
+            "rpc DeleteOrder (Order) returns (Order);",
+            "rpc DeleteOrder (Order) returns (Order);\n  rpc ListOrders (Order) returns (Order);",
+            1,
+        );
+    let new = format!("{new}\nmessage Coupon {{}}\n\nenum Region {{ REGION_UNSPECIFIED = 0; }}\n");
+
+    let changes = changes(BASE, &new);
+    let order = || "shop.v1.Order".to_string();
+    assert_eq!(
+        changes,
+        [
+            Change::FieldRenamed {
+                message: order(),
+                number: 4,
+                old: "note".to_string(),
+                new: "comment".to_string(),
+            },
+            Change::FieldAdded {
+                message: order(),
+                field: "coupon".to_string(),
+                number: 6,
+                required: false,
+            },
+            Change::MessageAdded {
+                message: "shop.v1.Coupon".to_string(),
+            },
+            Change::EnumValueAdded {
+                enum_name: "shop.v1.Status".to_string(),
+                value: "STATUS_HELD".to_string(),
+                number: 3,
+            },
+            Change::EnumAdded {
+                enum_name: "shop.v1.Region".to_string(),
+            },
+            Change::MethodAdded {
+                service: "shop.v1.Orders".to_string(),
+                method: "ListOrders".to_string(),
+            },
+        ]
+    );
+    assert!(!breaking(&changes));
+}
+
+#[test]
+fn required_field_added_is_breaking() {
+    let changes = edit(
+        "optional Status status = 5;",
+        "optional Status status = 5;\n  required string currency = 6;",
+    );
+    assert!(matches!(
+        changes.as_slice(),
+        [Change::FieldAdded { required: true, .. }]
+    ));
+    assert!(breaking(&changes));
+}
+
+#[test]
+fn nested_messages_are_compared_by_qualified_name() {
+    // Вложенный Item и одноимённое сообщение верхнего уровня не путаются
+    let new = format!("{BASE}\nmessage Item {{\n  optional bytes sku = 1;\n}}\n");
+    let changes = changes(BASE, &new.replacen("optional int32 quantity = 2;", "", 1));
+    assert_eq!(
+        changes,
+        [
+            Change::FieldRemoved {
+                message: "shop.v1.Order.Item".to_string(),
+                field: "quantity".to_string(),
+                number: 2,
+            },
+            Change::MessageAdded {
+                message: "shop.v1.Item".to_string(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn package_change_keeps_nested_types_resolvable() {
+    let changes = edit("package shop.v1;", "package shop.v2;");
+    // `repeated Item` по-прежнему Order.Item: тип поля не менялся
+    assert_eq!(
+        changes,
+        [Change::PackageChanged {
+            old: "shop.v1".to_string(),
+            new: "shop.v2".to_string(),
+        }]
+    );
+}
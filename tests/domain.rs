@@ -0,0 +1,157 @@
+mod common;
+
+use common::fixture;
+use dot_proto_parser::{
+    Field, FieldRule, FieldType, Message, Method, OptionValue, ProtoFile, ProtoParser, ScalarType,
+    Service, ValidationError,
+};
+
+/// Model with several options on the file, a field and a method, added out
+/// of alphabetical order
+fn model_with_options() -> ProtoFile {
+    let mut proto = ProtoFile::new("shop.v1");
+    proto.add_option("java_package", "com.example.shop");
+    proto.add_option("go_package", "example.com/shop");
+    proto.add_option("csharp_namespace", "Shop");
+
+    let mut field = Field::new(
+        "name",
+        FieldType::Scalar(ScalarType::String),
+        1,
+        FieldRule::Singular,
+    );
+    field.add_option("json_name", OptionValue::String("displayName".to_string()));
+    field.add_option("deprecated", OptionValue::Bool(true));
+    field.add_option("(validate.rules).string.min_len", OptionValue::Int(1));
+    field.add_option("(custom.weight)", OptionValue::Float(1.5));
+    let mut message = Message::new("Item");
+    message.add_field(field).unwrap();
+    proto.add_message(message).unwrap();
+
+    let mut method = Method::new("GetItem", "Item", "Item");
+    method.add_option(
+        "idempotency_level",
+        OptionValue::Identifier("NO_SIDE_EFFECTS".to_string()),
+    );
+    method.add_option("deprecated", OptionValue::Bool(true));
+    method.add_option(
+        "(google.api.http)",
+        OptionValue::Aggregate("{get: \"/v1/items\"}".to_string()),
+    );
+    let mut service = Service::new("ItemService");
+    service.add_method(method).unwrap();
+    proto.add_service(service).unwrap();
+    proto
+}
+
+#[test]
+fn options_render_in_insertion_order() {
+    let proto = model_with_options();
+    let first = proto.to_proto_text();
+    let second = model_with_options().to_proto_text();
+    assert_eq!(first, second);
+    assert_eq!(first, proto.to_proto_text());
+
+    assert!(first.contains(concat!(
+        "option java_package = \"com.example.shop\";\n",
+        "option go_package = \"example.com/shop\";\n",
+        "option csharp_namespace = \"Shop\";\n",
+    )));
+    assert!(first.contains(
+        "string name = 1 [json_name = \"displayName\", deprecated = true, \
+         (validate.rules).string.min_len = 1, (custom.weight) = 1.5];"
+    ));
+    let idempotency = first.find("option idempotency_level").unwrap();
+    let deprecated = first.find("option deprecated").unwrap();
+    let http = first.find("option (google.api.http)").unwrap();
+    assert!(idempotency < deprecated && deprecated < http);
+}
+
+#[test]
+fn options_keep_their_order_through_json() {
+    let proto = model_with_options();
+    let json = serde_json::to_string(&proto).unwrap();
+    let restored: ProtoFile = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.to_proto_text(), proto.to_proto_text());
+}
+
+#[test]
+fn multi_line_comments_are_split_and_unprefixed() {
+    let mut message = Message::new("Item");
+    message.add_comment("First line\nsecond line\r\n// already commented\n");
+    message.add_comment("// // twice");
+
+    assert_eq!(
+        message.comments,
+        ["First line", "second line", "already commented", "twice"]
+    );
+    assert_eq!(
+        message.to_proto_text(0),
+        "// First line\n// second line\n// already commented\n// twice\nmessage Item {\n}\n\n"
+    );
+}
+
+#[test]
+fn slashes_inside_comments_are_kept() {
+    let mut field = Field::new(
+        "url",
+        FieldType::Scalar(ScalarType::String),
+        1,
+        FieldRule::Singular,
+    );
+    field.add_comment("See https://example.com/docs // section 2");
+    field.add_comment("Accepts */* and text/plain");
+
+    assert_eq!(
+        field.to_proto_text(0),
+        "// See https://example.com/docs // section 2\n\
+         // Accepts */* and text/plain\n\
+         string url = 1;\n"
+    );
+}
+
+/// Proto fixtures and their text as rendered by the `String`-building
+/// formatter that `write_proto_text` replaced
+const RENDERED: [(&str, &str); 3] = [
+    ("formatting.proto", "formatting.expected.proto"),
+    ("recursive.proto", "recursive.proto"),
+    ("security.proto", "security.proto"),
+];
+
+#[test]
+fn write_proto_text_matches_the_previous_output() {
+    for (input, expected) in RENDERED {
+        let proto = ProtoParser::new().parse(&fixture(input)).unwrap();
+        let expected = fixture(expected);
+
+        let mut bytes = Vec::new();
+        proto.write_proto_text(&mut bytes).unwrap();
+        assert_eq!(String::from_utf8(bytes).unwrap(), expected, "{input}");
+        assert_eq!(proto.to_proto_text(), expected, "{input}");
+    }
+}
+
+#[test]
+fn duplicate_enum_numbers_need_allow_alias() {
+    let text = |option: &str| {
+        format!(
+            "syntax = \"proto3\";\npackage shop.v1;\nmessage Order {{\n  enum State {{\n    {option}\n    \
+             STATE_UNSPECIFIED = 0;\n    STATE_OPEN = 1;\n    STATE_ACTIVE = 1;\n  }}\n}}\n"
+        )
+    };
+
+    let proto = ProtoParser::new().parse(&text("")).unwrap();
+    assert_eq!(
+        proto.validate(),
+        Err(vec![ValidationError::DuplicateEnumNumber {
+            path: "shop.v1.Order.State.STATE_ACTIVE".to_string(),
+            number: 1,
+            other: "STATE_OPEN".to_string(),
+        }])
+    );
+
+    let aliased = ProtoParser::new()
+        .parse(&text("option allow_alias = true;"))
+        .unwrap();
+    assert_eq!(aliased.validate(), Ok(()));
+}
@@ -0,0 +1,161 @@
+use std::collections::HashSet;
+
+use dot_proto_parser::{ConverterError, Error, ProtoParseError};
+
+// Матчи без `_`: новый вариант не скомпилируется, пока ему не выдан код
+
+fn error_code(error: &Error) -> &'static str {
+    match error {
+        Error::ProtoParse(error) => parse_error_code(error),
+        Error::Converter(error) => converter_error_code(error),
+        Error::Descriptor(_) => "E_DESCRIPTOR_001",
+        Error::IncompatibleModelVersion { .. } => "E_MODEL_001",
+        #[cfg(feature = "watch")]
+        Error::Watch(_) => "E_WATCH_001",
+    }
+}
+
+fn converter_error_code(error: &ConverterError) -> &'static str {
+    match error {
+        ConverterError::Io(_) => "E_IO_001",
+        ConverterError::JsonParse(_) => "E_JSON_001",
+        ConverterError::Yaml(_) => "E_YAML_001",
+        ConverterError::Toml(_) => "E_TOML_001",
+        ConverterError::UnsupportedSchemaType(_) => "E_CONVERT_001",
+        ConverterError::MissingReference(_) => "E_CONVERT_002",
+        ConverterError::InvalidArrayDefinition => "E_CONVERT_003",
+        ConverterError::CircularReference(_) => "E_CONVERT_004",
+        ConverterError::DuplicateMessageName(_) => "E_CONVERT_005",
+        ConverterError::InvalidParameterLocation(_) => "E_CONVERT_006",
+        ConverterError::UnsupportedHttpMethod(_) => "E_CONVERT_007",
+        ConverterError::InvalidFieldName(_) => "E_CONVERT_008",
+        ConverterError::InvalidFieldType(_) => "E_CONVERT_009",
+        ConverterError::ServiceNotFound(_) => "E_CONVERT_010",
+        ConverterError::MessageNotFound(_) => "E_CONVERT_011",
+        ConverterError::RemoteReference(_) => "E_CONVERT_012",
+        ConverterError::CircularExternalReference { .. } => "E_CONVERT_013",
+        ConverterError::ExternalDocument { .. } => "E_CONVERT_014",
+        ConverterError::InvalidOutput(_) => "E_CONVERT_015",
+        ConverterError::ConflictingMessage { .. } => "E_CONVERT_016",
+        ConverterError::DepthLimitExceeded { .. } => "E_CONVERT_017",
+        ConverterError::AtLocation { source, .. } | ConverterError::InputFile { source, .. } => {
+            converter_error_code(source)
+        }
+    }
+}
+
+fn parse_error_code(error: &ProtoParseError) -> &'static str {
+    match error {
+        ProtoParseError::Io(_) => "E_IO_001",
+        ProtoParseError::ParseError { .. } => "E_PROTO_PARSE_001",
+        ProtoParseError::UnexpectedToken(_) => "E_PROTO_PARSE_002",
+        ProtoParseError::MissingField(_) => "E_PROTO_PARSE_003",
+        ProtoParseError::DuplicateDefinition(_) => "E_PROTO_PARSE_004",
+        ProtoParseError::MissingImport { .. } => "E_PROTO_PARSE_005",
+        ProtoParseError::InputTooLarge { .. } => "E_PROTO_PARSE_006",
+        ProtoParseError::InFile { source, .. } => parse_error_code(source),
+    }
+}
+
+/// One error of every variant
+fn every_error() -> Vec<Error> {
+    let io = || std::io::Error::other("disk");
+    let json = || serde_json::from_str::<u8>("x").unwrap_err();
+    let yaml = || serde_yaml::from_str::<u8>("x").unwrap_err();
+    let toml = || toml::from_str::<toml::Table>("=").unwrap_err();
+    let s = || "x".to_string();
+
+    let mut errors = vec![
+        Error::Descriptor(s()),
+        Error::IncompatibleModelVersion {
+            found: 2,
+            supported: 1,
+        },
+    ];
+    errors.extend(
+        [
+            ProtoParseError::Io(io()),
+            ProtoParseError::ParseError {
+                line: 1,
+                message: s(),
+            },
+            ProtoParseError::UnexpectedToken(s()),
+            ProtoParseError::MissingField(s()),
+            ProtoParseError::DuplicateDefinition(s()),
+            ProtoParseError::MissingImport {
+                import: s(),
+                importer: s().into(),
+                searched: vec![],
+            },
+            ProtoParseError::InputTooLarge { limit: 1 },
+            ProtoParseError::InFile {
+                path: "api.proto".into(),
+                source: Box::new(ProtoParseError::MissingField(s())),
+            },
+        ]
+        .map(Error::from),
+    );
+    errors.extend(
+        [
+            ConverterError::Io(io()),
+            ConverterError::JsonParse(json()),
+            ConverterError::Yaml(yaml()),
+            ConverterError::Toml(toml()),
+            ConverterError::UnsupportedSchemaType(s()),
+            ConverterError::MissingReference(s()),
+            ConverterError::InvalidArrayDefinition,
+            ConverterError::CircularReference(s()),
+            ConverterError::DuplicateMessageName(s()),
+            ConverterError::InvalidParameterLocation(s()),
+            ConverterError::UnsupportedHttpMethod(s()),
+            ConverterError::InvalidFieldName(s()),
+            ConverterError::InvalidFieldType(s()),
+            ConverterError::ServiceNotFound(s()),
+            ConverterError::MessageNotFound(s()),
+            ConverterError::RemoteReference(s()),
+            ConverterError::CircularExternalReference { from: s(), to: s() },
+            ConverterError::ExternalDocument {
+                path: s(),
+                message: s(),
+            },
+            ConverterError::InvalidOutput(Vec::new()),
+            ConverterError::ConflictingMessage {
+                definition: s(),
+                message: s(),
+                details: s(),
+            },
+            ConverterError::DepthLimitExceeded { limit: 1 },
+            ConverterError::InvalidFieldName(s()).at("#/paths"),
+            ConverterError::Toml(toml()).in_file("api.toml".as_ref()),
+        ]
+        .map(Error::from),
+    );
+    #[cfg(feature = "watch")]
+    errors.push(Error::Watch(notify::Error::generic("x")));
+    errors
+}
+
+#[test]
+fn every_variant_has_its_code() {
+    for error in every_error() {
+        assert_eq!(error.code(), error_code(&error), "{error}");
+    }
+}
+
+#[test]
+fn codes_are_unique_per_variant() {
+    // Вложенные ошибки и IO разделяют код с источником
+    let mut seen = HashSet::new();
+    for error in every_error() {
+        let wrapper = matches!(
+            &error,
+            Error::ProtoParse(ProtoParseError::Io(_) | ProtoParseError::InFile { .. })
+                | Error::Converter(
+                    ConverterError::AtLocation { .. } | ConverterError::InputFile { .. }
+                )
+        );
+        if !wrapper {
+            assert!(seen.insert(error.code()), "{} is used twice", error.code());
+        }
+    }
+}
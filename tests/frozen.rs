@@ -0,0 +1,126 @@
+use std::sync::Arc;
+use std::thread;
+
+use dot_proto_parser::{
+    ArcProtoFile, Field, FieldRule, Message, OptionValue, ProtoFile, ProtoParser, ResolvedItem,
+};
+
+fn file_with(messages: &[(&str, &[(&str, &str)])]) -> ProtoFile {
+    let mut file = ProtoFile::new("shop.v1");
+    for (name, fields) in messages {
+        let mut message = Message::new(name);
+        for (number, (field, type_)) in (1..).zip(fields.iter()) {
+            message
+                .add_field(Field::new(field, *type_, number, FieldRule::Singular))
+                .unwrap();
+        }
+        file.add_message(message).unwrap();
+    }
+    file
+}
+
+#[test]
+fn freezing_again_rebuilds_the_index() {
+    let frozen = file_with(&[("Order", &[("item", "Item")]), ("Item", &[])]).freeze();
+    assert!(matches!(
+        frozen.resolve("Item"),
+        Some(ResolvedItem::Message(_))
+    ));
+    assert!(frozen.resolve("Customer").is_none());
+    assert_eq!(frozen.usages_of("Item"), ["Order.item"]);
+    let fingerprint = frozen.fingerprint();
+
+    let mut file = frozen.into_inner();
+    file.add_message(Message::new("Customer")).unwrap();
+    file.find_message_mut("Order")
+        .unwrap()
+        .add_field(Field::new("buyer", "Customer", 2, FieldRule::Singular))
+        .unwrap();
+    let frozen = file.freeze();
+
+    assert!(matches!(
+        frozen.resolve("shop.v1.Customer"),
+        Some(ResolvedItem::Message(_))
+    ));
+    assert_eq!(frozen.usages_of("Customer"), ["Order.buyer"]);
+    assert_eq!(
+        frozen.dependencies()["Order"],
+        ["Item".to_string(), "Customer".to_string()]
+    );
+    assert_ne!(frozen.fingerprint(), fingerprint);
+}
+
+#[test]
+fn indexed_lookups_match_the_linear_ones() {
+    let text = "syntax = \"proto3\";
+package shop.v1;
+message Order {
+  message Line { Item item = 1; }
+  enum Status { STATUS_UNSPECIFIED = 0; }
+  repeated Line lines = 1;
+  Status status = 2;
+}
+message Item {}
+service Orders {
+  rpc Get (Item) returns (Order);
+}";
+    let file = ProtoParser::new().parse(text).unwrap();
+    let frozen = file.clone().freeze();
+
+    for path in [
+        "Order",
+        "Order.Line",
+        "Order.Status",
+        "shop.v1.Item",
+        "Orders",
+        "Orders.Get",
+        "Missing",
+    ] {
+        assert_eq!(
+            format!("{:?}", frozen.resolve(path)),
+            format!("{:?}", file.resolve(path)),
+            "{}",
+            path
+        );
+    }
+    for type_name in ["Item", "Order", "Order.Line"] {
+        assert_eq!(frozen.usages_of(type_name), file.usages_of(type_name));
+    }
+}
+
+#[test]
+fn identical_files_have_the_same_fingerprint() {
+    let build = || {
+        let mut file = file_with(&[("Order", &[("id", "string"), ("total", "int64")])]);
+        let field = &mut file.find_message_mut("Order").unwrap().fields[0];
+        for (key, value) in [("deprecated", "true"), ("json_name", "\"orderId\"")] {
+            field.add_option(key, OptionValue::from_literal(value));
+        }
+        file.add_option("go_package", "shop/v1");
+        file.add_option("java_package", "com.shop.v1");
+        file
+    };
+
+    let (first, second) = (build(), build());
+    assert_eq!(first.fingerprint(), second.fingerprint());
+    assert_eq!(first.freeze().fingerprint(), second.freeze().fingerprint());
+}
+
+#[test]
+fn clones_share_the_caches_across_threads() {
+    let frozen = ArcProtoFile::new(Arc::new(file_with(&[
+        ("Order", &[("item", "Item")]),
+        ("Item", &[]),
+    ])));
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let frozen = frozen.clone();
+            thread::spawn(move || (frozen.usages_of("Item").to_vec(), frozen.fingerprint()))
+        })
+        .collect();
+    for handle in handles {
+        let (usages, fingerprint) = handle.join().unwrap();
+        assert_eq!(usages, ["Order.item"]);
+        assert_eq!(fingerprint, frozen.fingerprint());
+    }
+}
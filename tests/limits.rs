@@ -0,0 +1,213 @@
+use std::io::Cursor;
+
+use dot_proto_parser::{
+    ConverterError, Error, ProtoParseError, ProtoParser, SwaggerToProtoConverter,
+};
+use serde_json::{Value, json};
+
+fn spec(schemas: Value) -> Value {
+    json!({
+        "openapi": "3.0.0",
+        "info": {"title": "T", "version": "1"},
+        "paths": {},
+        "components": {"schemas": schemas}
+    })
+}
+
+/// Inline object schemas nested `levels` deep
+fn nested_spec(levels: usize) -> Value {
+    let mut schema = json!({"type": "string"});
+    for _ in 0..levels {
+        schema = json!({"type": "object", "properties": {"child": schema}});
+    }
+    spec(json!({ "Deep": schema }))
+}
+
+/// The error without the location it was raised at
+fn cause(error: &ConverterError) -> &ConverterError {
+    match error {
+        ConverterError::AtLocation { source, .. } | ConverterError::InputFile { source, .. } => {
+            cause(source)
+        }
+        other => other,
+    }
+}
+
+#[test]
+fn schema_nesting_over_the_limit() {
+    let mut converter = SwaggerToProtoConverter::builder().max_depth(10).build();
+    assert!(converter.convert_value(nested_spec(8)).is_ok());
+
+    let mut converter = SwaggerToProtoConverter::builder().max_depth(10).build();
+    let error = converter.convert_value(nested_spec(20)).unwrap_err();
+    assert!(
+        matches!(
+            cause(&error),
+            ConverterError::DepthLimitExceeded { limit: 10 }
+        ),
+        "{error}"
+    );
+}
+
+/// Runs `f` with the stack of a main thread: test threads get 2 MiB,
+/// and serde needs more than that for schemas nested near the default
+/// limit in a debug build
+fn on_main_sized_stack(f: impl FnOnce() + Send + 'static) {
+    std::thread::Builder::new()
+        .stack_size(8 << 20)
+        .spawn(f)
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+#[test]
+fn schema_nesting_over_the_default_limit() {
+    on_main_sized_stack(|| {
+        let mut converter = SwaggerToProtoConverter::builder().build();
+        let error = converter.convert_value(nested_spec(200)).unwrap_err();
+        assert!(
+            matches!(
+                cause(&error),
+                ConverterError::DepthLimitExceeded { limit: 128 }
+            ),
+            "{error}"
+        );
+    });
+}
+
+#[test]
+fn deep_document_fails_before_conversion() {
+    on_main_sized_stack(|| {
+        let mut converter = SwaggerToProtoConverter::builder().build();
+        let error = converter.convert_value(nested_spec(300)).unwrap_err();
+        assert!(
+            matches!(error, ConverterError::DepthLimitExceeded { limit: 128 }),
+            "{error}"
+        );
+    });
+}
+
+#[test]
+fn long_all_of_chain() {
+    let count = 5000;
+    let schemas: serde_json::Map<_, _> = (0..count)
+        .map(|i| {
+            let schema = json!({"allOf": [
+                {"$ref": format!("#/components/schemas/S{}", (i + 1) % count)},
+                {"type": "object", "properties": {format!("f{i}"): {"type": "string"}}}
+            ]});
+            (format!("S{i}"), schema)
+        })
+        .collect();
+    // Последняя схема замыкает цепочку, но предел глубины срабатывает раньше
+    let mut converter = SwaggerToProtoConverter::builder().build();
+    let error = converter.convert_value(spec(schemas.into())).unwrap_err();
+    assert!(
+        matches!(
+            cause(&error),
+            ConverterError::DepthLimitExceeded { limit: 128 }
+        ),
+        "{error}"
+    );
+}
+
+#[test]
+fn deeply_nested_json_text() {
+    let text = format!("{}{}", "[".repeat(100_000), "]".repeat(100_000));
+    let mut converter = SwaggerToProtoConverter::builder().build();
+    let error = converter.convert_str(&text).unwrap_err();
+    assert!(matches!(error, ConverterError::JsonParse(_)), "{error}");
+}
+
+fn nested_messages(levels: usize) -> String {
+    format!(
+        "syntax = \"proto3\";\n{}{}",
+        "message M {\n".repeat(levels),
+        "}\n".repeat(levels)
+    )
+}
+
+#[test]
+fn message_nesting_over_the_limit() {
+    assert!(
+        ProtoParser::new()
+            .max_depth(4)
+            .parse(&nested_messages(4))
+            .is_ok()
+    );
+
+    let error = ProtoParser::new()
+        .max_depth(4)
+        .parse(&nested_messages(5))
+        .unwrap_err();
+    assert!(
+        matches!(
+            &error,
+            Error::ProtoParse(ProtoParseError::ParseError { line: 6, message })
+                if message.contains("deeper than 4")
+        ),
+        "{error}"
+    );
+
+    let error = ProtoParser::new()
+        .parse(&nested_messages(100_000))
+        .unwrap_err();
+    assert!(error.to_string().contains("deeper than 128"), "{error}");
+}
+
+#[test]
+fn deeply_nested_option_aggregate() {
+    let levels = 100_000;
+    let text = format!(
+        "syntax = \"proto3\";\nmessage M {{ string a = 1 [(x) = {}{}]; }}\n",
+        "{ a ".repeat(levels),
+        "}".repeat(levels)
+    );
+    let proto = ProtoParser::new().parse(&text).unwrap();
+    assert_eq!(proto.messages[0].fields[0].name, "a");
+}
+
+#[test]
+fn oversized_input() {
+    let text = format!("syntax = \"proto3\";\n// {}\n", "x".repeat(4096));
+
+    let error = ProtoParser::new()
+        .max_input_size(1024)
+        .parse(&text)
+        .unwrap_err();
+    assert!(
+        matches!(
+            error,
+            Error::ProtoParse(ProtoParseError::InputTooLarge { limit: 1024 })
+        ),
+        "{error}"
+    );
+
+    let error = ProtoParser::new()
+        .max_input_size(1024)
+        .parse_reader(Cursor::new(text.into_bytes()))
+        .unwrap_err();
+    assert!(
+        matches!(
+            error,
+            Error::ProtoParse(ProtoParseError::InputTooLarge { limit: 1024 })
+        ),
+        "{error}"
+    );
+}
+
+#[test]
+fn columns_on_a_long_line() {
+    let fields: String = (1..=10_000)
+        .map(|i| format!("int32 f{i} = {i}; "))
+        .collect();
+    let text = format!(
+        "syntax = \"proto3\";\nmessage M {{ {fields}int32 b = x; /* é */ int32 c = ?; }}\n"
+    );
+    let (_, diagnostics) = ProtoParser::new().parse_partial(&text);
+
+    let prefix = "message M { ".len() + fields.len();
+    let columns: Vec<_> = diagnostics.iter().map(|d| (d.line, d.column)).collect();
+    assert_eq!(columns, [(2, prefix + 11), (2, prefix + 32)]);
+}
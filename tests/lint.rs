@@ -0,0 +1,72 @@
+mod common;
+
+use common::{convert, convert_with, fixture};
+use dot_proto_parser::lint::{LintConfig, LintRule, lint, lint_with};
+use dot_proto_parser::{ProtoParser, SwaggerToProtoConverter};
+
+/// Swagger and OpenAPI fixtures the converter reads
+const SPECS: [&str; 10] = [
+    "inline_types.json",
+    "openapi31.json",
+    "path_params.json",
+    "recursive.json",
+    "responses.json",
+    "round_trip.json",
+    "security.json",
+    "servers.json",
+    "sort_order.json",
+    "wide_schema.json",
+];
+
+#[test]
+fn converted_fixtures_are_lint_clean() {
+    let petstore = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/swagger.json"))
+        .expect("petstore spec exists");
+    let specs = SPECS
+        .iter()
+        .map(|name| (*name, fixture(name)))
+        .chain([("swagger.json", petstore)]);
+
+    for (name, spec) in specs {
+        let violations: Vec<String> = lint(&convert(&spec))
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert!(violations.is_empty(), "{name}: {violations:#?}");
+    }
+}
+
+#[test]
+fn escaped_names_follow_the_configured_suffix() {
+    let builder = SwaggerToProtoConverter::builder().reserved_suffix("_value");
+    let proto = convert_with(
+        builder,
+        r##"{
+          "openapi": "3.0.0",
+          "info": {"title": "Keywords", "version": "1"},
+          "paths": {},
+          "components": {"schemas": {"String": {
+            "type": "object",
+            "properties": {"message": {"type": "string"}}
+          }}}
+        }"##,
+    );
+    let message = &proto.messages[0];
+    assert_eq!(message.name, "String_value");
+    assert_eq!(message.fields[0].name, "message_value");
+
+    let config = LintConfig::default().reserved_suffix("_value");
+    assert_eq!(lint_with(&proto, &config), []);
+    // С суффиксом по умолчанию `String_value` не PascalCase
+    let rules: Vec<_> = lint(&proto).iter().map(|v| v.rule).collect();
+    assert_eq!(rules, [LintRule::MessagePascalCase]);
+}
+
+#[test]
+fn rule_ids_name_the_rule() {
+    let proto = ProtoParser::new()
+        .parse("syntax = \"proto3\";\nenum Color { COLOR_RED = 0; COLOR_GREEN = 1; }\n")
+        .unwrap();
+    let ids: Vec<_> = lint(&proto).iter().map(|v| v.rule.id()).collect();
+    assert_eq!(ids, ["ENUM_ZERO_VALUE_UNSPECIFIED"]);
+}
@@ -0,0 +1,38 @@
+mod common;
+
+use common::{assert_golden, fixture};
+use dot_proto_parser::{Error, MODEL_VERSION, ProtoFile, ProtoParser};
+
+fn parsed_fixture() -> ProtoFile {
+    ProtoParser::new()
+        .parse(&fixture("formatting.proto"))
+        .unwrap()
+}
+
+#[test]
+fn serialized_model_shape() {
+    let json = parsed_fixture().to_json().unwrap();
+    assert_golden("formatting.model.json", &json);
+}
+
+#[test]
+fn model_survives_json() {
+    let proto = parsed_fixture();
+    let restored = ProtoFile::from_json(&proto.to_json().unwrap()).unwrap();
+    assert_eq!(restored, proto);
+}
+
+#[test]
+fn other_model_versions_are_rejected() {
+    let json = parsed_fixture().to_json().unwrap().replacen(
+        &format!("\"model_version\": {}", MODEL_VERSION),
+        "\"model_version\": 999",
+        1,
+    );
+    match ProtoFile::from_json(&json) {
+        Err(Error::IncompatibleModelVersion { found, supported }) => {
+            assert_eq!((found, supported), (999, MODEL_VERSION));
+        }
+        other => panic!("expected IncompatibleModelVersion, got {other:?}"),
+    }
+}
@@ -0,0 +1,115 @@
+use dot_proto_parser::{
+    DefaultNaming, NameFormatter, OptionValue, ProtoParser, SwaggerToProtoConverter,
+};
+use serde_json::json;
+
+/// Keywords and scalar type names of the proto3 grammar
+const PROTO3_KEYWORDS: [&str; 37] = [
+    "syntax", "import", "weak", "public", "package", "option", "repeated", "optional", "oneof",
+    "map", "reserved", "to", "max", "enum", "message", "service", "rpc", "stream", "returns",
+    "true", "false", "inf", "nan", "double", "float", "int32", "int64", "uint32", "uint64",
+    "sint32", "sint64", "fixed32", "fixed64", "sfixed32", "sfixed64", "bool", "string",
+];
+
+#[test]
+fn snake_case() {
+    let cases = [
+        ("userId", "user_id"),
+        ("UserId", "user_id"),
+        ("user_id", "user_id"),
+        ("HTTPServer", "http_server"),
+        ("getHTTPResponseCode", "get_http_response_code"),
+        ("XMLHttpRequest", "xml_http_request"),
+        ("APIKey", "api_key"),
+        ("ID", "id"),
+        ("v2Api", "v2_api"),
+        ("address2", "address2"),
+        ("ipv4Address", "ipv4_address"),
+        ("X-Request-ID", "x_request_id"),
+        ("content-type", "content_type"),
+        ("user.first name", "user_first_name"),
+        ("__private__", "private"),
+        ("snake_And-kebab.Mixed", "snake_and_kebab_mixed"),
+    ];
+    for (input, expected) in cases {
+        assert_eq!(DefaultNaming.to_snake_case(input), expected, "{input}");
+    }
+}
+
+#[test]
+fn camel_case() {
+    let cases = [
+        ("user_id", "userId"),
+        ("userId", "userId"),
+        ("UserId", "userId"),
+        ("HTTPServer", "httpServer"),
+        ("getHTTPResponseCode", "getHttpResponseCode"),
+        ("APIKey", "apiKey"),
+        ("ID", "id"),
+        ("address_2", "address2"),
+        ("v2_api", "v2Api"),
+        ("x-request-id", "xRequestId"),
+        ("X-Request-ID", "xRequestId"),
+        ("user.first name", "userFirstName"),
+        ("__private__", "private"),
+        ("snake_And-kebab.Mixed", "snakeAndKebabMixed"),
+    ];
+    for (input, expected) in cases {
+        assert_eq!(DefaultNaming.to_camel_case(input), expected, "{input}");
+    }
+}
+
+#[test]
+fn every_proto3_keyword_is_escaped() {
+    for keyword in PROTO3_KEYWORDS.into_iter().chain(["bytes"]) {
+        assert_eq!(
+            DefaultNaming.sanitize_field_name(keyword),
+            format!("{keyword}_"),
+            "{keyword}"
+        );
+    }
+}
+
+#[test]
+fn scalar_type_names_are_escaped_as_types() {
+    for scalar in [
+        "double", "float", "int32", "int64", "bool", "string", "bytes",
+    ] {
+        let type_name = DefaultNaming.sanitize_type_name(scalar);
+        assert!(type_name.ends_with('_'), "{scalar} -> {type_name}");
+    }
+    assert_eq!(DefaultNaming.sanitize_type_name("String"), "String_");
+    assert_eq!(DefaultNaming.sanitize_type_name("Strings"), "Strings");
+}
+
+#[test]
+fn keyword_properties_keep_their_json_name_and_parse_back() {
+    let properties: serde_json::Map<_, _> = PROTO3_KEYWORDS
+        .iter()
+        .map(|k| (k.to_string(), json!({"type": "string"})))
+        .collect();
+    let spec = json!({
+        "openapi": "3.0.0",
+        "info": {"title": "T", "version": "1"},
+        "paths": {},
+        "components": {"schemas": {"Keywords": {"type": "object", "properties": properties}}}
+    });
+    let mut converter = SwaggerToProtoConverter::builder().build();
+    let text = converter
+        .convert_str(&spec.to_string())
+        .unwrap()
+        .to_proto_text();
+
+    let proto = ProtoParser::new().parse(&text).unwrap();
+    let message = proto.find_message("Keywords").unwrap();
+    assert_eq!(message.fields.len(), PROTO3_KEYWORDS.len());
+    for (field, keyword) in message.fields.iter().zip(PROTO3_KEYWORDS) {
+        assert_eq!(field.name, format!("{keyword}_"));
+        // Без явного json_name protoc выводит его из имени поля
+        let json_name = match field.options.get("json_name") {
+            Some(OptionValue::String(name)) => name.clone(),
+            _ => DefaultNaming.to_camel_case(&field.name),
+        };
+        assert_eq!(json_name, keyword);
+    }
+}
@@ -0,0 +1,286 @@
+mod common;
+
+use common::{assert_golden, convert, convert_with, fixture};
+use dot_proto_parser::{ConverterOptions, OptionValue, ProtoFile, SwaggerToProtoConverter};
+
+/// `Type name` of every field of `message`
+fn fields(proto: &ProtoFile, message: &str) -> Vec<String> {
+    proto
+        .find_message(message)
+        .unwrap_or_else(|| panic!("{message} is generated"))
+        .fields
+        .iter()
+        .map(|f| format!("{} {}", f.type_name(), f.name))
+        .collect()
+}
+
+#[test]
+fn path_level_parameters_apply_to_every_operation() {
+    let proto = convert(&fixture("path_params.json"));
+
+    assert_eq!(
+        fields(&proto, "PetsGetPetQueryParams"),
+        ["int64 pet_id", "string trace"]
+    );
+    // Параметр операции с тем же именем и местом перекрывает параметр пути
+    assert_eq!(
+        fields(&proto, "PetsDeletePetQueryParams"),
+        ["int64 pet_id", "bool trace"]
+    );
+
+    // Исходное имя, которое protoc не выведет из имени поля, остаётся в json_name
+    let params = proto.find_message("PetsGetPetQueryParams").unwrap();
+    let json_names: Vec<_> = params
+        .fields
+        .iter()
+        .map(|f| f.options.get("json_name").and_then(OptionValue::as_str))
+        .collect();
+    assert_eq!(json_names, [None, Some("Trace")]);
+}
+
+const TYPED_PARAMS: [&str; 5] = [
+    "int64 user_id",
+    "int32 page",
+    "google.protobuf.Timestamp since",
+    "bytes token",
+    "float ratio",
+];
+
+#[test]
+fn swagger2_flat_parameter_types() {
+    let proto = convert(
+        r##"{
+          "swagger": "2.0",
+          "info": {"title": "Users", "version": "1"},
+          "paths": {"/users/{userId}": {"get": {
+            "operationId": "getUser",
+            "tags": ["users"],
+            "parameters": [
+              {"name": "userId", "in": "path", "required": true, "type": "integer", "format": "int64"},
+              {"name": "page", "in": "query", "type": "integer", "format": "int32"},
+              {"name": "since", "in": "query", "type": "string", "format": "date-time"},
+              {"name": "token", "in": "query", "type": "string", "format": "byte"},
+              {"name": "ratio", "in": "query", "type": "number", "format": "float"}
+            ],
+            "responses": {"200": {"description": "ok"}}
+          }}}
+        }"##,
+    );
+
+    assert_eq!(fields(&proto, "UsersGetUserQueryParams"), TYPED_PARAMS);
+    assert!(
+        proto
+            .imports
+            .contains(&"google/protobuf/timestamp.proto".to_string())
+    );
+}
+
+#[test]
+fn openapi3_schema_parameter_types() {
+    let proto = convert(
+        r##"{
+          "openapi": "3.0.0",
+          "info": {"title": "Users", "version": "1"},
+          "paths": {"/users/{userId}": {"get": {
+            "operationId": "getUser",
+            "tags": ["users"],
+            "parameters": [
+              {"name": "userId", "in": "path", "required": true, "schema": {"type": "integer", "format": "int64"}},
+              {"name": "page", "in": "query", "schema": {"type": "integer", "format": "int32"}},
+              {"name": "since", "in": "query", "schema": {"type": "string", "format": "date-time"}},
+              {"name": "token", "in": "query", "schema": {"type": "string", "format": "byte"}},
+              {"name": "ratio", "in": "query", "schema": {"type": "number", "format": "float"}}
+            ],
+            "responses": {"200": {"description": "ok"}}
+          }}}
+        }"##,
+    );
+
+    assert_eq!(fields(&proto, "UsersGetUserQueryParams"), TYPED_PARAMS);
+    assert!(
+        proto
+            .imports
+            .contains(&"google/protobuf/timestamp.proto".to_string())
+    );
+}
+
+#[test]
+fn security_requirements_become_method_comments() {
+    let builder = SwaggerToProtoConverter::builder()
+        .package("pets.v1")
+        .info_as_header_comment(false);
+    let proto = convert_with(builder, &fixture("security.json"));
+    assert_golden("security.proto", &proto.to_proto_text());
+}
+
+#[test]
+fn security_requirements_as_method_option() {
+    let builder = SwaggerToProtoConverter::builder().options(ConverterOptions {
+        security_option: Some("(auth.security)".to_string()),
+        ..Default::default()
+    });
+    let proto = convert_with(builder, &fixture("security.json"));
+    let service = proto.find_service("PetsService").unwrap();
+
+    let option = |method: &str| {
+        service
+            .find_method(method)
+            .unwrap()
+            .options
+            .get("(auth.security)")
+            .cloned()
+    };
+    assert_eq!(
+        option("ListPets"),
+        Some(OptionValue::String(
+            "petstore_auth (scopes: read:pets)".to_string()
+        ))
+    );
+    assert_eq!(
+        option("CreatePet"),
+        Some(OptionValue::String(
+            "petstore_auth (scopes: write:pets, read:pets) | api_key".to_string()
+        ))
+    );
+    // `security: []` отключает глобальные требования
+    assert_eq!(option("Health"), None);
+}
+
+/// `(rpc, output type)` of every rpc of `service`
+fn outputs<'a>(proto: &'a ProtoFile, service: &str) -> Vec<(&'a str, &'a str)> {
+    proto
+        .find_service(service)
+        .unwrap()
+        .methods
+        .iter()
+        .map(|m| (m.name.as_str(), m.output_type.as_str()))
+        .collect()
+}
+
+#[test]
+fn response_selection_priority() {
+    let proto = convert(&fixture("responses.json"));
+
+    assert_eq!(
+        outputs(&proto, "ItemsService"),
+        [
+            // Наименьший 2xx, независимо от порядка в документе
+            ("ListItems", "Item"),
+            ("CreateItem", "Created"),
+            // 2xx без тела: берётся default
+            ("ReplaceItems", "Problem"),
+            // 204 всегда Empty, даже при default с телом
+            ("PurgeItems", "google.protobuf.Empty"),
+            ("TouchItems", "google.protobuf.Empty"),
+        ]
+    );
+    let service = proto.find_service("ItemsService").unwrap();
+    let from_default: Vec<_> = service
+        .methods
+        .iter()
+        .filter(|m| m.comments.iter().any(|c| c.contains("`default` response")))
+        .map(|m| m.name.as_str())
+        .collect();
+    assert_eq!(from_default, ["ReplaceItems"]);
+}
+
+#[test]
+fn operation_servers_take_priority_over_path_and_document_ones() {
+    let proto = convert(&fixture("servers.json"));
+    let service = proto.find_service("ItemsService").unwrap();
+
+    let option = |method: &str, key: &str| {
+        service
+            .find_method(method)
+            .unwrap()
+            .options
+            .get(key)
+            .and_then(OptionValue::as_str)
+            .map(str::to_string)
+    };
+    let path = |method| option(method, "http_path").unwrap();
+    let server = |method| option(method, "http_server");
+
+    assert_eq!(path("ListItems"), "/search/items");
+    assert_eq!(
+        server("ListItems").as_deref(),
+        Some("https://search.example.com/search")
+    );
+    assert_eq!(path("CreateItem"), "/items-api/items");
+    assert_eq!(
+        server("CreateItem").as_deref(),
+        Some("https://items.example.com/items-api")
+    );
+    assert_eq!(path("Health"), "/v1/health");
+    assert_eq!(server("Health"), None);
+}
+
+const CLIENT_SERVED: &str = r#"{
+  "openapi": "3.1.0",
+  "info": {"title": "Pets", "version": "1"},
+  "paths": {
+    "/subscriptions": {
+      "post": {
+        "operationId": "subscribe",
+        "responses": {"201": {"description": "created"}},
+        "callbacks": {
+          "onEvent": {
+            "{$request.body#/callbackUrl}": {
+              "post": {"responses": {"200": {"description": "ok"}}}
+            }
+          }
+        }
+      }
+    }
+  },
+  "webhooks": {
+    "newPet": {
+      "post": {
+        "operationId": "newPet",
+        "responses": {"200": {"description": "ok"}}
+      }
+    }
+  }
+}"#;
+
+#[test]
+fn webhooks_and_callbacks_have_no_http_binding() {
+    for annotations in [false, true] {
+        let builder = SwaggerToProtoConverter::builder().http_annotations(annotations);
+        let proto = convert_with(builder, CLIENT_SERVED);
+
+        let webhook = &proto.find_service("NewPetWebhookService").unwrap().methods[0];
+        let callback = &proto
+            .find_service("SubscribeCallbackService")
+            .unwrap()
+            .methods[0];
+        for method in [webhook, callback] {
+            assert!(
+                !["http_method", "http_path", "(google.api.http)"]
+                    .iter()
+                    .any(|key| method.options.contains_key(*key)),
+                "{} is bound: {:?}",
+                method.name,
+                method.options
+            );
+        }
+        assert!(
+            webhook
+                .comments
+                .contains(&"Webhook: POST newPet".to_string())
+        );
+        assert!(
+            callback
+                .comments
+                .contains(&"Callback: POST {$request.body#/callbackUrl}".to_string())
+        );
+
+        let subscribe = &proto.find_service("SubscriptionsService").unwrap().methods[0];
+        let key = if annotations {
+            "(google.api.http)"
+        } else {
+            "http_path"
+        };
+        assert!(subscribe.options.contains_key(key));
+    }
+}
@@ -0,0 +1,209 @@
+mod common;
+
+use common::fixture;
+use dot_proto_parser::{
+    FieldType, NumberRange, OptionValue, ProtoParser, ProtoToOpenApiConverter, ScalarType,
+    Severity, SwaggerToProtoConverter, roundtrip_check,
+};
+use serde_json::{Value, json};
+
+#[test]
+fn swagger_to_proto_and_back_keeps_schema_shapes() {
+    let spec = fixture("round_trip.json");
+    let mut converter = SwaggerToProtoConverter::builder()
+        .http_annotations(true)
+        .build();
+    let text = converter.convert_str(&spec).unwrap().to_proto_text();
+    let proto = ProtoParser::new().parse(&text).unwrap();
+    let document = ProtoToOpenApiConverter::new().convert(&proto);
+
+    let original: Value = serde_json::from_str(&spec).unwrap();
+    let schemas = &document["components"]["schemas"];
+    assert_eq!(
+        schemas["Category"],
+        original["components"]["schemas"]["Category"]
+    );
+
+    // Inline enum выносится в отдельную схему, остальное совпадает
+    let mut pet = original["components"]["schemas"]["Pet"].clone();
+    let status = pet["properties"]["status"].take();
+    pet["properties"]["status"] = json!({"$ref": "#/components/schemas/PetStatus"});
+    assert_eq!(schemas["Pet"], pet);
+    assert_eq!(schemas["PetStatus"], status);
+
+    let operation = &document["paths"]["/pets"]["post"];
+    assert_eq!(
+        operation["requestBody"]["content"]["application/json"]["schema"],
+        json!({"$ref": "#/components/schemas/Pet"})
+    );
+    assert_eq!(
+        operation["responses"]["200"]["content"]["application/json"]["schema"],
+        json!({"$ref": "#/components/schemas/Pet"})
+    );
+}
+
+#[test]
+fn snake_case_parameters_keep_their_original_names() {
+    let proto = SwaggerToProtoConverter::builder()
+        .build()
+        .convert_str(&fixture("path_params.json"))
+        .unwrap()
+        .clone();
+    let proto = ProtoParser::new().parse(&proto.to_proto_text()).unwrap();
+    let document = ProtoToOpenApiConverter::new().convert(&proto);
+
+    // pet_id связан с {petId}, Trace восстанавливается из json_name
+    let names = |method: &str| -> Vec<(String, String)> {
+        document["paths"]["/pets/{petId}"][method]["parameters"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| (p["name"].to_string(), p["in"].to_string()))
+            .collect()
+    };
+    let expected = [
+        ("\"petId\"".to_string(), "\"path\"".to_string()),
+        ("\"Trace\"".to_string(), "\"query\"".to_string()),
+    ];
+    assert_eq!(names("get"), expected);
+    assert_eq!(names("delete"), expected);
+}
+
+const RESERVED: &str = r#"syntax = "proto2";
+
+message Order {
+  option deprecated = true;
+  reserved 2, 9 to 11;
+  reserved "legacy_id", "notes";
+  extensions 100 to max;
+  optional string id = 1;
+  enum State {
+    option allow_alias = true;
+    reserved 5 to 7;
+    reserved "ARCHIVED";
+    STATE_UNSPECIFIED = 0;
+    STATE_OPEN = 1;
+    STATE_ACTIVE = 1;
+  }
+
+}
+
+"#;
+
+#[test]
+fn reserved_ranges_and_block_options_are_kept() {
+    let proto = ProtoParser::new().parse(RESERVED).unwrap();
+    let order = proto.find_message("Order").unwrap();
+    assert_eq!(
+        order.options.get("deprecated"),
+        Some(&OptionValue::Bool(true))
+    );
+    assert_eq!(
+        order.reserved_ranges,
+        [NumberRange::single(2), NumberRange::new(9, 11)]
+    );
+    assert_eq!(order.reserved_names, ["legacy_id", "notes"]);
+    assert_eq!(order.extension_ranges, [NumberRange::new(100, i32::MAX)]);
+
+    let state = &order.nested_enums[0];
+    assert!(state.allows_alias());
+    assert_eq!(state.reserved_ranges, [NumberRange::new(5, 7)]);
+    assert_eq!(state.reserved_names, ["ARCHIVED"]);
+
+    assert_eq!(proto.to_proto_text(), RESERVED);
+    assert_eq!(roundtrip_check(RESERVED), Ok(()));
+}
+
+#[test]
+fn statements_without_a_place_in_the_model_are_reported() {
+    let text = r#"syntax = "proto2";
+message Order {
+  extensions 100 to 199 [verification = UNVERIFIED];
+  extend Base { optional int32 weight = 100; }
+  optional string id = 1;
+}
+extend Order { optional string note = 101; }
+"#;
+    let (proto, diagnostics) = ProtoParser::new().parse_partial(text);
+    let order = proto.find_message("Order").unwrap();
+    assert_eq!(order.extension_ranges, [NumberRange::new(100, 199)]);
+    assert_eq!(order.fields.len(), 1);
+
+    let warnings: Vec<_> = diagnostics
+        .iter()
+        .map(|d| (d.severity, d.line, d.message.as_str()))
+        .collect();
+    assert_eq!(
+        warnings,
+        [
+            (
+                Severity::Warning,
+                3,
+                "Options of `extensions` are not kept in the model"
+            ),
+            (
+                Severity::Warning,
+                4,
+                "`extend` blocks are not kept in the model"
+            ),
+            (
+                Severity::Warning,
+                7,
+                "`extend` blocks are not kept in the model"
+            ),
+        ]
+    );
+}
+
+#[test]
+fn proto_fixtures_survive_render_and_reparse() {
+    for name in [
+        "formatting.proto",
+        "recursive.proto",
+        "security.proto",
+        "tricky.proto",
+    ] {
+        let content = fixture(name);
+        assert_eq!(roundtrip_check(&content), Ok(()), "{name}");
+
+        let parsed = ProtoParser::new().parse(&content).unwrap();
+        let reparsed = ProtoParser::new().parse(&parsed.to_proto_text()).unwrap();
+        assert_eq!(reparsed, parsed, "{name}");
+    }
+}
+
+#[test]
+fn statements_are_split_by_tokens_not_lines() {
+    let proto = ProtoParser::new().parse(&fixture("tricky.proto")).unwrap();
+    assert_eq!(proto.package, "tricky.v1");
+    assert_eq!(proto.options.len(), 2);
+
+    let split = proto.find_message("Split").unwrap();
+    let names: Vec<_> = split.fields.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(names, ["url", "pattern", "quoted", "counts", "a", "b"]);
+    assert_eq!(split.fields[0].options.len(), 2);
+    assert_eq!(
+        split.fields[1].options["json_name"],
+        OptionValue::String("x//y".to_string())
+    );
+    assert_eq!(
+        split.fields[2].options["json_name"],
+        OptionValue::String("say \"hi\"".to_string())
+    );
+    assert_eq!(
+        split.fields[3].type_,
+        FieldType::map(ScalarType::String, ScalarType::Int64)
+    );
+
+    let mode = proto.find_enum("Mode").unwrap();
+    assert_eq!(mode.comments, ["Block comment", "spanning lines"]);
+    assert_eq!(mode.values.len(), 2);
+
+    let service = proto.find_service("Tricky").unwrap();
+    let Some(OptionValue::Aggregate(http)) = service.methods[0].options.get("(google.api.http)")
+    else {
+        panic!("(google.api.http) is an aggregate");
+    };
+    assert!(http.contains("additional_bindings { post: \"/v1/items:get\" body: \"*\" }"));
+    assert!(service.methods[1].server_streaming);
+}
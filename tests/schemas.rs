@@ -0,0 +1,285 @@
+mod common;
+
+use common::{assert_golden, convert, convert_with, fixture};
+use dot_proto_parser::{ConverterOptions, NullableStrategy, SwaggerToProtoConverter};
+
+#[test]
+fn one_of_keeps_sibling_properties() {
+    let proto = convert(
+        r##"{
+          "openapi": "3.0.0",
+          "info": {"title": "T", "version": "1"},
+          "paths": {},
+          "components": {"schemas": {
+            "Cat": {"type": "object", "properties": {"meow": {"type": "boolean"}}},
+            "Dog": {"type": "object", "properties": {"bark": {"type": "boolean"}}},
+            "Pet": {
+              "type": "object",
+              "properties": {"name": {"type": "string"}, "petType": {"type": "string"}},
+              "oneOf": [{"$ref": "#/components/schemas/Cat"}, {"$ref": "#/components/schemas/Dog"}],
+              "discriminator": {"propertyName": "petType"}
+            }
+          }}
+        }"##,
+    );
+
+    let pet = proto.find_message("Pet").unwrap();
+    let fields: Vec<_> = pet
+        .fields
+        .iter()
+        .map(|f| (f.name.as_str(), f.number))
+        .collect();
+    assert_eq!(fields, [("name", 1), ("pet_type", 2)]);
+    let variants: Vec<_> = pet.oneofs[0]
+        .fields
+        .iter()
+        .map(|f| (f.name.as_str(), f.number))
+        .collect();
+    assert_eq!(variants, [("cat", 3), ("dog", 4)]);
+}
+
+#[test]
+fn recursive_schemas_reference_themselves() {
+    let builder = SwaggerToProtoConverter::builder()
+        .package("catalog.v1")
+        .info_as_header_comment(false);
+    let proto = convert_with(builder, &fixture("recursive.json"));
+    assert_golden("recursive.proto", &proto.to_proto_text());
+}
+
+const NULLABLE_SPEC: &str = r##"{
+  "openapi": "3.1.0",
+  "info": {"title": "T", "version": "1"},
+  "paths": {},
+  "components": {"schemas": {
+    "Owner": {"type": "object", "properties": {"id": {"type": "string"}}},
+    "Pet": {
+      "type": "object",
+      "required": ["name", "age", "owner", "nickname"],
+      "properties": {
+        "name": {"type": "string", "nullable": true},
+        "age": {"type": "integer", "format": "int64", "nullable": true},
+        "owner": {"anyOf": [{"$ref": "#/components/schemas/Owner"}, {"type": "null"}]},
+        "nickname": {"type": ["string", "null"]},
+        "tag": {"type": "string"}
+      }
+    }
+  }}
+}"##;
+
+fn pet_fields(strategy: NullableStrategy) -> (Vec<String>, Vec<String>) {
+    let builder = SwaggerToProtoConverter::builder().options(ConverterOptions {
+        nullable: strategy,
+        ..Default::default()
+    });
+    let proto = convert_with(builder, NULLABLE_SPEC);
+    let fields = proto
+        .find_message("Pet")
+        .unwrap()
+        .fields
+        .iter()
+        .map(|f| format!("{:?} {} {}", f.rule, f.type_name(), f.name))
+        .collect();
+    (fields, proto.imports.clone())
+}
+
+#[test]
+fn nullable_wrappers() {
+    let (fields, imports) = pet_fields(NullableStrategy::Wrappers);
+    assert_eq!(
+        fields,
+        [
+            "Singular google.protobuf.StringValue name",
+            "Singular google.protobuf.Int64Value age",
+            "Optional Owner owner",
+            "Singular google.protobuf.StringValue nickname",
+            "Optional string tag",
+        ]
+    );
+    assert!(imports.contains(&"google/protobuf/wrappers.proto".to_string()));
+}
+
+#[test]
+fn nullable_optional() {
+    let (fields, imports) = pet_fields(NullableStrategy::Optional);
+    assert_eq!(
+        fields,
+        [
+            "Optional string name",
+            "Optional int64 age",
+            "Optional Owner owner",
+            "Optional string nickname",
+            "Optional string tag",
+        ]
+    );
+    assert!(imports.is_empty());
+}
+
+#[test]
+fn nullable_ignored() {
+    let (fields, imports) = pet_fields(NullableStrategy::Ignore);
+    assert_eq!(
+        fields,
+        [
+            "Singular string name",
+            "Singular int64 age",
+            "Singular Owner owner",
+            "Singular string nickname",
+            "Optional string tag",
+        ]
+    );
+    assert!(imports.is_empty());
+}
+
+#[test]
+fn identical_inline_enums_collapse_into_one() {
+    let proto = convert(&fixture("sort_order.json"));
+
+    assert_eq!(proto.enums.len(), 1);
+    let name = &proto.enums[0].name;
+    let users: Vec<_> = proto
+        .messages
+        .iter()
+        .filter(|m| m.fields.iter().any(|f| f.type_name() == *name))
+        .map(|m| m.name.as_str())
+        .collect();
+    assert_eq!(
+        users,
+        [
+            "Report",
+            "CustomersListCustomersQueryParams",
+            "OrdersListOrdersQueryParams",
+            "ProductsListProductsQueryParams",
+        ]
+    );
+}
+
+#[test]
+fn multi_line_descriptions_become_comment_lines() {
+    let proto = convert(
+        r##"{
+          "openapi": "3.0.0",
+          "info": {"title": "T", "version": "1"},
+          "paths": {},
+          "components": {"schemas": {
+            "Link": {
+              "type": "object",
+              "description": "A link.\n\nFollows RFC 8288 // web linking",
+              "properties": {
+                "href": {"type": "string", "description": "// Target URL\nsuch as https://example.com/a/b"}
+              }
+            }
+          }}
+        }"##,
+    );
+
+    let text = proto.find_message("Link").unwrap().to_proto_text(0);
+    assert!(
+        text.starts_with(concat!(
+            "// A link.\n",
+            "//\n",
+            "// Follows RFC 8288 // web linking\n",
+            "message Link {\n",
+            "  // Target URL\n",
+            "  // such as https://example.com/a/b\n",
+            "  optional string href = 1;\n",
+        )),
+        "{text}"
+    );
+}
+
+#[test]
+fn openapi31_const_and_nullable_unions() {
+    let builder = SwaggerToProtoConverter::builder()
+        .package("events.v1")
+        .info_as_header_comment(false);
+    let proto = convert_with(builder, &fixture("openapi31.json"));
+    assert_golden("openapi31.proto", &proto.to_proto_text());
+
+    let event = proto.find_message("Event").unwrap();
+    // `const` без `type`: тип выводится из значения
+    let kind = event.find_field("kind").unwrap();
+    assert_eq!(kind.type_name(), "string");
+    assert!(kind.comments.contains(&"const: \"event\"".to_string()));
+    let version = event.find_field("version").unwrap();
+    assert_eq!(version.type_name(), "int64");
+    assert!(version.comments.contains(&"const: 2".to_string()));
+}
+
+#[test]
+fn openapi31_version_markers() {
+    let spec = fixture("openapi31.json");
+    let expected = convert(&spec).to_proto_text();
+    for version in ["3.1.1", "3.1.0-rc1"] {
+        let marked = spec.replace("\"3.1.0\"", &format!("\"{version}\""));
+        assert_eq!(convert(&marked).to_proto_text(), expected, "{version}");
+    }
+}
+
+const COUNTERS_SPEC: &str = r##"{
+  "openapi": "3.0.3",
+  "info": {"title": "Counters", "version": "1"},
+  "paths": {},
+  "components": {"schemas": {
+    "Counter": {
+      "type": "object",
+      "properties": {
+        "hits": {"type": "integer", "format": "int64", "minimum": 0},
+        "shard": {"type": "integer", "format": "int32", "minimum": 0, "maximum": 64},
+        "delta": {"type": "integer", "format": "sint32", "minimum": -10},
+        "checksum": {"type": "integer", "format": "fixed64", "minimum": 1}
+      }
+    }
+  }}
+}"##;
+
+/// `type name [rule option]` of every `Counter` field
+fn counter_fields(prefer_unsigned: bool) -> Vec<String> {
+    let builder = SwaggerToProtoConverter::builder().options(ConverterOptions {
+        prefer_unsigned,
+        validation_rules: true,
+        ..Default::default()
+    });
+    let proto = convert_with(builder, COUNTERS_SPEC);
+    proto
+        .find_message("Counter")
+        .unwrap()
+        .fields
+        .iter()
+        .map(|f| {
+            let rules: Vec<_> = f
+                .options
+                .iter()
+                .map(|(k, v)| format!("{} = {}", k, v))
+                .collect();
+            format!("{} {} [{}]", f.type_name(), f.name, rules.join(", "))
+        })
+        .collect()
+}
+
+#[test]
+fn prefer_unsigned_switches_the_validation_rule_type() {
+    assert_eq!(
+        counter_fields(true),
+        [
+            "uint64 hits [(validate.rules).uint64 = {gte: 0}]",
+            "uint32 shard [(validate.rules).uint32 = {gte: 0, lte: 64}]",
+            "sint32 delta [(validate.rules).sint32 = {gte: -10}]",
+            "fixed64 checksum [(validate.rules).fixed64 = {gte: 1}]",
+        ]
+    );
+}
+
+#[test]
+fn signed_types_are_kept_without_prefer_unsigned() {
+    assert_eq!(
+        counter_fields(false),
+        [
+            "int64 hits [(validate.rules).int64 = {gte: 0}]",
+            "int32 shard [(validate.rules).int32 = {gte: 0, lte: 64}]",
+            // Явный format проходит как есть, правило того же типа
+            "sint32 delta [(validate.rules).sint32 = {gte: -10}]",
+            "fixed64 checksum [(validate.rules).fixed64 = {gte: 1}]",
+        ]
+    );
+}